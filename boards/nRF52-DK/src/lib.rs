@@ -30,6 +30,8 @@ use nrf52832_hal::{
         CorePeripherals,
         Peripherals,
     },
+    spim,
+    twim,
     uarte::{
         self,
         Uarte,
@@ -53,6 +55,12 @@ pub struct Board {
     /// The buttons on the nRF52-DK board
     pub buttons: Buttons,
 
+    /// The SPI pins of the Arduino header on the nRF52-DK board
+    pub spi: SpiPins,
+
+    /// The I2C pins of the Arduino header on the nRF52-DK board
+    pub i2c: I2cPins,
+
     pub nfc: NFC,
 
     /// Core peripheral: Cache and branch predictor maintenance operations
@@ -133,6 +141,9 @@ pub struct Board {
     /// nRF52 peripheral: SPIS1
     pub SPIS1: nrf52::SPIS1,
 
+    /// nRF52 peripheral: TWIM1
+    pub TWIM1: nrf52::TWIM1,
+
     /// nRF52 peripheral: TWIS1
     pub TWIS1: nrf52::TWIS1,
 
@@ -256,6 +267,15 @@ pub struct Board {
     /// nRF52 peripheral: PWM2
     pub PWM2: nrf52::PWM2,
 
+    /// nRF52 peripheral: SPIM2
+    pub SPIM2: nrf52::SPIM2,
+
+    /// nRF52 peripheral: SPIS2
+    pub SPIS2: nrf52::SPIS2,
+
+    /// nRF52 peripheral: SPI2
+    pub SPI2: nrf52::SPI2,
+
     /// nRF52 peripheral: RTC2
     pub RTC2: nrf52::RTC2,
 
@@ -322,17 +342,10 @@ impl Board {
                 P0_04 : pins0.p0_04,
                 P0_11 : pins0.p0_11,
                 P0_12 : pins0.p0_12,
-                P0_24 : pins0.p0_24,
-                P0_25 : pins0.p0_25,
                 P0_28 : pins0.p0_28,
                 P0_29 : pins0.p0_29,
                 P0_30 : pins0.p0_30,
                 P0_31 : pins0.p0_31,
-
-                P0_22 : pins0.p0_22,
-                P0_23 : pins0.p0_23,
-                P0_26 : pins0.p0_26,
-                P0_27 : pins0.p0_27,
             },
 
             leds: Leds {
@@ -349,6 +362,18 @@ impl Board {
                 button_4: Button::new(pins0.p0_16.degrade()),
             },
 
+            spi: SpiPins {
+                sck : pins0.p0_25,
+                mosi: pins0.p0_23,
+                miso: pins0.p0_24,
+                cs  : pins0.p0_22,
+            },
+
+            i2c: I2cPins {
+                scl: pins0.p0_27,
+                sda: pins0.p0_26,
+            },
+
             nfc: NFC {
                 nfc_1: pins0.p0_09,
                 nfc_2: pins0.p0_10,
@@ -384,6 +409,7 @@ impl Board {
             TWI0  : p.TWI0,
             SPIM1 : p.SPIM1,
             SPIS1 : p.SPIS1,
+            TWIM1 : p.TWIM1,
             TWIS1 : p.TWIS1,
             SPI1  : p.SPI1,
             TWI1  : p.TWI1,
@@ -425,6 +451,9 @@ impl Board {
             MWU   : p.MWU,
             PWM1  : p.PWM1,
             PWM2  : p.PWM2,
+            SPIM2 : p.SPIM2,
+            SPIS2 : p.SPIS2,
+            SPI2  : p.SPI2,
             RTC2  : p.RTC2,
             I2S   : p.I2S,
         }
@@ -447,14 +476,7 @@ pub struct Pins {
     pub P0_11: p0::P0_11<Input<Floating>>,
     pub P0_12: p0::P0_12<Input<Floating>>,
 
-    pub P0_22: p0::P0_22<Input<Floating>>,
-    pub P0_23: p0::P0_23<Input<Floating>>,
-    pub P0_24: p0::P0_24<Input<Floating>>,
-    pub P0_25: p0::P0_25<Input<Floating>>,
-
     pub P0_02: p0::P0_02<Input<Floating>>,
-    pub P0_26: p0::P0_26<Input<Floating>>,
-    pub P0_27: p0::P0_27<Input<Floating>>,
 }
 
 
@@ -516,6 +538,55 @@ impl Button {
     }
 }
 
+/// The SPI pins of the Arduino header on the nRF52-DK board
+pub struct SpiPins {
+    /// nRF52-DK: D13, nRF52: P0.25
+    pub sck: p0::P0_25<Input<Floating>>,
+
+    /// nRF52-DK: D11, nRF52: P0.23
+    pub mosi: p0::P0_23<Input<Floating>>,
+
+    /// nRF52-DK: D12, nRF52: P0.24
+    pub miso: p0::P0_24<Input<Floating>>,
+
+    /// nRF52-DK: D10, nRF52: P0.22
+    pub cs: p0::P0_22<Input<Floating>>,
+}
+
+impl SpiPins {
+    /// Configure the pins for `Spim::new`, along with the chip select pin,
+    /// which is not selected
+    pub fn into_spim_pins(self) -> (spim::Pins, Pin<Output<PushPull>>) {
+        let pins = spim::Pins {
+            sck : self.sck.into_push_pull_output(Level::Low).degrade(),
+            mosi: Some(self.mosi.into_push_pull_output(Level::Low).degrade()),
+            miso: Some(self.miso.into_floating_input().degrade()),
+        };
+        let cs = self.cs.into_push_pull_output(Level::High).degrade();
+
+        (pins, cs)
+    }
+}
+
+/// The I2C pins of the Arduino header on the nRF52-DK board
+pub struct I2cPins {
+    /// nRF52-DK: SCL, nRF52: P0.27
+    pub scl: p0::P0_27<Input<Floating>>,
+
+    /// nRF52-DK: SDA, nRF52: P0.26
+    pub sda: p0::P0_26<Input<Floating>>,
+}
+
+impl I2cPins {
+    /// Configure the pins for `Twim::new`
+    pub fn into_twim_pins(self) -> twim::Pins {
+        twim::Pins {
+            scl: self.scl.degrade(),
+            sda: self.sda.degrade(),
+        }
+    }
+}
+
 /// The NFC pins on the nRF52-DK board
 pub struct NFC {
     /// nRF52-DK: NFC1, nRF52: P0.09
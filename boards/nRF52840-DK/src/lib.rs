@@ -41,6 +41,7 @@ use nrf52840_hal::{
         MODE_0,
         Spim
     },
+    twim,
     uarte::{
         self,
         Uarte,
@@ -68,6 +69,12 @@ pub struct Board {
     /// The buttons on the nRF52840-DK board
     pub buttons: Buttons,
 
+    /// The SPI pins of the Arduino header on the nRF52840-DK board
+    pub spi: SpiPins,
+
+    /// The I2C pins of the Arduino header on the nRF52840-DK board
+    pub i2c: I2cPins,
+
     pub nfc: NFC,
 
     /// Core peripheral: Cache and branch predictor maintenance operations
@@ -352,8 +359,6 @@ impl Board {
                 _RESET : pins0.p0_18,
                 P0_22 : pins0.p0_22,
                 P0_23 : pins0.p0_23,
-                P0_26 : pins0.p0_26,
-                P0_27 : pins0.p0_27,
                 P0_28 : pins0.p0_28,
                 P0_29 : pins0.p0_29,
                 P0_30 : pins0.p0_30,
@@ -370,10 +375,6 @@ impl Board {
                 P1_09 : pins1.p1_09,
                 P1_10 : pins1.p1_10,
                 P1_11 : pins1.p1_11,
-                P1_12 : pins1.p1_12,
-                P1_13 : pins1.p1_13,
-                P1_14 : pins1.p1_14,
-                P1_15 : pins1.p1_15,
             },
 
             leds: Leds {
@@ -390,6 +391,18 @@ impl Board {
                 button_4: Button::new(pins0.p0_25.degrade()),
             },
 
+            spi: SpiPins {
+                sck : pins1.p1_15,
+                mosi: pins1.p1_13,
+                miso: pins1.p1_14,
+                cs  : pins1.p1_12,
+            },
+
+            i2c: I2cPins {
+                scl: pins0.p0_27,
+                sda: pins0.p0_26,
+            },
+
             nfc: NFC {
                 nfc_1: pins0.p0_09,
                 nfc_2: pins0.p0_10,
@@ -482,8 +495,6 @@ pub struct Pins {
        _RESET: p0::P0_18<Input<Floating>>,
     pub P0_22: p0::P0_22<Input<Floating>>,
     pub P0_23: p0::P0_23<Input<Floating>>,
-    pub P0_26: p0::P0_26<Input<Floating>>,
-    pub P0_27: p0::P0_27<Input<Floating>>,
     pub P0_28: p0::P0_28<Input<Floating>>,
     pub P0_29: p0::P0_29<Input<Floating>>,
     pub P0_30: p0::P0_30<Input<Floating>>,
@@ -500,10 +511,6 @@ pub struct Pins {
     pub P1_09: p1::P1_09<Input<Floating>>,
     pub P1_10: p1::P1_10<Input<Floating>>,
     pub P1_11: p1::P1_11<Input<Floating>>,
-    pub P1_12: p1::P1_12<Input<Floating>>,
-    pub P1_13: p1::P1_13<Input<Floating>>,
-    pub P1_14: p1::P1_14<Input<Floating>>,
-    pub P1_15: p1::P1_15<Input<Floating>>,
 }
 
 
@@ -565,6 +572,55 @@ impl Button {
     }
 }
 
+/// The SPI pins of the Arduino header on the nRF52840-DK board
+pub struct SpiPins {
+    /// nRF52840-DK: D13, nRF52: P1.15
+    pub sck: p1::P1_15<Input<Floating>>,
+
+    /// nRF52840-DK: D11, nRF52: P1.13
+    pub mosi: p1::P1_13<Input<Floating>>,
+
+    /// nRF52840-DK: D12, nRF52: P1.14
+    pub miso: p1::P1_14<Input<Floating>>,
+
+    /// nRF52840-DK: D10, nRF52: P1.12
+    pub cs: p1::P1_12<Input<Floating>>,
+}
+
+impl SpiPins {
+    /// Configure the pins for `Spim::new`, along with the chip select pin,
+    /// which is not selected
+    pub fn into_spim_pins(self) -> (spim::Pins, Pin<Output<PushPull>>) {
+        let pins = spim::Pins {
+            sck : self.sck.into_push_pull_output(Level::Low).degrade(),
+            mosi: Some(self.mosi.into_push_pull_output(Level::Low).degrade()),
+            miso: Some(self.miso.into_floating_input().degrade()),
+        };
+        let cs = self.cs.into_push_pull_output(Level::High).degrade();
+
+        (pins, cs)
+    }
+}
+
+/// The I2C pins of the Arduino header on the nRF52840-DK board
+pub struct I2cPins {
+    /// nRF52840-DK: SCL, nRF52: P0.27
+    pub scl: p0::P0_27<Input<Floating>>,
+
+    /// nRF52840-DK: SDA, nRF52: P0.26
+    pub sda: p0::P0_26<Input<Floating>>,
+}
+
+impl I2cPins {
+    /// Configure the pins for `Twim::new`
+    pub fn into_twim_pins(self) -> twim::Pins {
+        twim::Pins {
+            scl: self.scl.degrade(),
+            sda: self.sda.degrade(),
+        }
+    }
+}
+
 /// The LEDs on the nRF52840-DK board
 pub struct NFC {
     /// nRF52840-DK: LED1, nRF52: P0.30
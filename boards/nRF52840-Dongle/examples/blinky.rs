@@ -0,0 +1,45 @@
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use nb::block;
+
+#[allow(unused_imports)]
+use panic_halt;
+
+use nrf52840_dongle_bsp::{
+    hal::{
+        prelude::*,
+        timer::{self, Timer},
+    },
+    Board,
+};
+
+
+#[entry]
+fn main() -> ! {
+    let mut nrf52 = Board::take().unwrap();
+
+    let mut timer = Timer::new(nrf52.TIMER0);
+
+    // Flash the green led, or the red one while the button is pressed
+    loop {
+        if nrf52.button.is_pressed() {
+            nrf52.leds.led_2_r.enable();
+        } else {
+            nrf52.leds.led_1.enable();
+        }
+        delay(&mut timer, 250_000); // 250ms
+        nrf52.leds.led_1.disable();
+        nrf52.leds.led_2_r.disable();
+        delay(&mut timer, 1_000_000); // 1s
+    }
+}
+
+fn delay<T>(timer: &mut Timer<T>, cycles: u32)
+where
+    T: timer::Instance,
+{
+    timer.start(cycles);
+    let _ = block!(timer.wait());
+}
@@ -0,0 +1,519 @@
+//! Board support crate for the Nordic nRF52840 Dongle
+//! https://www.nordicsemi.com/Software-and-Tools/Development-Kits/nRF52840-Dongle
+//!
+//! The dongle has no debugger, so there is no UART over USB, and no headers
+//! for SPI or I2C: all free pins are on the castellated edge.
+#![no_std]
+
+pub use cortex_m;
+pub use cortex_m_rt;
+pub use embedded_hal;
+pub use nrf52840_hal as hal;
+
+/// Exports traits that are usually needed when using this crate
+pub mod prelude {
+    pub use nrf52840_hal::prelude::*;
+}
+
+use nrf52840_hal::{
+    prelude::*,
+    gpio::{
+        p0,
+        p1,
+        Pin,
+        Floating,
+        Input,
+        Output,
+        PushPull,
+        PullUp,
+        Level,
+    },
+    nrf52840_pac::{
+        self as nrf52,
+        CorePeripherals,
+        Peripherals,
+    },
+};
+
+/// Provides access to all features of the nRF52840 Dongle
+#[allow(non_snake_case)]
+pub struct Board {
+    /// The nRF52's pins which are available on the edge of the dongle
+    pub pins: Pins,
+
+    /// The LEDs on the nRF52840 Dongle
+    pub leds: Leds,
+
+    /// The user button on the nRF52840 Dongle
+    pub button: Button,
+
+    /// Core peripheral: Cache and branch predictor maintenance operations
+    pub CBP: nrf52::CBP,
+
+    /// Core peripheral: CPUID
+    pub CPUID: nrf52::CPUID,
+
+    /// Core peripheral: Debug Control Block
+    pub DCB: nrf52::DCB,
+
+    /// Core peripheral: Data Watchpoint and Trace unit
+    pub DWT: nrf52::DWT,
+
+    /// Core peripheral: Flash Patch and Breakpoint unit
+    pub FPB: nrf52::FPB,
+
+    /// Core peripheral: Floating Point Unit
+    pub FPU: nrf52::FPU,
+
+    /// Core peripheral: Instrumentation Trace Macrocell
+    pub ITM: nrf52::ITM,
+
+    /// Core peripheral: Memory Protection Unit
+    pub MPU: nrf52::MPU,
+
+    /// Core peripheral: Nested Vector Interrupt Controller
+    pub NVIC: nrf52::NVIC,
+
+    /// Core peripheral: System Control Block
+    pub SCB: nrf52::SCB,
+
+    /// Core peripheral: SysTick Timer
+    pub SYST: nrf52::SYST,
+
+    /// Core peripheral: Trace Port Interface Unit
+    pub TPIU: nrf52::TPIU,
+
+    /// nRF52 peripheral: FICR
+    pub FICR: nrf52::FICR,
+
+    /// nRF52 peripheral: UICR
+    pub UICR: nrf52::UICR,
+
+    /// nRF52 peripheral: CLOCK
+    pub CLOCK: nrf52::CLOCK,
+
+    /// nRF52 peripheral: POWER
+    pub POWER: nrf52::POWER,
+
+    /// nRF52 peripheral: RADIO
+    pub RADIO: nrf52::RADIO,
+
+    /// nRF52 peripheral: UART0
+    pub UART0: nrf52::UART0,
+
+    /// nRF52 peripheral: UARTE0
+    pub UARTE0: nrf52::UARTE0,
+
+    /// nRF52 peripheral: SPI0
+    pub SPI0: nrf52::SPI0,
+
+    /// nRF52 peripheral: SPIM0
+    pub SPIM0: nrf52::SPIM0,
+
+    /// nRF52 peripheral: SPIS0
+    pub SPIS0: nrf52::SPIS0,
+
+    /// nRF52 peripheral: TWI0
+    pub TWI0: nrf52::TWI0,
+
+    /// nRF52 peripheral: TWIM0
+    pub TWIM0: nrf52::TWIM0,
+
+    /// nRF52 peripheral: TWIS0
+    pub TWIS0: nrf52::TWIS0,
+
+    /// nRF52 peripheral: SPI1
+    pub SPI1: nrf52::SPI1,
+
+    /// nRF52 peripheral: SPIM1
+    pub SPIM1: nrf52::SPIM1,
+
+    /// nRF52 peripheral: SPIS1
+    pub SPIS1: nrf52::SPIS1,
+
+    /// nRF52 peripheral: TWI1
+    pub TWI1: nrf52::TWI1,
+
+    /// nRF52 peripheral: TWIM1
+    pub TWIM1: nrf52::TWIM1,
+
+    /// nRF52 peripheral: TWIS1
+    pub TWIS1: nrf52::TWIS1,
+
+    /// nRF52 peripheral: NFCT
+    pub NFCT: nrf52::NFCT,
+
+    /// nRF52 peripheral: GPIOTE
+    pub GPIOTE: nrf52::GPIOTE,
+
+    /// nRF52 peripheral: SAADC
+    pub SAADC: nrf52::SAADC,
+
+    /// nRF52 peripheral: TIMER0
+    pub TIMER0: nrf52::TIMER0,
+
+    /// nRF52 peripheral: TIMER1
+    pub TIMER1: nrf52::TIMER1,
+
+    /// nRF52 peripheral: TIMER2
+    pub TIMER2: nrf52::TIMER2,
+
+    /// nRF52 peripheral: RTC0
+    pub RTC0: nrf52::RTC0,
+
+    /// nRF52 peripheral: TEMP
+    pub TEMP: nrf52::TEMP,
+
+    /// nRF52 peripheral: RNG
+    pub RNG: nrf52::RNG,
+
+    /// nRF52 peripheral: ECB
+    pub ECB: nrf52::ECB,
+
+    /// nRF52 peripheral: AAR
+    pub AAR: nrf52::AAR,
+
+    /// nRF52 peripheral: CCM
+    pub CCM: nrf52::CCM,
+
+    /// nRF52 peripheral: WDT
+    pub WDT: nrf52::WDT,
+
+    /// nRF52 peripheral: RTC1
+    pub RTC1: nrf52::RTC1,
+
+    /// nRF52 peripheral: QDEC
+    pub QDEC: nrf52::QDEC,
+
+    /// nRF52 peripheral: COMP
+    pub COMP: nrf52::COMP,
+
+    /// nRF52 peripheral: LPCOMP
+    pub LPCOMP: nrf52::LPCOMP,
+
+    /// nRF52 peripheral: EGU0
+    pub EGU0: nrf52::EGU0,
+
+    /// nRF52 peripheral: SWI0
+    pub SWI0: nrf52::SWI0,
+
+    /// nRF52 peripheral: EGU1
+    pub EGU1: nrf52::EGU1,
+
+    /// nRF52 peripheral: SWI1
+    pub SWI1: nrf52::SWI1,
+
+    /// nRF52 peripheral: EGU2
+    pub EGU2: nrf52::EGU2,
+
+    /// nRF52 peripheral: SWI2
+    pub SWI2: nrf52::SWI2,
+
+    /// nRF52 peripheral: EGU3
+    pub EGU3: nrf52::EGU3,
+
+    /// nRF52 peripheral: SWI3
+    pub SWI3: nrf52::SWI3,
+
+    /// nRF52 peripheral: EGU4
+    pub EGU4: nrf52::EGU4,
+
+    /// nRF52 peripheral: SWI4
+    pub SWI4: nrf52::SWI4,
+
+    /// nRF52 peripheral: EGU5
+    pub EGU5: nrf52::EGU5,
+
+    /// nRF52 peripheral: SWI5
+    pub SWI5: nrf52::SWI5,
+
+    /// nRF52 peripheral: TIMER3
+    pub TIMER3: nrf52::TIMER3,
+
+    /// nRF52 peripheral: TIMER4
+    pub TIMER4: nrf52::TIMER4,
+
+    /// nRF52 peripheral: PWM0
+    pub PWM0: nrf52::PWM0,
+
+    /// nRF52 peripheral: PDM
+    pub PDM: nrf52::PDM,
+
+    /// nRF52 peripheral: ACL
+    pub ACL: nrf52::ACL,
+
+    /// nRF52 peripheral: NVMC
+    pub NVMC: nrf52::NVMC,
+
+    /// nRF52 peripheral: PPI
+    pub PPI: nrf52::PPI,
+
+    /// nRF52 peripheral: MWU
+    pub MWU: nrf52::MWU,
+
+    /// nRF52 peripheral: PWM1
+    pub PWM1: nrf52::PWM1,
+
+    /// nRF52 peripheral: PWM2
+    pub PWM2: nrf52::PWM2,
+
+    /// nRF52 peripheral: SPI2
+    pub SPI2: nrf52::SPI2,
+
+    /// nRF52 peripheral: SPIM2
+    pub SPIM2: nrf52::SPIM2,
+
+    /// nRF52 peripheral: SPIS2
+    pub SPIS2: nrf52::SPIS2,
+
+    /// nRF52 peripheral: RTC2
+    pub RTC2: nrf52::RTC2,
+
+    /// nRF52 peripheral: I2S
+    pub I2S: nrf52::I2S,
+
+    /// nRF52 peripheral: USBD
+    pub USBD: nrf52::USBD,
+
+    /// nRF52 peripheral: UARTE1
+    pub UARTE1: nrf52::UARTE1,
+
+    /// nRF52 peripheral: QSPI
+    pub QSPI: nrf52::QSPI,
+
+    /// nRF52 peripheral: PWM3
+    pub PWM3: nrf52::PWM3,
+
+    /// nRF52 peripheral: SPIM3
+    pub SPIM3: nrf52::SPIM3,
+
+    /// nRF52 peripheral: CC_HOST_RGF
+    pub CC_HOST_RGF: nrf52::CC_HOST_RGF,
+
+    /// nRF52 peripheral: CRYPTOCELL
+    pub CRYPTOCELL: nrf52::CRYPTOCELL,
+}
+
+impl Board {
+    /// Take the peripherals safely
+    ///
+    /// This method will return an instance of `Board` the first time it is
+    /// called. It will return only `None` on subsequent calls.
+    pub fn take() -> Option<Self> {
+        Some(Self::new(
+            CorePeripherals::take()?,
+            Peripherals::take()?,
+        ))
+    }
+
+    /// Steal the peripherals
+    ///
+    /// This method produces an instance of `Board`, regardless of whether
+    /// another instance was create previously.
+    ///
+    /// # Safety
+    ///
+    /// This method can be used to create multiple instances of `Board`. Those
+    /// instances can interfere with each other, causing all kinds of unexpected
+    /// behavior and circumventing safety guarantees in many ways.
+    ///
+    /// Always use `Board::take`, unless you really know what you're doing.
+    pub unsafe fn steal() -> Self {
+        Self::new(
+            CorePeripherals::steal(),
+            Peripherals::steal(),
+        )
+    }
+
+    fn new(cp: CorePeripherals, p: Peripherals) -> Self {
+        let pins0 = p0::Parts::new(p.P0);
+        let pins1 = p1::Parts::new(p.P1);
+
+        Board {
+            pins: Pins {
+                _RESET: pins0.p0_18,
+                P0_02 : pins0.p0_02,
+                P0_09 : pins0.p0_09,
+                P0_10 : pins0.p0_10,
+                P0_13 : pins0.p0_13,
+                P0_15 : pins0.p0_15,
+                P0_17 : pins0.p0_17,
+                P0_20 : pins0.p0_20,
+                P0_22 : pins0.p0_22,
+                P0_24 : pins0.p0_24,
+                P0_29 : pins0.p0_29,
+                P0_31 : pins0.p0_31,
+                P1_00 : pins1.p1_00,
+                P1_10 : pins1.p1_10,
+                P1_13 : pins1.p1_13,
+                P1_15 : pins1.p1_15,
+            },
+
+            leds: Leds {
+                led_1  : Led::new(pins0.p0_06.degrade()),
+                led_2_r: Led::new(pins0.p0_08.degrade()),
+                led_2_g: Led::new(pins1.p1_09.degrade()),
+                led_2_b: Led::new(pins0.p0_12.degrade()),
+            },
+
+            button: Button::new(pins1.p1_06.degrade()),
+
+            // Core peripherals
+            CBP  : cp.CBP,
+            CPUID: cp.CPUID,
+            DCB  : cp.DCB,
+            DWT  : cp.DWT,
+            FPB  : cp.FPB,
+            FPU  : cp.FPU,
+            ITM  : cp.ITM,
+            MPU  : cp.MPU,
+            NVIC : cp.NVIC,
+            SCB  : cp.SCB,
+            SYST : cp.SYST,
+            TPIU : cp.TPIU,
+
+            // nRF52 peripherals
+            FICR       : p.FICR,
+            UICR       : p.UICR,
+            CLOCK      : p.CLOCK,
+            POWER      : p.POWER,
+            RADIO      : p.RADIO,
+            UART0      : p.UART0,
+            UARTE0     : p.UARTE0,
+            SPI0       : p.SPI0,
+            SPIM0      : p.SPIM0,
+            SPIS0      : p.SPIS0,
+            TWI0       : p.TWI0,
+            TWIM0      : p.TWIM0,
+            TWIS0      : p.TWIS0,
+            SPI1       : p.SPI1,
+            SPIM1      : p.SPIM1,
+            SPIS1      : p.SPIS1,
+            TWI1       : p.TWI1,
+            TWIM1      : p.TWIM1,
+            TWIS1      : p.TWIS1,
+            NFCT       : p.NFCT,
+            GPIOTE     : p.GPIOTE,
+            SAADC      : p.SAADC,
+            TIMER0     : p.TIMER0,
+            TIMER1     : p.TIMER1,
+            TIMER2     : p.TIMER2,
+            RTC0       : p.RTC0,
+            TEMP       : p.TEMP,
+            RNG        : p.RNG,
+            ECB        : p.ECB,
+            AAR        : p.AAR,
+            CCM        : p.CCM,
+            WDT        : p.WDT,
+            RTC1       : p.RTC1,
+            QDEC       : p.QDEC,
+            COMP       : p.COMP,
+            LPCOMP     : p.LPCOMP,
+            EGU0       : p.EGU0,
+            SWI0       : p.SWI0,
+            EGU1       : p.EGU1,
+            SWI1       : p.SWI1,
+            EGU2       : p.EGU2,
+            SWI2       : p.SWI2,
+            EGU3       : p.EGU3,
+            SWI3       : p.SWI3,
+            EGU4       : p.EGU4,
+            SWI4       : p.SWI4,
+            EGU5       : p.EGU5,
+            SWI5       : p.SWI5,
+            TIMER3     : p.TIMER3,
+            TIMER4     : p.TIMER4,
+            PWM0       : p.PWM0,
+            PDM        : p.PDM,
+            ACL        : p.ACL,
+            NVMC       : p.NVMC,
+            PPI        : p.PPI,
+            MWU        : p.MWU,
+            PWM1       : p.PWM1,
+            PWM2       : p.PWM2,
+            SPI2       : p.SPI2,
+            SPIM2      : p.SPIM2,
+            SPIS2      : p.SPIS2,
+            RTC2       : p.RTC2,
+            I2S        : p.I2S,
+            USBD       : p.USBD,
+            UARTE1     : p.UARTE1,
+            QSPI       : p.QSPI,
+            PWM3       : p.PWM3,
+            SPIM3      : p.SPIM3,
+            CC_HOST_RGF: p.CC_HOST_RGF,
+            CRYPTOCELL : p.CRYPTOCELL,
+        }
+    }
+}
+
+
+/// The nRF52 pins that are available on the nRF52840 Dongle
+#[allow(non_snake_case)]
+pub struct Pins {
+       _RESET: p0::P0_18<Input<Floating>>,
+    pub P0_02: p0::P0_02<Input<Floating>>,
+    pub P0_09: p0::P0_09<Input<Floating>>,
+    pub P0_10: p0::P0_10<Input<Floating>>,
+    pub P0_13: p0::P0_13<Input<Floating>>,
+    pub P0_15: p0::P0_15<Input<Floating>>,
+    pub P0_17: p0::P0_17<Input<Floating>>,
+    pub P0_20: p0::P0_20<Input<Floating>>,
+    pub P0_22: p0::P0_22<Input<Floating>>,
+    pub P0_24: p0::P0_24<Input<Floating>>,
+    pub P0_29: p0::P0_29<Input<Floating>>,
+    pub P0_31: p0::P0_31<Input<Floating>>,
+    pub P1_00: p1::P1_00<Input<Floating>>,
+    pub P1_10: p1::P1_10<Input<Floating>>,
+    pub P1_13: p1::P1_13<Input<Floating>>,
+    pub P1_15: p1::P1_15<Input<Floating>>,
+}
+
+
+/// The LEDs on the nRF52840 Dongle
+pub struct Leds {
+    /// nRF52840 Dongle: LD1 (green), nRF52: P0.06
+    pub led_1: Led,
+
+    /// nRF52840 Dongle: LD2 (red), nRF52: P0.08
+    pub led_2_r: Led,
+
+    /// nRF52840 Dongle: LD2 (green), nRF52: P1.09
+    pub led_2_g: Led,
+
+    /// nRF52840 Dongle: LD2 (blue), nRF52: P0.12
+    pub led_2_b: Led,
+}
+
+/// An LED on the nRF52840 Dongle
+pub struct Led(Pin<Output<PushPull>>);
+
+impl Led {
+    fn new<Mode>(pin: Pin<Mode>) -> Self {
+        Led(pin.into_push_pull_output(Level::High))
+    }
+
+    /// Enable the LED
+    pub fn enable(&mut self) {
+        self.0.set_low()
+    }
+
+    /// Disable the LED
+    pub fn disable(&mut self) {
+        self.0.set_high()
+    }
+}
+
+/// The user button on the nRF52840 Dongle, SW1 on P1.06
+pub struct Button(Pin<Input<PullUp>>);
+
+impl Button {
+    fn new<Mode>(pin: Pin<Mode>) -> Self {
+        Button(pin.into_pullup_input())
+    }
+
+    /// Is the button pressed?
+    pub fn is_pressed(&self) -> bool {
+        self.0.is_low()
+    }
+}
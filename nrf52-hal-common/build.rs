@@ -0,0 +1,52 @@
+//! Emit the peripherals of the selected chip as cfg flags
+//!
+//! The drivers gate their instances and the optional peripherals on these
+//! flags, instead of listing chips, so nothing can refer to hardware the chip
+//! doesn't have. Adding a chip only needs a new line in `CHIPS`.
+use std::env;
+
+/// Peripheral instances and optional peripherals beyond those all chips have
+const PERIPHERALS: &[&str] = &[
+    "acl", "bprot", "cc310", "i2s", "lpcomp", "nfct", "qspi", "rtc2", "spim1", "spim2", "spim3",
+    "timer3", "timer4", "twim1", "uarte1", "usbd",
+];
+
+/// Width of the EasyDMA MAXCNT registers, and peripherals of each chip
+const CHIPS: &[(&str, &str, &[&str])] = &[
+    ("52810", "10", &["bprot"]),
+    (
+        "52832",
+        "8",
+        &[
+            "bprot", "i2s", "lpcomp", "nfct", "rtc2", "spim1", "spim2", "timer3", "timer4", "twim1",
+        ],
+    ),
+    (
+        "52840",
+        "16",
+        &[
+            "acl", "cc310", "i2s", "lpcomp", "nfct", "qspi", "rtc2", "spim1", "spim2", "spim3",
+            "timer3", "timer4", "twim1", "uarte1", "usbd",
+        ],
+    ),
+];
+
+fn main() {
+    for peripheral in PERIPHERALS {
+        println!("cargo:rustc-check-cfg=cfg({})", peripheral);
+    }
+    println!("cargo:rustc-check-cfg=cfg(easydma_maxcnt_bits, values(\"8\", \"10\", \"16\"))");
+
+    for (chip, maxcnt_bits, peripherals) in CHIPS {
+        if env::var_os(format!("CARGO_FEATURE_{}", chip)).is_none() {
+            continue;
+        }
+
+        println!("cargo:rustc-cfg=easydma_maxcnt_bits=\"{}\"", maxcnt_bits);
+        for peripheral in *peripherals {
+            println!("cargo:rustc-cfg={}", peripheral);
+        }
+    }
+
+    println!("cargo:rerun-if-changed=build.rs");
+}
@@ -0,0 +1,135 @@
+//! HAL interface to the Accelerated Address Resolver (AAR) peripheral
+//!
+//! The AAR resolves resolvable private addresses, as used by BLE devices with
+//! privacy enabled, by checking them against a list of identity resolving
+//! keys (IRKs).
+//!
+//! The AAR shares its registers with the CCM, so only one of them can be
+//! used at a time.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 30
+//! - nrf52840: Section 6.1
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+use crate::slice_in_ram;
+use crate::target::AAR;
+
+/// Largest number of IRKs that can be checked at once
+pub const MAX_IRKS: usize = 16;
+
+/// Size of an address
+pub const ADDRESS_SIZE: usize = 6;
+
+/// Size of the packet header preceding the address in memory
+const HEADER_SIZE: usize = 3;
+
+/// Interface to the AAR peripheral
+pub struct Aar {
+    periph: AAR,
+    packet: [u8; HEADER_SIZE + ADDRESS_SIZE],
+    scratch: [u8; 3],
+}
+
+impl Aar {
+    /// Take ownership of the AAR peripheral
+    pub fn new(aar: AAR) -> Self {
+        aar.enable.write(|w| w.enable().enabled());
+
+        Aar {
+            periph: aar,
+            packet: [0; HEADER_SIZE + ADDRESS_SIZE],
+            scratch: [0; 3],
+        }
+    }
+
+    /// Resolve `address` against the list of IRKs, blocking until done
+    ///
+    /// `address` is stored least significant byte first, as received over
+    /// the air. Returns the index of the IRK that resolved the address, or
+    /// `None` if no IRK did. Addresses that are not resolvable private
+    /// addresses return `Error::NotResolvable`.
+    ///
+    /// The IRKs must reside in data RAM, as they are read by EasyDMA.
+    pub fn resolve(
+        &mut self,
+        address: &[u8; ADDRESS_SIZE],
+        irks: &[[u8; 16]],
+    ) -> Result<Option<usize>, Error> {
+        if irks.len() > MAX_IRKS {
+            return Err(Error::TooManyIrks);
+        }
+        if !irks.iter().all(|irk| slice_in_ram(irk)) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+        // The two most significant bits of a resolvable private address are
+        // 0b01
+        if address[ADDRESS_SIZE - 1] >> 6 != 0b01 {
+            return Err(Error::NotResolvable);
+        }
+        if irks.is_empty() {
+            return Ok(None);
+        }
+
+        self.packet[HEADER_SIZE..].copy_from_slice(address);
+
+        self.periph
+            .nirk
+            .write(|w| unsafe { w.nirk().bits(irks.len() as u8) });
+        self.periph
+            .irkptr
+            .write(|w| unsafe { w.bits(irks.as_ptr() as u32) });
+        self.periph
+            .addrptr
+            .write(|w| unsafe { w.bits(self.packet.as_ptr() as u32) });
+        let scratch = self.scratch.as_mut_ptr() as u32;
+        self.periph
+            .scratchptr
+            .write(|w| unsafe { w.bits(scratch) });
+
+        self.periph.events_end.reset();
+        self.periph.events_resolved.reset();
+        self.periph.events_notresolved.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+
+        self.periph.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        while self.periph.events_end.read().bits() == 0 {}
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        let resolved = self.periph.events_resolved.read().bits() != 0;
+
+        self.periph.events_end.reset();
+        self.periph.events_resolved.reset();
+        self.periph.events_notresolved.reset();
+
+        if resolved {
+            Ok(Some(usize::from(self.periph.status.read().status().bits())))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Return the underlying AAR peripheral
+    pub fn free(self) -> AAR {
+        self.periph.enable.write(|w| w.enable().disabled());
+        self.periph
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    TooManyIrks,
+    NotResolvable,
+    DMABufferNotInDataMemory,
+}
@@ -0,0 +1,89 @@
+//! HAL interface to the Access Control Lists (ACL) peripheral
+//!
+//! The ACL protects up to eight regions of flash against write and erase, and
+//! optionally against reads. Protection can only be removed by a reset.
+//!
+//! See product specification:
+//!
+//! - nrf52840: Section 4.1
+use core::ops::Range;
+
+use crate::target::ACL;
+
+/// Regions must be aligned to a flash page
+pub const PAGE_SIZE: u32 = 4096;
+
+/// Number of regions that can be protected
+pub const REGION_COUNT: usize = 8;
+
+/// Size of the flash
+const FLASH_SIZE: u32 = 1024 * 1024;
+
+/// Interface to the ACL peripheral
+pub struct Acl {
+    periph: ACL,
+    next: usize,
+}
+
+impl Acl {
+    /// Take ownership of the ACL peripheral
+    pub fn new(acl: ACL) -> Self {
+        // Find the first region that has not been configured since reset
+        let next = acl
+            .acl
+            .iter()
+            .position(|r| r.size.read().bits() == 0)
+            .unwrap_or(REGION_COUNT);
+
+        Acl { periph: acl, next }
+    }
+
+    /// Protect the flash in `range` until the next reset
+    ///
+    /// `range` must start and end at a page boundary.
+    pub fn protect(&mut self, range: Range<u32>, permission: Permission) -> Result<(), Error> {
+        if range.start % PAGE_SIZE != 0 || range.end % PAGE_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+        if range.start >= range.end || range.end > FLASH_SIZE {
+            return Err(Error::OutOfBounds);
+        }
+        if self.next >= REGION_COUNT {
+            return Err(Error::NoFreeRegion);
+        }
+
+        let region = &self.periph.acl[self.next];
+        region.addr.write(|w| unsafe { w.bits(range.start) });
+        region.perm.write(|w| match permission {
+            Permission::ReadOnly => w.write().disable(),
+            Permission::NoAccess => w.write().disable().read().disable(),
+        });
+        // The region is only active once the size is set
+        region.size.write(|w| unsafe { w.bits(range.end - range.start) });
+        self.next += 1;
+
+        Ok(())
+    }
+
+    /// Return the underlying ACL peripheral
+    pub fn free(self) -> ACL {
+        self.periph
+    }
+}
+
+/// Access allowed to a protected region
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Permission {
+    /// The region can be read, but not written or erased
+    ReadOnly,
+    /// The region can not be read, written or erased
+    NoAccess,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    NotAligned,
+    OutOfBounds,
+    NoFreeRegion,
+}
@@ -0,0 +1,94 @@
+//! HAL interface to the Block Protection (BPROT) peripheral
+//!
+//! BPROT write-protects blocks of 4 KiB of flash against erase and write,
+//! e.g. to protect a bootloader. Protection can only be removed by a reset.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 12
+use core::ops::Range;
+
+use crate::target::BPROT;
+
+/// Size of a protection block
+pub const BLOCK_SIZE: u32 = 4096;
+
+/// Number of protection blocks
+#[cfg(feature = "52810")]
+pub const BLOCK_COUNT: u32 = 48;
+#[cfg(feature = "52832")]
+pub const BLOCK_COUNT: u32 = 128;
+
+/// Interface to the BPROT peripheral
+pub struct Bprot(BPROT);
+
+impl Bprot {
+    /// Take ownership of the BPROT peripheral
+    pub fn new(bprot: BPROT) -> Self {
+        Bprot(bprot)
+    }
+
+    /// Write-protect the flash in `range` until the next reset
+    ///
+    /// `range` must start and end at a block boundary.
+    pub fn protect(&mut self, range: Range<u32>) -> Result<(), Error> {
+        if range.start % BLOCK_SIZE != 0 || range.end % BLOCK_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+        if range.start > range.end || range.end > BLOCK_COUNT * BLOCK_SIZE {
+            return Err(Error::OutOfBounds);
+        }
+
+        let mut masks = [0u32; 4];
+        for block in range.start / BLOCK_SIZE..range.end / BLOCK_SIZE {
+            masks[(block / 32) as usize] |= 1 << (block % 32);
+        }
+
+        // Writing 0 has no effect, so other blocks stay protected
+        self.0.config0.write(|w| unsafe { w.bits(masks[0]) });
+        self.0.config1.write(|w| unsafe { w.bits(masks[1]) });
+        #[cfg(feature = "52832")]
+        {
+            self.0.config2.write(|w| unsafe { w.bits(masks[2]) });
+            self.0.config3.write(|w| unsafe { w.bits(masks[3]) });
+        }
+
+        Ok(())
+    }
+
+    /// Is the block containing `address` protected?
+    pub fn is_protected(&self, address: u32) -> bool {
+        let block = address / BLOCK_SIZE;
+        let config = match block / 32 {
+            0 => self.0.config0.read().bits(),
+            1 => self.0.config1.read().bits(),
+            #[cfg(feature = "52832")]
+            2 => self.0.config2.read().bits(),
+            #[cfg(feature = "52832")]
+            3 => self.0.config3.read().bits(),
+            _ => return false,
+        };
+        config & (1 << (block % 32)) != 0
+    }
+
+    /// Disable the protection while a debugger is attached
+    pub fn set_disable_in_debug(&mut self, disable: bool) {
+        if disable {
+            self.0.disableindebug.write(|w| w.disableindebug().disabled());
+        } else {
+            self.0.disableindebug.write(|w| w.disableindebug().enabled());
+        }
+    }
+
+    /// Return the underlying BPROT peripheral
+    pub fn free(self) -> BPROT {
+        self.0
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    NotAligned,
+    OutOfBounds,
+}
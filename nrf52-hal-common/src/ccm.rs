@@ -0,0 +1,343 @@
+//! HAL interface to the AES CCM mode encryption (CCM) peripheral
+//!
+//! The CCM encrypts and decrypts packets in the format of the radio, with a
+//! 4 byte message integrity check (MIC), as used by encrypted BLE links.
+//!
+//! Packets consist of a 3 byte header and the payload. The first header byte
+//! (S0) is authenticated, the second is the length of the payload, and the
+//! third is reserved.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 29
+//! - nrf52840: Section 6.3
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+use crate::slice_in_ram;
+use crate::target::CCM;
+
+/// Size of the packet header
+pub const HEADER_SIZE: usize = 3;
+
+/// Size of the message integrity check appended to encrypted packets
+pub const MIC_SIZE: usize = 4;
+
+/// Largest payload in the default length mode
+pub const DEFAULT_MAX_PAYLOAD_LEN: u8 = 27;
+
+/// Largest payload in the extended length mode
+pub const EXTENDED_MAX_PAYLOAD_LEN: u8 = 251;
+
+/// Smallest scratch area for any packet length
+const MIN_SCRATCH_SIZE: usize = 43;
+
+/// Direction of a packet, which is part of the nonce
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    SlaveToMaster = 0,
+    MasterToSlave = 1,
+}
+
+/// Key, packet counter and IV of a connection
+///
+/// This is the data structure CNFPTR points to, so it must stay in place
+/// while the CCM uses it.
+#[repr(C)]
+pub struct CcmData {
+    key: [u8; 16],
+    packet_counter: [u8; 8],
+    direction: u8,
+    iv: [u8; 8],
+}
+
+impl CcmData {
+    /// Create the CCM data for a connection
+    ///
+    /// The key is stored most significant byte first, and the packet counter
+    /// starts at 0.
+    pub fn new(key: [u8; 16], iv: [u8; 8]) -> Self {
+        CcmData {
+            key,
+            packet_counter: [0; 8],
+            direction: Direction::SlaveToMaster as u8,
+            iv,
+        }
+    }
+
+    /// Set the 39 bit packet counter
+    pub fn set_packet_counter(&mut self, counter: u64) {
+        self.packet_counter = (counter & 0x7F_FFFF_FFFF).to_le_bytes();
+    }
+
+    /// Return the packet counter
+    pub fn packet_counter(&self) -> u64 {
+        u64::from_le_bytes(self.packet_counter)
+    }
+
+    /// Increment the packet counter, to be done after every packet
+    pub fn increment_packet_counter(&mut self) {
+        let counter = self.packet_counter();
+        self.set_packet_counter(counter.wrapping_add(1));
+    }
+
+    /// Set the direction of the following packets
+    pub fn set_direction(&mut self, direction: Direction) {
+        self.direction = direction as u8;
+    }
+}
+
+/// Interface to the CCM peripheral
+pub struct Ccm {
+    periph: CCM,
+    max_payload_len: u8,
+}
+
+impl Ccm {
+    /// Take ownership of the CCM peripheral
+    ///
+    /// Packets can have up to `DEFAULT_MAX_PAYLOAD_LEN` bytes of payload,
+    /// which can be raised with `set_max_payload_len`.
+    pub fn new(ccm: CCM) -> Self {
+        ccm.enable.write(|w| w.enable().enabled());
+        ccm.mode.write(|w| w.datarate()._1mbit().length().default());
+
+        Ccm {
+            periph: ccm,
+            max_payload_len: DEFAULT_MAX_PAYLOAD_LEN,
+        }
+    }
+
+    /// Set the largest payload that can be encrypted or decrypted
+    ///
+    /// Payloads above `DEFAULT_MAX_PAYLOAD_LEN` use the extended length mode,
+    /// which needs a larger scratch area.
+    pub fn set_max_payload_len(&mut self, len: u8) -> Result<(), Error> {
+        if len > EXTENDED_MAX_PAYLOAD_LEN {
+            return Err(Error::PayloadTooLong);
+        }
+
+        if len > DEFAULT_MAX_PAYLOAD_LEN {
+            self.periph.mode.modify(|_, w| w.length().extended());
+        } else {
+            self.periph.mode.modify(|_, w| w.length().default());
+        }
+
+        #[cfg(any(feature = "52810", feature = "52840"))]
+        self.periph
+            .maxpacketsize
+            .write(|w| unsafe { w.bits(u32::from(len.max(DEFAULT_MAX_PAYLOAD_LEN))) });
+
+        self.max_payload_len = len;
+        Ok(())
+    }
+
+    /// Return the current maximum payload length
+    pub fn max_payload_len(&self) -> u8 {
+        self.max_payload_len
+    }
+
+    /// Return the size of the scratch area needed for the current maximum
+    /// payload length
+    pub fn scratch_size(&self) -> usize {
+        if self.max_payload_len > DEFAULT_MAX_PAYLOAD_LEN {
+            MIN_SCRATCH_SIZE.max(16 + usize::from(self.max_payload_len))
+        } else {
+            MIN_SCRATCH_SIZE
+        }
+    }
+
+    /// Encrypt `clear_packet` into `cipher_packet`
+    ///
+    /// The encrypted packet is `MIC_SIZE` bytes longer than the clear packet,
+    /// unless the payload is empty. The packet counter of `data` is not
+    /// incremented.
+    pub fn encrypt_packet(
+        &mut self,
+        data: &mut CcmData,
+        clear_packet: &[u8],
+        cipher_packet: &mut [u8],
+        scratch: &mut [u8],
+    ) -> Result<(), Error> {
+        let len = self.check_packet(clear_packet, scratch)?;
+        let mic = if len > 0 { MIC_SIZE } else { 0 };
+        if len + mic > usize::from(EXTENDED_MAX_PAYLOAD_LEN) {
+            return Err(Error::PayloadTooLong);
+        }
+        if cipher_packet.len() < HEADER_SIZE + len + mic {
+            return Err(Error::BufferTooShort);
+        }
+
+        self.periph.mode.modify(|_, w| w.mode().encryption());
+        self.crypt(data, clear_packet, cipher_packet, scratch)
+    }
+
+    /// Decrypt `cipher_packet` into `clear_packet` and check its MIC
+    ///
+    /// The packet counter of `data` is not incremented.
+    pub fn decrypt_packet(
+        &mut self,
+        data: &mut CcmData,
+        cipher_packet: &[u8],
+        clear_packet: &mut [u8],
+        scratch: &mut [u8],
+    ) -> Result<(), Error> {
+        let len = self.check_packet(cipher_packet, scratch)?;
+        if len > 0 && len < MIC_SIZE {
+            return Err(Error::WrongMic);
+        }
+        if clear_packet.len() < HEADER_SIZE + len.saturating_sub(MIC_SIZE) {
+            return Err(Error::BufferTooShort);
+        }
+
+        self.periph.mode.modify(|_, w| w.mode().decryption());
+        self.crypt(data, cipher_packet, clear_packet, scratch)?;
+
+        if len > 0 && self.mic_failed() {
+            return Err(Error::WrongMic);
+        }
+        Ok(())
+    }
+
+    /// Return the underlying CCM peripheral
+    pub fn free(self) -> CCM {
+        self.periph.enable.write(|w| w.enable().disabled());
+        self.periph
+    }
+
+    /// Check an input packet and the scratch area, returning the length of
+    /// the payload
+    pub(crate) fn check_packet(&self, packet: &[u8], scratch: &[u8]) -> Result<usize, Error> {
+        if !slice_in_ram(packet) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+        if packet.len() < HEADER_SIZE {
+            return Err(Error::BufferTooShort);
+        }
+
+        let len = usize::from(packet[1]);
+        if packet.len() < HEADER_SIZE + len {
+            return Err(Error::BufferTooShort);
+        }
+        if len > usize::from(self.max_payload_len) + MIC_SIZE {
+            return Err(Error::PayloadTooLong);
+        }
+        if scratch.len() < self.scratch_size() {
+            return Err(Error::InsufficientScratchArea);
+        }
+
+        Ok(len)
+    }
+
+    fn crypt(
+        &mut self,
+        data: &mut CcmData,
+        input: &[u8],
+        output: &mut [u8],
+        scratch: &mut [u8],
+    ) -> Result<(), Error> {
+        // Start the encryption as soon as the key stream is generated
+        self.prepare(data, input.as_ptr() as u32, output.as_mut_ptr() as u32, scratch);
+        self.periph.shorts.write(|w| w.endksgen_crypt().enabled());
+
+        self.periph.tasks_ksgen.write(|w| unsafe { w.bits(1) });
+
+        self.wait()
+    }
+
+    /// Set up the pointers for an operation, without starting it
+    ///
+    /// Used by the radio to encrypt and decrypt packets on-the-fly, where the
+    /// tasks are triggered through PPI.
+    pub(crate) fn prepare(
+        &mut self,
+        data: &mut CcmData,
+        input: u32,
+        output: u32,
+        scratch: &mut [u8],
+    ) {
+        self.periph
+            .cnfptr
+            .write(|w| unsafe { w.bits(data as *mut _ as u32) });
+        self.periph.inptr.write(|w| unsafe { w.bits(input) });
+        self.periph.outptr.write(|w| unsafe { w.bits(output) });
+        self.periph
+            .scratchptr
+            .write(|w| unsafe { w.bits(scratch.as_mut_ptr() as u32) });
+
+        self.periph.events_endksgen.reset();
+        self.periph.events_endcrypt.reset();
+        self.periph.events_error.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+    }
+
+    /// Wait for the current operation to end
+    pub(crate) fn wait(&mut self) -> Result<(), Error> {
+        let result = loop {
+            if self.periph.events_endcrypt.read().bits() != 0 {
+                break Ok(());
+            }
+            if self.periph.events_error.read().bits() != 0 {
+                break Err(Error::EasyDMAError);
+            }
+        };
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        self.periph.shorts.reset();
+        self.periph.events_endksgen.reset();
+        self.periph.events_endcrypt.reset();
+        self.periph.events_error.reset();
+
+        result
+    }
+
+    /// Select encryption or decryption, and the data rate of the radio for
+    /// on-the-fly operation
+    pub(crate) fn set_mode(&mut self, encrypt: bool, two_mbit: bool) {
+        self.periph.mode.modify(|_, w| {
+            let w = if encrypt {
+                w.mode().encryption()
+            } else {
+                w.mode().decryption()
+            };
+            if two_mbit {
+                w.datarate()._2mbit()
+            } else {
+                w.datarate()._1mbit()
+            }
+        });
+    }
+
+    /// Enable or disable the shortcut starting the operation once the key
+    /// stream has been generated
+    pub(crate) fn set_crypt_on_keystream(&mut self, enabled: bool) {
+        if enabled {
+            self.periph.shorts.write(|w| w.endksgen_crypt().enabled());
+        } else {
+            self.periph.shorts.reset();
+        }
+    }
+
+    /// Did the MIC check of the last decryption fail?
+    pub(crate) fn mic_failed(&self) -> bool {
+        self.periph.micstatus.read().micstatus().is_check_failed()
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    BufferTooShort,
+    PayloadTooLong,
+    InsufficientScratchArea,
+    DMABufferNotInDataMemory,
+    EasyDMAError,
+    WrongMic,
+}
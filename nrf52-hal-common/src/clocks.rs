@@ -1,7 +1,14 @@
 //! Configuration and control of the High and Low Frequency Clock
 //! sources
+//!
+//! Many peripherals depend on the clock configuration: the RADIO requires
+//! the external high frequency crystal, the RTC runs from the low frequency
+//! clock, and UARTE baud rates are only accurate with the crystal running.
+
+use fpa::I30F2;
 
 use crate::target::CLOCK;
+use crate::temp::Temp;
 
 // ZST Type States
 
@@ -11,12 +18,19 @@ pub struct Internal;
 /// External Crystal Oscillator
 pub struct ExternalOscillator;
 
+/// External Crystal Oscillator, which has been started but may not be running
+/// yet
+pub struct ExternalOscillatorStarting;
+
 /// Low Frequency Clock synthesize from High Frequency Clock
 pub struct LfOscSynthesized;
 
 /// Low Frequency Clock Started
 pub struct LfOscStarted;
 
+/// Low Frequency Clock which has been started but may not be running yet
+pub struct LfOscStarting;
+
 /// Low Frequency Clock Stopped
 pub struct LfOscStopped;
 
@@ -63,6 +77,71 @@ impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
         }
     }
 
+    /// Start the external oscillator without waiting for it to run
+    ///
+    /// The crystal takes several hundred microseconds to start, which can be
+    /// used for other initialization. Use `wait_ext_hfosc` or
+    /// `try_ext_hfosc` to continue once it is running, or enable the
+    /// `ClockEvent::HfclkStarted` interrupt.
+    pub fn start_hfxo_nonblocking(self) -> Clocks<ExternalOscillatorStarting, L, LSTAT> {
+        self.periph
+            .events_hfclkstarted
+            .write(|w| unsafe { w.bits(0) });
+        self.periph.tasks_hfclkstart.write(|w| unsafe { w.bits(1) });
+
+        Clocks {
+            hfclk: ExternalOscillatorStarting,
+            lfclk: self.lfclk,
+            lfstat: self.lfstat,
+            periph: self.periph,
+        }
+    }
+
+    /// Enable the interrupt for a given event
+    ///
+    /// The CLOCK peripheral shares the `POWER_CLOCK` interrupt with the POWER
+    /// peripheral, which must be unmasked in the NVIC separately.
+    pub fn enable_interrupt(&mut self, evt: ClockEvent) {
+        match evt {
+            ClockEvent::HfclkStarted => self.periph.intenset.write(|w| w.hfclkstarted().set()),
+            ClockEvent::LfclkStarted => self.periph.intenset.write(|w| w.lfclkstarted().set()),
+            ClockEvent::Done => self.periph.intenset.write(|w| w.done().set()),
+            ClockEvent::Ctto => self.periph.intenset.write(|w| w.ctto().set()),
+        }
+    }
+
+    /// Disable the interrupt for a given event
+    pub fn disable_interrupt(&mut self, evt: ClockEvent) {
+        match evt {
+            ClockEvent::HfclkStarted => self.periph.intenclr.write(|w| w.hfclkstarted().clear()),
+            ClockEvent::LfclkStarted => self.periph.intenclr.write(|w| w.lfclkstarted().clear()),
+            ClockEvent::Done => self.periph.intenclr.write(|w| w.done().clear()),
+            ClockEvent::Ctto => self.periph.intenclr.write(|w| w.ctto().clear()),
+        }
+    }
+
+    /// Obtain the state of a given event, and optionally clear the event if
+    /// it is set
+    pub fn get_event_triggered(&mut self, evt: ClockEvent, clear_on_read: bool) -> bool {
+        let triggered = match evt {
+            ClockEvent::HfclkStarted => self.periph.events_hfclkstarted.read().bits() == 1,
+            ClockEvent::LfclkStarted => self.periph.events_lfclkstarted.read().bits() == 1,
+            ClockEvent::Done => self.periph.events_done.read().bits() == 1,
+            ClockEvent::Ctto => self.periph.events_ctto.read().bits() == 1,
+        };
+
+        if triggered && clear_on_read {
+            match evt {
+                ClockEvent::HfclkStarted => self.periph.events_hfclkstarted.reset(),
+                ClockEvent::LfclkStarted => self.periph.events_lfclkstarted.reset(),
+                ClockEvent::Done => self.periph.events_done.reset(),
+                ClockEvent::Ctto => self.periph.events_ctto.reset(),
+            }
+        }
+
+        triggered
+    }
+
     /// Use the internal oscillator as the high frequency clock source
     pub fn disable_ext_hfosc(self) -> Clocks<Internal, L, LSTAT> {
         self.periph.tasks_hfclkstop.write(|w| unsafe { w.bits(1) });
@@ -74,6 +153,47 @@ impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
         }
     }
 
+    /// Is the high frequency clock currently running from the external crystal?
+    ///
+    /// This reflects the state of the hardware, which may differ from the
+    /// type state while the crystal is starting up, or if another owner of
+    /// the CLOCK peripheral (e.g. a SoftDevice) has requested the crystal.
+    pub fn is_hfclk_xtal_running(&self) -> bool {
+        let stat = self.periph.hfclkstat.read();
+        stat.src().is_xtal() && stat.state().is_running()
+    }
+
+    /// Is the low frequency clock currently running?
+    pub fn is_lfclk_running(&self) -> bool {
+        self.periph.lfclkstat.read().state().is_running()
+    }
+
+    /// Return the raw interface to the underlying CLOCK peripheral
+    ///
+    /// The clock configuration is left untouched.
+    pub fn free(self) -> CLOCK {
+        self.periph
+    }
+
+    /// Start the Low Frequency clock without waiting for it to run
+    ///
+    /// Starting from an external crystal can take up to 0.25 s. Use
+    /// `wait_lfclk` or `try_lfclk` to continue once it is running, or enable
+    /// the `ClockEvent::LfclkStarted` interrupt.
+    pub fn start_lfclk_nonblocking(self) -> Clocks<H, L, LfOscStarting> {
+        self.periph
+            .events_lfclkstarted
+            .write(|w| unsafe { w.bits(0) });
+        self.periph.tasks_lfclkstart.write(|w| unsafe { w.bits(1) });
+
+        Clocks {
+            hfclk: self.hfclk,
+            lfclk: self.lfclk,
+            lfstat: LfOscStarting,
+            periph: self.periph,
+        }
+    }
+
     /// Start the Low Frequency clock
     pub fn start_lfclk(self) -> Clocks<H, L, LfOscStarted> {
         self.periph.tasks_lfclkstart.write(|w| unsafe { w.bits(1) });
@@ -94,6 +214,253 @@ impl<H, L, LSTAT> Clocks<H, L, LSTAT> {
     }
 }
 
+impl Clocks<ExternalOscillator, Internal, LfOscStarted> {
+    /// Calibrate the internal RC oscillator of the low frequency clock
+    ///
+    /// Blocks until the calibration is done, which takes about 16 ms. The
+    /// calibration requires the high frequency clock to run from the external
+    /// crystal.
+    pub fn calibrate_lfrc(&mut self) {
+        self.periph.events_done.write(|w| unsafe { w.bits(0) });
+        self.periph.tasks_cal.write(|w| unsafe { w.bits(1) });
+
+        while self.periph.events_done.read().bits() != 1 {}
+        self.periph.events_done.write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Start the calibration timer
+    ///
+    /// The timer expires after `interval` times 0.25 seconds. `interval` must
+    /// be between 1 and 127. Use `calibration_timer_expired` to check whether
+    /// the timer has expired.
+    pub fn start_calibration_timer(&mut self, interval: u8) -> Result<(), Error> {
+        if interval == 0 || interval > 127 {
+            return Err(Error::CalibrationIntervalOutOfRange);
+        }
+
+        self.periph.ctiv.write(|w| unsafe { w.ctiv().bits(interval) });
+        self.periph.events_ctto.write(|w| unsafe { w.bits(0) });
+        self.periph.tasks_ctstart.write(|w| unsafe { w.bits(1) });
+
+        Ok(())
+    }
+
+    /// Stop the calibration timer
+    pub fn stop_calibration_timer(&mut self) {
+        self.periph.tasks_ctstop.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Has the calibration timer expired?
+    ///
+    /// Clears the event if it is set. The timer has to be restarted using
+    /// `start_calibration_timer` after it has expired.
+    pub fn calibration_timer_expired(&mut self) -> bool {
+        if self.periph.events_ctto.read().bits() == 0 {
+            return false;
+        }
+
+        self.periph.events_ctto.write(|w| unsafe { w.bits(0) });
+        true
+    }
+}
+
+/// Periodic calibration of the internal RC oscillator of the low frequency
+/// clock
+///
+/// The frequency of the RC oscillator drifts with temperature. To stay within
+/// the specified accuracy of 500 ppm, it must be recalibrated every 4 seconds
+/// or whenever the temperature has changed by 0.5 °C.
+///
+/// This uses the calibration timer to check the temperature periodically,
+/// and calibrates the oscillator whenever the temperature has changed by more
+/// than a threshold since the last calibration, or when a maximum number of
+/// intervals have passed without a calibration.
+pub struct LfrcCalibration {
+    interval: u8,
+    max_skip: u8,
+    temp_threshold: i32,
+    skipped: u8,
+    last_temp: Option<I30F2>,
+}
+
+impl LfrcCalibration {
+    /// Configure the calibration
+    ///
+    /// The temperature is checked every `interval` times 0.25 seconds (1 to
+    /// 127). The oscillator is calibrated if the temperature has changed by at
+    /// least `temp_threshold` (in units of 0.25 °C) since the last
+    /// calibration, or if the temperature has been checked `max_skip` times
+    /// without calibrating.
+    ///
+    /// The Nordic recommendation is an interval of 16 (4 s), a threshold of 2
+    /// (0.5 °C) and a `max_skip` of 1.
+    pub fn new(interval: u8, max_skip: u8, temp_threshold: i32) -> Result<Self, Error> {
+        if interval == 0 || interval > 127 {
+            return Err(Error::CalibrationIntervalOutOfRange);
+        }
+
+        Ok(LfrcCalibration {
+            interval,
+            max_skip,
+            temp_threshold,
+            skipped: 0,
+            last_temp: None,
+        })
+    }
+
+    /// Calibrate the oscillator, then start the calibration timer
+    pub fn start(
+        &mut self,
+        clocks: &mut Clocks<ExternalOscillator, Internal, LfOscStarted>,
+        temp: &mut Temp,
+    ) {
+        self.calibrate(clocks, temp);
+        // The interval has been validated in `new`
+        let _ = clocks.start_calibration_timer(self.interval);
+    }
+
+    /// Check whether the calibration timer has expired, and if so, calibrate
+    /// the oscillator if required
+    ///
+    /// This must be called regularly, or from the interrupt handler of the
+    /// CLOCK peripheral. Returns `true` if a calibration has been performed.
+    pub fn poll(
+        &mut self,
+        clocks: &mut Clocks<ExternalOscillator, Internal, LfOscStarted>,
+        temp: &mut Temp,
+    ) -> bool {
+        if !clocks.calibration_timer_expired() {
+            return false;
+        }
+
+        let current = temp.measure();
+        let changed = match self.last_temp {
+            Some(last) => {
+                (current.into_bits() - last.into_bits()).abs() >= self.temp_threshold
+            }
+            None => true,
+        };
+
+        let calibrated = if changed || self.skipped >= self.max_skip {
+            self.calibrate(clocks, temp);
+            true
+        } else {
+            self.skipped += 1;
+            false
+        };
+
+        let _ = clocks.start_calibration_timer(self.interval);
+        calibrated
+    }
+
+    fn calibrate(
+        &mut self,
+        clocks: &mut Clocks<ExternalOscillator, Internal, LfOscStarted>,
+        temp: &mut Temp,
+    ) {
+        clocks.calibrate_lfrc();
+        self.last_temp = Some(temp.measure());
+        self.skipped = 0;
+    }
+}
+
+/// Error types associated with the CLOCK peripheral interface
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    CalibrationIntervalOutOfRange,
+}
+
+impl<L, LSTAT> Clocks<ExternalOscillatorStarting, L, LSTAT> {
+    /// Is the external oscillator running yet?
+    ///
+    /// This reads HFCLKSTAT rather than the HFCLKSTARTED event, which may have
+    /// been cleared by an interrupt handler.
+    pub fn is_hfxo_started(&self) -> bool {
+        self.is_hfclk_xtal_running()
+    }
+
+    /// Wait for the external oscillator to run
+    pub fn wait_ext_hfosc(self) -> Clocks<ExternalOscillator, L, LSTAT> {
+        while !self.is_hfxo_started() {}
+        self.into_ext_hfosc()
+    }
+
+    /// Continue with the external oscillator if it is running, otherwise
+    /// return `self` unchanged
+    pub fn try_ext_hfosc(self) -> Result<Clocks<ExternalOscillator, L, LSTAT>, Self> {
+        if self.is_hfxo_started() {
+            Ok(self.into_ext_hfosc())
+        } else {
+            Err(self)
+        }
+    }
+
+    fn into_ext_hfosc(self) -> Clocks<ExternalOscillator, L, LSTAT> {
+        self.periph
+            .events_hfclkstarted
+            .write(|w| unsafe { w.bits(0) });
+
+        Clocks {
+            hfclk: ExternalOscillator,
+            lfclk: self.lfclk,
+            lfstat: self.lfstat,
+            periph: self.periph,
+        }
+    }
+}
+
+impl<H, L> Clocks<H, L, LfOscStarting> {
+    /// Is the Low Frequency clock running yet?
+    ///
+    /// This reads LFCLKSTAT rather than the LFCLKSTARTED event, which may have
+    /// been cleared by an interrupt handler.
+    pub fn is_lfclk_started(&self) -> bool {
+        self.is_lfclk_running()
+    }
+
+    /// Wait for the Low Frequency clock to run
+    pub fn wait_lfclk(self) -> Clocks<H, L, LfOscStarted> {
+        while !self.is_lfclk_started() {}
+        self.into_started()
+    }
+
+    /// Continue with the Low Frequency clock if it is running, otherwise
+    /// return `self` unchanged
+    pub fn try_lfclk(self) -> Result<Clocks<H, L, LfOscStarted>, Self> {
+        if self.is_lfclk_started() {
+            Ok(self.into_started())
+        } else {
+            Err(self)
+        }
+    }
+
+    fn into_started(self) -> Clocks<H, L, LfOscStarted> {
+        self.periph
+            .events_lfclkstarted
+            .write(|w| unsafe { w.bits(0) });
+
+        Clocks {
+            hfclk: self.hfclk,
+            lfclk: self.lfclk,
+            lfstat: LfOscStarted,
+            periph: self.periph,
+        }
+    }
+}
+
+/// Events that can be generated by the CLOCK peripheral
+pub enum ClockEvent {
+    /// The high frequency crystal oscillator has started
+    HfclkStarted,
+    /// The low frequency clock has started
+    LfclkStarted,
+    /// The calibration of the low frequency RC oscillator is done
+    Done,
+    /// The calibration timer has expired
+    Ctto,
+}
+
 /// Allowable configuration options for the low frequency oscillator when
 /// driven fron an external crystal
 pub enum LfOscConfiguration {
@@ -0,0 +1,255 @@
+//! HAL interface to the Comparator (COMP) peripheral
+//!
+//! The COMP compares the voltage of an analog input to a reference, either a
+//! fraction of a reference voltage in single-ended mode, or a second analog
+//! input in differential mode. Its output is sampled by software, or raises
+//! events when it changes. These events can trigger tasks of other
+//! peripherals through the PPI, e.g. to start a SAADC conversion. The
+//! `event_*` methods return them for use as PPI event end points.
+//!
+//! The analog inputs are selected through the same pin mapping as the SAADC.
+//! On the nRF52810, AIN7 can't be used as input, and constructing a `Comp`
+//! with it panics.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 38
+//! - nrf52840: Section 6.4
+use embedded_hal::adc::Channel;
+
+pub use crate::target::comp::{extrefsel::EXTREFSELW as ExternalReference, mode::SPW as SpeedMode};
+
+use crate::saadc::Saadc;
+use crate::target::{comp, COMP};
+
+/// Largest threshold, in 64ths of the reference voltage minus one
+pub const MAX_THRESHOLD: u8 = 63;
+
+/// Interface to the COMP peripheral
+pub struct Comp<PIN> {
+    periph: COMP,
+    input: PIN,
+}
+
+impl<PIN> Comp<PIN>
+where
+    PIN: Channel<Saadc, ID = u8>,
+{
+    /// Take ownership of the COMP peripheral, and compare `input` to a
+    /// fraction of `reference` in single-ended mode
+    ///
+    /// The comparator switches at half of the reference voltage, which can be
+    /// changed with `set_thresholds`. It runs in normal speed mode, and is
+    /// started by `start`.
+    pub fn new(comp: COMP, input: PIN, reference: Reference) -> Self {
+        match reference {
+            Reference::Int1V2 => comp.refsel.write(|w| w.refsel().int1v2()),
+            Reference::Int1V8 => comp.refsel.write(|w| w.refsel().int1v8()),
+            Reference::Int2V4 => comp.refsel.write(|w| w.refsel().int2v4()),
+            Reference::Vdd => comp.refsel.write(|w| w.refsel().vdd()),
+            Reference::Aref(aref) => {
+                comp.refsel.write(|w| w.refsel().aref());
+                comp.extrefsel.write(|w| w.extrefsel().variant(aref));
+            }
+        }
+        comp.th
+            .write(|w| unsafe { w.thup().bits(31).thdown().bits(31) });
+        comp.mode.write(|w| w.sp().normal().main().se());
+
+        Comp::setup(comp, input)
+    }
+
+    /// Take ownership of the COMP peripheral, and compare `input` to
+    /// `negative` in differential mode
+    ///
+    /// With `hysteresis`, the output switches 50 mV above and below the
+    /// voltage of `negative`. The comparator runs in normal speed mode, and is
+    /// started by `start`.
+    pub fn new_differential(
+        comp: COMP,
+        input: PIN,
+        negative: ExternalReference,
+        hysteresis: bool,
+    ) -> Self {
+        comp.extrefsel.write(|w| w.extrefsel().variant(negative));
+        comp.hyst.write(|w| w.hyst().bit(hysteresis));
+        comp.mode.write(|w| w.sp().normal().main().diff());
+
+        Comp::setup(comp, input)
+    }
+
+    fn setup(comp: COMP, input: PIN) -> Self {
+        match PIN::channel() {
+            0 => comp.psel.write(|w| w.psel().analog_input0()),
+            1 => comp.psel.write(|w| w.psel().analog_input1()),
+            2 => comp.psel.write(|w| w.psel().analog_input2()),
+            3 => comp.psel.write(|w| w.psel().analog_input3()),
+            4 => comp.psel.write(|w| w.psel().analog_input4()),
+            5 => comp.psel.write(|w| w.psel().analog_input5()),
+            6 => comp.psel.write(|w| w.psel().analog_input6()),
+            #[cfg(not(feature = "52810"))]
+            7 => comp.psel.write(|w| w.psel().analog_input7()),
+            #[cfg(feature = "52810")]
+            7 => panic!("the COMP of the nRF52810 can't use AIN7"),
+            // Only the analog pins implement `Channel<Saadc>`
+            _ => unreachable!(),
+        }
+        comp.enable.write(|w| w.enable().enabled());
+
+        Comp {
+            periph: comp,
+            input,
+        }
+    }
+
+    /// Set the voltages at which the output switches in single-ended mode
+    ///
+    /// The output switches to above at `(up + 1) / 64`, and back to below at
+    /// `(down + 1) / 64` of the reference voltage. A lower `down` adds
+    /// hysteresis.
+    pub fn set_thresholds(&mut self, up: u8, down: u8) -> Result<(), Error> {
+        if up > MAX_THRESHOLD || down > MAX_THRESHOLD || down > up {
+            return Err(Error::InvalidThresholds);
+        }
+        self.periph
+            .th
+            .write(|w| unsafe { w.thup().bits(up).thdown().bits(down) });
+        Ok(())
+    }
+
+    /// Trade the current consumption of the comparator against its response
+    /// time
+    ///
+    /// The speed mode can only be changed while the comparator is stopped.
+    pub fn set_speed(&mut self, speed: SpeedMode) {
+        self.periph.mode.modify(|_, w| w.sp().variant(speed));
+    }
+
+    /// Start the comparator, and wait until it is ready
+    pub fn start(&mut self) {
+        self.periph.events_ready.reset();
+        self.periph.tasks_start.write(|w| unsafe { w.bits(1) });
+        while self.periph.events_ready.read().bits() == 0 {}
+        self.periph.events_ready.reset();
+    }
+
+    /// Stop the comparator
+    pub fn stop(&mut self) {
+        self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Sample the output of the running comparator, true if the input is
+    /// above the reference
+    pub fn read_output(&mut self) -> bool {
+        self.periph.tasks_sample.write(|w| unsafe { w.bits(1) });
+        self.periph.result.read().result().is_above()
+    }
+
+    /// Enable the interrupt for a given event
+    ///
+    /// The COMP peripheral shares the `COMP_LPCOMP` interrupt with the LPCOMP
+    /// peripheral, which must be unmasked in the NVIC separately.
+    pub fn enable_interrupt(&mut self, evt: CompEvent) {
+        match evt {
+            CompEvent::Ready => self.periph.intenset.write(|w| w.ready().set()),
+            CompEvent::Down => self.periph.intenset.write(|w| w.down().set()),
+            CompEvent::Up => self.periph.intenset.write(|w| w.up().set()),
+            CompEvent::Cross => self.periph.intenset.write(|w| w.cross().set()),
+        }
+    }
+
+    /// Disable the interrupt for a given event
+    pub fn disable_interrupt(&mut self, evt: CompEvent) {
+        match evt {
+            CompEvent::Ready => self.periph.intenclr.write(|w| w.ready().clear()),
+            CompEvent::Down => self.periph.intenclr.write(|w| w.down().clear()),
+            CompEvent::Up => self.periph.intenclr.write(|w| w.up().clear()),
+            CompEvent::Cross => self.periph.intenclr.write(|w| w.cross().clear()),
+        }
+    }
+
+    /// Obtain the state of a given event, and optionally clear the event if
+    /// it is set
+    pub fn get_event_triggered(&mut self, evt: CompEvent, clear_on_read: bool) -> bool {
+        let triggered = match evt {
+            CompEvent::Ready => self.periph.events_ready.read().bits() == 1,
+            CompEvent::Down => self.periph.events_down.read().bits() == 1,
+            CompEvent::Up => self.periph.events_up.read().bits() == 1,
+            CompEvent::Cross => self.periph.events_cross.read().bits() == 1,
+        };
+
+        if triggered && clear_on_read {
+            match evt {
+                CompEvent::Ready => self.periph.events_ready.reset(),
+                CompEvent::Down => self.periph.events_down.reset(),
+                CompEvent::Up => self.periph.events_up.reset(),
+                CompEvent::Cross => self.periph.events_cross.reset(),
+            }
+        }
+
+        triggered
+    }
+
+    /// Return the READY event, generated when the comparator has started
+    pub fn event_ready(&self) -> &comp::EVENTS_READY {
+        &self.periph.events_ready
+    }
+
+    /// Return the DOWN event, generated when the input falls below the
+    /// reference
+    pub fn event_down(&self) -> &comp::EVENTS_DOWN {
+        &self.periph.events_down
+    }
+
+    /// Return the UP event, generated when the input rises above the reference
+    pub fn event_up(&self) -> &comp::EVENTS_UP {
+        &self.periph.events_up
+    }
+
+    /// Return the CROSS event, generated when the input crosses the reference
+    /// in either direction
+    pub fn event_cross(&self) -> &comp::EVENTS_CROSS {
+        &self.periph.events_cross
+    }
+
+    /// Stop and disable the comparator, and return the underlying COMP
+    /// peripheral and the input pin
+    pub fn free(self) -> (COMP, PIN) {
+        self.periph.intenclr.write(|w| unsafe { w.bits(0xF) });
+        self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.periph.enable.write(|w| w.enable().disabled());
+        (self.periph, self.input)
+    }
+}
+
+/// Reference of the comparator in single-ended mode
+pub enum Reference {
+    /// Internal 1.2 V reference
+    Int1V2,
+    /// Internal 1.8 V reference, needs VDD of at least 2.7 V
+    Int1V8,
+    /// Internal 2.4 V reference, needs VDD of at least 2.7 V
+    Int2V4,
+    /// The supply voltage
+    Vdd,
+    /// External reference on an analog input
+    Aref(ExternalReference),
+}
+
+/// Events that can be generated by the COMP peripheral
+pub enum CompEvent {
+    /// The comparator has started and is ready to compare
+    Ready,
+    /// The input crossed the reference downwards
+    Down,
+    /// The input crossed the reference upwards
+    Up,
+    /// The input crossed the reference in either direction
+    Cross,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    InvalidThresholds,
+}
@@ -0,0 +1,324 @@
+//! Portable cryptography facade
+//!
+//! `Crypto` provides AES-128, SHA-256 and random numbers through the traits of
+//! this module, so application code does not depend on the chip it runs on:
+//!
+//! - On the nRF52840 with the `cryptocell` feature, everything is done by the
+//!   CC310, see the `cryptocell` module.
+//! - Otherwise, AES-128 uses the ECB peripheral, random numbers the RNG
+//!   peripheral, and SHA-256 is computed in software.
+//!
+//! Only the constructor of `Crypto` differs between the two.
+use rand_core::{CryptoRng, RngCore};
+
+#[cfg(all(cc310, feature = "cryptocell"))]
+use crate::cryptocell::{self, CryptoCell};
+#[cfg(not(all(cc310, feature = "cryptocell")))]
+use crate::{
+    ecb::{self, Ecb},
+    rng::Rng,
+};
+
+/// Size of a SHA-256 digest
+pub const SHA256_SIZE: usize = 32;
+
+/// AES-128 block encryption
+pub trait Aes128 {
+    type Error;
+
+    /// Encrypt a single block with `key`
+    fn aes128_encrypt_block(
+        &mut self,
+        key: &[u8; 16],
+        block: &[u8; 16],
+    ) -> Result<[u8; 16], Self::Error>;
+}
+
+/// SHA-256 hashing
+pub trait Sha256 {
+    type Error;
+
+    /// Compute the SHA-256 digest of `data`
+    fn sha256(&mut self, data: &[u8]) -> Result<[u8; SHA256_SIZE], Self::Error>;
+}
+
+/// Cryptographically secure random numbers
+pub trait Random {
+    type Error;
+
+    /// Fill the provided buffer with random bytes
+    fn random(&mut self, buf: &mut [u8]) -> Result<(), Self::Error>;
+}
+
+/// The cryptography of the chip, behind the traits of this module
+#[cfg(all(cc310, feature = "cryptocell"))]
+pub struct Crypto {
+    cryptocell: CryptoCell,
+}
+
+#[cfg(all(cc310, feature = "cryptocell"))]
+impl Crypto {
+    /// Use the CC310 for all cryptography
+    pub fn new(cryptocell: CryptoCell) -> Self {
+        Crypto { cryptocell }
+    }
+
+    /// Return the underlying CryptoCell
+    pub fn free(self) -> CryptoCell {
+        self.cryptocell
+    }
+}
+
+#[cfg(all(cc310, feature = "cryptocell"))]
+pub type Error = cryptocell::Error;
+
+#[cfg(all(cc310, feature = "cryptocell"))]
+impl Aes128 for Crypto {
+    type Error = Error;
+
+    fn aes128_encrypt_block(&mut self, key: &[u8; 16], block: &[u8; 16]) -> Result<[u8; 16], Error> {
+        self.cryptocell.aes_ecb_encrypt_block(key, block)
+    }
+}
+
+#[cfg(all(cc310, feature = "cryptocell"))]
+impl Sha256 for Crypto {
+    type Error = Error;
+
+    /// Data outside of RAM, which the CC310 can't access, is hashed in
+    /// software
+    fn sha256(&mut self, data: &[u8]) -> Result<[u8; SHA256_SIZE], Error> {
+        if !crate::slice_in_ram(data) {
+            return Ok(sha256(data));
+        }
+        self.cryptocell.sha256(data)
+    }
+}
+
+#[cfg(all(cc310, feature = "cryptocell"))]
+impl Random for Crypto {
+    type Error = Error;
+
+    fn random(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self.cryptocell.trng().random(buf)
+    }
+}
+
+/// The cryptography of the chip, behind the traits of this module
+#[cfg(not(all(cc310, feature = "cryptocell")))]
+pub struct Crypto {
+    ecb: Ecb,
+    rng: Rng,
+}
+
+#[cfg(not(all(cc310, feature = "cryptocell")))]
+impl Crypto {
+    /// Use the ECB and RNG peripherals, and software SHA-256
+    pub fn new(ecb: Ecb, rng: Rng) -> Self {
+        Crypto { ecb, rng }
+    }
+
+    /// Return the underlying peripherals
+    pub fn free(self) -> (Ecb, Rng) {
+        (self.ecb, self.rng)
+    }
+}
+
+#[cfg(not(all(cc310, feature = "cryptocell")))]
+pub type Error = ecb::Error;
+
+#[cfg(not(all(cc310, feature = "cryptocell")))]
+impl Aes128 for Crypto {
+    type Error = Error;
+
+    fn aes128_encrypt_block(&mut self, key: &[u8; 16], block: &[u8; 16]) -> Result<[u8; 16], Error> {
+        self.ecb.set_key(key);
+        self.ecb.encrypt_block(block)
+    }
+}
+
+#[cfg(not(all(cc310, feature = "cryptocell")))]
+impl Sha256 for Crypto {
+    type Error = Error;
+
+    fn sha256(&mut self, data: &[u8]) -> Result<[u8; SHA256_SIZE], Error> {
+        Ok(sha256(data))
+    }
+}
+
+#[cfg(not(all(cc310, feature = "cryptocell")))]
+impl Random for Crypto {
+    type Error = Error;
+
+    fn random(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self.rng.random(buf);
+        Ok(())
+    }
+}
+
+impl RngCore for Crypto {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest).unwrap()
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.random(dest).map_err(|_| {
+            rand_core::Error::new(rand_core::ErrorKind::Unavailable, "random number generation failed")
+        })
+    }
+}
+
+impl CryptoRng for Crypto {}
+
+/// Compute the SHA-256 digest of `data` in software
+pub fn sha256(data: &[u8]) -> [u8; SHA256_SIZE] {
+    let mut hash = SoftwareSha256::new();
+    hash.update(data);
+    hash.finalize()
+}
+
+/// SHA-256 digest of data given in parts, computed in software
+pub struct SoftwareSha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    buffered: usize,
+    len: u64,
+}
+
+impl SoftwareSha256 {
+    pub fn new() -> Self {
+        SoftwareSha256 {
+            state: [
+                0x6a09_e667,
+                0xbb67_ae85,
+                0x3c6e_f372,
+                0xa54f_f53a,
+                0x510e_527f,
+                0x9b05_688c,
+                0x1f83_d9ab,
+                0x5be0_cd19,
+            ],
+            block: [0; 64],
+            buffered: 0,
+            len: 0,
+        }
+    }
+
+    /// Add `data` to the digest
+    pub fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+
+        while !data.is_empty() {
+            let n = (64 - self.buffered).min(data.len());
+            self.block[self.buffered..self.buffered + n].copy_from_slice(&data[..n]);
+            self.buffered += n;
+            data = &data[n..];
+
+            if self.buffered == 64 {
+                self.compress();
+                self.buffered = 0;
+            }
+        }
+    }
+
+    /// Return the digest of all data added
+    pub fn finalize(mut self) -> [u8; SHA256_SIZE] {
+        let bits = self.len * 8;
+
+        // Append the 1 bit, then zeros up to the length at the end of a block
+        self.block[self.buffered] = 0x80;
+        self.buffered += 1;
+        if self.buffered > 56 {
+            for b in &mut self.block[self.buffered..] {
+                *b = 0;
+            }
+            self.compress();
+            self.buffered = 0;
+        }
+        for b in &mut self.block[self.buffered..56] {
+            *b = 0;
+        }
+        self.block[56..].copy_from_slice(&bits.to_be_bytes());
+        self.compress();
+
+        let mut digest = [0; SHA256_SIZE];
+        for (bytes, word) in digest.chunks_mut(4).zip(self.state.iter()) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self) {
+        const K: [u32; 64] = [
+            0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1,
+            0x923f_82a4, 0xab1c_5ed5, 0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3,
+            0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174, 0xe49b_69c1, 0xefbe_4786,
+            0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+            0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147,
+            0x06ca_6351, 0x1429_2967, 0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13,
+            0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85, 0xa2bf_e8a1, 0xa81a_664b,
+            0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+            0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a,
+            0x5b9c_ca4f, 0x682e_6ff3, 0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208,
+            0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+        ];
+
+        let mut w = [0u32; 64];
+        for (i, bytes) in self.block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (state, value) in self.state.iter_mut().zip(&[a, b, c, d, e, f, g, h]) {
+            *state = state.wrapping_add(*value);
+        }
+    }
+}
+
+impl Default for SoftwareSha256 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
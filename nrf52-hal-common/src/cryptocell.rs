@@ -0,0 +1,1058 @@
+//! HAL interface to the CryptoCell (CC310) security subsystem
+//!
+//! The CC310 is only operated through Nordic's `nrf_cc310` runtime library,
+//! which is not part of this crate. The application must link it, e.g. by
+//! adding `libnrf_cc310_0.9.x.a` from nrfxlib to its link arguments. The
+//! CRYPTOCELL interrupt does not need to be enabled.
+//!
+//! All data handed to the CC310 must reside in data RAM, as it is accessed by
+//! its DMA.
+//!
+//! The true random number generator of the CC310 is available as a
+//! `rand_core::RngCore`, which is stronger than the RNG peripheral, as its
+//! output is conditioned by a NIST SP 800-90A DRBG.
+//!
+//! P-256 keys are handled as fixed size arrays: private keys as 32 byte big
+//! endian integers, public keys in the uncompressed form, `0x04` followed by
+//! the X and Y coordinates, and ECDSA signatures as R followed by S.
+//!
+//! The CC310 has no GCM mode, so AES-GCM uses the hardware for the AES-CTR
+//! encryption, and computes GHASH in software.
+//!
+//! See product specification:
+//!
+//! - nrf52840: Section 6.5
+use rand_core::{CryptoRng, RngCore};
+
+use crate::slice_in_ram;
+use crate::target::CRYPTOCELL;
+
+/// Size of a SHA-256 digest
+pub const SHA256_SIZE: usize = 32;
+
+/// Size of an AES block
+pub const AES_BLOCK_SIZE: usize = 16;
+
+/// Size of an AES-GCM nonce
+pub const GCM_NONCE_SIZE: usize = 12;
+
+/// Size of an AES-GCM tag
+pub const GCM_TAG_SIZE: usize = 16;
+
+/// Size of a P-256 private key
+pub const P256_PRIVATE_KEY_SIZE: usize = 32;
+
+/// Size of an uncompressed P-256 public key
+pub const P256_PUBLIC_KEY_SIZE: usize = 65;
+
+/// Size of a P-256 ECDSA signature
+pub const P256_SIGNATURE_SIZE: usize = 64;
+
+/// Size of a P-256 ECDH shared secret
+pub const P256_SHARED_SECRET_SIZE: usize = 32;
+
+/// Interface to the CryptoCell
+pub struct CryptoCell {
+    periph: CRYPTOCELL,
+    rnd: ffi::RndState,
+}
+
+impl CryptoCell {
+    /// Take ownership of the CRYPTOCELL peripheral and initialize the runtime
+    /// library
+    ///
+    /// This also instantiates the random number generator, seeded by the
+    /// TRNG, which uses about 6 KiB of stack.
+    pub fn new(cryptocell: CRYPTOCELL) -> Result<Self, Error> {
+        cryptocell.enable.write(|w| w.enable().enabled());
+
+        let result = unsafe { ffi::SaSi_LibInit() };
+        if result != 0 {
+            cryptocell.enable.write(|w| w.enable().disabled());
+            return Err(Error::CryptoCell(result));
+        }
+
+        let mut rnd = ffi::RndState::new();
+        let mut work = ffi::RndWorkBuffer::new();
+        let result = unsafe { ffi::CRYS_RndInit(&mut rnd, &mut work) };
+        if result != 0 {
+            unsafe { ffi::SaSi_LibFini() };
+            cryptocell.enable.write(|w| w.enable().disabled());
+            return Err(Error::CryptoCell(result));
+        }
+
+        Ok(CryptoCell {
+            periph: cryptocell,
+            rnd,
+        })
+    }
+
+    /// Compute the SHA-256 digest of `data`
+    pub fn sha256(&mut self, data: &[u8]) -> Result<[u8; SHA256_SIZE], Error> {
+        let mut hash = self.sha256_stream()?;
+        hash.update(data)?;
+        hash.finalize()
+    }
+
+    /// Start computing the SHA-256 digest of data given in parts
+    pub fn sha256_stream(&mut self) -> Result<Sha256<'_>, Error> {
+        let mut context = ffi::HashContext::new();
+        check(unsafe { ffi::CRYS_HASH_Init(&mut context, ffi::HASH_SHA256_MODE) })?;
+
+        Ok(Sha256 {
+            _cryptocell: self,
+            context,
+        })
+    }
+
+    /// Compute the HMAC-SHA-256 of `data` using `key`
+    pub fn hmac_sha256(&mut self, key: &[u8], data: &[u8]) -> Result<[u8; SHA256_SIZE], Error> {
+        let mut hmac = self.hmac_sha256_stream(key)?;
+        hmac.update(data)?;
+        hmac.finalize()
+    }
+
+    /// Start computing the HMAC-SHA-256 of data given in parts
+    pub fn hmac_sha256_stream(&mut self, key: &[u8]) -> Result<HmacSha256<'_>, Error> {
+        if !slice_in_ram(key) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+
+        let mut context = ffi::HmacContext::new();
+        check(unsafe {
+            ffi::CRYS_HMAC_Init(&mut context, ffi::HASH_SHA256_MODE, key.as_ptr(), key.len())
+        })?;
+
+        Ok(HmacSha256 {
+            _cryptocell: self,
+            context,
+        })
+    }
+
+    /// Encrypt a single block using AES-128-ECB
+    pub fn aes_ecb_encrypt_block(
+        &mut self,
+        key: &[u8; 16],
+        block: &[u8; AES_BLOCK_SIZE],
+    ) -> Result<[u8; AES_BLOCK_SIZE], Error> {
+        let mut context = ffi::AesContext::new();
+        check(unsafe {
+            ffi::SaSi_AesInit(
+                &mut context,
+                ffi::AES_ENCRYPT,
+                ffi::AES_MODE_ECB,
+                ffi::AES_PADDING_NONE,
+            )
+        })?;
+
+        // The key and block are copied to the stack, so they are in RAM
+        let mut key = *key;
+        let block = *block;
+        let mut output = [0; AES_BLOCK_SIZE];
+        let mut key_data = ffi::AesUserKeyData {
+            key: key.as_mut_ptr(),
+            key_len: key.len(),
+        };
+        let result = check(unsafe {
+            ffi::SaSi_AesSetKey(
+                &mut context,
+                ffi::AES_USER_KEY,
+                &mut key_data,
+                core::mem::size_of::<ffi::AesUserKeyData>(),
+            )
+        })
+        .and_then(|_| {
+            check(unsafe {
+                ffi::SaSi_AesBlock(&mut context, block.as_ptr(), block.len(), output.as_mut_ptr())
+            })
+        });
+        unsafe { ffi::SaSi_AesFree(&mut context) };
+        result?;
+
+        Ok(output)
+    }
+
+    /// Start an AES-128-CTR encryption or decryption
+    ///
+    /// `iv` is the initial counter block, which is incremented as a 128 bit
+    /// big endian number for every block.
+    pub fn aes_ctr(&mut self, key: &[u8; 16], iv: &[u8; 16]) -> Result<AesCtr<'_>, Error> {
+        let mut context = ffi::AesContext::new();
+        check(unsafe {
+            ffi::SaSi_AesInit(
+                &mut context,
+                ffi::AES_ENCRYPT,
+                ffi::AES_MODE_CTR,
+                ffi::AES_PADDING_NONE,
+            )
+        })?;
+
+        // From here on, the context is freed when `ctr` is dropped
+        let mut ctr = AesCtr {
+            _cryptocell: self,
+            context,
+        };
+
+        // The key and IV are copied to the stack, so they are in RAM
+        let mut key = *key;
+        let mut iv = *iv;
+        let mut key_data = ffi::AesUserKeyData {
+            key: key.as_mut_ptr(),
+            key_len: key.len(),
+        };
+        check(unsafe {
+            ffi::SaSi_AesSetKey(
+                &mut ctr.context,
+                ffi::AES_USER_KEY,
+                &mut key_data,
+                core::mem::size_of::<ffi::AesUserKeyData>(),
+            )
+        })?;
+        check(unsafe { ffi::SaSi_AesSetIv(&mut ctr.context, &mut iv) })?;
+
+        Ok(ctr)
+    }
+
+    /// Encrypt or decrypt `input` into `output` using AES-128-CTR
+    pub fn aes_ctr_apply(
+        &mut self,
+        key: &[u8; 16],
+        iv: &[u8; 16],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), Error> {
+        if output.len() < input.len() {
+            return Err(Error::BufferTooShort);
+        }
+
+        let mut ctr = self.aes_ctr(key, iv)?;
+        let full = input.len() / AES_BLOCK_SIZE * AES_BLOCK_SIZE;
+        if full > 0 {
+            ctr.update(&input[..full], &mut output[..full])?;
+        }
+        ctr.finish(&input[full..], &mut output[full..])
+    }
+
+    /// Encrypt `plaintext` into `ciphertext` using AES-128-GCM, returning the
+    /// tag
+    pub fn aes_gcm_encrypt(
+        &mut self,
+        key: &[u8; 16],
+        nonce: &[u8; GCM_NONCE_SIZE],
+        aad: &[u8],
+        plaintext: &[u8],
+        ciphertext: &mut [u8],
+    ) -> Result<[u8; GCM_TAG_SIZE], Error> {
+        if ciphertext.len() < plaintext.len() {
+            return Err(Error::BufferTooShort);
+        }
+        let ciphertext = &mut ciphertext[..plaintext.len()];
+
+        self.aes_ctr_apply(key, &gcm_counter(nonce, 2), plaintext, ciphertext)?;
+        self.gcm_tag(key, nonce, aad, ciphertext)
+    }
+
+    /// Check the tag of `ciphertext`, and decrypt it into `plaintext` using
+    /// AES-128-GCM
+    ///
+    /// If the tag does not match, `Error::TagMismatch` is returned and
+    /// `plaintext` is left untouched.
+    pub fn aes_gcm_decrypt(
+        &mut self,
+        key: &[u8; 16],
+        nonce: &[u8; GCM_NONCE_SIZE],
+        aad: &[u8],
+        ciphertext: &[u8],
+        tag: &[u8; GCM_TAG_SIZE],
+        plaintext: &mut [u8],
+    ) -> Result<(), Error> {
+        if plaintext.len() < ciphertext.len() {
+            return Err(Error::BufferTooShort);
+        }
+
+        let expected = self.gcm_tag(key, nonce, aad, ciphertext)?;
+        if !constant_time_eq(&expected, tag) {
+            return Err(Error::TagMismatch);
+        }
+
+        self.aes_ctr_apply(key, &gcm_counter(nonce, 2), ciphertext, plaintext)
+    }
+
+    /// Access the random number generator, seeded by the TRNG
+    pub fn trng(&mut self) -> Trng<'_> {
+        Trng { cryptocell: self }
+    }
+
+    /// Generate a P-256 key pair
+    ///
+    /// The private key never leaves the key pair, which can be used for
+    /// ECDH with `p256_ecdh_key_pair`.
+    pub fn p256_generate_key_pair(&mut self) -> Result<P256KeyPair, Error> {
+        let domain = p256_domain();
+        let mut private = ffi::EcPrivateKey::new();
+        let mut public = ffi::EcPublicKey::new();
+        let mut temp = ffi::EcKeyGenTemp::new();
+        check(unsafe {
+            ffi::CRYS_ECPKI_GenKeyPair(
+                &mut self.rnd,
+                ffi::CRYS_RND_GenerateVector,
+                domain,
+                &mut private,
+                &mut public,
+                &mut temp,
+                core::ptr::null_mut(),
+            )
+        })?;
+
+        let mut public_key = [0; P256_PUBLIC_KEY_SIZE];
+        let mut len = public_key.len();
+        check(unsafe {
+            ffi::CRYS_ECPKI_ExportPublKey(
+                &mut public,
+                ffi::EC_POINT_UNCOMPRESSED,
+                public_key.as_mut_ptr(),
+                &mut len,
+            )
+        })?;
+
+        Ok(P256KeyPair {
+            private,
+            public: public_key,
+        })
+    }
+
+    /// Sign `message` with ECDSA over P-256, using SHA-256 as the hash
+    pub fn p256_sign(
+        &mut self,
+        private_key: &[u8; P256_PRIVATE_KEY_SIZE],
+        message: &[u8],
+    ) -> Result<[u8; P256_SIGNATURE_SIZE], Error> {
+        if !slice_in_ram(message) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+        self.sign(private_key, ffi::ECPKI_HASH_SHA256, message)
+    }
+
+    /// Sign a SHA-256 `digest` with ECDSA over P-256
+    pub fn p256_sign_digest(
+        &mut self,
+        private_key: &[u8; P256_PRIVATE_KEY_SIZE],
+        digest: &[u8; SHA256_SIZE],
+    ) -> Result<[u8; P256_SIGNATURE_SIZE], Error> {
+        let digest = *digest;
+        self.sign(private_key, ffi::ECPKI_AFTER_HASH_SHA256, &digest)
+    }
+
+    /// Verify the ECDSA signature of `message` over P-256, using SHA-256 as
+    /// the hash
+    ///
+    /// Returns `Error::InvalidSignature` if the signature does not match.
+    pub fn p256_verify(
+        &mut self,
+        public_key: &[u8; P256_PUBLIC_KEY_SIZE],
+        message: &[u8],
+        signature: &[u8; P256_SIGNATURE_SIZE],
+    ) -> Result<(), Error> {
+        if !slice_in_ram(message) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+        self.verify(public_key, ffi::ECPKI_HASH_SHA256, message, signature)
+    }
+
+    /// Verify the ECDSA signature of a SHA-256 `digest` over P-256
+    ///
+    /// Returns `Error::InvalidSignature` if the signature does not match.
+    pub fn p256_verify_digest(
+        &mut self,
+        public_key: &[u8; P256_PUBLIC_KEY_SIZE],
+        digest: &[u8; SHA256_SIZE],
+        signature: &[u8; P256_SIGNATURE_SIZE],
+    ) -> Result<(), Error> {
+        let digest = *digest;
+        self.verify(public_key, ffi::ECPKI_AFTER_HASH_SHA256, &digest, signature)
+    }
+
+    /// Compute the ECDH shared secret of `private_key` and the public key of
+    /// the peer
+    ///
+    /// The public key of the peer is fully validated.
+    pub fn p256_ecdh(
+        &mut self,
+        private_key: &[u8; P256_PRIVATE_KEY_SIZE],
+        peer_public_key: &[u8; P256_PUBLIC_KEY_SIZE],
+    ) -> Result<[u8; P256_SHARED_SECRET_SIZE], Error> {
+        let mut private = build_private_key(private_key)?;
+        ecdh(&mut private, peer_public_key)
+    }
+
+    /// Compute the ECDH shared secret of the private key of `key_pair` and
+    /// the public key of the peer
+    pub fn p256_ecdh_key_pair(
+        &mut self,
+        key_pair: &mut P256KeyPair,
+        peer_public_key: &[u8; P256_PUBLIC_KEY_SIZE],
+    ) -> Result<[u8; P256_SHARED_SECRET_SIZE], Error> {
+        ecdh(&mut key_pair.private, peer_public_key)
+    }
+
+    /// Shut down the runtime library, and return the CRYPTOCELL peripheral
+    pub fn free(mut self) -> CRYPTOCELL {
+        unsafe {
+            ffi::CRYS_RND_UnInstantiation(&mut self.rnd);
+            ffi::SaSi_LibFini();
+        }
+        self.periph.enable.write(|w| w.enable().disabled());
+        self.periph
+    }
+
+    /// Compute the GCM tag of `ciphertext`
+    fn gcm_tag(
+        &mut self,
+        key: &[u8; 16],
+        nonce: &[u8; GCM_NONCE_SIZE],
+        aad: &[u8],
+        ciphertext: &[u8],
+    ) -> Result<[u8; GCM_TAG_SIZE], Error> {
+        // The hash key is the encrypted zero block, and the tag is masked
+        // with the encrypted first counter block. The zero block is a local,
+        // so it is in RAM.
+        let zero = [0; AES_BLOCK_SIZE];
+        let mut hash_key = [0; AES_BLOCK_SIZE];
+        self.aes_ctr_apply(key, &zero, &zero, &mut hash_key)?;
+        let mut mask = [0; AES_BLOCK_SIZE];
+        self.aes_ctr_apply(key, &gcm_counter(nonce, 1), &zero, &mut mask)?;
+
+        let mut ghash = Ghash::new(&hash_key);
+        ghash.update(aad);
+        ghash.update(ciphertext);
+        let tag = ghash.finalize(aad.len(), ciphertext.len()) ^ u128::from_be_bytes(mask);
+
+        Ok(tag.to_be_bytes())
+    }
+
+    fn sign(
+        &mut self,
+        private_key: &[u8; P256_PRIVATE_KEY_SIZE],
+        hash_mode: u32,
+        data: &[u8],
+    ) -> Result<[u8; P256_SIGNATURE_SIZE], Error> {
+        let mut private = build_private_key(private_key)?;
+        let mut context = ffi::EcdsaSignContext::new();
+        let mut signature = [0; P256_SIGNATURE_SIZE];
+        let mut len = signature.len();
+        check(unsafe {
+            ffi::CRYS_ECDSA_Sign(
+                &mut self.rnd,
+                ffi::CRYS_RND_GenerateVector,
+                &mut context,
+                &mut private,
+                hash_mode,
+                data.as_ptr(),
+                data.len(),
+                signature.as_mut_ptr(),
+                &mut len,
+            )
+        })?;
+
+        Ok(signature)
+    }
+
+    fn verify(
+        &mut self,
+        public_key: &[u8; P256_PUBLIC_KEY_SIZE],
+        hash_mode: u32,
+        data: &[u8],
+        signature: &[u8; P256_SIGNATURE_SIZE],
+    ) -> Result<(), Error> {
+        let mut public = build_public_key(public_key)?;
+        let mut context = ffi::EcdsaVerifyContext::new();
+        let signature = *signature;
+        let result = unsafe {
+            ffi::CRYS_ECDSA_Verify(
+                &mut context,
+                &mut public,
+                hash_mode,
+                signature.as_ptr(),
+                signature.len(),
+                data.as_ptr(),
+                data.len(),
+            )
+        };
+
+        match result {
+            0 => Ok(()),
+            _ => Err(Error::InvalidSignature),
+        }
+    }
+}
+
+/// SHA-256 digest of data given in parts
+pub struct Sha256<'a> {
+    _cryptocell: &'a mut CryptoCell,
+    context: ffi::HashContext,
+}
+
+impl<'a> Sha256<'a> {
+    /// Add `data` to the digest
+    pub fn update(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if !slice_in_ram(data) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+        check(unsafe { ffi::CRYS_HASH_Update(&mut self.context, data.as_ptr(), data.len()) })
+    }
+
+    /// Return the digest of all data added
+    pub fn finalize(mut self) -> Result<[u8; SHA256_SIZE], Error> {
+        let mut result = [0; 16];
+        check(unsafe { ffi::CRYS_HASH_Finish(&mut self.context, &mut result) })?;
+        Ok(digest(&result))
+    }
+}
+
+/// HMAC-SHA-256 of data given in parts
+pub struct HmacSha256<'a> {
+    _cryptocell: &'a mut CryptoCell,
+    context: ffi::HmacContext,
+}
+
+impl<'a> HmacSha256<'a> {
+    /// Add `data` to the MAC
+    pub fn update(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.is_empty() {
+            return Ok(());
+        }
+        if !slice_in_ram(data) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+        check(unsafe { ffi::CRYS_HMAC_Update(&mut self.context, data.as_ptr(), data.len()) })
+    }
+
+    /// Return the MAC of all data added
+    pub fn finalize(mut self) -> Result<[u8; SHA256_SIZE], Error> {
+        let mut result = [0; 16];
+        check(unsafe { ffi::CRYS_HMAC_Finish(&mut self.context, &mut result) })?;
+        Ok(digest(&result))
+    }
+}
+
+/// AES-128-CTR encryption or decryption of data given in parts
+pub struct AesCtr<'a> {
+    _cryptocell: &'a mut CryptoCell,
+    context: ffi::AesContext,
+}
+
+impl<'a> AesCtr<'a> {
+    /// Encrypt or decrypt `input` into `output`
+    ///
+    /// The length of `input` must be a multiple of `AES_BLOCK_SIZE`. Use
+    /// `finish` for the last part.
+    pub fn update(&mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        if input.is_empty() || input.len() % AES_BLOCK_SIZE != 0 {
+            return Err(Error::InvalidLength);
+        }
+        if output.len() < input.len() {
+            return Err(Error::BufferTooShort);
+        }
+        if !slice_in_ram(input) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+
+        check(unsafe {
+            ffi::SaSi_AesBlock(
+                &mut self.context,
+                input.as_ptr(),
+                input.len(),
+                output.as_mut_ptr(),
+            )
+        })
+    }
+
+    /// Encrypt or decrypt the last part, which can have any length
+    pub fn finish(mut self, input: &[u8], output: &mut [u8]) -> Result<(), Error> {
+        if output.len() < input.len() {
+            return Err(Error::BufferTooShort);
+        }
+        if input.is_empty() {
+            return Ok(());
+        }
+        if !slice_in_ram(input) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+
+        let mut output_len = output.len();
+        check(unsafe {
+            ffi::SaSi_AesFinish(
+                &mut self.context,
+                input.len(),
+                input.as_ptr(),
+                input.len(),
+                output.as_mut_ptr(),
+                &mut output_len,
+            )
+        })
+    }
+}
+
+impl<'a> Drop for AesCtr<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::SaSi_AesFree(&mut self.context) };
+    }
+}
+
+/// P-256 key pair generated by the CC310
+pub struct P256KeyPair {
+    private: ffi::EcPrivateKey,
+    public: [u8; P256_PUBLIC_KEY_SIZE],
+}
+
+impl P256KeyPair {
+    /// Return the public key
+    pub fn public_key(&self) -> &[u8; P256_PUBLIC_KEY_SIZE] {
+        &self.public
+    }
+}
+
+/// Return the domain of P-256, which the library checks for null
+fn p256_domain() -> *const ffi::EcDomain {
+    unsafe { ffi::CRYS_ECPKI_GetEcDomain(ffi::ECPKI_DOMAIN_SECP256R1) }
+}
+
+fn build_private_key(key: &[u8; P256_PRIVATE_KEY_SIZE]) -> Result<ffi::EcPrivateKey, Error> {
+    let mut private = ffi::EcPrivateKey::new();
+    let result = unsafe {
+        ffi::CRYS_ECPKI_BuildPrivKey(p256_domain(), key.as_ptr(), key.len() as u32, &mut private)
+    };
+    if result != 0 {
+        return Err(Error::InvalidKey);
+    }
+    Ok(private)
+}
+
+fn build_public_key(key: &[u8; P256_PUBLIC_KEY_SIZE]) -> Result<ffi::EcPublicKey, Error> {
+    // Copied to the stack, so it is in RAM
+    let mut key = *key;
+    let mut public = ffi::EcPublicKey::new();
+    let mut temp = ffi::EcBuildTemp::new();
+    let result = unsafe {
+        ffi::_DX_ECPKI_BuildPublKey(
+            p256_domain(),
+            key.as_mut_ptr(),
+            key.len() as u32,
+            ffi::EC_PUBLIC_KEY_FULL_CHECK,
+            &mut public,
+            &mut temp,
+        )
+    };
+    if result != 0 {
+        return Err(Error::InvalidKey);
+    }
+    Ok(public)
+}
+
+fn ecdh(
+    private: &mut ffi::EcPrivateKey,
+    peer_public_key: &[u8; P256_PUBLIC_KEY_SIZE],
+) -> Result<[u8; P256_SHARED_SECRET_SIZE], Error> {
+    let mut public = build_public_key(peer_public_key)?;
+    let mut temp = ffi::EcdhTemp::new();
+    let mut secret = [0; P256_SHARED_SECRET_SIZE];
+    let mut len = secret.len();
+    let result = check(unsafe {
+        ffi::CRYS_ECDH_SVDP_DH(&mut public, private, secret.as_mut_ptr(), &mut len, &mut temp)
+    });
+    zeroize(&mut temp.0);
+    result?;
+
+    Ok(secret)
+}
+
+/// Overwrite secret material, without the writes being optimized away
+fn zeroize(words: &mut [u32]) {
+    for word in words {
+        unsafe { core::ptr::write_volatile(word, 0) };
+    }
+}
+
+/// Random number generator seeded by the TRNG of the CC310
+pub struct Trng<'a> {
+    cryptocell: &'a mut CryptoCell,
+}
+
+impl<'a> Trng<'a> {
+    /// Fill the provided buffer with random bytes
+    pub fn random(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        for chunk in buf.chunks_mut(usize::from(u16::max_value())) {
+            check(unsafe {
+                ffi::CRYS_RND_GenerateVector(
+                    &mut self.cryptocell.rnd,
+                    chunk.len() as u16,
+                    chunk.as_mut_ptr(),
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> RngCore for Trng<'a> {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0; 4];
+        self.fill_bytes(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0; 8];
+        self.fill_bytes(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    /// Panics if the CC310 reports an error
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest).unwrap()
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.random(dest).map_err(|_| {
+            rand_core::Error::new(rand_core::ErrorKind::Unavailable, "CryptoCell RNG failed")
+        })
+    }
+}
+
+impl<'a> CryptoRng for Trng<'a> {}
+
+/// GHASH of the GCM mode
+struct Ghash {
+    key: u128,
+    state: u128,
+}
+
+impl Ghash {
+    fn new(key: &[u8; AES_BLOCK_SIZE]) -> Self {
+        Ghash {
+            key: u128::from_be_bytes(*key),
+            state: 0,
+        }
+    }
+
+    /// Add `data`, padded with zeros to a multiple of the block size
+    fn update(&mut self, data: &[u8]) {
+        for chunk in data.chunks(AES_BLOCK_SIZE) {
+            let mut block = [0; AES_BLOCK_SIZE];
+            block[..chunk.len()].copy_from_slice(chunk);
+            self.state = gf128_mul(self.state ^ u128::from_be_bytes(block), self.key);
+        }
+    }
+
+    /// Add the lengths block, and return the hash
+    fn finalize(mut self, aad_len: usize, text_len: usize) -> u128 {
+        let lengths = (aad_len as u128 * 8) << 64 | text_len as u128 * 8;
+        self.state = gf128_mul(self.state ^ lengths, self.key);
+        self.state
+    }
+}
+
+/// Multiply in GF(2^128) as defined by GCM, in constant time
+fn gf128_mul(x: u128, y: u128) -> u128 {
+    const R: u128 = 0xE1 << 120;
+
+    let mut z = 0;
+    let mut v = y;
+    for i in 0..128 {
+        z ^= v & 0u128.wrapping_sub((x >> (127 - i)) & 1);
+        v = (v >> 1) ^ (R & 0u128.wrapping_sub(v & 1));
+    }
+    z
+}
+
+/// Return the counter block `counter` for a 96 bit GCM nonce
+fn gcm_counter(nonce: &[u8; GCM_NONCE_SIZE], counter: u32) -> [u8; AES_BLOCK_SIZE] {
+    let mut block = [0; AES_BLOCK_SIZE];
+    block[..GCM_NONCE_SIZE].copy_from_slice(nonce);
+    block[GCM_NONCE_SIZE..].copy_from_slice(&counter.to_be_bytes());
+    block
+}
+
+/// Compare two byte slices, taking the same time wherever they differ
+///
+/// To be used for comparing MACs and other secrets.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Convert the result buffer of the library, which holds the digest in
+/// memory order
+fn digest(result: &[u32; 16]) -> [u8; SHA256_SIZE] {
+    let mut digest = [0; SHA256_SIZE];
+    for (bytes, word) in digest.chunks_mut(4).zip(result.iter()) {
+        bytes.copy_from_slice(&word.to_ne_bytes());
+    }
+    digest
+}
+
+fn check(result: u32) -> Result<(), Error> {
+    match result {
+        0 => Ok(()),
+        code => Err(Error::CryptoCell(code)),
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The runtime library returned an error code
+    CryptoCell(u32),
+    DMABufferNotInDataMemory,
+    BufferTooShort,
+    InvalidLength,
+    TagMismatch,
+    InvalidKey,
+    InvalidSignature,
+}
+
+/// Bindings to the `nrf_cc310` runtime library
+#[allow(non_snake_case)]
+mod ffi {
+    /// `CRYS_HASH_SHA256_mode`
+    pub const HASH_SHA256_MODE: u32 = 2;
+
+    /// `CRYS_HASHUserContext_t`
+    #[repr(C)]
+    pub struct HashContext([u32; 60]);
+
+    impl HashContext {
+        pub fn new() -> Self {
+            HashContext([0; 60])
+        }
+    }
+
+    /// `CRYS_HMACUserContext_t`
+    #[repr(C)]
+    pub struct HmacContext([u32; 94]);
+
+    impl HmacContext {
+        pub fn new() -> Self {
+            HmacContext([0; 94])
+        }
+    }
+
+    /// `SASI_AES_ENCRYPT`
+    pub const AES_ENCRYPT: u32 = 0;
+    /// `SASI_AES_MODE_ECB`
+    pub const AES_MODE_ECB: u32 = 0;
+    /// `SASI_AES_MODE_CTR`
+    pub const AES_MODE_CTR: u32 = 3;
+    /// `SASI_AES_PADDING_NONE`
+    pub const AES_PADDING_NONE: u32 = 0;
+    /// `SASI_AES_USER_KEY`
+    pub const AES_USER_KEY: u32 = 0;
+
+    /// `SaSiAesUserContext_t`
+    #[repr(C)]
+    pub struct AesContext([u32; 131]);
+
+    impl AesContext {
+        pub fn new() -> Self {
+            AesContext([0; 131])
+        }
+    }
+
+    /// `SaSiAesUserKeyData_t`
+    #[repr(C)]
+    pub struct AesUserKeyData {
+        pub key: *mut u8,
+        pub key_len: usize,
+    }
+
+    /// `CRYS_RND_State_t`, rounded up
+    #[repr(C)]
+    pub struct RndState([u32; 128]);
+
+    impl RndState {
+        pub fn new() -> Self {
+            RndState([0; 128])
+        }
+    }
+
+    /// `CRYS_RND_WorkBuff_t`
+    #[repr(C)]
+    pub struct RndWorkBuffer([u32; 1528]);
+
+    impl RndWorkBuffer {
+        pub fn new() -> Self {
+            RndWorkBuffer([0; 1528])
+        }
+    }
+
+    /// `CRYS_ECPKI_DomainID_secp256r1`
+    pub const ECPKI_DOMAIN_SECP256R1: u32 = 8;
+    /// `CRYS_ECPKI_HASH_SHA256_mode`
+    pub const ECPKI_HASH_SHA256: u32 = 2;
+    /// `CRYS_ECPKI_AFTER_HASH_SHA256_mode`
+    pub const ECPKI_AFTER_HASH_SHA256: u32 = 7;
+    /// `CRYS_EC_PointUncompressed`
+    pub const EC_POINT_UNCOMPRESSED: u32 = 2;
+    /// `ECpublKeyFullCheck`
+    pub const EC_PUBLIC_KEY_FULL_CHECK: u32 = 2;
+
+    /// `CRYS_ECPKI_Domain_t`, only used by reference
+    pub enum EcDomain {}
+
+    macro_rules! buffer {
+        ($($(#[$attr:meta])* $name:ident: $words:expr,)+) => {
+            $(
+                $(#[$attr])*
+                #[repr(C)]
+                pub struct $name(pub [u32; $words]);
+
+                impl $name {
+                    pub fn new() -> Self {
+                        $name([0; $words])
+                    }
+                }
+            )+
+        }
+    }
+
+    // The sizes are rounded up from the definitions of the library
+    buffer!(
+        /// `CRYS_ECPKI_UserPrivKey_t`
+        EcPrivateKey: 256,
+        /// `CRYS_ECPKI_UserPublKey_t`
+        EcPublicKey: 256,
+        /// `CRYS_ECPKI_BUILD_TempData_t`
+        EcBuildTemp: 512,
+        /// `CRYS_ECPKI_KG_TempData_t`
+        EcKeyGenTemp: 512,
+        /// `CRYS_ECDH_TempData_t`
+        EcdhTemp: 512,
+        /// `CRYS_ECDSA_SignUserContext_t`
+        EcdsaSignContext: 1024,
+        /// `CRYS_ECDSA_VerifyUserContext_t`
+        EcdsaVerifyContext: 1024,
+    );
+
+    impl Drop for EcPrivateKey {
+        fn drop(&mut self) {
+            super::zeroize(&mut self.0);
+        }
+    }
+
+    /// `SaSiRndGenerateVectWorkFunc_t`
+    pub type RndGenerate = unsafe extern "C" fn(*mut RndState, u16, *mut u8) -> u32;
+
+    extern "C" {
+        pub fn SaSi_LibInit() -> u32;
+        pub fn SaSi_LibFini();
+
+        pub fn CRYS_HASH_Init(context: *mut HashContext, mode: u32) -> u32;
+        pub fn CRYS_HASH_Update(context: *mut HashContext, data: *const u8, len: usize) -> u32;
+        pub fn CRYS_HASH_Finish(context: *mut HashContext, result: *mut [u32; 16]) -> u32;
+
+        pub fn CRYS_HMAC_Init(
+            context: *mut HmacContext,
+            mode: u32,
+            key: *const u8,
+            key_len: usize,
+        ) -> u32;
+        pub fn CRYS_HMAC_Update(context: *mut HmacContext, data: *const u8, len: usize) -> u32;
+        pub fn CRYS_HMAC_Finish(context: *mut HmacContext, result: *mut [u32; 16]) -> u32;
+
+        pub fn SaSi_AesInit(
+            context: *mut AesContext,
+            direction: u32,
+            mode: u32,
+            padding: u32,
+        ) -> u32;
+        pub fn SaSi_AesSetKey(
+            context: *mut AesContext,
+            key_type: u32,
+            key_data: *mut AesUserKeyData,
+            key_data_len: usize,
+        ) -> u32;
+        pub fn SaSi_AesSetIv(context: *mut AesContext, iv: *mut [u8; 16]) -> u32;
+        pub fn SaSi_AesBlock(
+            context: *mut AesContext,
+            input: *const u8,
+            len: usize,
+            output: *mut u8,
+        ) -> u32;
+        pub fn SaSi_AesFinish(
+            context: *mut AesContext,
+            len: usize,
+            input: *const u8,
+            input_len: usize,
+            output: *mut u8,
+            output_len: *mut usize,
+        ) -> u32;
+        pub fn SaSi_AesFree(context: *mut AesContext) -> u32;
+
+        pub fn CRYS_RndInit(state: *mut RndState, work: *mut RndWorkBuffer) -> u32;
+        pub fn CRYS_RND_GenerateVector(state: *mut RndState, len: u16, output: *mut u8) -> u32;
+        pub fn CRYS_RND_UnInstantiation(state: *mut RndState) -> u32;
+
+        pub fn CRYS_ECPKI_GetEcDomain(domain_id: u32) -> *const EcDomain;
+        pub fn CRYS_ECPKI_BuildPrivKey(
+            domain: *const EcDomain,
+            key: *const u8,
+            len: u32,
+            private: *mut EcPrivateKey,
+        ) -> u32;
+        pub fn _DX_ECPKI_BuildPublKey(
+            domain: *const EcDomain,
+            key: *mut u8,
+            len: u32,
+            check_mode: u32,
+            public: *mut EcPublicKey,
+            temp: *mut EcBuildTemp,
+        ) -> u32;
+        pub fn CRYS_ECPKI_GenKeyPair(
+            rnd: *mut RndState,
+            generate: RndGenerate,
+            domain: *const EcDomain,
+            private: *mut EcPrivateKey,
+            public: *mut EcPublicKey,
+            temp: *mut EcKeyGenTemp,
+            fips_context: *mut u8,
+        ) -> u32;
+        pub fn CRYS_ECPKI_ExportPublKey(
+            public: *mut EcPublicKey,
+            compression: u32,
+            output: *mut u8,
+            len: *mut usize,
+        ) -> u32;
+        pub fn CRYS_ECDSA_Sign(
+            rnd: *mut RndState,
+            generate: RndGenerate,
+            context: *mut EcdsaSignContext,
+            private: *mut EcPrivateKey,
+            hash_mode: u32,
+            data: *const u8,
+            len: usize,
+            signature: *mut u8,
+            signature_len: *mut usize,
+        ) -> u32;
+        pub fn CRYS_ECDSA_Verify(
+            context: *mut EcdsaVerifyContext,
+            public: *mut EcPublicKey,
+            hash_mode: u32,
+            signature: *const u8,
+            signature_len: usize,
+            data: *const u8,
+            len: usize,
+        ) -> u32;
+        pub fn CRYS_ECDH_SVDP_DH(
+            public: *mut EcPublicKey,
+            private: *mut EcPrivateKey,
+            secret: *mut u8,
+            secret_len: *mut usize,
+            temp: *mut EcdhTemp,
+        ) -> u32;
+    }
+}
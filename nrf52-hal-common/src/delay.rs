@@ -23,33 +23,12 @@ impl Delay {
     pub fn free(self) -> SYST {
         self.syst
     }
-}
-
-impl DelayMs<u32> for Delay {
-    fn delay_ms(&mut self, ms: u32) {
-        self.delay_us(ms * 1_000);
-    }
-}
-
-impl DelayMs<u16> for Delay {
-    fn delay_ms(&mut self, ms: u16) {
-        self.delay_ms(u32(ms));
-    }
-}
 
-impl DelayMs<u8> for Delay {
-    fn delay_ms(&mut self, ms: u8) {
-        self.delay_ms(u32(ms));
-    }
-}
-
-impl DelayUs<u32> for Delay {
-    fn delay_us(&mut self, us: u32) {
+    /// Wait for `total_rvr` cycles of the core clock
+    fn delay_cycles(&mut self, mut total_rvr: u32) {
         // The SysTick Reload Value register supports values between 1 and 0x00FFFFFF.
         const MAX_RVR: u32 =  0x00FF_FFFF;
 
-        let mut total_rvr = us * (HFCLK_FREQ / 1_000_000);
-
         while total_rvr != 0 {
             let current_rvr = if total_rvr <= MAX_RVR {
                 total_rvr
@@ -71,6 +50,30 @@ impl DelayUs<u32> for Delay {
     }
 }
 
+impl DelayMs<u32> for Delay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay_us(ms * 1_000);
+    }
+}
+
+impl DelayMs<u16> for Delay {
+    fn delay_ms(&mut self, ms: u16) {
+        self.delay_ms(u32(ms));
+    }
+}
+
+impl DelayMs<u8> for Delay {
+    fn delay_ms(&mut self, ms: u8) {
+        self.delay_ms(u32(ms));
+    }
+}
+
+impl DelayUs<u32> for Delay {
+    fn delay_us(&mut self, us: u32) {
+        self.delay_cycles(us * (HFCLK_FREQ / 1_000_000));
+    }
+}
+
 impl DelayUs<u16> for Delay {
     fn delay_us(&mut self, us: u16) {
         self.delay_us(u32(us))
@@ -82,3 +85,15 @@ impl DelayUs<u8> for Delay {
         self.delay_us(u32(us))
     }
 }
+
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::delay::DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        let cycles = (ns as u64 * HFCLK_FREQ as u64 + 999_999_999) / 1_000_000_000;
+        self.delay_cycles(cycles as u32);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        DelayUs::delay_us(self, us);
+    }
+}
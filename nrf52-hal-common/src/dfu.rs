@@ -0,0 +1,308 @@
+//! Dual-bank firmware image management
+//!
+//! The flash holds two image slots of the same size: the active slot, which
+//! contains the running application, and the update slot, which receives a
+//! new image. Once a new image has been received and verified, it is marked
+//! as pending. On the next boot, the bootloader swaps the two slots and
+//! boots the new image for a trial. If the new image does not confirm itself
+//! before the following reset, the bootloader swaps the slots back.
+//!
+//! The slots are swapped page by page through a scratch page, recording the
+//! progress in the metadata page, so a swap that is interrupted by a reset is
+//! completed on the next boot.
+use embedded_storage::nor_flash::ReadNorFlash;
+
+use crate::nvmc::{self, Nvmc, PAGE_SIZE, WORD_SIZE};
+
+/// Marks a pending image in the metadata page
+const METADATA_MAGIC: u32 = 0x4446_5531;
+
+/// Offsets of the words of the metadata page
+const MAGIC: u32 = 0;
+const SIZE: u32 = 4;
+const CRC: u32 = 8;
+const SWAPPED: u32 = 12;
+const CONFIRMED: u32 = 16;
+const REVERTED: u32 = 20;
+const PROGRESS: u32 = 32;
+
+/// Progress of a swap is recorded in three words per page, for the swap and
+/// for the revert
+const STEPS: u32 = 3;
+const PASSES: u32 = 2;
+
+/// Largest slot that can be swapped, limited by the space for recording
+/// the progress in the metadata page
+pub const MAX_SLOT_SIZE: u32 =
+    (PAGE_SIZE as u32 - PROGRESS) / (WORD_SIZE as u32 * STEPS * PASSES) * PAGE_SIZE as u32;
+
+const ERASED: u32 = 0xFFFF_FFFF;
+
+/// Location of the slots, as offsets into the flash region of the NVMC
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    /// Start of the slot containing the running application
+    pub active: u32,
+    /// Start of the slot receiving a new image
+    pub update: u32,
+    /// Page used for swapping the slots
+    pub scratch: u32,
+    /// Page recording the state of the update
+    pub metadata: u32,
+    /// Size of each slot
+    pub slot_size: u32,
+}
+
+/// State of the update
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum State {
+    /// No update in progress
+    Idle,
+    /// A new image will be swapped in on the next boot
+    Pending,
+    /// The new image is being tried, and must be confirmed
+    Testing,
+    /// The new image has been confirmed
+    Confirmed,
+    /// The new image was not confirmed, and the previous image was restored
+    Reverted,
+}
+
+/// Manages the image slots
+pub struct Dfu {
+    nvmc: Nvmc,
+    layout: Layout,
+}
+
+impl Dfu {
+    /// Create a manager for the slots in `layout`
+    ///
+    /// All slots and pages must be page aligned, must not overlap, and must be
+    /// within the flash region of `nvmc`.
+    pub fn new(nvmc: Nvmc, layout: Layout) -> Result<Self, Error> {
+        let page = PAGE_SIZE as u32;
+        let regions = [
+            (layout.active, layout.slot_size),
+            (layout.update, layout.slot_size),
+            (layout.scratch, page),
+            (layout.metadata, page),
+        ];
+
+        if layout.slot_size == 0 || layout.slot_size > MAX_SLOT_SIZE {
+            return Err(Error::InvalidLayout);
+        }
+        for (i, &(start, len)) in regions.iter().enumerate() {
+            if start % page != 0 || len % page != 0 {
+                return Err(Error::InvalidLayout);
+            }
+            if (start + len) as usize > nvmc.capacity() {
+                return Err(Error::InvalidLayout);
+            }
+            for &(other, other_len) in &regions[i + 1..] {
+                if start < other + other_len && other < start + len {
+                    return Err(Error::InvalidLayout);
+                }
+            }
+        }
+
+        Ok(Dfu { nvmc, layout })
+    }
+
+    /// Read the state of the update
+    pub fn state(&mut self) -> Result<State, Error> {
+        if self.metadata(MAGIC)? != METADATA_MAGIC {
+            return Ok(State::Idle);
+        }
+
+        Ok(if self.is_set(REVERTED)? {
+            State::Reverted
+        } else if self.is_set(CONFIRMED)? {
+            State::Confirmed
+        } else if self.is_set(SWAPPED)? {
+            State::Testing
+        } else {
+            State::Pending
+        })
+    }
+
+    /// Prepare the update slot to receive a new image
+    ///
+    /// This erases the update slot, which discards the previous image if the
+    /// current one has not been confirmed yet.
+    pub fn begin_update(&mut self) -> Result<(), Error> {
+        self.nvmc.erase_page(self.layout.metadata)?;
+        for page in (0..self.layout.slot_size).step_by(PAGE_SIZE) {
+            self.nvmc.erase_page(self.layout.update + page)?;
+        }
+
+        Ok(())
+    }
+
+    /// Write part of the new image to the update slot
+    ///
+    /// `offset` is relative to the start of the slot. The update slot must
+    /// have been prepared with `begin_update`.
+    pub fn write(&mut self, offset: u32, data: &[u8]) -> Result<(), Error> {
+        if offset as usize % WORD_SIZE != 0 {
+            return Err(Error::Flash(nvmc::Error::NotAligned));
+        }
+        if offset + data.len() as u32 > self.layout.slot_size {
+            return Err(Error::Flash(nvmc::Error::OutOfBounds));
+        }
+
+        for (i, chunk) in data.chunks(WORD_SIZE).enumerate() {
+            let mut word = [0xFF; WORD_SIZE];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.nvmc.write_word(
+                self.layout.update + offset + (i * WORD_SIZE) as u32,
+                u32::from_le_bytes(word),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Verify the new image and mark it to be swapped in on the next boot
+    ///
+    /// `size` and `crc` are the size and CRC32 of the complete image.
+    pub fn mark_pending(&mut self, size: u32, crc: u32) -> Result<(), Error> {
+        if size > self.layout.slot_size {
+            return Err(Error::Flash(nvmc::Error::OutOfBounds));
+        }
+        if !self.verify_update(size, crc)? {
+            return Err(Error::Crc);
+        }
+
+        self.set_metadata(SIZE, size)?;
+        self.set_metadata(CRC, crc)?;
+        self.set_metadata(MAGIC, METADATA_MAGIC)
+    }
+
+    /// Confirm the new image, so it is kept after the next reset
+    ///
+    /// To be called by the new image once it has checked it works.
+    pub fn confirm(&mut self) -> Result<(), Error> {
+        match self.state()? {
+            State::Testing => self.set_metadata(CONFIRMED, 0),
+            _ => Ok(()),
+        }
+    }
+
+    /// Swap the slots as needed on boot
+    ///
+    /// To be called by the bootloader before it starts the application in the
+    /// active slot. Returns the state after the swap, where `State::Testing`
+    /// means the new image is booted for the first time.
+    pub fn on_boot(&mut self) -> Result<State, Error> {
+        match self.state()? {
+            State::Pending => {
+                let (size, crc) = (self.metadata(SIZE)?, self.metadata(CRC)?);
+                if !self.is_swap_started(0)? && !self.verify_update(size, crc)? {
+                    // The new image was damaged after it was marked pending
+                    self.nvmc.erase_page(self.layout.metadata)?;
+                    return Ok(State::Idle);
+                }
+
+                self.swap(0)?;
+                self.set_metadata(SWAPPED, 0)?;
+                Ok(State::Testing)
+            }
+            State::Testing => {
+                self.swap(1)?;
+                self.set_metadata(REVERTED, 0)?;
+                Ok(State::Reverted)
+            }
+            state => Ok(state),
+        }
+    }
+
+    /// Return the underlying NVMC
+    pub fn free(self) -> Nvmc {
+        self.nvmc
+    }
+
+    /// Swap the active and update slots, resuming an interrupted swap
+    fn swap(&mut self, pass: u32) -> Result<(), Error> {
+        let pages = self.layout.slot_size / PAGE_SIZE as u32;
+
+        for i in 0..pages {
+            let active = self.layout.active + i * PAGE_SIZE as u32;
+            let update = self.layout.update + i * PAGE_SIZE as u32;
+            let scratch = self.layout.scratch;
+            let progress = PROGRESS + (pass * pages + i) * STEPS * WORD_SIZE as u32;
+
+            // Every step only starts once the source of the previous step is
+            // no longer needed, so an interrupted step can be repeated
+            if !self.is_set(progress)? {
+                self.copy_page(active, scratch)?;
+                self.set_metadata(progress, 0)?;
+            }
+            if !self.is_set(progress + 4)? {
+                self.copy_page(update, active)?;
+                self.set_metadata(progress + 4, 0)?;
+            }
+            if !self.is_set(progress + 8)? {
+                self.copy_page(scratch, update)?;
+                self.set_metadata(progress + 8, 0)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Has a swap been started?
+    fn is_swap_started(&mut self, pass: u32) -> Result<bool, Error> {
+        let pages = self.layout.slot_size / PAGE_SIZE as u32;
+        self.is_set(PROGRESS + pass * pages * STEPS * WORD_SIZE as u32)
+    }
+
+    fn copy_page(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        self.nvmc.erase_page(to)?;
+        for offset in (0..PAGE_SIZE as u32).step_by(WORD_SIZE) {
+            let word = self.read_word(from + offset)?;
+            if word != ERASED {
+                self.nvmc.write_word(to + offset, word)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn verify_update(&mut self, size: u32, crc: u32) -> Result<bool, Error> {
+        let start = self.layout.update;
+        Ok(self.nvmc.verify(start..start + size, crc)?)
+    }
+
+    fn is_set(&mut self, offset: u32) -> Result<bool, Error> {
+        Ok(self.metadata(offset)? != ERASED)
+    }
+
+    fn metadata(&mut self, offset: u32) -> Result<u32, Error> {
+        self.read_word(self.layout.metadata + offset)
+    }
+
+    fn set_metadata(&mut self, offset: u32, value: u32) -> Result<(), Error> {
+        self.nvmc.write_word(self.layout.metadata + offset, value)?;
+        Ok(())
+    }
+
+    fn read_word(&mut self, offset: u32) -> Result<u32, Error> {
+        let mut word = [0; WORD_SIZE];
+        self.nvmc.read(offset, &mut word)?;
+        Ok(u32::from_le_bytes(word))
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    Flash(nvmc::Error),
+    InvalidLayout,
+    Crc,
+}
+
+impl From<nvmc::Error> for Error {
+    fn from(e: nvmc::Error) -> Self {
+        Error::Flash(e)
+    }
+}
@@ -0,0 +1,567 @@
+//! Owned EasyDMA transfers
+//!
+//! The drivers of the EasyDMA peripherals can run a transfer in the
+//! background. Starting it consumes both the driver and the buffers, which
+//! are only handed back by `Transfer::wait` once the hardware is done with
+//! them. Touching a buffer while EasyDMA still accesses it is thus a compile
+//! error.
+//!
+//! The buffers are passed through the `embedded_dma` traits, which are
+//! implemented for `&'static mut` slices and arrays, and for other buffers that
+//! can't move or be freed while they are owned, e.g. pool boxes. Buffers
+//! created with the `dma_buffer!` macro are guaranteed to be placed in RAM,
+//! as EasyDMA can't access flash. Data that is known to be in RAM can be
+//! passed around as `FromRam`, e.g. by drivers building on this crate.
+use core::cell::{Cell, UnsafeCell};
+use core::mem::{self, MaybeUninit};
+use core::ops::{Deref, DerefMut};
+use core::slice::{self, Chunks, ChunksMut};
+use core::sync::atomic::{compiler_fence, AtomicBool, AtomicU32, Ordering, Ordering::SeqCst};
+
+#[cfg(feature = "async")]
+use core::future::poll_fn;
+#[cfg(feature = "async")]
+use core::task::Poll;
+
+#[cfg(feature = "async")]
+use cortex_m::interrupt::Nr;
+use cortex_m::interrupt::{self, Mutex};
+use embedded_dma::{ReadBuffer, ReadTarget, WriteBuffer};
+
+#[cfg(feature = "async")]
+use crate::target::Interrupt;
+use crate::target_constants::EASY_DMA_SIZE;
+#[cfg(feature = "async")]
+use crate::waker;
+use crate::{slice_in_ram, DmaSlice};
+
+/// Return whether a buffer of `len` words fits in a single EasyDMA transfer,
+/// whose length is limited by the width of the MAXCNT registers
+pub(crate) fn fits_in_maxcnt(len: usize) -> bool {
+    len <= EASY_DMA_SIZE
+}
+
+/// Split `buffer` into parts that each fit in a single EasyDMA transfer
+pub(crate) fn chunks<T>(buffer: &[T]) -> Chunks<'_, T> {
+    buffer.chunks(EASY_DMA_SIZE)
+}
+
+/// Split `buffer` into mutable parts that each fit in a single EasyDMA
+/// transfer
+pub(crate) fn chunks_mut<T>(buffer: &mut [T]) -> ChunksMut<'_, T> {
+    buffer.chunks_mut(EASY_DMA_SIZE)
+}
+
+/// Implemented by drivers that can run a transfer in the background
+pub trait Transferable {
+    /// The error of a failed transfer
+    type Error;
+
+    /// Return whether the transfer that has been started has ended
+    fn is_done(&mut self) -> bool;
+
+    /// Clean up after the transfer has ended, and check its outcome
+    fn finish(&mut self) -> Result<(), Self::Error>;
+
+    /// Stop the transfer that has been started, wait until it has stopped,
+    /// and clean up after it
+    fn cancel(&mut self) -> Progress;
+}
+
+/// The number of bytes a cancelled transfer has moved in each direction
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// Bytes read from the transmit buffer
+    pub transmitted: usize,
+    /// Bytes written to the receive buffer
+    pub received: usize,
+}
+
+/// A transfer running in the background, owning its buffer `B` and the driver
+/// `PERIPH`
+///
+/// Dropping an unfinished transfer blocks until it has ended, so the buffer is
+/// never freed while EasyDMA accesses it.
+pub struct Transfer<B, PERIPH>
+where
+    PERIPH: Transferable,
+{
+    inner: Option<(B, PERIPH)>,
+}
+
+impl<B, PERIPH> Transfer<B, PERIPH>
+where
+    PERIPH: Transferable,
+{
+    /// Wrap a transfer that has just been started by `periph`
+    pub(crate) fn new(buffer: B, periph: PERIPH) -> Self {
+        Transfer {
+            inner: Some((buffer, periph)),
+        }
+    }
+
+    /// Return whether the transfer has ended, without blocking
+    pub fn is_done(&mut self) -> bool {
+        match &mut self.inner {
+            Some((_, periph)) => periph.is_done(),
+            None => true,
+        }
+    }
+
+    /// Block until the transfer has ended, and return the buffer and the
+    /// driver
+    ///
+    /// If the transfer failed, both are returned along with the error.
+    pub fn wait(mut self) -> Result<(B, PERIPH), (PERIPH::Error, B, PERIPH)> {
+        // The inner value is only taken here and in `drop`
+        let (buffer, mut periph) = self.inner.take().unwrap();
+
+        while !periph.is_done() {}
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        match periph.finish() {
+            Ok(()) => Ok((buffer, periph)),
+            Err(e) => Err((e, buffer, periph)),
+        }
+    }
+
+    /// Stop the transfer, and return how far it got along with the buffer and
+    /// the driver
+    ///
+    /// This blocks only until the hardware has stopped, e.g. to give up on a
+    /// transfer after a protocol timeout. A transfer that has already ended
+    /// reports its full length.
+    pub fn cancel(mut self) -> (Progress, B, PERIPH) {
+        // The inner value is only taken here, in `wait` and in `drop`
+        let (buffer, mut periph) = self.inner.take().unwrap();
+
+        let progress = periph.cancel();
+        (progress, buffer, periph)
+    }
+}
+
+impl<B, PERIPH> Drop for Transfer<B, PERIPH>
+where
+    PERIPH: Transferable,
+{
+    fn drop(&mut self) {
+        if let Some((_, periph)) = &mut self.inner {
+            while !periph.is_done() {}
+            compiler_fence(SeqCst);
+            let _ = periph.finish();
+        }
+    }
+}
+
+/// Cancels the transfer of a driver when dropped, unless it has been taken
+/// back
+#[cfg(feature = "async")]
+struct CancelOnDrop<'a, PERIPH: Transferable>(Option<&'a mut PERIPH>);
+
+#[cfg(feature = "async")]
+impl<'a, PERIPH: Transferable> Drop for CancelOnDrop<'a, PERIPH> {
+    fn drop(&mut self) {
+        if let Some(periph) = self.0.take() {
+            periph.cancel();
+        }
+    }
+}
+
+/// Wait for the transfer that `periph` has started to end, without blocking,
+/// and clean up after it
+///
+/// `listen` enables `interrupt` at the end of the transfer, whose handler
+/// wakes the future through the `waker` registry. If the future is dropped before the transfer has
+/// ended, the transfer is cancelled, so EasyDMA doesn't outlive the borrowed
+/// buffers.
+#[cfg(feature = "async")]
+pub(crate) async fn finish<PERIPH>(
+    periph: &mut PERIPH,
+    interrupt: Interrupt,
+    listen: impl Fn(&mut PERIPH),
+) -> Result<(), PERIPH::Error>
+where
+    PERIPH: Transferable,
+{
+    let nr = interrupt.nr();
+    let mut guard = CancelOnDrop(Some(periph));
+
+    poll_fn(|cx| {
+        let periph = guard.0.as_mut().unwrap();
+
+        // Register before checking, so the end can't be missed in between
+        waker::register_nr(nr, cx.waker());
+        listen(periph);
+
+        if periph.is_done() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+    .await;
+
+    let periph = guard.0.take().unwrap();
+
+    // Conservative compiler fence to prevent optimizations that do not
+    // take in to account actions by DMA. The fence has been placed here,
+    // after all possible DMA actions have completed
+    compiler_fence(SeqCst);
+
+    periph.finish()
+}
+
+/// Notification of the completion of transfers, one per peripheral instance
+///
+/// Once notifications have been enabled through a driver, the end of every
+/// transfer of the instance raises its interrupt. The interrupt handler calls
+/// the `handle_interrupt` function of the driver, which sets the flag
+/// returned by `take`, and calls the hook, if any. This lets an application
+/// wait for transfers with `WFI` instead of polling, without an executor.
+///
+/// The interrupt of the instance must be unmasked in the NVIC.
+pub struct Notifier {
+    enabled: AtomicBool,
+    pending: AtomicBool,
+    hook: Mutex<Cell<Option<fn()>>>,
+}
+
+impl Notifier {
+    pub(crate) const fn new() -> Self {
+        Notifier {
+            enabled: AtomicBool::new(false),
+            pending: AtomicBool::new(false),
+            hook: Mutex::new(Cell::new(None)),
+        }
+    }
+
+    pub(crate) fn enable(&self, hook: Option<fn()>) {
+        interrupt::free(|cs| self.hook.borrow(cs).set(hook));
+        self.enabled.store(true, SeqCst);
+    }
+
+    pub(crate) fn disable(&self) {
+        self.enabled.store(false, SeqCst);
+        interrupt::free(|cs| self.hook.borrow(cs).set(None));
+    }
+
+    pub(crate) fn is_enabled(&self) -> bool {
+        self.enabled.load(SeqCst)
+    }
+
+    pub(crate) fn notify(&self) {
+        self.pending.store(true, SeqCst);
+        if let Some(hook) = interrupt::free(|cs| self.hook.borrow(cs).get()) {
+            hook();
+        }
+    }
+
+    /// Return whether a transfer has ended since the last call
+    pub fn take(&self) -> bool {
+        self.pending.swap(false, SeqCst)
+    }
+}
+
+/// Statically allocated storage for an EasyDMA buffer
+///
+/// A `DmaCell` is meant to be placed in a RAM section that is not initialized
+/// at startup, see `dma_buffer!`, and is only initialized when it is taken
+/// with `init`.
+pub struct DmaCell<T>(UnsafeCell<MaybeUninit<T>>);
+
+// The contents are only accessed through the unique reference returned by
+// `init`
+unsafe impl<T> Sync for DmaCell<T> where T: Send {}
+
+impl<T> DmaCell<T> {
+    /// Create uninitialized storage
+    pub const fn new() -> Self {
+        DmaCell(UnsafeCell::new(MaybeUninit::uninit()))
+    }
+
+    /// Initialize the storage with `value`, and return a reference to it
+    ///
+    /// # Safety
+    ///
+    /// This must be called at most once, otherwise the returned references
+    /// alias. `dma_buffer!` takes care of this.
+    pub unsafe fn init(&'static self, value: T) -> &'static mut T {
+        let slot = &mut *self.0.get();
+        slot.as_mut_ptr().write(value);
+        &mut *slot.as_mut_ptr()
+    }
+}
+
+/// Create a buffer in RAM for use with EasyDMA, and return a `&'static mut`
+/// reference to it
+///
+/// The buffer is placed in its own section below `.uninit`, which the
+/// `cortex-m-rt` linker script (0.6.11 and later) puts in RAM without
+/// initializing it at startup, so the buffer is never in flash nor on the
+/// stack. It is initialized with the given value instead. The reference
+/// implements the `embedded_dma` traits when the buffer is an array of bytes.
+///
+/// Like `cortex_m::singleton!`, this returns `Some` the first time it is
+/// executed, and `None` afterwards.
+///
+/// ``` ignore
+/// let buffer: &'static mut [u8; 64] = dma_buffer!(: [u8; 64] = [0; 64]).unwrap();
+/// ```
+#[macro_export]
+macro_rules! dma_buffer {
+    (: $ty:ty = $expr:expr) => {{
+        #[link_section = ".uninit.nrf52_hal_common.dma_buffer"]
+        static CELL: $crate::dma::DmaCell<$ty> = $crate::dma::DmaCell::new();
+        static TAKEN: core::sync::atomic::AtomicBool = core::sync::atomic::AtomicBool::new(false);
+
+        if TAKEN.swap(true, core::sync::atomic::Ordering::SeqCst) {
+            None
+        } else {
+            // Only the first execution gets here
+            Some(unsafe { CELL.init($expr) })
+        }
+    }};
+}
+
+/// A reference to data that resides in RAM, where EasyDMA can read it
+///
+/// A `FromRam` is either checked at runtime with `new`, or converted from a
+/// mutable reference, which can't point to flash. This includes the
+/// references returned by `dma_buffer!`, so buffers created with it are
+/// known to be in RAM at compile time.
+///
+/// It dereferences to the data, to be passed to the blocking methods of the
+/// drivers, and a `FromRam<'static, _>` can be passed to the owned transfers.
+#[derive(Debug)]
+pub struct FromRam<'a, T: ?Sized>(&'a T);
+
+/// A slice of bytes that resides in RAM
+pub type InRamSlice<'a> = FromRam<'a, [u8]>;
+
+impl<'a, T: ?Sized> FromRam<'a, T> {
+    /// Check that `data` resides in RAM, and return `None` otherwise
+    pub fn new(data: &'a T) -> Option<Self> {
+        let bytes =
+            unsafe { slice::from_raw_parts(data as *const T as *const u8, mem::size_of_val(data)) };
+        if slice_in_ram(bytes) {
+            Some(FromRam(data))
+        } else {
+            None
+        }
+    }
+
+    /// Wrap `data` without checking where it resides
+    ///
+    /// # Safety
+    ///
+    /// `data` must reside in RAM, otherwise EasyDMA transfers reading it fail
+    /// or transfer garbage.
+    pub unsafe fn new_unchecked(data: &'a T) -> Self {
+        FromRam(data)
+    }
+
+    /// Return the reference to the data
+    pub fn into_inner(self) -> &'a T {
+        self.0
+    }
+}
+
+impl<'a, T: ?Sized> From<&'a mut T> for FromRam<'a, T> {
+    fn from(data: &'a mut T) -> Self {
+        FromRam(data)
+    }
+}
+
+impl<'a, T: ?Sized> Clone for FromRam<'a, T> {
+    fn clone(&self) -> Self {
+        FromRam(self.0)
+    }
+}
+
+impl<'a, T: ?Sized> Copy for FromRam<'a, T> {}
+
+impl<'a, T: ?Sized> Deref for FromRam<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.0
+    }
+}
+
+// The data is borrowed for `'static`, so it stays valid and in place while
+// a transfer owns the reference
+unsafe impl<T> ReadBuffer for FromRam<'static, T>
+where
+    T: ReadTarget + ?Sized,
+{
+    type Word = T::Word;
+
+    unsafe fn read_buffer(&self) -> (*const T::Word, usize) {
+        self.0.as_read_buffer()
+    }
+}
+
+/// A list of `N` items of `LEN` bytes each, laid out back to back as expected
+/// by the ArrayList mode of EasyDMA
+///
+/// In ArrayList mode, a peripheral advances its data pointer by the length of
+/// an item after every transfer, so consecutive transfers go through the
+/// items in order without being set up again. It is supported by the SPIM and
+/// TWIM drivers.
+#[repr(transparent)]
+pub struct ArrayList<const N: usize, const LEN: usize>([[u8; LEN]; N]);
+
+impl<const N: usize, const LEN: usize> ArrayList<N, LEN> {
+    /// Create a list with all items zeroed
+    pub const fn new() -> Self {
+        ArrayList([[0; LEN]; N])
+    }
+
+    /// Return the items of the list
+    pub fn items(&self) -> &[[u8; LEN]; N] {
+        &self.0
+    }
+
+    /// Return the items of the list for modification
+    pub fn items_mut(&mut self) -> &mut [[u8; LEN]; N] {
+        &mut self.0
+    }
+
+    /// Return all items as a single slice
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        // The items are contiguous, as arrays have no padding
+        unsafe { slice::from_raw_parts(self.0.as_ptr() as *const u8, N * LEN) }
+    }
+
+    /// Return the first item for a transfer that reads the list
+    pub(crate) fn first_item(&self) -> DmaSlice {
+        DmaSlice::from_raw_parts(self.0.as_ptr() as *const u8, LEN)
+    }
+
+    /// Return the first item for a transfer that writes the list
+    pub(crate) fn first_item_mut(&mut self) -> DmaSlice {
+        DmaSlice::from_raw_parts(self.0.as_mut_ptr() as *const u8, LEN)
+    }
+}
+
+/// A fixed number of EasyDMA buffers of `SIZE` bytes each, handed out without
+/// an allocator
+///
+/// A pool is meant to be a `static`, so the buffers are in RAM, and the boxes
+/// taken from it can be used for background transfers, e.g. to keep
+/// receiving from an interrupt handler while the previous buffer is
+/// processed. Taking and returning buffers is lock-free. A pool holds at most
+/// 32 buffers.
+pub struct Pool<const COUNT: usize, const SIZE: usize> {
+    buffers: [UnsafeCell<[u8; SIZE]>; COUNT],
+    // Bit `i` is set while buffer `i` is free
+    free: AtomicU32,
+}
+
+// Each buffer is only accessed through the single box that owns it
+unsafe impl<const COUNT: usize, const SIZE: usize> Sync for Pool<COUNT, SIZE> {}
+
+impl<const COUNT: usize, const SIZE: usize> Pool<COUNT, SIZE> {
+    const EMPTY: UnsafeCell<[u8; SIZE]> = UnsafeCell::new([0; SIZE]);
+
+    /// Create a pool with all buffers free and zeroed
+    pub const fn new() -> Self {
+        assert!(COUNT <= 32, "a pool holds at most 32 buffers");
+
+        Pool {
+            buffers: [Self::EMPTY; COUNT],
+            free: AtomicU32::new(if COUNT == 0 {
+                0
+            } else {
+                u32::MAX >> (32 - COUNT)
+            }),
+        }
+    }
+
+    /// Take a free buffer from the pool, or `None` if all are in use
+    ///
+    /// The buffer still holds the data it had when it was last returned.
+    pub fn alloc(&'static self) -> Option<PoolBox<SIZE>> {
+        let mut free = self.free.load(Ordering::Acquire);
+        loop {
+            if free == 0 {
+                return None;
+            }
+
+            let index = free.trailing_zeros();
+            match self.free.compare_exchange_weak(
+                free,
+                free & !(1 << index),
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => {
+                    return Some(PoolBox {
+                        // The buffer has just been marked as used, so this is
+                        // the only reference to it
+                        buffer: unsafe { &mut *self.buffers[index as usize].get() },
+                        free: &self.free,
+                        index,
+                    });
+                }
+                Err(current) => free = current,
+            }
+        }
+    }
+
+    /// Return the number of free buffers
+    pub fn available(&self) -> usize {
+        self.free.load(Ordering::Relaxed).count_ones() as usize
+    }
+}
+
+/// A buffer taken from a `Pool`, which is returned to it when dropped
+///
+/// Boxes implement the `embedded_dma` traits, so they can be used for
+/// background transfers.
+pub struct PoolBox<const SIZE: usize> {
+    buffer: &'static mut [u8; SIZE],
+    free: &'static AtomicU32,
+    index: u32,
+}
+
+impl<const SIZE: usize> Deref for PoolBox<SIZE> {
+    type Target = [u8; SIZE];
+
+    fn deref(&self) -> &[u8; SIZE] {
+        self.buffer
+    }
+}
+
+impl<const SIZE: usize> DerefMut for PoolBox<SIZE> {
+    fn deref_mut(&mut self) -> &mut [u8; SIZE] {
+        self.buffer
+    }
+}
+
+impl<const SIZE: usize> Drop for PoolBox<SIZE> {
+    fn drop(&mut self) {
+        self.free.fetch_or(1 << self.index, Ordering::Release);
+    }
+}
+
+// The buffer is in a static pool, and stays in place until the box is dropped
+unsafe impl<const SIZE: usize> ReadBuffer for PoolBox<SIZE> {
+    type Word = u8;
+
+    unsafe fn read_buffer(&self) -> (*const u8, usize) {
+        (self.buffer.as_ptr(), SIZE)
+    }
+}
+
+unsafe impl<const SIZE: usize> WriteBuffer for PoolBox<SIZE> {
+    type Word = u8;
+
+    unsafe fn write_buffer(&mut self) -> (*mut u8, usize) {
+        (self.buffer.as_mut_ptr(), SIZE)
+    }
+}
@@ -0,0 +1,196 @@
+//! HAL interface to the AES Electronic Codebook (ECB) peripheral
+//!
+//! The ECB encrypts a single 128 bit block with AES-128. It is the building
+//! block for other modes of operation, e.g. CCM nonces or resolvable private
+//! addresses.
+//!
+//! The ECB shares the AES core with the CCM and AAR peripherals, which take
+//! priority. An encryption that is aborted by them is retried.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 28
+//! - nrf52840: Section 6.6
+#[cfg(feature = "cipher")]
+use core::cell::RefCell;
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+#[cfg(feature = "cipher")]
+use cipher::{
+    consts::{U1, U16},
+    inout::InOut,
+    Block, BlockBackend, BlockCipher, BlockClosure, BlockEncrypt, BlockSizeUser,
+    ParBlocksSizeUser,
+};
+
+use crate::target::ECB;
+
+/// Size of an AES block and key
+pub const BLOCK_SIZE: usize = 16;
+
+/// Number of times an aborted encryption is retried
+const RETRIES: usize = 8;
+
+/// The data structure ECBDATAPTR points to
+#[repr(C)]
+struct EcbData {
+    key: [u8; BLOCK_SIZE],
+    cleartext: [u8; BLOCK_SIZE],
+    ciphertext: [u8; BLOCK_SIZE],
+}
+
+/// Interface to the ECB peripheral
+pub struct Ecb {
+    periph: ECB,
+    data: EcbData,
+}
+
+impl Ecb {
+    /// Take ownership of the ECB peripheral
+    pub fn new(ecb: ECB) -> Self {
+        Ecb {
+            periph: ecb,
+            data: EcbData {
+                key: [0; BLOCK_SIZE],
+                cleartext: [0; BLOCK_SIZE],
+                ciphertext: [0; BLOCK_SIZE],
+            },
+        }
+    }
+
+    /// Set the key used for all following encryptions
+    ///
+    /// The key is stored most significant byte first.
+    pub fn set_key(&mut self, key: &[u8; BLOCK_SIZE]) {
+        self.data.key = *key;
+    }
+
+    /// Encrypt a single block with the current key
+    ///
+    /// Blocks until the encryption is finished, which takes about 7 µs.
+    pub fn encrypt_block(&mut self, block: &[u8; BLOCK_SIZE]) -> Result<[u8; BLOCK_SIZE], Error> {
+        self.data.cleartext = *block;
+
+        // The data structure is part of `self`, which may have moved since
+        // the last encryption
+        self.periph
+            .ecbdataptr
+            .write(|w| unsafe { w.bits(&self.data as *const _ as u32) });
+
+        for _ in 0..RETRIES {
+            // Conservative compiler fence to prevent optimizations that do not
+            // take in to account actions by DMA. The fence has been placed here,
+            // before any DMA action has started
+            compiler_fence(SeqCst);
+
+            self.periph.events_endecb.reset();
+            self.periph.events_errorecb.reset();
+            self.periph.tasks_startecb.write(|w| unsafe { w.bits(1) });
+
+            loop {
+                if self.periph.events_endecb.read().bits() != 0 {
+                    self.periph.events_endecb.reset();
+
+                    // Conservative compiler fence to prevent optimizations that do not
+                    // take in to account actions by DMA. The fence has been placed here,
+                    // after all possible DMA actions have completed
+                    compiler_fence(SeqCst);
+
+                    return Ok(self.data.ciphertext);
+                }
+                if self.periph.events_errorecb.read().bits() != 0 {
+                    // Aborted by CCM or AAR, try again
+                    self.periph.events_errorecb.reset();
+                    break;
+                }
+            }
+        }
+
+        Err(Error::Aborted)
+    }
+
+    /// Return the underlying ECB peripheral
+    pub fn free(self) -> ECB {
+        self.periph.tasks_stopecb.write(|w| unsafe { w.bits(1) });
+        self.periph
+    }
+}
+
+/// AES-128 block cipher on the ECB peripheral
+///
+/// Implements the RustCrypto `BlockEncrypt` trait, so software modes of
+/// operation like CTR or CMAC can use the hardware block function. The ECB
+/// can only encrypt, so decryption is not available.
+///
+/// Panics if an encryption is repeatedly aborted by the CCM or AAR.
+#[cfg(feature = "cipher")]
+pub struct Aes128 {
+    ecb: RefCell<Ecb>,
+}
+
+#[cfg(feature = "cipher")]
+impl Aes128 {
+    /// Use the ECB peripheral with the given key
+    pub fn new(mut ecb: Ecb, key: &[u8; BLOCK_SIZE]) -> Self {
+        ecb.set_key(key);
+        Aes128 {
+            ecb: RefCell::new(ecb),
+        }
+    }
+
+    /// Return the ECB driver
+    pub fn free(self) -> Ecb {
+        self.ecb.into_inner()
+    }
+}
+
+#[cfg(feature = "cipher")]
+impl BlockSizeUser for Aes128 {
+    type BlockSize = U16;
+}
+
+#[cfg(feature = "cipher")]
+impl BlockCipher for Aes128 {}
+
+#[cfg(feature = "cipher")]
+impl BlockEncrypt for Aes128 {
+    fn encrypt_with_backend(&self, f: impl BlockClosure<BlockSize = U16>) {
+        f.call(&mut Aes128Backend(self))
+    }
+}
+
+#[cfg(feature = "cipher")]
+struct Aes128Backend<'a>(&'a Aes128);
+
+#[cfg(feature = "cipher")]
+impl<'a> BlockSizeUser for Aes128Backend<'a> {
+    type BlockSize = U16;
+}
+
+#[cfg(feature = "cipher")]
+impl<'a> ParBlocksSizeUser for Aes128Backend<'a> {
+    type ParBlocksSize = U1;
+}
+
+#[cfg(feature = "cipher")]
+impl<'a> BlockBackend for Aes128Backend<'a> {
+    fn proc_block(&mut self, mut block: InOut<'_, '_, Block<Self>>) {
+        let mut cleartext = [0; BLOCK_SIZE];
+        cleartext.copy_from_slice(block.get_in());
+
+        let ciphertext = self
+            .0
+            .ecb
+            .borrow_mut()
+            .encrypt_block(&cleartext)
+            .expect("ECB encryption aborted");
+        block.get_out().copy_from_slice(&ciphertext);
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The encryption was aborted repeatedly by a higher priority peripheral
+    Aborted,
+}
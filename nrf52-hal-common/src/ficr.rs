@@ -0,0 +1,142 @@
+//! HAL interface to the Factory Information Configuration Registers (FICR)
+//!
+//! The FICR are pre-programmed in the factory and cannot be erased by the
+//! user. They contain the unique device identifier, the BLE device address
+//! and information about the chip.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 13
+//! - nrf52840: Section 4.4
+use core::str;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use cortex_m::interrupt;
+
+use crate::target::FICR;
+
+/// Number of characters of the serial number
+pub const SERIAL_NUMBER_LEN: usize = 16;
+
+/// Interface to the FICR
+pub struct Ficr(FICR);
+
+impl Ficr {
+    /// Take ownership of the FICR
+    pub fn new(ficr: FICR) -> Self {
+        Ficr(ficr)
+    }
+
+    /// Read all device information
+    pub fn info(&self) -> DeviceInfo {
+        DeviceInfo {
+            device_id: self.device_id(),
+            device_address: self.device_address(),
+            address_type: self.address_type(),
+            part: self.0.info.part.read().bits(),
+            variant: self.0.info.variant.read().bits().to_be_bytes(),
+            package: self.0.info.package.read().bits(),
+            ram_size: self.0.info.ram.read().bits(),
+            flash_size: self.0.info.flash.read().bits(),
+            code_page_size: self.0.codepagesize.read().bits(),
+            code_size: self.0.codesize.read().bits(),
+        }
+    }
+
+    /// Read the 64 bit unique device identifier
+    pub fn device_id(&self) -> u64 {
+        u64::from(self.0.deviceid[0].read().bits())
+            | u64::from(self.0.deviceid[1].read().bits()) << 32
+    }
+
+    /// Return the device identifier as a serial number string
+    ///
+    /// The serial number consists of 16 uppercase hexadecimal digits, and is
+    /// unique and stable for every chip, as needed for e.g. the serial number
+    /// descriptor of a USB device. It is formatted into a static buffer on
+    /// the first call.
+    pub fn serial_number(&self) -> &'static str {
+        static FORMATTED: AtomicBool = AtomicBool::new(false);
+        static mut SERIAL_NUMBER: [u8; SERIAL_NUMBER_LEN] = [0; SERIAL_NUMBER_LEN];
+
+        interrupt::free(|_| {
+            if !FORMATTED.load(Ordering::SeqCst) {
+                let id = self.device_id();
+                // The buffer isn't referenced before it has been formatted
+                let serial_number = unsafe { &mut *core::ptr::addr_of_mut!(SERIAL_NUMBER) };
+                for (i, c) in serial_number.iter_mut().enumerate() {
+                    let digit = (id >> (4 * (SERIAL_NUMBER_LEN - 1 - i))) as u8 & 0xF;
+                    *c = b"0123456789ABCDEF"[usize::from(digit)];
+                }
+                FORMATTED.store(true, Ordering::SeqCst);
+            }
+        });
+
+        // Only ASCII hexadecimal digits are written to the buffer, and never
+        // again once formatted
+        unsafe { str::from_utf8_unchecked(&*core::ptr::addr_of!(SERIAL_NUMBER)) }
+    }
+
+    /// Read the 48 bit device address, least significant byte first
+    pub fn device_address(&self) -> [u8; 6] {
+        let low = self.0.deviceaddr[0].read().bits().to_le_bytes();
+        let high = self.0.deviceaddr[1].read().bits().to_le_bytes();
+        [low[0], low[1], low[2], low[3], high[0], high[1]]
+    }
+
+    /// Read the type of the device address
+    pub fn address_type(&self) -> AddressType {
+        if self.0.deviceaddrtype.read().bits() & 1 == 0 {
+            AddressType::Public
+        } else {
+            AddressType::RandomStatic
+        }
+    }
+
+    /// Read the 7 byte NFCID1 assigned to the chip, for use with the NFCT
+    ///
+    /// The first byte is the manufacturer ID of Nordic Semiconductor.
+    #[cfg(nfct)]
+    pub fn nfc_id(&self) -> [u8; 7] {
+        let low = self.0.nfc.tagheader0.read().bits().to_le_bytes();
+        let high = self.0.nfc.tagheader1.read().bits().to_le_bytes();
+        [low[0], low[1], low[2], low[3], high[0], high[1], high[2]]
+    }
+
+    /// Return the underlying FICR peripheral
+    pub fn free(self) -> FICR {
+        self.0
+    }
+}
+
+/// Information about the device, read from the FICR
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DeviceInfo {
+    /// Unique device identifier
+    pub device_id: u64,
+    /// Device address, least significant byte first
+    pub device_address: [u8; 6],
+    /// Type of the device address
+    pub address_type: AddressType,
+    /// Part code, e.g. `0x52832`
+    pub part: u32,
+    /// Part variant as ASCII, e.g. `b"AAB0"`
+    pub variant: [u8; 4],
+    /// Package option, e.g. `0x2000` for QFxx
+    pub package: u32,
+    /// RAM size in KiB
+    pub ram_size: u32,
+    /// Flash size in KiB
+    pub flash_size: u32,
+    /// Size of a flash page in bytes
+    pub code_page_size: u32,
+    /// Size of the flash in pages
+    pub code_size: u32,
+}
+
+/// Type of the device address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AddressType {
+    Public,
+    RandomStatic,
+}
@@ -60,9 +60,44 @@ use crate::target::{ P1 };
 
 use crate::hal::digital::{OutputPin, StatefulOutputPin, InputPin};
 
+/// Are P0.09 and P0.10 configured as GPIO instead of the NFC antenna?
+///
+/// This is configured in the UICR, see `Uicr::set_nfc_pins_as_gpio`. While
+/// the pins are used for NFC, they don't work as GPIO.
+#[cfg(nfct)]
+pub fn nfc_pins_are_gpio() -> bool {
+    unsafe { &*crate::target::UICR::ptr() }
+        .nfcpins
+        .read()
+        .protect()
+        .is_disabled()
+}
+
+/// Panic in debug builds if pin `i` of port `port` is an NFC antenna pin that
+/// can't be used as GPIO
+fn check_nfc_pin(port: bool, i: usize) {
+    #[cfg(nfct)]
+    debug_assert!(
+        port || (i != 9 && i != 10) || nfc_pins_are_gpio(),
+        "P0.09 and P0.10 are used for NFC, see `Uicr::set_nfc_pins_as_gpio`"
+    );
+    #[cfg(not(nfct))]
+    let _ = (port, i);
+}
+
 impl<MODE> Pin<MODE> {
+    /// See `check_nfc_pin`
+    fn check_nfc(&self) {
+        #[cfg(feature = "52840")]
+        check_nfc_pin(self.port, self.pin as usize);
+        #[cfg(not(feature = "52840"))]
+        check_nfc_pin(false, self.pin as usize);
+    }
+
     /// Convert the pin to be a floating input
     pub fn into_floating_input(self) -> Pin<Input<Floating>> {
+        self.check_nfc();
+
         unsafe {
             &(*{
                 #[cfg(any(feature = "52810", feature = "52832"))]
@@ -87,6 +122,8 @@ impl<MODE> Pin<MODE> {
         }
     }
     pub fn into_pullup_input(self) -> Pin<Input<PullUp>> {
+        self.check_nfc();
+
         unsafe {
             &(*{
                 #[cfg(any(feature = "52810", feature = "52832"))]
@@ -111,6 +148,8 @@ impl<MODE> Pin<MODE> {
         }
     }
     pub fn into_pulldown_input(self) -> Pin<Input<PullDown>> {
+        self.check_nfc();
+
         unsafe {
             &(*{
                 #[cfg(any(feature = "52810", feature = "52832"))]
@@ -139,6 +178,8 @@ impl<MODE> Pin<MODE> {
     pub fn into_push_pull_output(self, initial_output: Level)
         -> Pin<Output<PushPull>>
     {
+        self.check_nfc();
+
         let mut pin = Pin {
             _mode: PhantomData,
             #[cfg(feature = "52840")]
@@ -180,6 +221,8 @@ impl<MODE> Pin<MODE> {
     )
         -> Pin<Output<OpenDrain>>
     {
+        self.check_nfc();
+
         let mut pin = Pin {
             _mode: PhantomData,
             #[cfg(feature = "52840")]
@@ -283,6 +326,71 @@ impl<MODE> StatefulOutputPin for Pin<Output<MODE>> {
     }
 }
 
+#[cfg(feature = "embedded-hal-1")]
+impl<MODE> embedded_hal_1::digital::ErrorType for Pin<MODE> {
+    type Error = core::convert::Infallible;
+}
+
+#[cfg(feature = "embedded-hal-1")]
+#[allow(deprecated)]
+impl<MODE> embedded_hal_1::digital::InputPin for Pin<Input<MODE>> {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(InputPin::is_high(self))
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(InputPin::is_low(self))
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+#[allow(deprecated)]
+impl<MODE> embedded_hal_1::digital::OutputPin for Pin<Output<MODE>> {
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        OutputPin::set_high(self);
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        OutputPin::set_low(self);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+#[allow(deprecated)]
+impl<MODE> embedded_hal_1::digital::StatefulOutputPin for Pin<Output<MODE>> {
+    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(StatefulOutputPin::is_set_high(self))
+    }
+
+    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(StatefulOutputPin::is_set_low(self))
+    }
+}
+
+/// Adapters for pins implementing only the fallible v2 digital traits, such
+/// as those of IO expanders, to be used with drivers of the v1 traits
+///
+/// The pins of this crate implement the infallible v1 traits, and the v2
+/// traits through the blanket implementations of embedded-hal, so they can
+/// be used with drivers of either version as they are.
+pub use crate::hal::digital::v1_compat::{OldInputPin, OldOutputPin};
+
+// Both versions of the digital traits must stay implemented by the pins
+#[allow(dead_code, deprecated)]
+fn assert_digital_traits() {
+    use crate::hal::digital::{v1, v2};
+
+    fn input<P: v1::InputPin + v2::InputPin>() {}
+    fn output<P: v1::StatefulOutputPin + v2::StatefulOutputPin>() {}
+
+    input::<Pin<Input<Floating>>>();
+    output::<Pin<Output<PushPull>>>();
+    input::<p0::P0_00<Input<Floating>>>();
+    output::<p0::P0_00<Output<PushPull>>>();
+}
+
 /// Pin configuration for open-drain mode
 pub enum OpenDrainConfig {
     Disconnect0Standard1,
@@ -377,6 +485,8 @@ macro_rules! gpio {
                 impl<MODE> $PXi<MODE> {
                     /// Convert the pin to be a floating input
                     pub fn into_floating_input(self) -> $PXi<Input<Floating>> {
+                        super::check_nfc_pin($port_value, $i);
+
                         unsafe { &(*$PX::ptr()).pin_cnf[$i] }.write(|w| {
                             w.dir().input()
                              .input().connect()
@@ -390,6 +500,8 @@ macro_rules! gpio {
                         }
                     }
                     pub fn into_pulldown_input(self) -> $PXi<Input<PullDown>> {
+                        super::check_nfc_pin($port_value, $i);
+
                         unsafe { &(*$PX::ptr()).pin_cnf[$i] }.write(|w| {
                             w.dir().input()
                              .input().connect()
@@ -403,6 +515,8 @@ macro_rules! gpio {
                         }
                     }
                     pub fn into_pullup_input(self) -> $PXi<Input<PullUp>> {
+                        super::check_nfc_pin($port_value, $i);
+
                         unsafe { &(*$PX::ptr()).pin_cnf[$i] }.write(|w| {
                             w.dir().input()
                              .input().connect()
@@ -420,6 +534,8 @@ macro_rules! gpio {
                     pub fn into_push_pull_output(self, initial_output: Level)
                         -> $PXi<Output<PushPull>>
                     {
+                        super::check_nfc_pin($port_value, $i);
+
                         let mut pin = $PXi {
                             _mode: PhantomData,
                         };
@@ -450,6 +566,8 @@ macro_rules! gpio {
                     )
                         -> $PXi<Output<OpenDrain>>
                     {
+                        super::check_nfc_pin($port_value, $i);
+
                         let mut pin = $PXi {
                             _mode: PhantomData,
                         };
@@ -526,6 +644,49 @@ macro_rules! gpio {
                         unsafe { ((*$PX::ptr()).out.read().bits() & (1 << $i)) == 0 }
                     }
                 }
+
+                #[cfg(feature = "embedded-hal-1")]
+                impl<MODE> embedded_hal_1::digital::ErrorType for $PXi<MODE> {
+                    type Error = core::convert::Infallible;
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                #[allow(deprecated)]
+                impl<MODE> embedded_hal_1::digital::InputPin for $PXi<Input<MODE>> {
+                    fn is_high(&mut self) -> Result<bool, Self::Error> {
+                        Ok(InputPin::is_high(self))
+                    }
+
+                    fn is_low(&mut self) -> Result<bool, Self::Error> {
+                        Ok(InputPin::is_low(self))
+                    }
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                #[allow(deprecated)]
+                impl<MODE> embedded_hal_1::digital::OutputPin for $PXi<Output<MODE>> {
+                    fn set_high(&mut self) -> Result<(), Self::Error> {
+                        OutputPin::set_high(self);
+                        Ok(())
+                    }
+
+                    fn set_low(&mut self) -> Result<(), Self::Error> {
+                        OutputPin::set_low(self);
+                        Ok(())
+                    }
+                }
+
+                #[cfg(feature = "embedded-hal-1")]
+                #[allow(deprecated)]
+                impl<MODE> embedded_hal_1::digital::StatefulOutputPin for $PXi<Output<MODE>> {
+                    fn is_set_high(&mut self) -> Result<bool, Self::Error> {
+                        Ok(StatefulOutputPin::is_set_high(self))
+                    }
+
+                    fn is_set_low(&mut self) -> Result<bool, Self::Error> {
+                        Ok(StatefulOutputPin::is_set_low(self))
+                    }
+                }
             )+
         }
     }
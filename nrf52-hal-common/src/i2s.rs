@@ -0,0 +1,878 @@
+//! HAL interface to the Inter-IC Sound (I2S) peripheral
+//!
+//! The I2S generates the master clock (MCK), the bit clock (SCK) and the word
+//! clock (LRCK) for an external DAC, ADC or codec, and streams samples between
+//! it and RAM using EasyDMA. Transmission and reception can run at the same
+//! time, sharing the clocks and the buffer length.
+//!
+//! Samples are transferred in 32 bit words. With 16 bit samples in stereo,
+//! every word holds the left sample in its lower and the right sample in its
+//! upper half. With 8 bit samples, a word holds four samples, and with 24 bit
+//! samples, a word holds a single sample.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 45
+//! - nrf52840: Section 6.10
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+pub use crate::target::i2s::config::{
+    align::ALIGNW as Align, channels::CHANNELSW as Channels, format::FORMATW as Format,
+    mckfreq::MCKFREQW as MckFreq, ratio::RATIOW as Ratio, swidth::SWIDTHW as SampleWidth,
+};
+
+use crate::gpio::{Floating, Input, Output, Pin, PushPull};
+use crate::stream::DoubleBuffered;
+use crate::target::I2S;
+
+use embedded_dma::WriteBuffer;
+
+/// Largest number of words in a buffer
+pub const MAX_WORDS: usize = 0x3FFF;
+
+/// Frequency the MCK is divided from
+const MCK_SOURCE: u32 = 32_000_000;
+
+/// MCK dividers, from the fastest MCK to the slowest
+const MCK_DIVIDERS: [u32; 18] = [2, 3, 4, 5, 6, 8, 10, 11, 15, 16, 21, 23, 30, 31, 32, 42, 63, 125];
+
+/// Ratios of MCK to LRCK, from the smallest to the largest
+const RATIOS: [u32; 9] = [32, 48, 64, 96, 128, 192, 256, 384, 512];
+
+/// Interface to the I2S peripheral
+pub struct I2s {
+    periph: I2S,
+}
+
+impl I2s {
+    /// Take ownership of the I2S peripheral, as the master of the bus
+    ///
+    /// Transmission requires `pins.sdout`, and reception `pins.sdin`. The
+    /// sample rate is the MCK frequency divided by the ratio.
+    pub fn new(i2s: I2S, pins: Pins, config: Config) -> Self {
+        match &pins.mck {
+            Some(mck) => i2s.psel.mck.write(|w| {
+                let w = unsafe { w.pin().bits(mck.pin) };
+                #[cfg(feature = "52840")]
+                let w = w.port().bit(mck.port);
+                w.connect().connected()
+            }),
+            None => i2s.psel.mck.write(|w| w.connect().disconnected()),
+        }
+        i2s.psel.sck.write(|w| {
+            let w = unsafe { w.pin().bits(pins.sck.pin) };
+            #[cfg(feature = "52840")]
+            let w = w.port().bit(pins.sck.port);
+            w.connect().connected()
+        });
+        i2s.psel.lrck.write(|w| {
+            let w = unsafe { w.pin().bits(pins.lrck.pin) };
+            #[cfg(feature = "52840")]
+            let w = w.port().bit(pins.lrck.port);
+            w.connect().connected()
+        });
+        match &pins.sdout {
+            Some(sdout) => i2s.psel.sdout.write(|w| {
+                let w = unsafe { w.pin().bits(sdout.pin) };
+                #[cfg(feature = "52840")]
+                let w = w.port().bit(sdout.port);
+                w.connect().connected()
+            }),
+            None => i2s.psel.sdout.write(|w| w.connect().disconnected()),
+        }
+        match &pins.sdin {
+            Some(sdin) => i2s.psel.sdin.write(|w| {
+                let w = unsafe { w.pin().bits(sdin.pin) };
+                #[cfg(feature = "52840")]
+                let w = w.port().bit(sdin.port);
+                w.connect().connected()
+            }),
+            None => i2s.psel.sdin.write(|w| w.connect().disconnected()),
+        }
+
+        i2s.config.mode.write(|w| w.mode().master());
+        i2s.config.mcken.write(|w| w.mcken().enabled());
+
+        let mut i2s = I2s { periph: i2s };
+        i2s.configure(config);
+        i2s.periph.enable.write(|w| w.enable().enabled());
+
+        i2s
+    }
+
+    /// Apply a configuration, while the I2S is stopped
+    pub fn configure(&mut self, config: Config) {
+        let Config {
+            mck_freq,
+            ratio,
+            sample_width,
+            align,
+            format,
+            channels,
+        } = config;
+
+        let c = &self.periph.config;
+        c.mckfreq.write(|w| w.mckfreq().variant(mck_freq));
+        c.ratio.write(|w| w.ratio().variant(ratio));
+        c.swidth.write(|w| w.swidth().variant(sample_width));
+        c.align.write(|w| w.align().variant(align));
+        c.format.write(|w| w.format().variant(format));
+        c.channels.write(|w| w.channels().variant(channels));
+    }
+
+    /// Play `buffer` once, blocking until it has been sent
+    pub fn transmit(&mut self, buffer: &[u32]) -> Result<(), Error> {
+        check_buffer(buffer)?;
+
+        self.set_directions(true, false)?;
+        self.set_tx_buffer(buffer);
+        self.start();
+
+        // The first update marks the start of the buffer, the second one its
+        // end
+        for _ in 0..2 {
+            while self.periph.events_txptrupd.read().bits() == 0 {}
+            self.periph.events_txptrupd.reset();
+        }
+
+        self.stop();
+        Ok(())
+    }
+
+    /// Fill `buffer` with received samples, blocking until it is full
+    pub fn receive(&mut self, buffer: &mut [u32]) -> Result<(), Error> {
+        check_buffer(buffer)?;
+
+        self.set_directions(false, true)?;
+        self.set_rx_buffer(buffer);
+        self.start();
+
+        for _ in 0..2 {
+            while self.periph.events_rxptrupd.read().bits() == 0 {}
+            self.periph.events_rxptrupd.reset();
+        }
+
+        // Stop right away, before the restarted transfer overwrites the first
+        // word of the buffer
+        self.stop();
+        Ok(())
+    }
+
+    /// Start playing two buffers alternately
+    ///
+    /// While one buffer is played, the other one is handed out by
+    /// `TxStream::next_buffer` to be refilled. Both buffers must have the same
+    /// length. They are owned by the stream until it is stopped, so EasyDMA
+    /// can't outlive them even if the stream is leaked.
+    pub fn start_tx<B>(
+        mut self,
+        mut first: B,
+        mut second: B,
+    ) -> Result<TxStream<B>, (Error, B, B, Self)>
+    where
+        B: WriteBuffer<Word = u32> + 'static,
+    {
+        let (first_slice, second_slice) = (as_slice(&mut first), as_slice(&mut second));
+        if let Err(e) = check_buffer(first_slice).and(check_buffer(second_slice)) {
+            return Err((e, first, second, self));
+        }
+        if first_slice.len() != second_slice.len() {
+            return Err((Error::BufferLengthMismatch, first, second, self));
+        }
+        if let Err(e) = self.set_directions(true, false) {
+            return Err((e, first, second, self));
+        }
+
+        self.set_tx_buffer(as_slice(&mut first));
+        self.start();
+
+        // Once the first pointer has been latched, the second buffer can be
+        // queued
+        while self.periph.events_txptrupd.read().bits() == 0 {}
+        self.periph.events_txptrupd.reset();
+        self.set_tx_buffer(as_slice(&mut second));
+
+        Ok(TxStream {
+            inner: Some((self, [first, second])),
+            playing: 0,
+        })
+    }
+
+    /// Start receiving into two buffers alternately
+    ///
+    /// While one buffer is filled, the other one is handed out by
+    /// `RxStream::next_frame`. Both buffers must have the same length. They
+    /// are owned by the stream until it is stopped, so EasyDMA can't outlive
+    /// them even if the stream is leaked.
+    pub fn start_rx<B>(
+        mut self,
+        mut first: B,
+        mut second: B,
+    ) -> Result<RxStream<B>, (Error, B, B, Self)>
+    where
+        B: WriteBuffer<Word = u32> + 'static,
+    {
+        let (first_slice, second_slice) = (as_slice(&mut first), as_slice(&mut second));
+        if let Err(e) = check_buffer(first_slice).and(check_buffer(second_slice)) {
+            return Err((e, first, second, self));
+        }
+        if first_slice.len() != second_slice.len() {
+            return Err((Error::BufferLengthMismatch, first, second, self));
+        }
+        if let Err(e) = self.set_directions(false, true) {
+            return Err((e, first, second, self));
+        }
+
+        self.set_rx_buffer(as_slice(&mut first));
+        self.start();
+
+        // Once the first pointer has been latched, the second buffer can be
+        // queued
+        while self.periph.events_rxptrupd.read().bits() == 0 {}
+        self.periph.events_rxptrupd.reset();
+        self.set_rx_buffer(as_slice(&mut second));
+
+        Ok(RxStream {
+            inner: Some((self, [first, second])),
+            filling: 0,
+        })
+    }
+
+    /// Start transmitting and receiving at the same time
+    ///
+    /// Two transmit and two receive buffers are used alternately, see
+    /// `DuplexStream::next_buffers`. As both directions share the buffer
+    /// length, all four buffers must have the same length. They are owned by
+    /// the stream until it is stopped, so EasyDMA can't outlive them even if
+    /// the stream is leaked.
+    pub fn start_duplex<TxB, RxB>(
+        mut self,
+        mut tx: [TxB; 2],
+        mut rx: [RxB; 2],
+    ) -> Result<DuplexStream<TxB, RxB>, (Error, [TxB; 2], [RxB; 2], Self)>
+    where
+        TxB: WriteBuffer<Word = u32> + 'static,
+        RxB: WriteBuffer<Word = u32> + 'static,
+    {
+        let len = as_slice(&mut tx[0]).len();
+        let [tx0, tx1] = &mut tx;
+        let [rx0, rx1] = &mut rx;
+        let buffers = [as_slice(tx0), as_slice(tx1), as_slice(rx0), as_slice(rx1)];
+        for buffer in buffers.iter() {
+            if let Err(e) = check_buffer(buffer) {
+                return Err((e, tx, rx, self));
+            }
+            if buffer.len() != len {
+                return Err((Error::BufferLengthMismatch, tx, rx, self));
+            }
+        }
+        if let Err(e) = self.set_directions(true, true) {
+            return Err((e, tx, rx, self));
+        }
+
+        self.set_tx_buffer(as_slice(&mut tx[0]));
+        self.set_rx_buffer(as_slice(&mut rx[0]));
+        self.start();
+
+        // Both pointers are latched at the same time
+        while self.periph.events_txptrupd.read().bits() == 0
+            || self.periph.events_rxptrupd.read().bits() == 0
+        {}
+        self.periph.events_txptrupd.reset();
+        self.periph.events_rxptrupd.reset();
+        self.set_tx_buffer(as_slice(&mut tx[1]));
+        self.set_rx_buffer(as_slice(&mut rx[1]));
+
+        Ok(DuplexStream {
+            inner: Some((self, tx, rx)),
+            current: 0,
+        })
+    }
+
+    /// Return the underlying I2S peripheral
+    pub fn free(self) -> I2S {
+        self.periph.enable.write(|w| w.enable().disabled());
+        self.periph
+    }
+
+    fn set_directions(&self, tx: bool, rx: bool) -> Result<(), Error> {
+        let psel = &self.periph.psel;
+        if tx && psel.sdout.read().connect().is_disconnected() {
+            return Err(Error::SdoutNotConnected);
+        }
+        if rx && psel.sdin.read().connect().is_disconnected() {
+            return Err(Error::SdinNotConnected);
+        }
+
+        let config = &self.periph.config;
+        config.txen.write(|w| w.txen().bit(tx));
+        config.rxen.write(|w| w.rxen().bit(rx));
+        Ok(())
+    }
+
+    fn set_tx_buffer(&mut self, buffer: &[u32]) {
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+
+        self.periph
+            .txd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(buffer.as_ptr() as u32) });
+        self.periph
+            .rxtxd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(buffer.len() as u16) });
+    }
+
+    fn set_rx_buffer(&mut self, buffer: &mut [u32]) {
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+
+        self.periph
+            .rxd
+            .ptr
+            .write(|w| unsafe { w.ptr().bits(buffer.as_mut_ptr() as u32) });
+        self.periph
+            .rxtxd
+            .maxcnt
+            .write(|w| unsafe { w.maxcnt().bits(buffer.len() as u16) });
+    }
+
+    fn start(&mut self) {
+        self.periph.events_txptrupd.reset();
+        self.periph.events_rxptrupd.reset();
+        self.periph.events_stopped.reset();
+        self.periph.tasks_start.write(|w| unsafe { w.bits(1) });
+    }
+
+    fn stop(&mut self) {
+        self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+        while self.periph.events_stopped.read().bits() == 0 {}
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        self.periph.events_txptrupd.reset();
+        self.periph.events_rxptrupd.reset();
+        self.periph.events_stopped.reset();
+    }
+}
+
+/// Continuous playback of two buffers
+pub struct TxStream<B> {
+    // Only taken by `stop`, so the I2S is stopped when the stream is dropped
+    inner: Option<(I2s, [B; 2])>,
+    playing: usize,
+}
+
+impl<B> TxStream<B>
+where
+    B: WriteBuffer<Word = u32>,
+{
+    /// Return the next buffer to be refilled, once it has been played
+    ///
+    /// The buffer is played again as soon as the other buffer has been
+    /// played, so it must be refilled within the time it takes to play a
+    /// buffer.
+    pub fn next_buffer(&mut self) -> nb::Result<&mut [u32], Error> {
+        match self.complete() {
+            Some(played) => Ok(self.buffer(played)),
+            None => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    /// Stop playing, and return the I2S and the buffers
+    pub fn stop(mut self) -> (I2s, B, B) {
+        let (mut i2s, [first, second]) = self.inner.take().unwrap();
+        i2s.stop();
+        (i2s, first, second)
+    }
+
+    fn i2s(&self) -> &I2s {
+        &self.inner.as_ref().unwrap().0
+    }
+
+    fn buffer(&mut self, index: usize) -> &mut [u32] {
+        as_slice(&mut self.inner.as_mut().unwrap().1[index])
+    }
+
+    /// Requeue the buffer that has been played, and return its index
+    fn complete(&mut self) -> Option<usize> {
+        if self.i2s().periph.events_txptrupd.read().bits() == 0 {
+            return None;
+        }
+        self.i2s().periph.events_txptrupd.reset();
+
+        // The other buffer has been latched, so the played buffer is queued
+        // next
+        let played = self.playing;
+        self.playing ^= 1;
+        let (i2s, buffers) = self.inner.as_mut().unwrap();
+        i2s.set_tx_buffer(as_slice(&mut buffers[played]));
+
+        Some(played)
+    }
+}
+
+impl<B> DoubleBuffered for TxStream<B>
+where
+    B: WriteBuffer<Word = u32>,
+{
+    type Word = u32;
+
+    fn next_completed(&mut self) -> Option<&mut [u32]> {
+        let played = self.complete()?;
+        Some(self.buffer(played))
+    }
+
+    fn enable_interrupt(&mut self) {
+        self.i2s().periph.intenset.write(|w| w.txptrupd().set());
+    }
+
+    fn disable_interrupt(&mut self) {
+        self.i2s().periph.intenclr.write(|w| w.txptrupd().clear());
+    }
+}
+
+impl<B> Drop for TxStream<B> {
+    fn drop(&mut self) {
+        if let Some((i2s, _)) = &mut self.inner {
+            i2s.stop();
+        }
+    }
+}
+
+/// Continuous reception into two buffers
+pub struct RxStream<B> {
+    // Only taken by `stop`, so the I2S is stopped when the stream is dropped
+    inner: Option<(I2s, [B; 2])>,
+    filling: usize,
+}
+
+impl<B> RxStream<B>
+where
+    B: WriteBuffer<Word = u32>,
+{
+    /// Return the next buffer of samples, once it has been filled
+    ///
+    /// The buffer is refilled as soon as the other buffer is full, so it must
+    /// be processed within the time it takes to fill a buffer.
+    pub fn next_frame(&mut self) -> nb::Result<&[u32], Error> {
+        match self.complete() {
+            Some(filled) => Ok(self.buffer(filled)),
+            None => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    /// Stop receiving, and return the I2S and the buffers
+    pub fn stop(mut self) -> (I2s, B, B) {
+        let (mut i2s, [first, second]) = self.inner.take().unwrap();
+        i2s.stop();
+        (i2s, first, second)
+    }
+
+    fn i2s(&self) -> &I2s {
+        &self.inner.as_ref().unwrap().0
+    }
+
+    fn buffer(&mut self, index: usize) -> &mut [u32] {
+        as_slice(&mut self.inner.as_mut().unwrap().1[index])
+    }
+
+    /// Requeue the buffer that has been filled, and return its index
+    fn complete(&mut self) -> Option<usize> {
+        if self.i2s().periph.events_rxptrupd.read().bits() == 0 {
+            return None;
+        }
+        self.i2s().periph.events_rxptrupd.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions on the filled buffer have completed
+        compiler_fence(SeqCst);
+
+        // The other buffer has been latched, so the filled buffer is queued
+        // next
+        let filled = self.filling;
+        self.filling ^= 1;
+        let (i2s, buffers) = self.inner.as_mut().unwrap();
+        i2s.set_rx_buffer(as_slice(&mut buffers[filled]));
+
+        Some(filled)
+    }
+}
+
+impl<B> DoubleBuffered for RxStream<B>
+where
+    B: WriteBuffer<Word = u32>,
+{
+    type Word = u32;
+
+    fn next_completed(&mut self) -> Option<&mut [u32]> {
+        let filled = self.complete()?;
+        Some(self.buffer(filled))
+    }
+
+    fn enable_interrupt(&mut self) {
+        self.i2s().periph.intenset.write(|w| w.rxptrupd().set());
+    }
+
+    fn disable_interrupt(&mut self) {
+        self.i2s().periph.intenclr.write(|w| w.rxptrupd().clear());
+    }
+}
+
+impl<B> Drop for RxStream<B> {
+    fn drop(&mut self) {
+        if let Some((i2s, _)) = &mut self.inner {
+            i2s.stop();
+        }
+    }
+}
+
+/// Continuous transmission and reception, with two buffers per direction
+pub struct DuplexStream<TxB, RxB> {
+    // Only taken by `stop`, so the I2S is stopped when the stream is dropped
+    inner: Option<(I2s, [TxB; 2], [RxB; 2])>,
+    current: usize,
+}
+
+impl<TxB, RxB> DuplexStream<TxB, RxB>
+where
+    TxB: WriteBuffer<Word = u32>,
+    RxB: WriteBuffer<Word = u32>,
+{
+    /// Return the next pair of buffers, once they have been transferred
+    ///
+    /// The first buffer has been played and is to be refilled, the second one
+    /// holds the samples received meanwhile. Both are transferred again as
+    /// soon as the other pair has been, so they must be processed within the
+    /// time it takes to transfer a buffer.
+    pub fn next_buffers(&mut self) -> nb::Result<(&mut [u32], &[u32]), Error> {
+        let (i2s, tx, rx) = self.inner.as_mut().unwrap();
+        let periph = &i2s.periph;
+        if periph.events_txptrupd.read().bits() == 0 || periph.events_rxptrupd.read().bits() == 0
+        {
+            return Err(nb::Error::WouldBlock);
+        }
+        periph.events_txptrupd.reset();
+        periph.events_rxptrupd.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions on the transferred buffers have
+        // completed
+        compiler_fence(SeqCst);
+
+        let done = self.current;
+        self.current ^= 1;
+        i2s.set_tx_buffer(as_slice(&mut tx[done]));
+        i2s.set_rx_buffer(as_slice(&mut rx[done]));
+
+        Ok((as_slice(&mut tx[done]), as_slice(&mut rx[done])))
+    }
+
+    /// Stop transferring, and return the I2S and the transmit and receive
+    /// buffers
+    pub fn stop(mut self) -> (I2s, [TxB; 2], [RxB; 2]) {
+        let (mut i2s, tx, rx) = self.inner.take().unwrap();
+        i2s.stop();
+        (i2s, tx, rx)
+    }
+}
+
+impl<TxB, RxB> Drop for DuplexStream<TxB, RxB> {
+    fn drop(&mut self) {
+        if let Some((i2s, _, _)) = &mut self.inner {
+            i2s.stop();
+        }
+    }
+}
+
+fn check_buffer(buffer: &[u32]) -> Result<(), Error> {
+    if buffer.is_empty() {
+        return Err(Error::BufferEmpty);
+    }
+    if buffer.len() > MAX_WORDS {
+        return Err(Error::BufferTooLong);
+    }
+    if !crate::slice_in_ram(as_bytes(buffer)) {
+        return Err(Error::DMABufferNotInDataMemory);
+    }
+    Ok(())
+}
+
+fn as_slice<B>(buffer: &mut B) -> &mut [u32]
+where
+    B: WriteBuffer<Word = u32>,
+{
+    // The buffer traits guarantee that the memory stays valid and in place
+    // for as long as the buffer is owned
+    unsafe {
+        let (ptr, len) = buffer.write_buffer();
+        core::slice::from_raw_parts_mut(ptr, len)
+    }
+}
+
+fn as_bytes(buffer: &[u32]) -> &[u8] {
+    unsafe { core::slice::from_raw_parts(buffer.as_ptr() as *const u8, buffer.len() * 4) }
+}
+
+/// Configuration of the I2S
+///
+/// The default configuration generates a 4 MHz MCK and a sample rate of
+/// 15.625 kHz, with 16 bit stereo samples in the I2S format.
+pub struct Config {
+    pub mck_freq: MckFreq,
+    pub ratio: Ratio,
+    pub sample_width: SampleWidth,
+    pub align: Align,
+    pub format: Format,
+    pub channels: Channels,
+}
+
+impl Config {
+    /// Configuration for a common audio format, in the I2S format with the
+    /// MCK connected
+    ///
+    /// The common formats are 16 and 24 bit samples at 16000, 44100 or 48000
+    /// Hz. Like `exact`, this returns `Error::UnsupportedSampleRate` unless
+    /// the rate can be divided exactly from the 32 MHz MCK source, which is
+    /// not the case for any of them. Use `nearest` to accept the closest rate
+    /// instead. Other formats return `Error::UnsupportedSampleRate` as well.
+    pub fn preset(sample_width: SampleWidth, sample_rate: u32) -> Result<Self, Error> {
+        match (&sample_width, sample_rate) {
+            (SampleWidth::_16BIT, 16_000)
+            | (SampleWidth::_16BIT, 44_100)
+            | (SampleWidth::_16BIT, 48_000)
+            | (SampleWidth::_24BIT, 16_000)
+            | (SampleWidth::_24BIT, 44_100)
+            | (SampleWidth::_24BIT, 48_000) => Self::exact(sample_width, sample_rate),
+            _ => Err(Error::UnsupportedSampleRate),
+        }
+    }
+
+    /// Configuration for exactly `sample_rate`, in the I2S format with the
+    /// MCK connected
+    ///
+    /// Of all combinations generating the rate, the one with the fastest MCK
+    /// is used. Returns `Error::UnsupportedSampleRate` if the rate can't be
+    /// divided exactly from the 32 MHz MCK source.
+    pub fn exact(sample_width: SampleWidth, sample_rate: u32) -> Result<Self, Error> {
+        let (divider, ratio) = combinations(&sample_width)
+            .find(|&(divider, ratio)| {
+                let lrck = divider * ratio;
+                MCK_SOURCE % lrck == 0 && MCK_SOURCE / lrck == sample_rate
+            })
+            .ok_or(Error::UnsupportedSampleRate)?;
+
+        Ok(Self::from_clocks(sample_width, divider, ratio))
+    }
+
+    /// Configuration for the rate closest to `sample_rate`, in the I2S format
+    /// with the MCK connected
+    ///
+    /// Of all combinations generating the closest rate, the one with the
+    /// fastest MCK is used. The rate is approximated, so check `sample_rate`
+    /// for the one actually generated. For the common audio formats, these
+    /// are:
+    ///
+    /// | Sample width | Sample rate | MCK          | Ratio | Actual rate |
+    /// |--------------|-------------|--------------|-------|-------------|
+    /// | 16 bit       | 16000 Hz    | 32 MHz / 21  | 96    | 15873 Hz    |
+    /// | 16 bit       | 44100 Hz    | 32 MHz / 15  | 48    | 44444 Hz    |
+    /// | 16 bit       | 48000 Hz    | 32 MHz / 21  | 32    | 47619 Hz    |
+    /// | 24 bit       | 16000 Hz    | 32 MHz / 21  | 96    | 15873 Hz    |
+    /// | 24 bit       | 44100 Hz    | 32 MHz / 15  | 48    | 44444 Hz    |
+    /// | 24 bit       | 48000 Hz    | 32 MHz / 15  | 48    | 44444 Hz    |
+    ///
+    /// All of them are within 0.8% of the nominal rate, except for 24 bit
+    /// samples at 48000 Hz, which are off by more than 7%.
+    pub fn nearest(sample_width: SampleWidth, sample_rate: u32) -> Self {
+        let (divider, ratio) = combinations(&sample_width)
+            .min_by_key(|&(divider, ratio)| {
+                let rate = MCK_SOURCE / (divider * ratio);
+                if rate > sample_rate { rate - sample_rate } else { sample_rate - rate }
+            })
+            // Every sample width fits in the largest ratio
+            .unwrap();
+
+        Self::from_clocks(sample_width, divider, ratio)
+    }
+
+    fn from_clocks(sample_width: SampleWidth, divider: u32, ratio: u32) -> Self {
+        Config {
+            mck_freq: to_mck_freq(divider),
+            ratio: to_ratio(ratio),
+            sample_width,
+            ..Config::default()
+        }
+    }
+
+    /// Return the sample rate generated by this configuration, in Hz
+    pub fn sample_rate(&self) -> u32 {
+        MCK_SOURCE / (mck_divider(&self.mck_freq) * ratio_value(&self.ratio))
+    }
+}
+
+/// Return the MCK dividers and ratios that can be used with `sample_width`,
+/// from the fastest MCK to the slowest
+fn combinations(sample_width: &SampleWidth) -> impl Iterator<Item = (u32, u32)> {
+    let bits = sample_width_bits(sample_width);
+
+    MCK_DIVIDERS
+        .iter()
+        .flat_map(|&divider| RATIOS.iter().map(move |&ratio| (divider, ratio)))
+        // A frame must hold two samples, and 24 bit samples must fill it
+        // completely
+        .filter(move |&(_, ratio)| ratio >= 2 * bits && (bits != 24 || ratio % 48 == 0))
+}
+
+fn sample_width_bits(sample_width: &SampleWidth) -> u32 {
+    match sample_width {
+        SampleWidth::_8BIT => 8,
+        SampleWidth::_16BIT => 16,
+        SampleWidth::_24BIT => 24,
+    }
+}
+
+fn mck_divider(mck_freq: &MckFreq) -> u32 {
+    match mck_freq {
+        MckFreq::_32MDIV2 => 2,
+        MckFreq::_32MDIV3 => 3,
+        MckFreq::_32MDIV4 => 4,
+        MckFreq::_32MDIV5 => 5,
+        MckFreq::_32MDIV6 => 6,
+        MckFreq::_32MDIV8 => 8,
+        MckFreq::_32MDIV10 => 10,
+        MckFreq::_32MDIV11 => 11,
+        MckFreq::_32MDIV15 => 15,
+        MckFreq::_32MDIV16 => 16,
+        MckFreq::_32MDIV21 => 21,
+        MckFreq::_32MDIV23 => 23,
+        MckFreq::_32MDIV30 => 30,
+        MckFreq::_32MDIV31 => 31,
+        MckFreq::_32MDIV32 => 32,
+        MckFreq::_32MDIV42 => 42,
+        MckFreq::_32MDIV63 => 63,
+        MckFreq::_32MDIV125 => 125,
+    }
+}
+
+fn to_mck_freq(divider: u32) -> MckFreq {
+    match divider {
+        2 => MckFreq::_32MDIV2,
+        3 => MckFreq::_32MDIV3,
+        4 => MckFreq::_32MDIV4,
+        5 => MckFreq::_32MDIV5,
+        6 => MckFreq::_32MDIV6,
+        8 => MckFreq::_32MDIV8,
+        10 => MckFreq::_32MDIV10,
+        11 => MckFreq::_32MDIV11,
+        15 => MckFreq::_32MDIV15,
+        16 => MckFreq::_32MDIV16,
+        21 => MckFreq::_32MDIV21,
+        23 => MckFreq::_32MDIV23,
+        30 => MckFreq::_32MDIV30,
+        31 => MckFreq::_32MDIV31,
+        32 => MckFreq::_32MDIV32,
+        42 => MckFreq::_32MDIV42,
+        63 => MckFreq::_32MDIV63,
+        _ => MckFreq::_32MDIV125,
+    }
+}
+
+fn ratio_value(ratio: &Ratio) -> u32 {
+    match ratio {
+        Ratio::_32X => 32,
+        Ratio::_48X => 48,
+        Ratio::_64X => 64,
+        Ratio::_96X => 96,
+        Ratio::_128X => 128,
+        Ratio::_192X => 192,
+        Ratio::_256X => 256,
+        Ratio::_384X => 384,
+        Ratio::_512X => 512,
+    }
+}
+
+fn to_ratio(value: u32) -> Ratio {
+    match value {
+        32 => Ratio::_32X,
+        48 => Ratio::_48X,
+        64 => Ratio::_64X,
+        96 => Ratio::_96X,
+        128 => Ratio::_128X,
+        192 => Ratio::_192X,
+        256 => Ratio::_256X,
+        384 => Ratio::_384X,
+        _ => Ratio::_512X,
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mck_freq: MckFreq::_32MDIV8,
+            ratio: Ratio::_256X,
+            sample_width: SampleWidth::_16BIT,
+            align: Align::LEFT,
+            format: Format::I2S,
+            channels: Channels::STEREO,
+        }
+    }
+}
+
+// The fields are register values of the PAC, which are formatted raw
+#[cfg(feature = "defmt")]
+impl defmt::Format for Config {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Config {{ mck_freq: {=u32:#x}, ratio: {=u8}, sample_width: {=u8}, align: {=bool}, format: {=bool}, channels: {=u8} }}",
+            self.mck_freq._bits(),
+            self.ratio._bits(),
+            self.sample_width._bits(),
+            self.align._bits(),
+            self.format._bits(),
+            self.channels._bits(),
+        )
+    }
+}
+
+/// GPIO pins for the I2S interface
+pub struct Pins {
+    /// Master clock output, None if unused
+    pub mck: Option<Pin<Output<PushPull>>>,
+
+    /// Bit clock output
+    pub sck: Pin<Output<PushPull>>,
+
+    /// Word clock output
+    pub lrck: Pin<Output<PushPull>>,
+
+    /// Serial data output, None if only receiving
+    pub sdout: Option<Pin<Output<PushPull>>>,
+
+    /// Serial data input, None if only transmitting
+    pub sdin: Option<Pin<Input<Floating>>>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    BufferEmpty,
+    BufferTooLong,
+    BufferLengthMismatch,
+    SdoutNotConnected,
+    SdinNotConnected,
+    UnsupportedSampleRate,
+    /// EasyDMA can only read from data memory, read only buffers in flash will fail
+    DMABufferNotInDataMemory,
+}
@@ -0,0 +1,181 @@
+//! Ownership of peripheral interrupts
+//!
+//! An `Irq` is the only handle to the interrupt of a peripheral, which can be
+//! taken once with `Irq::take`. Its owner masks, unmasks and prioritizes the
+//! interrupt without needing the `NVIC`, and without anybody else changing
+//! its configuration in the meantime.
+//!
+//! The drivers of peripherals with an interrupt can take ownership of it with
+//! `with_irq`, and expose it through `irq`:
+//!
+//! ``` ignore
+//! let irq = Irq::take(Interrupt::TIMER1).unwrap();
+//! let mut timer = Timer::new(p.TIMER1).with_irq(irq);
+//! timer.irq().unwrap().set_priority(2);
+//! ```
+//!
+//! The interrupt handler can also record the events of a peripheral in its
+//! `EventFlags`, which are then consumed from thread mode, e.g. with
+//! `Timer::<TIMER1>::take_event`, without a critical section.
+use core::ptr;
+use core::sync::atomic::{AtomicU32, Ordering::SeqCst};
+
+use cortex_m::interrupt::Nr;
+
+use crate::target::{Interrupt, NVIC};
+
+/// Number of bits of the interrupt priorities
+pub const PRIORITY_BITS: u8 = 3;
+
+/// Interrupts that have been taken, one bit per interrupt number
+static TAKEN: [AtomicU32; 2] = [AtomicU32::new(0), AtomicU32::new(0)];
+
+/// Exclusive ownership of a peripheral interrupt
+///
+/// Dropping the `Irq` gives up the ownership, so the interrupt can be taken
+/// again. Its configuration is left as it is.
+#[derive(Debug)]
+pub struct Irq {
+    nr: u8,
+}
+
+impl Irq {
+    /// Take ownership of `interrupt`, or return `None` if it is owned already
+    ///
+    /// Peripherals that share an interrupt, e.g. SPIM0 and TWIM0, share the
+    /// same `Irq`.
+    pub fn take(interrupt: Interrupt) -> Option<Self> {
+        let nr = interrupt.nr();
+        let bit = 1 << (nr % 32);
+        if TAKEN[nr as usize / 32].fetch_or(bit, SeqCst) & bit != 0 {
+            None
+        } else {
+            Some(Irq { nr })
+        }
+    }
+
+    /// Return the number of the interrupt
+    pub fn nr(&self) -> u8 {
+        self.nr
+    }
+
+    /// Return whether this is the `Irq` of `interrupt`
+    pub fn is(&self, interrupt: Interrupt) -> bool {
+        self.nr == interrupt.nr()
+    }
+
+    /// Unmask the interrupt in the NVIC
+    pub fn unmask(&mut self) {
+        // The ISER registers are write-1-to-set, so this doesn't touch other
+        // interrupts. The same goes for the other registers below.
+        unsafe { self.nvic().iser[self.index()].write(self.bit()) }
+    }
+
+    /// Mask the interrupt in the NVIC
+    pub fn mask(&mut self) {
+        unsafe { self.nvic().icer[self.index()].write(self.bit()) }
+    }
+
+    /// Return whether the interrupt is unmasked in the NVIC
+    pub fn is_unmasked(&self) -> bool {
+        self.nvic().iser[self.index()].read() & self.bit() != 0
+    }
+
+    /// Set the interrupt pending, so its handler runs once unmasked
+    pub fn pend(&mut self) {
+        unsafe { self.nvic().ispr[self.index()].write(self.bit()) }
+    }
+
+    /// Clear the pending state of the interrupt
+    pub fn unpend(&mut self) {
+        unsafe { self.nvic().icpr[self.index()].write(self.bit()) }
+    }
+
+    /// Return whether the interrupt is pending
+    pub fn is_pending(&self) -> bool {
+        self.nvic().ispr[self.index()].read() & self.bit() != 0
+    }
+
+    /// Set the priority of the interrupt, from 0, the highest, to 7
+    ///
+    /// The priorities 0, 1 and 4 are reserved by the SoftDevice, if it is
+    /// used.
+    pub fn set_priority(&mut self, priority: u8) {
+        assert!(priority < 1 << PRIORITY_BITS);
+        // The priority register of every interrupt is a byte of its own
+        unsafe {
+            self.nvic().ipr[self.nr as usize].write(priority << (8 - PRIORITY_BITS));
+        }
+    }
+
+    /// Return the priority of the interrupt, see `set_priority`
+    pub fn priority(&self) -> u8 {
+        self.nvic().ipr[self.nr as usize].read() >> (8 - PRIORITY_BITS)
+    }
+
+    fn nvic(&self) -> &'static cortex_m::peripheral::nvic::RegisterBlock {
+        unsafe { &*NVIC::ptr() }
+    }
+
+    fn index(&self) -> usize {
+        self.nr as usize / 32
+    }
+
+    fn bit(&self) -> u32 {
+        1 << (self.nr % 32)
+    }
+}
+
+impl Drop for Irq {
+    fn drop(&mut self) {
+        TAKEN[self.index()].fetch_and(!self.bit(), SeqCst);
+    }
+}
+
+/// Events of a peripheral instance that have been recorded by its interrupt
+/// handler, and not taken yet
+///
+/// There is one bit per event, in the order of the interrupt enable bits.
+/// Both recording and taking are single atomic operations.
+#[derive(Debug)]
+pub struct EventFlags(AtomicU32);
+
+impl EventFlags {
+    pub(crate) const fn new() -> Self {
+        EventFlags(AtomicU32::new(0))
+    }
+
+    /// Return whether any of the events in `mask` has been recorded, and
+    /// clear them
+    pub(crate) fn take(&self, mask: u32) -> bool {
+        self.0.fetch_and(!mask, SeqCst) & mask != 0
+    }
+
+    /// Return whether any of the events in `mask` has been recorded
+    pub(crate) fn is_set(&self, mask: u32) -> bool {
+        self.0.load(SeqCst) & mask != 0
+    }
+
+    /// Record and reset the events of the peripheral at `registers` whose
+    /// interrupt is enabled in `inten`
+    ///
+    /// # Safety
+    ///
+    /// `registers` must be the register block of a peripheral, whose event
+    /// registers are at `0x100 + 4 * n` for interrupt enable bit `n`, as for
+    /// all nRF52 peripherals.
+    pub(crate) unsafe fn record(&self, registers: *const u8, inten: u32) {
+        let mut recorded = 0;
+        for n in 0..32 {
+            if inten & (1 << n) == 0 {
+                continue;
+            }
+            let event = registers.add(0x100 + 4 * n) as *mut u32;
+            if ptr::read_volatile(event) != 0 {
+                ptr::write_volatile(event, 0);
+                recorded |= 1 << n;
+            }
+        }
+        self.0.fetch_or(recorded, SeqCst);
+    }
+}
@@ -2,6 +2,8 @@
 
 use embedded_hal as hal;
 
+pub use embedded_dma;
+
 #[cfg(feature = "52810")]
 pub use nrf52810_pac as target;
 
@@ -11,18 +13,65 @@ pub use nrf52832_pac as target;
 #[cfg(feature = "52840")]
 pub use nrf52840_pac as target;
 
+/// The peripheral access crate of the chip
+pub use crate::target as pac;
+
+pub mod aar;
+#[cfg(acl)]
+pub mod acl;
+#[cfg(bprot)]
+pub mod bprot;
+pub mod ccm;
 pub mod clocks;
+pub mod comp;
+pub mod crypto;
+#[cfg(all(cc310, feature = "cryptocell"))]
+pub mod cryptocell;
 pub mod delay;
+pub mod dfu;
+pub mod dma;
+pub mod ecb;
+pub mod ficr;
 pub mod gpio;
+#[cfg(i2s)]
+pub mod i2s;
+pub mod irq;
+#[cfg(lpcomp)]
+pub mod lpcomp;
+#[cfg(feature = "rtic-monotonic")]
+pub mod monotonic;
+#[cfg(nfct)]
+pub mod nfct;
+pub mod nvmc;
+pub mod pdm;
+pub mod peripherals;
+pub mod power;
+pub mod ppi;
+pub mod qdec;
+#[cfg(qspi)]
+pub mod qspi;
+pub mod radio;
 pub mod rng;
 pub mod rtc;
 pub mod saadc;
+pub mod settings;
 pub mod spim;
+pub mod stream;
 pub mod temp;
 pub mod time;
+#[cfg(feature = "embassy-time-driver")]
+pub mod time_driver;
 pub mod timer;
 pub mod twim;
 pub mod uarte;
+pub mod uicr;
+#[cfg(feature = "async")]
+pub mod waker;
+#[cfg(all(usbd, feature = "usb-device"))]
+pub mod usbd;
+pub mod wdt;
+
+pub use crate::peripherals::{init, Peripherals};
 
 pub mod prelude {
     pub use crate::hal::prelude::*;
@@ -31,27 +80,38 @@ pub mod prelude {
 }
 
 /// Length of Nordic EasyDMA differs for MCUs
-#[cfg(any(feature = "52810", feature = "52832"))]
 pub mod target_constants {
-    // NRF52832 8 bits1..0xFF
-    pub const EASY_DMA_SIZE: usize = 255;
+    // NRF52832 8 bits 1..0xFF
+    #[cfg(easydma_maxcnt_bits = "8")]
+    pub const MAXCNT_BITS: u32 = 8;
+    // NRF52810 10 bits 1..0x3FF
+    #[cfg(easydma_maxcnt_bits = "10")]
+    pub const MAXCNT_BITS: u32 = 10;
+    // NRF52840 16 bits 1..0xFFFF
+    #[cfg(easydma_maxcnt_bits = "16")]
+    pub const MAXCNT_BITS: u32 = 16;
+    pub const EASY_DMA_SIZE: usize = (1 << MAXCNT_BITS) - 1;
     // Easy DMA can only read from data ram
     pub const SRAM_LOWER: usize = 0x2000_0000;
     pub const SRAM_UPPER: usize = 0x3000_0000;
+    #[cfg(not(easydma_maxcnt_bits = "16"))]
     pub const FORCE_COPY_BUFFER_SIZE: usize = 255;
-}
-#[cfg(feature = "52840")]
-pub mod target_constants {
-    // NRF52840 16 bits 1..0xFFFF
-    pub const EASY_DMA_SIZE: usize = 65535;
-    // Limits for Easy DMA - it can only read from data ram
-    pub const SRAM_LOWER: usize = 0x2000_0000;
-    pub const SRAM_UPPER: usize = 0x3000_0000;
+    #[cfg(easydma_maxcnt_bits = "16")]
     pub const FORCE_COPY_BUFFER_SIZE: usize = 1024;
 }
 
 /// Does this slice reside entirely within RAM?
-pub(crate) fn slice_in_ram(slice: &[u8]) -> bool {
+///
+/// EasyDMA can only access data RAM. Buffers outside of it, e.g. constants
+/// in flash, can't be used for DMA transfers.
+///
+/// Empty slices are accepted wherever they point to, as EasyDMA doesn't
+/// access them. Their pointer is dangling, e.g. `0x1` for `&[]`.
+pub fn slice_in_ram(slice: &[u8]) -> bool {
+    if slice.is_empty() {
+        return true;
+    }
+
     let ptr = slice.as_ptr() as usize;
     ptr >= target_constants::SRAM_LOWER &&
         (ptr + slice.len()) < target_constants::SRAM_UPPER
@@ -79,10 +139,20 @@ impl DmaSlice {
             len: slice.len() as u32,
         }
     }
+
+    pub fn from_raw_parts(ptr: *const u8, len: usize) -> Self {
+        Self {
+            ptr: ptr as u32,
+            len: len as u32,
+        }
+    }
 }
 
 pub use crate::clocks::Clocks;
 pub use crate::delay::Delay;
+pub use crate::nvmc::Nvmc;
+pub use crate::power::Power;
+pub use crate::radio::Radio;
 pub use crate::rng::Rng;
 pub use crate::rtc::Rtc;
 pub use crate::saadc::Saadc;
@@ -0,0 +1,195 @@
+//! HAL interface to the Low Power Comparator (LPCOMP) peripheral
+//!
+//! The LPCOMP compares the voltage of an analog input to a fraction of VDD,
+//! or to an external reference, drawing very little current. Unlike the COMP,
+//! it keeps running in System OFF, and can wake the chip when the input
+//! crosses the reference, e.g. for a light sensor or battery insertion. While
+//! the chip is on, its events can be connected to other peripherals through
+//! the PPI, by passing the event registers handed out by `event_ready`,
+//! `event_down`, `event_up` and `event_cross` to a PPI channel.
+//!
+//! The analog inputs are selected through the same pin mapping as the SAADC.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 39
+//! - nrf52840: Section 6.11
+use embedded_hal::adc::Channel;
+
+pub use crate::target::lpcomp::{
+    anadetect::ANADETECTW as Detect, extrefsel::EXTREFSELW as ExternalReference,
+    refsel::REFSELW as Reference,
+};
+
+use crate::saadc::Saadc;
+use crate::target::{lpcomp, LPCOMP};
+
+/// Interface to the LPCOMP peripheral
+pub struct Lpcomp<PIN> {
+    periph: LPCOMP,
+    input: PIN,
+}
+
+impl<PIN> Lpcomp<PIN>
+where
+    PIN: Channel<Saadc, ID = u8>,
+{
+    /// Take ownership of the LPCOMP peripheral, and compare `input` to
+    /// `reference`
+    ///
+    /// With `Reference::AREF`, the reference is the analog input selected
+    /// with `set_external_reference`, AIN0 by default. The comparator is
+    /// started by `start`.
+    pub fn new(lpcomp: LPCOMP, input: PIN, reference: Reference) -> Self {
+        match PIN::channel() {
+            0 => lpcomp.psel.write(|w| w.psel().analog_input0()),
+            1 => lpcomp.psel.write(|w| w.psel().analog_input1()),
+            2 => lpcomp.psel.write(|w| w.psel().analog_input2()),
+            3 => lpcomp.psel.write(|w| w.psel().analog_input3()),
+            4 => lpcomp.psel.write(|w| w.psel().analog_input4()),
+            5 => lpcomp.psel.write(|w| w.psel().analog_input5()),
+            6 => lpcomp.psel.write(|w| w.psel().analog_input6()),
+            7 => lpcomp.psel.write(|w| w.psel().analog_input7()),
+            // Only the analog pins implement `Channel<Saadc>`
+            _ => unreachable!(),
+        }
+        lpcomp.refsel.write(|w| w.refsel().variant(reference));
+        lpcomp.enable.write(|w| w.enable().enabled());
+
+        Lpcomp {
+            periph: lpcomp,
+            input,
+        }
+    }
+
+    /// Select the analog input used as reference with `Reference::AREF`
+    ///
+    /// This can only be changed while the comparator is stopped.
+    pub fn set_external_reference(&mut self, aref: ExternalReference) {
+        self.periph.extrefsel.write(|w| w.extrefsel().variant(aref));
+    }
+
+    /// Add 50 mV of hysteresis around the reference to the output
+    ///
+    /// This can only be changed while the comparator is stopped.
+    pub fn set_hysteresis(&mut self, enabled: bool) {
+        self.periph.hyst.write(|w| w.hyst().bit(enabled));
+    }
+
+    /// Wake the chip from System OFF when the input crosses the reference in
+    /// the direction of `detect`
+    ///
+    /// The comparator must be running when `Power::system_off` is called.
+    /// After the wakeup, `ResetReason::lpcomp` is set.
+    pub fn enable_wakeup(&mut self, detect: Detect) {
+        self.periph
+            .anadetect
+            .write(|w| w.anadetect().variant(detect));
+    }
+
+    /// Start the comparator, and wait until it is ready
+    pub fn start(&mut self) {
+        self.periph.events_ready.reset();
+        self.periph.tasks_start.write(|w| unsafe { w.bits(1) });
+        while self.periph.events_ready.read().bits() == 0 {}
+        self.periph.events_ready.reset();
+    }
+
+    /// Stop the comparator
+    pub fn stop(&mut self) {
+        self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Sample the output of the running comparator, true if the input is
+    /// above the reference
+    pub fn read_output(&mut self) -> bool {
+        self.periph.tasks_sample.write(|w| unsafe { w.bits(1) });
+        self.periph.result.read().result().is_above()
+    }
+
+    /// Enable the interrupt for a given event
+    ///
+    /// The LPCOMP peripheral shares the `COMP_LPCOMP` interrupt with the COMP
+    /// peripheral, which must be unmasked in the NVIC separately.
+    pub fn enable_interrupt(&mut self, evt: LpcompEvent) {
+        match evt {
+            LpcompEvent::Ready => self.periph.intenset.write(|w| w.ready().set()),
+            LpcompEvent::Down => self.periph.intenset.write(|w| w.down().set()),
+            LpcompEvent::Up => self.periph.intenset.write(|w| w.up().set()),
+            LpcompEvent::Cross => self.periph.intenset.write(|w| w.cross().set()),
+        }
+    }
+
+    /// Disable the interrupt for a given event
+    pub fn disable_interrupt(&mut self, evt: LpcompEvent) {
+        match evt {
+            LpcompEvent::Ready => self.periph.intenclr.write(|w| w.ready().clear()),
+            LpcompEvent::Down => self.periph.intenclr.write(|w| w.down().clear()),
+            LpcompEvent::Up => self.periph.intenclr.write(|w| w.up().clear()),
+            LpcompEvent::Cross => self.periph.intenclr.write(|w| w.cross().clear()),
+        }
+    }
+
+    /// Obtain the state of a given event, and optionally clear the event if
+    /// it is set
+    pub fn get_event_triggered(&mut self, evt: LpcompEvent, clear_on_read: bool) -> bool {
+        let triggered = match evt {
+            LpcompEvent::Ready => self.periph.events_ready.read().bits() == 1,
+            LpcompEvent::Down => self.periph.events_down.read().bits() == 1,
+            LpcompEvent::Up => self.periph.events_up.read().bits() == 1,
+            LpcompEvent::Cross => self.periph.events_cross.read().bits() == 1,
+        };
+
+        if triggered && clear_on_read {
+            match evt {
+                LpcompEvent::Ready => self.periph.events_ready.reset(),
+                LpcompEvent::Down => self.periph.events_down.reset(),
+                LpcompEvent::Up => self.periph.events_up.reset(),
+                LpcompEvent::Cross => self.periph.events_cross.reset(),
+            }
+        }
+
+        triggered
+    }
+
+    /// Return the READY event, raised once the LPCOMP is running
+    pub fn event_ready(&self) -> &lpcomp::EVENTS_READY {
+        &self.periph.events_ready
+    }
+
+    /// Return the DOWN event, raised when the input drops below the reference
+    pub fn event_down(&self) -> &lpcomp::EVENTS_DOWN {
+        &self.periph.events_down
+    }
+
+    /// Return the UP event, raised when the input climbs above the reference
+    pub fn event_up(&self) -> &lpcomp::EVENTS_UP {
+        &self.periph.events_up
+    }
+
+    /// Return the CROSS event, raised on both an UP and a DOWN crossing
+    pub fn event_cross(&self) -> &lpcomp::EVENTS_CROSS {
+        &self.periph.events_cross
+    }
+
+    /// Stop and disable the comparator, and return the underlying LPCOMP
+    /// peripheral and the input pin
+    pub fn free(self) -> (LPCOMP, PIN) {
+        self.periph.intenclr.write(|w| unsafe { w.bits(0xF) });
+        self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.periph.enable.write(|w| w.enable().disabled());
+        (self.periph, self.input)
+    }
+}
+
+/// Events that can be generated by the LPCOMP peripheral
+pub enum LpcompEvent {
+    /// The comparator has started and is ready to compare
+    Ready,
+    /// The input crossed the reference downwards
+    Down,
+    /// The input crossed the reference upwards
+    Up,
+    /// The input crossed the reference in either direction
+    Cross,
+}
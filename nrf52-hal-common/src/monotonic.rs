@@ -0,0 +1,158 @@
+//! RTIC monotonic timer based on a TIMER instance
+//!
+//! `MonoTimer` implements the `Monotonic` trait of RTIC on a TIMER, so
+//! software tasks can be scheduled without an additional crate. The 32 bit
+//! counter is extended to 64 bits by counting its overflows, so instants
+//! don't wrap around.
+//!
+//! ``` ignore
+//! #[monotonic(binds = TIMER1, default = true)]
+//! type Mono = MonoTimer<TIMER1, 1_000_000>;
+//!
+//! #[init]
+//! fn init(cx: init::Context) -> (Shared, Local, init::Monotonics) {
+//!     let mono = MonoTimer::new(cx.device.TIMER1);
+//!     // ...
+//!     (Shared {}, Local {}, init::Monotonics(mono))
+//! }
+//! ```
+//!
+//! The timer uses its capture/compare registers 0 to 2: 0 for scheduling,
+//! 1 for detecting overflows and 2 for reading the counter.
+pub use fugit;
+
+use rtic_monotonic::Monotonic;
+
+use crate::target::NVIC;
+use crate::timer::Instance;
+
+/// Frequency of the clock of the TIMER peripherals
+const BASE_FREQUENCY: u32 = 16_000_000;
+
+/// Return the prescaler dividing the TIMER clock down to `frequency`
+const fn prescaler(frequency: u32) -> u32 {
+    let mut prescaler = 0;
+    while prescaler <= 9 {
+        if BASE_FREQUENCY >> prescaler == frequency {
+            return prescaler;
+        }
+        prescaler += 1;
+    }
+    panic!("the frequency must be 16 MHz divided by a power of two up to 512");
+}
+
+/// A monotonic timer for RTIC, counting at `FREQ` Hz
+pub struct MonoTimer<T, const FREQ: u32> {
+    timer: T,
+    overflows: u32,
+}
+
+impl<T, const FREQ: u32> MonoTimer<T, FREQ>
+where
+    T: Instance,
+{
+    const PRESCALER: u32 = prescaler(FREQ);
+
+    /// Take ownership of a TIMER instance, and configure it to count at
+    /// `FREQ` Hz
+    ///
+    /// `FREQ` must be 16 MHz divided by a power of two up to 512, e.g.
+    /// `1_000_000`, otherwise this fails to compile. The timer is started by
+    /// RTIC.
+    pub fn new(timer: T) -> Self {
+        timer.tasks_stop.write(|w| unsafe { w.bits(1) });
+        timer.mode.write(|w| w.mode().timer());
+        timer.bitmode.write(|w| w.bitmode()._32bit());
+        timer
+            .prescaler
+            .write(|w| unsafe { w.prescaler().bits(Self::PRESCALER as u8) });
+        timer.shorts.reset();
+
+        // The counter wraps around to 0
+        timer.cc[1].write(|w| unsafe { w.bits(0) });
+        timer
+            .intenset
+            .write(|w| w.compare0().set().compare1().set());
+
+        MonoTimer {
+            timer,
+            overflows: 0,
+        }
+    }
+
+    /// Return the underlying TIMER peripheral
+    pub fn free(self) -> T {
+        self.timer.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.timer
+            .intenclr
+            .write(|w| w.compare0().clear().compare1().clear());
+        self.timer
+    }
+}
+
+impl<T, const FREQ: u32> Monotonic for MonoTimer<T, FREQ>
+where
+    T: Instance,
+{
+    // The interrupt also counts the overflows
+    const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
+
+    type Instant = fugit::TimerInstantU64<FREQ>;
+    type Duration = fugit::TimerDurationU64<FREQ>;
+
+    fn now(&mut self) -> Self::Instant {
+        self.timer.tasks_capture[2].write(|w| unsafe { w.bits(1) });
+        let ticks = self.timer.cc[2].read().bits();
+
+        // Account for an overflow that `on_interrupt` hasn't handled yet. The
+        // counter can't have run through half a period since then.
+        let pending = self.timer.events_compare[1].read().bits() != 0 && ticks < 0x8000_0000;
+        let overflows = self.overflows + pending as u32;
+
+        Self::Instant::from_ticks((u64::from(overflows) << 32) | u64::from(ticks))
+    }
+
+    fn set_compare(&mut self, instant: Self::Instant) {
+        let now = self.now().ticks();
+        let target = instant.ticks();
+
+        if target <= now {
+            NVIC::pend(T::INTERRUPT);
+            return;
+        }
+
+        // Instants more than half a period away are scheduled in several
+        // steps, as RTIC sets the compare again when it fires early
+        let target = target.min(now + 0x7FFF_FFFF);
+        self.timer.cc[0].write(|w| unsafe { w.bits(target as u32) });
+
+        // The timer may have passed the target while CC was written, in which
+        // case the compare event won't fire until the counter wraps around
+        if self.now().ticks() >= target {
+            NVIC::pend(T::INTERRUPT);
+        }
+    }
+
+    fn clear_compare_flag(&mut self) {
+        self.timer.events_compare[0].reset();
+    }
+
+    fn zero() -> Self::Instant {
+        Self::Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        self.timer.tasks_clear.write(|w| w.bits(1));
+        self.timer.events_compare[0].reset();
+        self.timer.events_compare[1].reset();
+        self.overflows = 0;
+        self.timer.tasks_start.write(|w| w.bits(1));
+    }
+
+    fn on_interrupt(&mut self) {
+        if self.timer.events_compare[1].read().bits() != 0 {
+            self.timer.events_compare[1].reset();
+            self.overflows += 1;
+        }
+    }
+}
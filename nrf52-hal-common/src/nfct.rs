@@ -0,0 +1,543 @@
+//! HAL interface to the Near Field Communication Tag (NFCT) peripheral
+//!
+//! The NFCT lets the chip act as an NFC-A tag, powered by the field of a
+//! reader, e.g. a phone. It resolves collisions with other tags in the field
+//! on its own, and leaves the frames exchanged after the tag has been
+//! selected to software.
+//!
+//! Without answering readers, `FieldDetector` only watches for a field in the
+//! low power SENSE mode, e.g. to wake the chip from System OFF when a phone is
+//! held to it.
+//!
+//! `Type2Tag` builds on this to present a read-only NFC Forum Type 2 Tag,
+//! holding an NDEF message such as a URL or a pairing record.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 32
+//! - nrf52840: Section 6.13
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+use crate::target::NFCT;
+
+/// Size of the buffer frames are received into and transmitted from
+pub const BUFFER_SIZE: usize = 64;
+
+/// Largest NDEF message held by a `Type2Tag`
+pub const MAX_NDEF_LEN: usize = 2032;
+
+/// Type 2 Tag commands
+const T2T_READ: u8 = 0x30;
+const T2T_HALT: u8 = 0x50;
+
+/// Negative acknowledge, sent as a 4 bit frame
+const NAK: u8 = 0x0;
+
+/// Interface to the NFCT peripheral
+pub struct Nfct {
+    periph: NFCT,
+    buffer: &'static mut [u8; BUFFER_SIZE],
+}
+
+impl Nfct {
+    /// Take ownership of the NFCT peripheral, and start sensing for a field
+    ///
+    /// The tag answers to readers with the 7 byte `nfcid1`, usually the one
+    /// returned by `Ficr::nfc_id`, and announces itself as a Type 2 Tag. Once
+    /// a field is detected, the tag is activated, and deactivated again when
+    /// the field is lost.
+    pub fn new(nfct: NFCT, nfcid1: [u8; 7], buffer: &'static mut [u8; BUFFER_SIZE]) -> Self {
+        // nRF52832 anomaly 57: the NFCT doesn't work without this undocumented
+        // configuration
+        #[cfg(feature = "52832")]
+        unsafe {
+            core::ptr::write_volatile(0x4000_5610 as *mut u32, 0x0000_0005);
+            core::ptr::write_volatile(0x4000_5688 as *mut u32, 0x0000_0001);
+            core::ptr::write_volatile(0x4000_5618 as *mut u32, 0x0000_0000);
+            core::ptr::write_volatile(0x4000_5614 as *mut u32, 0x0000_003F);
+        }
+
+        let [id0, id1, id2, id3, id4, id5, id6] = nfcid1;
+        nfct.nfcid1_2nd_last.write(|w| unsafe {
+            w.nfcid1_v()
+                .bits(id0)
+                .nfcid1_u()
+                .bits(id1)
+                .nfcid1_t()
+                .bits(id2)
+        });
+        nfct.nfcid1_last.write(|w| unsafe {
+            w.nfcid1_w()
+                .bits(id3)
+                .nfcid1_x()
+                .bits(id4)
+                .nfcid1_y()
+                .bits(id5)
+                .nfcid1_z()
+                .bits(id6)
+        });
+        nfct.sensres
+            .write(|w| w.nfcidsize().nfcid1double().bitframesdd().sdd00100());
+        // Protocol 0 is a Type 2 Tag
+        nfct.selres.write(|w| unsafe { w.protocol().bits(0) });
+
+        nfct.rxd
+            .frameconfig
+            .write(|w| w.parity().parity().sof().so_f().crcmoderx().crc16rx());
+        nfct.maxlen
+            .write(|w| unsafe { w.maxlen().bits(BUFFER_SIZE as u16) });
+
+        // Answer within about 4.8 ms of the end of a command
+        nfct.framedelaymode
+            .write(|w| w.framedelaymode().window_grid());
+        nfct.framedelaymax
+            .write(|w| unsafe { w.framedelaymax().bits(0xFFFF) });
+
+        nfct.shorts.write(|w| {
+            w.fielddetected_activate()
+                .enabled()
+                .fieldlost_sense()
+                .enabled()
+        });
+        nfct.tasks_sense.write(|w| unsafe { w.bits(1) });
+
+        Nfct {
+            periph: nfct,
+            buffer,
+        }
+    }
+
+    /// Receive the next frame from the reader into `frame`, and return its
+    /// length
+    ///
+    /// Frames are only received while the tag is selected by a reader, and
+    /// frames with a CRC or parity error are dropped. The CRC isn't included
+    /// in `frame`. A received frame is answered with `respond` or `respond_nak`,
+    /// or by putting the tag to `sleep`.
+    pub fn receive(&mut self, frame: &mut [u8]) -> nb::Result<usize, Error> {
+        if self.periph.events_selected.read().bits() != 0 {
+            self.periph.events_selected.reset();
+            self.start_rx();
+        }
+
+        if self.periph.events_rxframeend.read().bits() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.periph.events_rxframeend.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        let status = self.periph.framestatus.rx.read();
+        let corrupt = status.crcerror().is_crcerror()
+            || status.paritystatus().is_parity_error()
+            || status.overrun().is_overrun();
+        if corrupt {
+            self.periph
+                .framestatus
+                .rx
+                .write(|w| unsafe { w.bits(0b111) });
+            self.start_rx();
+            return Err(nb::Error::WouldBlock);
+        }
+
+        // The received length includes the CRC
+        let len = usize::from(self.periph.rxd.amount.read().rxdatabytes().bits()).saturating_sub(2);
+        if len > frame.len() {
+            self.start_rx();
+            return Err(nb::Error::Other(Error::BufferTooShort));
+        }
+        frame[..len].copy_from_slice(&self.buffer[..len]);
+
+        Ok(len)
+    }
+
+    /// Answer the last received frame with `data`, followed by a CRC
+    pub fn respond(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.len() > BUFFER_SIZE {
+            return Err(Error::BufferTooLong);
+        }
+        self.buffer[..data.len()].copy_from_slice(data);
+
+        self.periph.txd.frameconfig.write(|w| {
+            w.parity()
+                .parity()
+                .discardmode()
+                .discard_end()
+                .sof()
+                .so_f()
+                .crcmodetx()
+                .crc16tx()
+        });
+        self.periph
+            .txd
+            .amount
+            .write(|w| unsafe { w.txdatabytes().bits(data.len() as u16).txdatabits().bits(0) });
+        self.transmit()
+    }
+
+    /// Answer the last received frame with a negative acknowledge
+    pub fn respond_nak(&mut self) -> Result<(), Error> {
+        self.buffer[0] = NAK;
+
+        self.periph.txd.frameconfig.write(|w| {
+            w.parity()
+                .no_parity()
+                .discardmode()
+                .discard_end()
+                .sof()
+                .so_f()
+                .crcmodetx()
+                .no_crctx()
+        });
+        self.periph
+            .txd
+            .amount
+            .write(|w| unsafe { w.txdatabytes().bits(0).txdatabits().bits(4) });
+        self.transmit()
+    }
+
+    /// Put the tag to sleep after the reader has halted it
+    ///
+    /// The tag doesn't receive frames until it is woken up and selected
+    /// again by a reader.
+    pub fn sleep(&mut self) {
+        self.periph.tasks_gosleep.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Signal received frames through the NFCT interrupt
+    ///
+    /// The NFCT interrupt must be unmasked in the NVIC separately.
+    pub fn enable_interrupt(&mut self) {
+        self.periph
+            .intenset
+            .write(|w| w.selected().set().rxframeend().set());
+    }
+
+    /// Stop signaling received frames through the NFCT interrupt
+    pub fn disable_interrupt(&mut self) {
+        self.periph
+            .intenclr
+            .write(|w| w.selected().clear().rxframeend().clear());
+    }
+
+    /// Return true if a field is present
+    pub fn is_field_present(&self) -> bool {
+        self.periph
+            .fieldpresent
+            .read()
+            .fieldpresent()
+            .is_field_present()
+    }
+
+    /// Return the last change of the field, and clear it
+    ///
+    /// The tag is activated and deactivated on its own, this is only for the
+    /// application to know when a reader comes and goes.
+    pub fn field_event(&mut self) -> Option<FieldEvent> {
+        field_event(&self.periph)
+    }
+
+    /// Signal changes of the field through the NFCT interrupt
+    ///
+    /// The events have to be cleared with `field_event`. The NFCT interrupt
+    /// must be unmasked in the NVIC separately.
+    pub fn enable_field_interrupt(&mut self) {
+        self.periph
+            .intenset
+            .write(|w| w.fielddetected().set().fieldlost().set());
+    }
+
+    /// Stop signaling changes of the field through the NFCT interrupt
+    pub fn disable_field_interrupt(&mut self) {
+        self.periph
+            .intenclr
+            .write(|w| w.fielddetected().clear().fieldlost().clear());
+    }
+
+    /// Disable the tag, and return the underlying NFCT peripheral and buffer
+    pub fn free(self) -> (NFCT, &'static mut [u8; BUFFER_SIZE]) {
+        self.periph.intenclr.write(|w| {
+            w.selected()
+                .clear()
+                .rxframeend()
+                .clear()
+                .fielddetected()
+                .clear()
+                .fieldlost()
+                .clear()
+        });
+        self.periph.shorts.reset();
+        self.periph.tasks_disable.write(|w| unsafe { w.bits(1) });
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        (self.periph, self.buffer)
+    }
+
+    fn start_rx(&mut self) {
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+
+        self.periph
+            .packetptr
+            .write(|w| unsafe { w.ptr().bits(self.buffer.as_ptr() as u32) });
+        self.periph
+            .tasks_enablerxdata
+            .write(|w| unsafe { w.bits(1) });
+    }
+
+    fn transmit(&mut self) -> Result<(), Error> {
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+
+        self.periph
+            .packetptr
+            .write(|w| unsafe { w.ptr().bits(self.buffer.as_ptr() as u32) });
+        self.periph.events_txframeend.reset();
+        self.periph.events_error.reset();
+        self.periph.tasks_starttx.write(|w| unsafe { w.bits(1) });
+
+        // The frame isn't sent if it's too late to answer the reader
+        let result = loop {
+            if self.periph.events_txframeend.read().bits() != 0 {
+                break Ok(());
+            }
+            if self.periph.events_error.read().bits() != 0 {
+                self.periph
+                    .errorstatus
+                    .write(|w| w.framedelaytimeout().set_bit());
+                break Err(Error::FrameDelayTimeout);
+            }
+        };
+        self.periph.events_txframeend.reset();
+        self.periph.events_error.reset();
+
+        self.start_rx();
+        result
+    }
+}
+
+/// Detector of an NFC field, which doesn't answer readers
+///
+/// The NFCT stays in SENSE mode, drawing very little current. This also lets
+/// a field wake the chip from `Power::system_off`, after which the reset
+/// reason shows `nfc`.
+pub struct FieldDetector {
+    periph: NFCT,
+}
+
+impl FieldDetector {
+    /// Take ownership of the NFCT peripheral, and start sensing for a field
+    pub fn new(nfct: NFCT) -> Self {
+        nfct.shorts.reset();
+        nfct.events_fielddetected.reset();
+        nfct.events_fieldlost.reset();
+        nfct.tasks_sense.write(|w| unsafe { w.bits(1) });
+
+        FieldDetector { periph: nfct }
+    }
+
+    /// Return true if a field is present
+    pub fn is_field_present(&self) -> bool {
+        self.periph
+            .fieldpresent
+            .read()
+            .fieldpresent()
+            .is_field_present()
+    }
+
+    /// Return the last change of the field, and clear it
+    pub fn field_event(&mut self) -> Option<FieldEvent> {
+        field_event(&self.periph)
+    }
+
+    /// Signal changes of the field through the NFCT interrupt
+    ///
+    /// The events have to be cleared with `field_event`. The NFCT interrupt
+    /// must be unmasked in the NVIC separately.
+    pub fn enable_interrupt(&mut self) {
+        self.periph
+            .intenset
+            .write(|w| w.fielddetected().set().fieldlost().set());
+    }
+
+    /// Stop signaling changes of the field through the NFCT interrupt
+    pub fn disable_interrupt(&mut self) {
+        self.periph
+            .intenclr
+            .write(|w| w.fielddetected().clear().fieldlost().clear());
+    }
+
+    /// Stop sensing, and return the underlying NFCT peripheral
+    pub fn free(self) -> NFCT {
+        self.periph
+            .intenclr
+            .write(|w| w.fielddetected().clear().fieldlost().clear());
+        self.periph.tasks_disable.write(|w| unsafe { w.bits(1) });
+        self.periph
+    }
+}
+
+fn field_event(nfct: &NFCT) -> Option<FieldEvent> {
+    if nfct.events_fieldlost.read().bits() != 0 {
+        nfct.events_fieldlost.reset();
+        nfct.events_fielddetected.reset();
+        return Some(FieldEvent::Lost);
+    }
+    if nfct.events_fielddetected.read().bits() != 0 {
+        nfct.events_fielddetected.reset();
+        return Some(FieldEvent::Detected);
+    }
+    None
+}
+
+/// Change of the NFC field
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldEvent {
+    /// A reader has been brought close
+    Detected,
+    /// The reader has been removed
+    Lost,
+}
+
+/// Read-only NFC Forum Type 2 Tag, holding an NDEF message
+///
+/// The memory of the tag consists of the UID, the lock bytes and the
+/// capability container in the first 16 bytes, followed by the NDEF message
+/// in a TLV block.
+pub struct Type2Tag<'a> {
+    header: [u8; 16],
+    tlv: [u8; 4],
+    tlv_len: usize,
+    ndef: &'a [u8],
+    size: usize,
+}
+
+impl<'a> Type2Tag<'a> {
+    /// Present the NDEF message `ndef`, on a tag with the 7 byte `nfcid1`
+    /// passed to `Nfct::new`
+    pub fn new(nfcid1: [u8; 7], ndef: &'a [u8]) -> Result<Self, Error> {
+        if ndef.len() > MAX_NDEF_LEN {
+            return Err(Error::BufferTooLong);
+        }
+
+        let mut tlv = [0x03, 0, 0, 0];
+        let tlv_len = if ndef.len() < 0xFF {
+            tlv[1] = ndef.len() as u8;
+            2
+        } else {
+            tlv[1] = 0xFF;
+            tlv[2..4].copy_from_slice(&(ndef.len() as u16).to_be_bytes());
+            4
+        };
+
+        // The data area holds the TLV block and the terminator TLV, in units
+        // of 8 bytes, and is at least as large as that of the smallest tags
+        let data_size = ((tlv_len + ndef.len() + 1 + 7) & !7).max(48);
+
+        let [id0, id1, id2, id3, id4, id5, id6] = nfcid1;
+        let header = [
+            id0,
+            id1,
+            id2,
+            0x88 ^ id0 ^ id1 ^ id2,
+            id3,
+            id4,
+            id5,
+            id6,
+            id3 ^ id4 ^ id5 ^ id6,
+            0x00,
+            // Static lock bytes, all blocks locked
+            0xFF,
+            0xFF,
+            // Capability container: NDEF, version 1.0, data area size, read
+            // only
+            0xE1,
+            0x10,
+            (data_size / 8) as u8,
+            0x0F,
+        ];
+
+        Ok(Type2Tag {
+            header,
+            tlv,
+            tlv_len,
+            ndef,
+            size: header.len() + data_size,
+        })
+    }
+
+    /// Answer the next command of the reader
+    ///
+    /// To be called in a loop, or from the NFCT interrupt handler after
+    /// `Nfct::enable_interrupt`.
+    pub fn process(&self, nfct: &mut Nfct) -> nb::Result<(), Error> {
+        let mut command = [0; 16];
+        let len = match nfct.receive(&mut command) {
+            Err(nb::Error::Other(Error::BufferTooShort)) => return Ok(nfct.respond_nak()?),
+            result => result?,
+        };
+
+        match command[..len] {
+            [T2T_READ, block] => {
+                let start = usize::from(block) * 4;
+                if start >= self.size {
+                    return Ok(nfct.respond_nak()?);
+                }
+
+                // Reads past the end of the memory roll over to the start
+                let mut data = [0; 16];
+                for (i, byte) in data.iter_mut().enumerate() {
+                    *byte = self.byte((start + i) % self.size);
+                }
+                Ok(nfct.respond(&data)?)
+            }
+            [T2T_HALT, 0x00] => {
+                nfct.sleep();
+                Ok(())
+            }
+            // Writes, and everything else
+            _ => Ok(nfct.respond_nak()?),
+        }
+    }
+
+    fn byte(&self, address: usize) -> u8 {
+        if address < self.header.len() {
+            return self.header[address];
+        }
+
+        let offset = address - self.header.len();
+        if offset < self.tlv_len {
+            return self.tlv[offset];
+        }
+        let offset = offset - self.tlv_len;
+        if offset < self.ndef.len() {
+            return self.ndef[offset];
+        }
+
+        // Terminator TLV, then empty memory
+        if offset == self.ndef.len() {
+            0xFE
+        } else {
+            0x00
+        }
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    BufferTooShort,
+    BufferTooLong,
+    FrameDelayTimeout,
+}
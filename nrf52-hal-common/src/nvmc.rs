@@ -0,0 +1,635 @@
+//! HAL interface to the Non-Volatile Memory Controller (NVMC)
+//!
+//! The NVMC erases and programs the internal flash. Flash can only be
+//! programmed one word at a time, and programming can only change bits from
+//! `1` to `0`, so a page must be erased before it can be written again.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 11
+//! - nrf52840: Section 4.3
+use core::ops::Range;
+use core::ptr;
+
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+
+use crate::hal::watchdog::Watchdog;
+use crate::target::NVMC;
+#[cfg(feature = "async")]
+use crate::waker;
+
+/// Size of a flash page, the smallest unit that can be erased
+pub const PAGE_SIZE: usize = 4096;
+
+/// Size of a flash word, the smallest unit that can be written
+pub const WORD_SIZE: usize = 4;
+
+/// Number of times a word may be written before its page must be erased
+pub const N_WRITE: u8 = 2;
+
+/// Interface to the NVMC, restricted to a region of flash
+///
+/// All offsets are relative to the start of the region.
+pub struct Nvmc {
+    periph: NVMC,
+    storage: &'static mut [u8],
+    operation: Option<Operation>,
+}
+
+/// An operation started without blocking
+enum Operation {
+    Erase,
+    Write { offset: usize, word: u32 },
+}
+
+impl Nvmc {
+    /// Take ownership of the NVMC and a region of flash
+    ///
+    /// The region is typically reserved in the linker script. It must start
+    /// on a page boundary and be a multiple of the page size.
+    pub fn new(periph: NVMC, storage: &'static mut [u8]) -> Self {
+        assert!(storage.as_ptr() as usize % PAGE_SIZE == 0);
+        assert!(storage.len() % PAGE_SIZE == 0);
+
+        Nvmc {
+            periph,
+            storage,
+            operation: None,
+        }
+    }
+
+    /// Erase the page starting at `offset`
+    pub fn erase_page(&mut self, offset: u32) -> Result<(), Error> {
+        self.start_erase_page(offset)?;
+        nb::block!(self.poll_ready())
+    }
+
+    /// Program the word at `offset` and verify it was written correctly
+    ///
+    /// Programming can only clear bits, so verification fails if the word was
+    /// not erased, or already written with bits `word` needs to be set.
+    pub fn write_word(&mut self, offset: u32, word: u32) -> Result<(), Error> {
+        self.start_write_word(offset, word)?;
+        nb::block!(self.poll_ready())
+    }
+
+    /// Start erasing the page starting at `offset`, without blocking
+    ///
+    /// The erase has ended once `poll_ready` no longer returns `WouldBlock`.
+    /// Erasing a page takes up to ~100 ms. The CPU still stalls if it fetches
+    /// code from flash in the meantime, so only code running from RAM, or
+    /// waiting in `WFI`, runs while the flash is busy.
+    pub fn start_erase_page(&mut self, offset: u32) -> Result<(), Error> {
+        let offset = offset as usize;
+        if offset % PAGE_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+        if offset >= self.storage.len() {
+            return Err(Error::OutOfBounds);
+        }
+
+        let address = self.storage.as_ptr() as u32 + offset as u32;
+        self.enable_erase();
+        self.periph.erasepage.write(|w| unsafe { w.bits(address) });
+        self.operation = Some(Operation::Erase);
+
+        Ok(())
+    }
+
+    /// Start programming the word at `offset`, without blocking
+    ///
+    /// The word has been programmed and verified once `poll_ready` no longer
+    /// returns `WouldBlock`, see `start_erase_page`.
+    pub fn start_write_word(&mut self, offset: u32, word: u32) -> Result<(), Error> {
+        let offset = offset as usize;
+        if offset % WORD_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+        if offset + WORD_SIZE > self.storage.len() {
+            return Err(Error::OutOfBounds);
+        }
+
+        self.enable_write();
+        self.write_word_unchecked(offset, word);
+        self.operation = Some(Operation::Write { offset, word });
+
+        Ok(())
+    }
+
+    /// Check whether the operation started by `start_erase_page` or
+    /// `start_write_word` has ended, and verify a programmed word
+    pub fn poll_ready(&mut self) -> nb::Result<(), Error> {
+        if self.periph.ready.read().ready().is_busy() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        match self.operation.take() {
+            None => Ok(()),
+            Some(Operation::Erase) => {
+                self.enable_read();
+                Ok(())
+            }
+            Some(Operation::Write { offset, word }) => {
+                self.enable_read();
+                if self.read_word(offset) != word {
+                    return Err(nb::Error::Other(Error::Verify));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Erase the page starting at `offset`, without blocking
+    ///
+    /// The NVMC has no interrupt, so the future polls `poll_ready`, yielding
+    /// to the executor in between. Other tasks only make progress if their
+    /// code runs from RAM, see `start_erase_page`.
+    #[cfg(feature = "async")]
+    pub async fn erase_page_async(&mut self, offset: u32) -> Result<(), Error> {
+        self.start_erase_page(offset)?;
+        self.ready_async().await
+    }
+
+    /// Program the word at `offset` and verify it was written correctly,
+    /// without blocking
+    ///
+    /// See `write_word` and `erase_page_async`.
+    #[cfg(feature = "async")]
+    pub async fn write_word_async(&mut self, offset: u32, word: u32) -> Result<(), Error> {
+        self.start_write_word(offset, word)?;
+        self.ready_async().await
+    }
+
+    /// Poll `poll_ready` until the operation has ended, yielding in between
+    #[cfg(feature = "async")]
+    async fn ready_async(&mut self) -> Result<(), Error> {
+        loop {
+            match self.poll_ready() {
+                Ok(()) => return Ok(()),
+                Err(nb::Error::Other(err)) => return Err(err),
+                Err(nb::Error::WouldBlock) => waker::yield_now().await,
+            }
+        }
+    }
+
+    /// Erase and program a large region, e.g. a new firmware image
+    ///
+    /// `offset` must be at a page boundary. Every page touched by `data` is
+    /// erased and then programmed, padding the last word with `0xFF`.
+    ///
+    /// `progress` is called with the number of bytes programmed so far and
+    /// the total after every page. If a `watchdog` is given, it is fed before
+    /// every page, as erasing and programming a page takes up to ~100 ms.
+    pub fn program_region<W, F>(
+        &mut self,
+        offset: u32,
+        data: &[u8],
+        mut watchdog: Option<&mut W>,
+        mut progress: F,
+    ) -> Result<(), Error>
+    where
+        W: Watchdog,
+        F: FnMut(usize, usize),
+    {
+        if offset as usize % PAGE_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+        if offset as usize + data.len() > self.storage.len() {
+            return Err(Error::OutOfBounds);
+        }
+
+        let mut done = 0;
+        for (i, page) in data.chunks(PAGE_SIZE).enumerate() {
+            let page_offset = offset + (i * PAGE_SIZE) as u32;
+
+            if let Some(watchdog) = watchdog.as_mut() {
+                watchdog.feed();
+            }
+            self.erase_page(page_offset)?;
+
+            for (j, chunk) in page.chunks(WORD_SIZE).enumerate() {
+                let mut word = [0xFF; WORD_SIZE];
+                word[..chunk.len()].copy_from_slice(chunk);
+                self.write_word(
+                    page_offset + (j * WORD_SIZE) as u32,
+                    u32::from_le_bytes(word),
+                )?;
+            }
+
+            done += page.len();
+            progress(done, data.len());
+        }
+
+        Ok(())
+    }
+
+    /// Calculate the CRC32 of the flash in `range`
+    pub fn crc32(&mut self, range: Range<u32>) -> Result<u32, Error> {
+        if range.start > range.end || range.end as usize > self.storage.len() {
+            return Err(Error::OutOfBounds);
+        }
+
+        let mut crc = !0;
+        for offset in range {
+            let byte = unsafe { ptr::read_volatile(self.storage.as_ptr().add(offset as usize)) };
+            crc = crc32_update(crc, &[byte]);
+        }
+
+        Ok(!crc)
+    }
+
+    /// Check that the flash in `range` has the CRC32 `expected`
+    pub fn verify(&mut self, range: Range<u32>, expected: u32) -> Result<bool, Error> {
+        Ok(self.crc32(range)? == expected)
+    }
+
+    /// Return the underlying NVMC peripheral and the flash region
+    pub fn free(self) -> (NVMC, &'static mut [u8]) {
+        (self.periph, self.storage)
+    }
+
+    /// Start writing a word, with write access already enabled
+    fn write_word_unchecked(&mut self, offset: usize, word: u32) {
+        let target = unsafe { self.storage.as_mut_ptr().add(offset) } as *mut u32;
+        unsafe { ptr::write_volatile(target, word) };
+    }
+
+    fn read_word(&self, offset: usize) -> u32 {
+        let source = unsafe { self.storage.as_ptr().add(offset) } as *const u32;
+        unsafe { ptr::read_volatile(source) }
+    }
+
+    pub(crate) fn enable_read(&mut self) {
+        self.periph.config.write(|w| w.wen().ren());
+        self.wait_ready();
+    }
+
+    pub(crate) fn enable_write(&mut self) {
+        self.periph.config.write(|w| w.wen().wen());
+        self.wait_ready();
+    }
+
+    fn enable_erase(&mut self) {
+        self.periph.config.write(|w| w.wen().een());
+        self.wait_ready();
+    }
+
+    pub(crate) fn wait_ready(&self) {
+        while self.periph.ready.read().ready().is_busy() {}
+    }
+}
+
+impl ErrorType for Nvmc {
+    type Error = Error;
+}
+
+impl ReadNorFlash for Nvmc {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        if offset + bytes.len() > self.storage.len() {
+            return Err(Error::OutOfBounds);
+        }
+
+        // Erasing changes the flash behind the compiler's back, so read it
+        // with volatile reads
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = unsafe { ptr::read_volatile(self.storage.as_ptr().add(offset + i)) };
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.storage.len()
+    }
+}
+
+impl NorFlash for Nvmc {
+    const WRITE_SIZE: usize = WORD_SIZE;
+    const ERASE_SIZE: usize = PAGE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if from as usize % PAGE_SIZE != 0 || to as usize % PAGE_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+        if from > to || to as usize > self.storage.len() {
+            return Err(Error::OutOfBounds);
+        }
+
+        for page in (from..to).step_by(PAGE_SIZE) {
+            self.erase_page(page)?;
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        if offset as usize % WORD_SIZE != 0 || bytes.len() % WORD_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+
+        for (i, chunk) in bytes.chunks(WORD_SIZE).enumerate() {
+            let word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            self.write_word(offset + (i * WORD_SIZE) as u32, word)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl embedded_storage_async::nor_flash::ReadNorFlash for Nvmc {
+    const READ_SIZE: usize = 1;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        // Reading doesn't wait for the flash
+        ReadNorFlash::read(self, offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.storage.len()
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl embedded_storage_async::nor_flash::NorFlash for Nvmc {
+    const WRITE_SIZE: usize = WORD_SIZE;
+    const ERASE_SIZE: usize = PAGE_SIZE;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if from as usize % PAGE_SIZE != 0 || to as usize % PAGE_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+        if from > to || to as usize > self.storage.len() {
+            return Err(Error::OutOfBounds);
+        }
+
+        for page in (from..to).step_by(PAGE_SIZE) {
+            self.erase_page_async(page).await?;
+        }
+        Ok(())
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        if offset as usize % WORD_SIZE != 0 || bytes.len() % WORD_SIZE != 0 {
+            return Err(Error::NotAligned);
+        }
+
+        for (i, chunk) in bytes.chunks(WORD_SIZE).enumerate() {
+            let word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            self.write_word_async(offset + (i * WORD_SIZE) as u32, word).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Update a CRC32 (IEEE 802.3) with `data`
+///
+/// Start with `!0` and invert the result after the last update, or use
+/// `Nvmc::crc32` to calculate the CRC of a flash region.
+pub fn crc32_update(mut crc: u32, data: &[u8]) -> u32 {
+    for byte in data {
+        crc = CRC32_TABLE[((crc ^ u32::from(*byte)) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    crc
+}
+
+/// CRC32 of `data`
+pub fn crc32(data: &[u8]) -> u32 {
+    !crc32_update(!0, data)
+}
+
+/// Lookup table for the reflected CRC32 polynomial `0xEDB88320`
+const CRC32_TABLE: [u32; 256] = [
+    0x00000000, 0x77073096, 0xEE0E612C, 0x990951BA, 0x076DC419, 0x706AF48F,
+    0xE963A535, 0x9E6495A3, 0x0EDB8832, 0x79DCB8A4, 0xE0D5E91E, 0x97D2D988,
+    0x09B64C2B, 0x7EB17CBD, 0xE7B82D07, 0x90BF1D91, 0x1DB71064, 0x6AB020F2,
+    0xF3B97148, 0x84BE41DE, 0x1ADAD47D, 0x6DDDE4EB, 0xF4D4B551, 0x83D385C7,
+    0x136C9856, 0x646BA8C0, 0xFD62F97A, 0x8A65C9EC, 0x14015C4F, 0x63066CD9,
+    0xFA0F3D63, 0x8D080DF5, 0x3B6E20C8, 0x4C69105E, 0xD56041E4, 0xA2677172,
+    0x3C03E4D1, 0x4B04D447, 0xD20D85FD, 0xA50AB56B, 0x35B5A8FA, 0x42B2986C,
+    0xDBBBC9D6, 0xACBCF940, 0x32D86CE3, 0x45DF5C75, 0xDCD60DCF, 0xABD13D59,
+    0x26D930AC, 0x51DE003A, 0xC8D75180, 0xBFD06116, 0x21B4F4B5, 0x56B3C423,
+    0xCFBA9599, 0xB8BDA50F, 0x2802B89E, 0x5F058808, 0xC60CD9B2, 0xB10BE924,
+    0x2F6F7C87, 0x58684C11, 0xC1611DAB, 0xB6662D3D, 0x76DC4190, 0x01DB7106,
+    0x98D220BC, 0xEFD5102A, 0x71B18589, 0x06B6B51F, 0x9FBFE4A5, 0xE8B8D433,
+    0x7807C9A2, 0x0F00F934, 0x9609A88E, 0xE10E9818, 0x7F6A0DBB, 0x086D3D2D,
+    0x91646C97, 0xE6635C01, 0x6B6B51F4, 0x1C6C6162, 0x856530D8, 0xF262004E,
+    0x6C0695ED, 0x1B01A57B, 0x8208F4C1, 0xF50FC457, 0x65B0D9C6, 0x12B7E950,
+    0x8BBEB8EA, 0xFCB9887C, 0x62DD1DDF, 0x15DA2D49, 0x8CD37CF3, 0xFBD44C65,
+    0x4DB26158, 0x3AB551CE, 0xA3BC0074, 0xD4BB30E2, 0x4ADFA541, 0x3DD895D7,
+    0xA4D1C46D, 0xD3D6F4FB, 0x4369E96A, 0x346ED9FC, 0xAD678846, 0xDA60B8D0,
+    0x44042D73, 0x33031DE5, 0xAA0A4C5F, 0xDD0D7CC9, 0x5005713C, 0x270241AA,
+    0xBE0B1010, 0xC90C2086, 0x5768B525, 0x206F85B3, 0xB966D409, 0xCE61E49F,
+    0x5EDEF90E, 0x29D9C998, 0xB0D09822, 0xC7D7A8B4, 0x59B33D17, 0x2EB40D81,
+    0xB7BD5C3B, 0xC0BA6CAD, 0xEDB88320, 0x9ABFB3B6, 0x03B6E20C, 0x74B1D29A,
+    0xEAD54739, 0x9DD277AF, 0x04DB2615, 0x73DC1683, 0xE3630B12, 0x94643B84,
+    0x0D6D6A3E, 0x7A6A5AA8, 0xE40ECF0B, 0x9309FF9D, 0x0A00AE27, 0x7D079EB1,
+    0xF00F9344, 0x8708A3D2, 0x1E01F268, 0x6906C2FE, 0xF762575D, 0x806567CB,
+    0x196C3671, 0x6E6B06E7, 0xFED41B76, 0x89D32BE0, 0x10DA7A5A, 0x67DD4ACC,
+    0xF9B9DF6F, 0x8EBEEFF9, 0x17B7BE43, 0x60B08ED5, 0xD6D6A3E8, 0xA1D1937E,
+    0x38D8C2C4, 0x4FDFF252, 0xD1BB67F1, 0xA6BC5767, 0x3FB506DD, 0x48B2364B,
+    0xD80D2BDA, 0xAF0A1B4C, 0x36034AF6, 0x41047A60, 0xDF60EFC3, 0xA867DF55,
+    0x316E8EEF, 0x4669BE79, 0xCB61B38C, 0xBC66831A, 0x256FD2A0, 0x5268E236,
+    0xCC0C7795, 0xBB0B4703, 0x220216B9, 0x5505262F, 0xC5BA3BBE, 0xB2BD0B28,
+    0x2BB45A92, 0x5CB36A04, 0xC2D7FFA7, 0xB5D0CF31, 0x2CD99E8B, 0x5BDEAE1D,
+    0x9B64C2B0, 0xEC63F226, 0x756AA39C, 0x026D930A, 0x9C0906A9, 0xEB0E363F,
+    0x72076785, 0x05005713, 0x95BF4A82, 0xE2B87A14, 0x7BB12BAE, 0x0CB61B38,
+    0x92D28E9B, 0xE5D5BE0D, 0x7CDCEFB7, 0x0BDBDF21, 0x86D3D2D4, 0xF1D4E242,
+    0x68DDB3F8, 0x1FDA836E, 0x81BE16CD, 0xF6B9265B, 0x6FB077E1, 0x18B74777,
+    0x88085AE6, 0xFF0F6A70, 0x66063BCA, 0x11010B5C, 0x8F659EFF, 0xF862AE69,
+    0x616BFFD3, 0x166CCF45, 0xA00AE278, 0xD70DD2EE, 0x4E048354, 0x3903B3C2,
+    0xA7672661, 0xD06016F7, 0x4969474D, 0x3E6E77DB, 0xAED16A4A, 0xD9D65ADC,
+    0x40DF0B66, 0x37D83BF0, 0xA9BCAE53, 0xDEBB9EC5, 0x47B2CF7F, 0x30B5FFE9,
+    0xBDBDF21C, 0xCABAC28A, 0x53B39330, 0x24B4A3A6, 0xBAD03605, 0xCDD70693,
+    0x54DE5729, 0x23D967BF, 0xB3667A2E, 0xC4614AB8, 0x5D681B02, 0x2A6F2B94,
+    0xB40BBE37, 0xC30C8EA1, 0x5A05DF1B, 0x2D02EF8D,
+];
+
+/// A word waiting to be written by a `BufferedWriter`
+#[derive(Clone, Copy)]
+pub struct PendingWord {
+    offset: u32,
+    value: u32,
+    used: bool,
+}
+
+impl PendingWord {
+    /// An unused entry, to initialize the buffer of a `BufferedWriter`
+    pub const EMPTY: PendingWord = PendingWord {
+        offset: 0,
+        value: 0,
+        used: false,
+    };
+}
+
+/// Collects writes in RAM and programs them as whole words
+///
+/// Writes may be of any length and alignment, and in any order. Writes to the
+/// same word are merged, so every word is programmed once per `flush` at
+/// most. The number of times every word has been written since it was last
+/// erased is tracked, so the `N_WRITE` limit is never exceeded.
+///
+/// The write counts are not retained across resets. Words that are not
+/// erased when the writer is created are assumed to have reached the limit.
+pub struct BufferedWriter<'a> {
+    nvmc: &'a mut Nvmc,
+    pending: &'a mut [PendingWord],
+    write_counts: &'a mut [u8],
+}
+
+impl<'a> BufferedWriter<'a> {
+    /// Create a writer for the flash region of `nvmc`
+    ///
+    /// `pending` holds the words waiting to be written, and determines how
+    /// many words can be collected before they are flushed automatically.
+    /// `write_counts` needs one entry for every word in the flash region.
+    pub fn new(
+        nvmc: &'a mut Nvmc,
+        pending: &'a mut [PendingWord],
+        write_counts: &'a mut [u8],
+    ) -> Result<Self, Error> {
+        let words = nvmc.storage.len() / WORD_SIZE;
+        if pending.is_empty() || write_counts.len() < words {
+            return Err(Error::BufferTooShort);
+        }
+
+        for entry in pending.iter_mut() {
+            *entry = PendingWord::EMPTY;
+        }
+        for (i, count) in write_counts[..words].iter_mut().enumerate() {
+            *count = if nvmc.read_word(i * WORD_SIZE) == 0xFFFF_FFFF {
+                0
+            } else {
+                N_WRITE
+            };
+        }
+
+        Ok(BufferedWriter {
+            nvmc,
+            pending,
+            write_counts,
+        })
+    }
+
+    /// Write `bytes` at `offset`
+    ///
+    /// The data is only programmed into flash once the buffer is full or
+    /// `flush` is called.
+    pub fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        if offset as usize + bytes.len() > self.nvmc.storage.len() {
+            return Err(Error::OutOfBounds);
+        }
+
+        for (i, byte) in bytes.iter().enumerate() {
+            let address = offset as usize + i;
+            let word_offset = (address - address % WORD_SIZE) as u32;
+            let shift = (address % WORD_SIZE) * 8;
+
+            let entry = self.entry(word_offset)?;
+            entry.value = entry.value & !(0xFF << shift) | u32::from(*byte) << shift;
+        }
+
+        Ok(())
+    }
+
+    /// Program all collected words into flash
+    pub fn flush(&mut self) -> Result<(), Error> {
+        for i in 0..self.pending.len() {
+            let entry = self.pending[i];
+            if !entry.used {
+                continue;
+            }
+
+            let index = entry.offset as usize / WORD_SIZE;
+            let current = self.nvmc.read_word(entry.offset as usize);
+            if current != entry.value {
+                // Programming can only clear bits
+                if current & entry.value != entry.value {
+                    return Err(Error::NeedsErase);
+                }
+                if self.write_counts[index] >= N_WRITE {
+                    return Err(Error::WriteLimit);
+                }
+
+                self.nvmc.write_word(entry.offset, entry.value)?;
+                self.write_counts[index] += 1;
+            }
+
+            self.pending[i] = PendingWord::EMPTY;
+        }
+
+        Ok(())
+    }
+
+    /// Erase the page starting at `offset`
+    ///
+    /// Collected writes to the page are discarded.
+    pub fn erase_page(&mut self, offset: u32) -> Result<(), Error> {
+        self.nvmc.erase_page(offset)?;
+
+        let page = offset..offset + PAGE_SIZE as u32;
+        for entry in self.pending.iter_mut() {
+            if entry.used && page.contains(&entry.offset) {
+                *entry = PendingWord::EMPTY;
+            }
+        }
+
+        let first = offset as usize / WORD_SIZE;
+        for count in &mut self.write_counts[first..first + PAGE_SIZE / WORD_SIZE] {
+            *count = 0;
+        }
+
+        Ok(())
+    }
+
+    /// Find the pending entry of a word, or add one
+    fn entry(&mut self, offset: u32) -> Result<&mut PendingWord, Error> {
+        let index = match self.pending.iter().position(|e| e.used && e.offset == offset) {
+            Some(index) => index,
+            None => {
+                let index = match self.pending.iter().position(|e| !e.used) {
+                    Some(index) => index,
+                    None => {
+                        self.flush()?;
+                        0
+                    }
+                };
+
+                self.pending[index] = PendingWord {
+                    offset,
+                    value: self.nvmc.read_word(offset as usize),
+                    used: true,
+                };
+                index
+            }
+        };
+
+        Ok(&mut self.pending[index])
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    NotAligned,
+    OutOfBounds,
+    Verify,
+    BufferTooShort,
+    NeedsErase,
+    WriteLimit,
+}
+
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::NotAligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            Error::Verify
+            | Error::BufferTooShort
+            | Error::NeedsErase
+            | Error::WriteLimit => NorFlashErrorKind::Other,
+        }
+    }
+}
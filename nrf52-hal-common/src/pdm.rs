@@ -0,0 +1,353 @@
+//! HAL interface to the Pulse Density Modulation interface (PDM) peripheral
+//!
+//! The PDM decimates the output of digital MEMS microphones into 16 bit PCM
+//! samples at PDM_CLK / 64, about 16 kHz with the default clock, and writes
+//! them into RAM using EasyDMA.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 44
+//! - nrf52840: Section 6.14
+use core::slice;
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+pub use crate::target::pdm::pdmclkctrl::FREQW as Frequency;
+
+use crate::gpio::{Floating, Input, Output, Pin, PushPull};
+use crate::stream::DoubleBuffered;
+use crate::target::PDM;
+
+use embedded_dma::WriteBuffer;
+
+/// Largest number of samples in a buffer
+pub const MAX_SAMPLES: usize = 0x7FFF;
+
+/// Smallest and largest gain, in steps of 0.5 dB
+pub const MIN_GAIN: i8 = -40;
+pub const MAX_GAIN: i8 = 40;
+
+/// Interface to the PDM peripheral
+pub struct Pdm {
+    periph: PDM,
+}
+
+impl Pdm {
+    /// Take ownership of the PDM peripheral
+    ///
+    /// The microphone is sampled in mono, on the falling edge of the clock.
+    pub fn new(pdm: PDM, pins: Pins, frequency: Frequency) -> Self {
+        pdm.psel.clk.write(|w| {
+            let w = unsafe { w.pin().bits(pins.clk.pin) };
+            #[cfg(feature = "52840")]
+            let w = w.port().bit(pins.clk.port);
+            w.connect().connected()
+        });
+        pdm.psel.din.write(|w| {
+            let w = unsafe { w.pin().bits(pins.din.pin) };
+            #[cfg(feature = "52840")]
+            let w = w.port().bit(pins.din.port);
+            w.connect().connected()
+        });
+
+        pdm.pdmclkctrl.write(|w| w.freq().variant(frequency));
+        pdm.mode
+            .write(|w| w.operation().mono().edge().left_falling());
+        pdm.enable.write(|w| w.enable().enabled());
+
+        Pdm { periph: pdm }
+    }
+
+    /// Sample one microphone, or two microphones sharing the data line
+    ///
+    /// In stereo operation, the samples of the left and right channels are
+    /// interleaved in the buffers, starting with the left channel, and buffer
+    /// lengths count the samples of both channels.
+    pub fn set_channels(&mut self, channels: Channels) {
+        match channels {
+            Channels::Mono => self.periph.mode.modify(|_, w| w.operation().mono()),
+            Channels::Stereo => self.periph.mode.modify(|_, w| w.operation().stereo()),
+        }
+    }
+
+    /// Select the clock edge on which the left channel is sampled
+    ///
+    /// The right channel is sampled on the other edge. In mono operation, the
+    /// left channel is used.
+    pub fn set_edge(&mut self, edge: Edge) {
+        match edge {
+            Edge::LeftFalling => self.periph.mode.modify(|_, w| w.edge().left_falling()),
+            Edge::LeftRising => self.periph.mode.modify(|_, w| w.edge().left_rising()),
+        }
+    }
+
+    /// Set the gain of the left and right channels, in steps of 0.5 dB
+    ///
+    /// The gain ranges from `MIN_GAIN` (-20 dB) to `MAX_GAIN` (+20 dB), and
+    /// defaults to 0 dB.
+    pub fn set_gain(&mut self, left: i8, right: i8) -> Result<(), Error> {
+        let range = MIN_GAIN..=MAX_GAIN;
+        if !range.contains(&left) || !range.contains(&right) {
+            return Err(Error::GainOutOfRange);
+        }
+
+        // A register value of 0x28 is 0 dB
+        self.periph
+            .gainl
+            .write(|w| unsafe { w.gainl().bits((left - MIN_GAIN) as u8) });
+        self.periph
+            .gainr
+            .write(|w| unsafe { w.gainr().bits((right - MIN_GAIN) as u8) });
+
+        Ok(())
+    }
+
+    /// Fill `buffer` with samples, blocking until it is full
+    ///
+    /// The first samples after starting contain the settling of the
+    /// decimation filter, and should be discarded.
+    pub fn sample(&mut self, buffer: &mut [i16]) -> Result<(), Error> {
+        check_buffer(buffer)?;
+
+        self.set_buffer(buffer);
+        self.periph.events_started.reset();
+        self.periph.events_end.reset();
+        self.periph.events_stopped.reset();
+        self.periph.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        while self.periph.events_end.read().bits() == 0 {}
+
+        self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+        while self.periph.events_stopped.read().bits() == 0 {}
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        self.periph.events_started.reset();
+        self.periph.events_end.reset();
+        self.periph.events_stopped.reset();
+
+        Ok(())
+    }
+
+    /// Start capturing samples continuously into two buffers
+    ///
+    /// While one buffer is filled, the other one is handed out by
+    /// `Capture::next_frame`. Both buffers must have the same length. They
+    /// are owned by the capture until it is stopped, so EasyDMA can't outlive
+    /// them even if the capture is leaked.
+    pub fn start_capture<B>(
+        mut self,
+        mut first: B,
+        mut second: B,
+    ) -> Result<Capture<B>, (Error, B, B, Self)>
+    where
+        B: WriteBuffer<Word = i16> + 'static,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffers, which the capture does
+        // until it has been stopped
+        let (first_ptr, first_len) = unsafe { first.write_buffer() };
+        let (second_ptr, second_len) = unsafe { second.write_buffer() };
+        let first_slice = unsafe { slice::from_raw_parts_mut(first_ptr, first_len) };
+        let second_slice = unsafe { slice::from_raw_parts_mut(second_ptr, second_len) };
+
+        if let Err(e) = check_buffer(first_slice).and(check_buffer(second_slice)) {
+            return Err((e, first, second, self));
+        }
+        if first_len != second_len {
+            return Err((Error::BufferLengthMismatch, first, second, self));
+        }
+
+        self.set_buffer(first_slice);
+        self.periph.events_started.reset();
+        self.periph.events_end.reset();
+        self.periph.events_stopped.reset();
+        self.periph.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        // Once the first pointer has been latched, the second buffer can be
+        // queued
+        while self.periph.events_started.read().bits() == 0 {}
+        self.periph.events_started.reset();
+        self.set_buffer(second_slice);
+
+        Ok(Capture {
+            inner: Some((self, [first, second])),
+            filling: 0,
+        })
+    }
+
+    /// Return the underlying PDM peripheral
+    pub fn free(self) -> PDM {
+        self.periph.enable.write(|w| w.enable().disabled());
+        self.periph
+    }
+
+    fn set_buffer(&mut self, buffer: &mut [i16]) {
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+
+        self.periph
+            .sample
+            .ptr
+            .write(|w| unsafe { w.bits(buffer.as_mut_ptr() as u32) });
+        self.periph
+            .sample
+            .maxcnt
+            .write(|w| unsafe { w.buffsize().bits(buffer.len() as u16) });
+    }
+
+    fn stop(&mut self) {
+        self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+        while self.periph.events_stopped.read().bits() == 0 {}
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        self.periph.events_started.reset();
+        self.periph.events_end.reset();
+        self.periph.events_stopped.reset();
+    }
+}
+
+/// Continuous capture into two buffers
+pub struct Capture<B> {
+    // Only taken by `stop`, so the PDM is stopped when the capture is dropped
+    inner: Option<(Pdm, [B; 2])>,
+    filling: usize,
+}
+
+impl<B> Capture<B>
+where
+    B: WriteBuffer<Word = i16>,
+{
+    /// Return the next buffer of samples, once it has been filled
+    ///
+    /// The buffer is refilled as soon as the other buffer is full, so it must
+    /// be processed within the time it takes to fill a buffer.
+    pub fn next_frame(&mut self) -> nb::Result<&[i16], Error> {
+        match self.complete() {
+            Some(filled) => Ok(self.buffer(filled)),
+            None => Err(nb::Error::WouldBlock),
+        }
+    }
+
+    /// Stop capturing, and return the PDM and the buffers
+    pub fn stop(mut self) -> (Pdm, B, B) {
+        let (mut pdm, [first, second]) = self.inner.take().unwrap();
+        pdm.stop();
+        (pdm, first, second)
+    }
+
+    fn pdm(&self) -> &Pdm {
+        &self.inner.as_ref().unwrap().0
+    }
+
+    fn buffer(&mut self, index: usize) -> &mut [i16] {
+        let (_, buffers) = self.inner.as_mut().unwrap();
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffer
+        let (ptr, len) = unsafe { buffers[index].write_buffer() };
+        unsafe { slice::from_raw_parts_mut(ptr, len) }
+    }
+
+    /// Requeue the buffer that has been filled, and return its index
+    fn complete(&mut self) -> Option<usize> {
+        if self.pdm().periph.events_end.read().bits() == 0 {
+            return None;
+        }
+        self.pdm().periph.events_end.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions on the filled buffer have completed
+        compiler_fence(SeqCst);
+
+        let filled = self.filling;
+        self.filling ^= 1;
+
+        // The pointer of the other buffer is latched at the same time, so the
+        // filled buffer can be queued next
+        while self.pdm().periph.events_started.read().bits() == 0 {}
+        self.pdm().periph.events_started.reset();
+        let buffer: *mut [i16] = self.buffer(filled);
+        self.inner.as_mut().unwrap().0.set_buffer(unsafe { &mut *buffer });
+
+        Some(filled)
+    }
+}
+
+impl<B> DoubleBuffered for Capture<B>
+where
+    B: WriteBuffer<Word = i16>,
+{
+    type Word = i16;
+
+    fn next_completed(&mut self) -> Option<&mut [i16]> {
+        let filled = self.complete()?;
+        Some(self.buffer(filled))
+    }
+
+    fn enable_interrupt(&mut self) {
+        self.pdm().periph.intenset.write(|w| w.end().set());
+    }
+
+    fn disable_interrupt(&mut self) {
+        self.pdm().periph.intenclr.write(|w| w.end().clear());
+    }
+}
+
+impl<B> Drop for Capture<B> {
+    fn drop(&mut self) {
+        if let Some((pdm, _)) = &mut self.inner {
+            pdm.stop();
+        }
+    }
+}
+
+fn check_buffer(buffer: &[i16]) -> Result<(), Error> {
+    if buffer.is_empty() {
+        return Err(Error::BufferEmpty);
+    }
+    if buffer.len() > MAX_SAMPLES {
+        return Err(Error::BufferTooLong);
+    }
+    Ok(())
+}
+
+/// Number of microphones
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Channels {
+    Mono,
+    Stereo,
+}
+
+/// Clock edge on which the left channel is sampled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    LeftFalling,
+    LeftRising,
+}
+
+/// GPIO pins for the PDM interface
+pub struct Pins {
+    /// Clock output to the microphone
+    pub clk: Pin<Output<PushPull>>,
+
+    /// Data input from the microphone
+    pub din: Pin<Input<Floating>>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    BufferEmpty,
+    BufferTooLong,
+    BufferLengthMismatch,
+    GainOutOfRange,
+}
@@ -0,0 +1,85 @@
+//! Takeover of all peripherals at once
+//!
+//! `init` takes the core and device peripherals, and hands back the GPIO
+//! ports already split into their pins, along with the other peripherals of
+//! the PAC, which is re-exported as `pac`. This way applications only have
+//! to depend on this crate.
+//!
+//! ``` ignore
+//! let p = hal::init().unwrap();
+//! let led = p.p0.p0_17.into_push_pull_output(Level::High);
+//! let timer = Timer::new(p.TIMER0);
+//! ```
+use cortex_m::Peripherals as CorePeripherals;
+
+use crate::gpio::p0;
+#[cfg(feature = "52840")]
+use crate::gpio::p1;
+use crate::target;
+
+macro_rules! peripherals {
+    ($($name:ident),* $(,)?) => {
+        /// All peripherals, with the GPIO ports split into their pins
+        #[allow(non_snake_case)]
+        pub struct Peripherals {
+            /// Core peripherals of the Cortex-M4
+            pub core: CorePeripherals,
+            /// Pins of port 0
+            pub p0: p0::Parts,
+            /// Pins of port 1
+            #[cfg(feature = "52840")]
+            pub p1: p1::Parts,
+            $(pub $name: target::$name,)*
+        }
+
+        impl Peripherals {
+            fn new(core: CorePeripherals, device: target::Peripherals) -> Self {
+                Peripherals {
+                    core,
+                    p0: p0::Parts::new(device.P0),
+                    #[cfg(feature = "52840")]
+                    p1: p1::Parts::new(device.P1),
+                    $($name: device.$name,)*
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "52810")]
+peripherals!(
+    FICR, UICR, BPROT, CLOCK, POWER, RADIO, UART0, UARTE0, TWI0, TWIM0, TWIS0,
+    SPI0, SPIM0, SPIS0, GPIOTE, SAADC, TIMER0, TIMER1, TIMER2, RTC0, TEMP, RNG,
+    ECB, AAR, CCM, WDT, RTC1, QDEC, COMP, EGU0, SWI0, EGU1, SWI1, SWI2, SWI3,
+    SWI4, SWI5, PWM0, PDM, NVMC, PPI,
+);
+
+#[cfg(feature = "52832")]
+peripherals!(
+    FICR, UICR, BPROT, POWER, CLOCK, RADIO, UARTE0, UART0, SPIM0, SPIS0, TWIM0,
+    TWIS0, SPI0, TWI0, SPIM1, SPIS1, TWIM1, TWIS1, SPI1, TWI1, NFCT, GPIOTE,
+    SAADC, TIMER0, TIMER1, TIMER2, RTC0, TEMP, RNG, ECB, CCM, AAR, WDT, RTC1,
+    QDEC, COMP, LPCOMP, SWI0, EGU0, SWI1, EGU1, SWI2, EGU2, SWI3, EGU3, SWI4,
+    EGU4, SWI5, EGU5, TIMER3, TIMER4, PWM0, PDM, NVMC, PPI, MWU, PWM1, PWM2,
+    SPIM2, SPIS2, SPI2, RTC2, I2S,
+);
+
+#[cfg(feature = "52840")]
+peripherals!(
+    FICR, UICR, CLOCK, POWER, RADIO, UART0, UARTE0, SPI0, SPIM0, SPIS0, TWI0,
+    TWIM0, TWIS0, SPI1, SPIM1, SPIS1, TWI1, TWIM1, TWIS1, NFCT, GPIOTE, SAADC,
+    TIMER0, TIMER1, TIMER2, RTC0, TEMP, RNG, ECB, AAR, CCM, WDT, RTC1, QDEC,
+    COMP, LPCOMP, EGU0, SWI0, EGU1, SWI1, EGU2, SWI2, EGU3, SWI3, EGU4, SWI4,
+    EGU5, SWI5, TIMER3, TIMER4, PWM0, PDM, ACL, NVMC, PPI, MWU, PWM1, PWM2,
+    SPI2, SPIM2, SPIS2, RTC2, I2S, USBD, UARTE1, QSPI, PWM3, SPIM3,
+    CC_HOST_RGF, CRYPTOCELL,
+);
+
+/// Take all peripherals
+///
+/// Returns `None` if the core or device peripherals have been taken already.
+pub fn init() -> Option<Peripherals> {
+    let core = CorePeripherals::take()?;
+    let device = target::Peripherals::take()?;
+    Some(Peripherals::new(core, device))
+}
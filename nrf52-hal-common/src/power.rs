@@ -0,0 +1,574 @@
+//! HAL interface to the POWER peripheral
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 18
+//! - nrf52840: Section 5.3
+use core::ops::Range;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use cortex_m::{asm, interrupt, peripheral::SCB};
+
+use crate::target::{power, CLOCK, POWER};
+
+/// Start address of the data RAM
+const RAM_START: u32 = 0x2000_0000;
+
+/// Size of the data RAM in bytes
+#[cfg(feature = "52810")]
+pub const RAM_SIZE: u32 = 24 * 1024;
+#[cfg(feature = "52832")]
+pub const RAM_SIZE: u32 = 64 * 1024;
+#[cfg(feature = "52840")]
+pub const RAM_SIZE: u32 = 256 * 1024;
+
+/// RAM0 to RAM7 consist of two 4 KiB sections each
+const SMALL_SECTION_SIZE: u32 = 4 * 1024;
+const SMALL_SECTIONS_PER_BLOCK: u32 = 2;
+
+/// RAM8 on the nrf52840 starts after RAM0 to RAM7 and consists of six 32 KiB
+/// sections
+#[cfg(feature = "52840")]
+const SMALL_BLOCKS_SIZE: u32 = 64 * 1024;
+#[cfg(feature = "52840")]
+const LARGE_SECTION_SIZE: u32 = 32 * 1024;
+
+/// Interface to the POWER peripheral
+pub struct Power(POWER);
+
+impl Power {
+    /// Take ownership of the POWER peripheral
+    pub fn new(power: POWER) -> Self {
+        Power(power)
+    }
+
+    /// Request constant latency until the returned guard is dropped
+    ///
+    /// In constant latency mode the CPU wakeup latency and the PPI task
+    /// response are constant and kept at a minimum, at the cost of a higher
+    /// idle current. This is useful for latency-critical phases such as radio
+    /// operation or fast SPI transfers. Dropping the guard returns the system
+    /// to low power mode.
+    pub fn constant_latency(&mut self) -> ConstantLatency<'_> {
+        self.0.tasks_constlat.write(|w| unsafe { w.bits(1) });
+        ConstantLatency { power: self }
+    }
+
+    /// Enable or disable the DC/DC converter of the main regulator
+    ///
+    /// The DC/DC converter roughly halves the active current compared to the
+    /// LDO, but only works when the external inductors are fitted. Enabling it
+    /// without them will brown out the chip. The converter is automatically
+    /// bypassed when the load is too low for it to be efficient.
+    ///
+    /// On the nrf52840 this controls REG1, the second regulator stage.
+    pub fn set_dcdc(&mut self, enabled: bool) {
+        self.0.dcdcen.write(|w| w.dcdcen().bit(enabled));
+    }
+
+    /// Enable or disable the DC/DC converter of the REG0 high voltage stage
+    ///
+    /// REG0 is only in use when the chip is supplied through VDDH (high
+    /// voltage mode), and requires its own inductor.
+    #[cfg(feature = "52840")]
+    pub fn set_dcdc0(&mut self, enabled: bool) {
+        self.0.dcdcen0.write(|w| w.dcdcen().bit(enabled));
+    }
+
+    /// Return which supply configuration the main regulator is in
+    ///
+    /// The output voltage of REG0 in high voltage mode is configured in
+    /// UICR.REGOUT0.
+    #[cfg(feature = "52840")]
+    pub fn main_regulator(&self) -> MainRegulator {
+        if self.0.mainregstatus.read().mainregstatus().is_high() {
+            MainRegulator::High
+        } else {
+            MainRegulator::Normal
+        }
+    }
+
+    /// Configure whether the RAM in `range` is retained in System OFF
+    ///
+    /// `range` is a range of addresses, e.g. the start and end symbols of a
+    /// linker section. RAM is retained in sections of 4 KiB (32 KiB for RAM8
+    /// on the nrf52840), so every section that overlaps `range` is
+    /// configured, including memory outside of it. Retaining fewer sections
+    /// lowers the System OFF current.
+    pub fn set_ram_retention(&mut self, range: Range<u32>, retain: bool) -> Result<(), Error> {
+        if range.start < RAM_START || range.end > RAM_START + RAM_SIZE || range.start > range.end {
+            return Err(Error::AddressNotInRam);
+        }
+        if range.start == range.end {
+            return Ok(());
+        }
+
+        let first = ram_section(range.start);
+        let last = ram_section(range.end - 1);
+
+        for block in first.0..=last.0 {
+            let sections = if block == first.0 && block == last.0 {
+                first.1..=last.1
+            } else if block == first.0 {
+                first.1..=sections_in_block(block) - 1
+            } else if block == last.0 {
+                0..=last.1
+            } else {
+                0..=sections_in_block(block) - 1
+            };
+
+            let mask = sections.fold(0, |mask, s| mask | (1 << (16 + s)));
+            let ram = self.ram_block(block);
+            if retain {
+                ram.powerset.write(|w| unsafe { w.bits(mask) });
+            } else {
+                ram.powerclr.write(|w| unsafe { w.bits(mask) });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Disable retention of all RAM in System OFF
+    pub fn clear_ram_retention(&mut self) {
+        for block in 0..ram_blocks() {
+            self.ram_block(block)
+                .powerclr
+                .write(|w| unsafe { w.bits(0xFFFF_0000) });
+        }
+    }
+
+    fn ram_block(&self, block: u32) -> &power::RAM {
+        match block {
+            0 => &self.0.ram0,
+            1 => &self.0.ram1,
+            2 => &self.0.ram2,
+            3 => &self.0.ram3,
+            4 => &self.0.ram4,
+            5 => &self.0.ram5,
+            6 => &self.0.ram6,
+            7 => &self.0.ram7,
+            #[cfg(feature = "52840")]
+            8 => &self.0.ram8,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Read the reasons for the last reset
+    ///
+    /// The reset reasons are accumulated over resets, except for power-on and
+    /// brownout resets, until they are cleared with `clear_reset_reason`.
+    pub fn read_reset_reason(&self) -> ResetReason {
+        ResetReason::from_bits(self.0.resetreas.read().bits())
+    }
+
+    /// Clear all reset reasons
+    pub fn clear_reset_reason(&mut self) {
+        let bits = self.0.resetreas.read().bits();
+        self.0.resetreas.write(|w| unsafe { w.bits(bits) });
+    }
+
+    /// Enable the interrupt for a given event
+    ///
+    /// The POWER peripheral shares the `POWER_CLOCK` interrupt with the CLOCK
+    /// peripheral, which must be unmasked in the NVIC separately.
+    pub fn enable_interrupt(&mut self, evt: PowerEvent) {
+        match evt {
+            PowerEvent::PofWarn => self.0.intenset.write(|w| w.pofwarn().set()),
+            PowerEvent::SleepEnter => self.0.intenset.write(|w| w.sleepenter().set()),
+            PowerEvent::SleepExit => self.0.intenset.write(|w| w.sleepexit().set()),
+            #[cfg(feature = "52840")]
+            PowerEvent::UsbDetected => self.0.intenset.write(|w| w.usbdetected().set()),
+            #[cfg(feature = "52840")]
+            PowerEvent::UsbRemoved => self.0.intenset.write(|w| w.usbremoved().set()),
+            #[cfg(feature = "52840")]
+            PowerEvent::UsbPowerReady => self.0.intenset.write(|w| w.usbpwrrdy().set()),
+        }
+    }
+
+    /// Disable the interrupt for a given event
+    pub fn disable_interrupt(&mut self, evt: PowerEvent) {
+        match evt {
+            PowerEvent::PofWarn => self.0.intenclr.write(|w| w.pofwarn().clear()),
+            PowerEvent::SleepEnter => self.0.intenclr.write(|w| w.sleepenter().clear()),
+            PowerEvent::SleepExit => self.0.intenclr.write(|w| w.sleepexit().clear()),
+            #[cfg(feature = "52840")]
+            PowerEvent::UsbDetected => self.0.intenclr.write(|w| w.usbdetected().clear()),
+            #[cfg(feature = "52840")]
+            PowerEvent::UsbRemoved => self.0.intenclr.write(|w| w.usbremoved().clear()),
+            #[cfg(feature = "52840")]
+            PowerEvent::UsbPowerReady => self.0.intenclr.write(|w| w.usbpwrrdy().clear()),
+        }
+    }
+
+    /// Obtain the state of a given event, and optionally clear the event if
+    /// it is set
+    pub fn get_event_triggered(&mut self, evt: PowerEvent, clear_on_read: bool) -> bool {
+        let triggered = match evt {
+            PowerEvent::PofWarn => self.0.events_pofwarn.read().bits() == 1,
+            PowerEvent::SleepEnter => self.0.events_sleepenter.read().bits() == 1,
+            PowerEvent::SleepExit => self.0.events_sleepexit.read().bits() == 1,
+            #[cfg(feature = "52840")]
+            PowerEvent::UsbDetected => self.0.events_usbdetected.read().bits() == 1,
+            #[cfg(feature = "52840")]
+            PowerEvent::UsbRemoved => self.0.events_usbremoved.read().bits() == 1,
+            #[cfg(feature = "52840")]
+            PowerEvent::UsbPowerReady => self.0.events_usbpwrrdy.read().bits() == 1,
+        };
+
+        if triggered && clear_on_read {
+            match evt {
+                PowerEvent::PofWarn => self.0.events_pofwarn.reset(),
+                PowerEvent::SleepEnter => self.0.events_sleepenter.reset(),
+                PowerEvent::SleepExit => self.0.events_sleepexit.reset(),
+                #[cfg(feature = "52840")]
+                PowerEvent::UsbDetected => self.0.events_usbdetected.reset(),
+                #[cfg(feature = "52840")]
+                PowerEvent::UsbRemoved => self.0.events_usbremoved.reset(),
+                #[cfg(feature = "52840")]
+                PowerEvent::UsbPowerReady => self.0.events_usbpwrrdy.reset(),
+            }
+        }
+
+        triggered
+    }
+
+    /// Read the status of the USB regulator
+    ///
+    /// A USB stack should only be started once the regulator output is ready,
+    /// and stopped when VBUS is removed.
+    #[cfg(feature = "52840")]
+    pub fn usb_regulator_status(&self) -> UsbRegulatorStatus {
+        let status = self.0.usbregstatus.read();
+        UsbRegulatorStatus {
+            vbus_detected: status.vbusdetect().bit(),
+            output_ready: status.outputrdy().bit(),
+        }
+    }
+
+    /// Read the general purpose retention register GPREGRET
+    ///
+    /// GPREGRET and GPREGRET2 keep their value across all resets except
+    /// power-on and brownout, and are commonly used to pass flags to a
+    /// bootloader.
+    pub fn gpregret(&self) -> u8 {
+        self.0.gpregret.read().gpregret().bits()
+    }
+
+    /// Write the general purpose retention register GPREGRET
+    pub fn set_gpregret(&mut self, value: u8) {
+        self.0.gpregret.write(|w| unsafe { w.gpregret().bits(value) });
+    }
+
+    /// Read the general purpose retention register GPREGRET2
+    pub fn gpregret2(&self) -> u8 {
+        self.0.gpregret2.read().gpregret().bits()
+    }
+
+    /// Write the general purpose retention register GPREGRET2
+    pub fn set_gpregret2(&mut self, value: u8) {
+        self.0.gpregret2.write(|w| unsafe { w.gpregret().bits(value) });
+    }
+
+    /// Write `magic` to GPREGRET and reset the chip
+    ///
+    /// The bootloader is expected to check GPREGRET for `magic` and stay in
+    /// DFU mode if it is found. See `Bootloader::magic` for common values.
+    pub fn reset_into_bootloader(&mut self, magic: u8, scb: &mut SCB) -> ! {
+        self.set_gpregret(magic);
+
+        #[allow(deprecated)]
+        scb.system_reset()
+    }
+
+    /// Enter System OFF, the deepest power saving mode
+    ///
+    /// Only a wakeup, which resets the chip, leaves System OFF. Wakeups are
+    /// caused by the reset pin, GPIO pins configured for DETECT, the LPCOMP,
+    /// the NFCT, where present, detecting a field while in SENSE mode, e.g.
+    /// through `nfct::FieldDetector`, and on the nRF52840 VBUS.
+    /// `read_reset_reason` tells them apart after the reset. RAM is only kept
+    /// in the sections retained with `set_ram_retention`.
+    pub fn system_off(&mut self) -> ! {
+        self.0.systemoff.write(|w| w.systemoff().enter());
+
+        // In debug interface mode, System OFF is emulated and execution
+        // continues, so it has to be stopped here
+        asm::dsb();
+        loop {
+            asm::wfe();
+        }
+    }
+
+    /// Return the underlying POWER peripheral
+    pub fn free(self) -> POWER {
+        self.0
+    }
+}
+
+/// Return the number of RAM blocks
+fn ram_blocks() -> u32 {
+    if cfg!(feature = "52840") {
+        9
+    } else {
+        (RAM_SIZE + SMALL_SECTION_SIZE * SMALL_SECTIONS_PER_BLOCK - 1)
+            / (SMALL_SECTION_SIZE * SMALL_SECTIONS_PER_BLOCK)
+    }
+}
+
+/// Return the number of retainable sections in a RAM block
+fn sections_in_block(block: u32) -> u32 {
+    if block < 8 {
+        SMALL_SECTIONS_PER_BLOCK
+    } else {
+        6
+    }
+}
+
+/// Return the RAM block and section containing `addr`
+fn ram_section(addr: u32) -> (u32, u32) {
+    let offset = addr - RAM_START;
+
+    #[cfg(feature = "52840")]
+    {
+        if offset >= SMALL_BLOCKS_SIZE {
+            return (8, (offset - SMALL_BLOCKS_SIZE) / LARGE_SECTION_SIZE);
+        }
+    }
+
+    let section = offset / SMALL_SECTION_SIZE;
+    (
+        section / SMALL_SECTIONS_PER_BLOCK,
+        section % SMALL_SECTIONS_PER_BLOCK,
+    )
+}
+
+/// Coordinates the use of the high frequency clock and puts the CPU to sleep
+///
+/// The drivers don't start the accurate HFXO clock themselves, so code using
+/// a driver that needs it, e.g. the radio, requests it here for as long as it
+/// uses the driver. The crystal is started by the first request and stopped
+/// when the last one is released, so the chip falls back to the low power
+/// HFINT clock whenever possible.
+///
+/// The sleep manager is meant to be shared, e.g. as a `static`. It must not
+/// be combined with `Clocks::enable_ext_hfosc`, which keeps the crystal
+/// running permanently.
+pub struct SleepManager {
+    hfclk_requests: AtomicUsize,
+}
+
+impl SleepManager {
+    pub const fn new() -> Self {
+        SleepManager {
+            hfclk_requests: AtomicUsize::new(0),
+        }
+    }
+
+    /// Request the external high frequency oscillator
+    ///
+    /// Blocks until the crystal is running if it was not already. The
+    /// request is released when the returned guard is dropped.
+    pub fn request_hfclk(&self) -> HfclkRequest<'_> {
+        interrupt::free(|_| {
+            if self.hfclk_requests.fetch_add(1, Ordering::SeqCst) == 0 {
+                let clock = unsafe { &*CLOCK::ptr() };
+                clock.events_hfclkstarted.write(|w| unsafe { w.bits(0) });
+                clock.tasks_hfclkstart.write(|w| unsafe { w.bits(1) });
+                while clock.events_hfclkstarted.read().bits() == 0 {}
+                clock.events_hfclkstarted.write(|w| unsafe { w.bits(0) });
+            }
+        });
+
+        HfclkRequest { manager: self }
+    }
+
+    /// Is the external high frequency oscillator currently requested?
+    pub fn hfclk_requested(&self) -> bool {
+        self.hfclk_requests.load(Ordering::SeqCst) != 0
+    }
+
+    fn release_hfclk(&self) {
+        interrupt::free(|_| {
+            if self.hfclk_requests.fetch_sub(1, Ordering::SeqCst) == 1 {
+                let clock = unsafe { &*CLOCK::ptr() };
+                clock.tasks_hfclkstop.write(|w| unsafe { w.bits(1) });
+            }
+        });
+    }
+
+    /// Put the CPU to sleep until something happens
+    ///
+    /// This sleeps with WFI until an interrupt is pending. WFI also wakes up
+    /// for interrupts masked by PRIMASK, so when called inside
+    /// `interrupt::free` after checking for pending work, there is no window
+    /// for a lost wakeup between the check and the sleep. The handler then
+    /// runs once interrupts are unmasked again.
+    pub fn idle(&self) {
+        asm::wfi();
+    }
+}
+
+impl Default for SleepManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Keeps the external high frequency oscillator running while it is alive
+///
+/// Created by `SleepManager::request_hfclk`.
+pub struct HfclkRequest<'a> {
+    manager: &'a SleepManager,
+}
+
+impl<'a> HfclkRequest<'a> {
+    /// Release the request
+    pub fn release(self) {}
+}
+
+impl<'a> Drop for HfclkRequest<'a> {
+    fn drop(&mut self) {
+        self.manager.release_hfclk();
+    }
+}
+
+/// Events that can be generated by the POWER peripheral
+pub enum PowerEvent {
+    /// The supply voltage is below the power failure threshold
+    PofWarn,
+    /// The CPU entered WFI/WFE sleep
+    SleepEnter,
+    /// The CPU left WFI/WFE sleep
+    SleepExit,
+    /// A voltage was detected on VBUS
+    #[cfg(feature = "52840")]
+    UsbDetected,
+    /// The voltage on VBUS was removed
+    #[cfg(feature = "52840")]
+    UsbRemoved,
+    /// The USB regulator output has settled
+    #[cfg(feature = "52840")]
+    UsbPowerReady,
+}
+
+/// Bootloaders that can be entered with `Power::reset_into_bootloader`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bootloader {
+    /// The Nordic secure bootloader of the nRF5 SDK
+    NordicSecure,
+    /// The Adafruit bootloader, in serial DFU mode
+    AdafruitSerial,
+    /// The Adafruit bootloader, as a UF2 mass storage device
+    AdafruitUf2,
+    /// Another bootloader, checking GPREGRET for the given magic value
+    Custom(u8),
+}
+
+impl Bootloader {
+    /// Return the value to be written to GPREGRET
+    pub fn magic(self) -> u8 {
+        match self {
+            Bootloader::NordicSecure => 0xB1,
+            Bootloader::AdafruitSerial => 0x4E,
+            Bootloader::AdafruitUf2 => 0x57,
+            Bootloader::Custom(magic) => magic,
+        }
+    }
+}
+
+/// Status of the USB regulator
+#[cfg(feature = "52840")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UsbRegulatorStatus {
+    /// VBUS is in the valid range
+    pub vbus_detected: bool,
+    /// The USB regulator output is ready to be used
+    pub output_ready: bool,
+}
+
+/// Reasons for a reset, decoded from the RESETREAS register
+///
+/// Several reasons may be set at once. If none is set, the chip was reset by
+/// power-on or brownout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ResetReason {
+    /// Reset from the reset pin
+    pub pin: bool,
+    /// Reset from the watchdog
+    pub watchdog: bool,
+    /// Soft reset, e.g. through `SCB::sys_reset`
+    pub soft: bool,
+    /// Reset from CPU lock-up
+    pub lockup: bool,
+    /// Wakeup from System OFF by a GPIO DETECT signal
+    pub off: bool,
+    /// Wakeup from System OFF by LPCOMP
+    pub lpcomp: bool,
+    /// Wakeup from System OFF by entering debug interface mode
+    pub debug_interface: bool,
+    /// Wakeup from System OFF by the NFC field detector
+    pub nfc: bool,
+    /// Wakeup from System OFF by VBUS rising into the valid range
+    pub vbus: bool,
+}
+
+impl ResetReason {
+    fn from_bits(bits: u32) -> Self {
+        ResetReason {
+            pin: bits & (1 << 0) != 0,
+            watchdog: bits & (1 << 1) != 0,
+            soft: bits & (1 << 2) != 0,
+            lockup: bits & (1 << 3) != 0,
+            off: bits & (1 << 16) != 0,
+            lpcomp: bits & (1 << 17) != 0,
+            debug_interface: bits & (1 << 18) != 0,
+            nfc: bits & (1 << 19) != 0,
+            vbus: bits & (1 << 20) != 0,
+        }
+    }
+
+    /// Was the chip reset by power-on or brownout?
+    pub fn is_power_on(&self) -> bool {
+        *self == ResetReason::default()
+    }
+
+    /// Did the chip wake up from System OFF?
+    pub fn is_wakeup_from_off(&self) -> bool {
+        self.off || self.lpcomp || self.debug_interface || self.nfc || self.vbus
+    }
+}
+
+/// Keeps the system in constant latency mode while it is alive
+///
+/// Created by `Power::constant_latency`.
+pub struct ConstantLatency<'a> {
+    power: &'a mut Power,
+}
+
+impl<'a> ConstantLatency<'a> {
+    /// Return to low power mode
+    pub fn release(self) {}
+}
+
+impl<'a> Drop for ConstantLatency<'a> {
+    fn drop(&mut self) {
+        self.power.0.tasks_lowpwr.write(|w| unsafe { w.bits(1) });
+    }
+}
+
+/// Supply configuration of the main regulator
+#[cfg(feature = "52840")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MainRegulator {
+    /// Supplied through VDD, only REG1 is in use
+    Normal,
+    /// Supplied through VDDH, both REG0 and REG1 are in use
+    High,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    AddressNotInRam,
+}
@@ -0,0 +1,296 @@
+//! HAL interface to the Programmable Peripheral Interconnect (PPI)
+//!
+//! The PPI connects an event of one peripheral to a task of another, so the
+//! task is triggered by hardware whenever the event occurs, without any
+//! involvement of the CPU.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 21
+//! - nrf52840: Section 6.15
+#[cfg(lpcomp)]
+use crate::target::lpcomp;
+use crate::target::{comp, radio, rtc0, saadc, timer0, PPI};
+
+/// A peripheral event register that can be used as the event end point of a
+/// PPI channel
+///
+/// This trait is sealed, as the channels write the address to the hardware.
+pub trait Event: sealed::Event + Sized {
+    /// Return the address of the event register
+    fn event_address(&self) -> u32 {
+        self as *const Self as u32
+    }
+}
+
+/// A peripheral task register that can be used as a task end point of a PPI
+/// channel
+///
+/// This trait is sealed, as the channels write the address to the hardware.
+pub trait Task: sealed::Task + Sized {
+    /// Return the address of the task register
+    fn task_address(&self) -> u32 {
+        self as *const Self as u32
+    }
+}
+
+/// Implemented by all programmable PPI channels
+///
+/// This trait is sealed, so every channel is only owned once by `Parts`.
+pub trait Channel: sealed::Channel {
+    /// The number of this channel
+    const CH: usize;
+
+    /// Set the event that triggers this channel
+    fn set_event_endpoint<E: Event>(&mut self, event: &E) {
+        regs()
+            .ch[Self::CH]
+            .eep
+            .write(|w| unsafe { w.bits(event.event_address()) });
+    }
+
+    /// Set the task that is triggered by this channel
+    fn set_task_endpoint<T: Task>(&mut self, task: &T) {
+        regs()
+            .ch[Self::CH]
+            .tep
+            .write(|w| unsafe { w.bits(task.task_address()) });
+    }
+
+    /// Set a second task that is triggered by this channel
+    fn set_fork_task_endpoint<T: Task>(&mut self, task: &T) {
+        regs()
+            .fork[Self::CH]
+            .tep
+            .write(|w| unsafe { w.bits(task.task_address()) });
+    }
+
+    /// Remove the second task that is triggered by this channel
+    fn clear_fork_task_endpoint(&mut self) {
+        regs().fork[Self::CH].tep.reset();
+    }
+
+    /// Enable this channel
+    fn enable(&mut self) {
+        regs()
+            .chenset
+            .write(|w| unsafe { w.bits(1 << Self::CH) });
+    }
+
+    /// Disable this channel
+    fn disable(&mut self) {
+        regs()
+            .chenclr
+            .write(|w| unsafe { w.bits(1 << Self::CH) });
+    }
+}
+
+/// Access the PPI registers
+///
+/// This is safe, as every channel only accesses the registers dedicated to
+/// it, and CHENSET/CHENCLR are stateless write-1-to-set registers.
+fn regs() -> &'static crate::target::ppi::RegisterBlock {
+    unsafe { &*PPI::ptr() }
+}
+
+mod sealed {
+    pub trait Event {}
+    pub trait Task {}
+    pub trait Channel {}
+    pub trait FixedChannel {}
+}
+
+/// Implemented by the pre-programmed PPI channels
+///
+/// These channels connect fixed events and tasks, and can only be enabled
+/// and disabled. See the product specification for their connections, e.g.
+/// channel 24 connects RADIO READY to CCM KSGEN, and channel 25 connects
+/// RADIO ADDRESS to CCM CRYPT.
+///
+/// This trait is sealed, so every channel is only owned once by `Parts`.
+pub trait FixedChannel: sealed::FixedChannel {
+    /// The number of this channel
+    const CH: usize;
+
+    /// Enable this channel
+    fn enable(&mut self) {
+        regs()
+            .chenset
+            .write(|w| unsafe { w.bits(1 << Self::CH) });
+    }
+
+    /// Disable this channel
+    fn disable(&mut self) {
+        regs()
+            .chenclr
+            .write(|w| unsafe { w.bits(1 << Self::CH) });
+    }
+}
+
+macro_rules! ppi {
+    (
+        $($ppix:ident: ($Ppix:ident, $i:expr),)+
+        ;
+        $($fppix:ident: ($FPpix:ident, $fi:expr),)+
+    ) => {
+        /// The PPI channels
+        pub struct Parts {
+            $(
+                /// Channel
+                pub $ppix: $Ppix,
+            )+
+            $(
+                /// Pre-programmed channel
+                pub $fppix: $FPpix,
+            )+
+        }
+
+        impl Parts {
+            pub fn new(_ppi: PPI) -> Self {
+                Self {
+                    $(
+                        $ppix: $Ppix { _private: () },
+                    )+
+                    $(
+                        $fppix: $FPpix { _private: () },
+                    )+
+                }
+            }
+        }
+
+        $(
+            /// Programmable PPI channel
+            pub struct $Ppix {
+                _private: (),
+            }
+
+            impl sealed::Channel for $Ppix {}
+
+            impl Channel for $Ppix {
+                const CH: usize = $i;
+            }
+        )+
+
+        $(
+            /// Pre-programmed PPI channel
+            pub struct $FPpix {
+                _private: (),
+            }
+
+            impl sealed::FixedChannel for $FPpix {}
+
+            impl FixedChannel for $FPpix {
+                const CH: usize = $fi;
+            }
+        )+
+    }
+}
+
+ppi!(
+    ppi0: (Ppi0, 0),
+    ppi1: (Ppi1, 1),
+    ppi2: (Ppi2, 2),
+    ppi3: (Ppi3, 3),
+    ppi4: (Ppi4, 4),
+    ppi5: (Ppi5, 5),
+    ppi6: (Ppi6, 6),
+    ppi7: (Ppi7, 7),
+    ppi8: (Ppi8, 8),
+    ppi9: (Ppi9, 9),
+    ppi10: (Ppi10, 10),
+    ppi11: (Ppi11, 11),
+    ppi12: (Ppi12, 12),
+    ppi13: (Ppi13, 13),
+    ppi14: (Ppi14, 14),
+    ppi15: (Ppi15, 15),
+    ppi16: (Ppi16, 16),
+    ppi17: (Ppi17, 17),
+    ppi18: (Ppi18, 18),
+    ppi19: (Ppi19, 19),
+;
+    // Pre-programmed channels
+    ppi20: (Ppi20, 20),
+    ppi21: (Ppi21, 21),
+    ppi22: (Ppi22, 22),
+    ppi23: (Ppi23, 23),
+    ppi24: (Ppi24, 24),
+    ppi25: (Ppi25, 25),
+    ppi26: (Ppi26, 26),
+    ppi27: (Ppi27, 27),
+    ppi28: (Ppi28, 28),
+    ppi29: (Ppi29, 29),
+    ppi30: (Ppi30, 30),
+    ppi31: (Ppi31, 31),
+);
+
+macro_rules! impl_event {
+    ($($name:path,)*) => {
+        $(
+            impl sealed::Event for $name {}
+            impl Event for $name {}
+        )*
+    }
+}
+
+macro_rules! impl_task {
+    ($($name:path,)*) => {
+        $(
+            impl sealed::Task for $name {}
+            impl Task for $name {}
+        )*
+    }
+}
+
+impl_event!(
+    radio::EVENTS_READY,
+    radio::EVENTS_ADDRESS,
+    radio::EVENTS_PAYLOAD,
+    radio::EVENTS_END,
+    radio::EVENTS_DISABLED,
+    radio::EVENTS_DEVMATCH,
+    radio::EVENTS_DEVMISS,
+    radio::EVENTS_RSSIEND,
+    radio::EVENTS_BCMATCH,
+    radio::EVENTS_CRCOK,
+    radio::EVENTS_CRCERROR,
+    rtc0::EVENTS_TICK,
+    rtc0::EVENTS_OVRFLW,
+    rtc0::EVENTS_COMPARE,
+    timer0::EVENTS_COMPARE,
+    comp::EVENTS_READY,
+    comp::EVENTS_DOWN,
+    comp::EVENTS_UP,
+    comp::EVENTS_CROSS,
+);
+
+#[cfg(lpcomp)]
+impl_event!(
+    lpcomp::EVENTS_READY,
+    lpcomp::EVENTS_DOWN,
+    lpcomp::EVENTS_UP,
+    lpcomp::EVENTS_CROSS,
+);
+
+impl_task!(
+    radio::TASKS_TXEN,
+    radio::TASKS_RXEN,
+    radio::TASKS_START,
+    radio::TASKS_STOP,
+    radio::TASKS_DISABLE,
+    radio::TASKS_RSSISTART,
+    radio::TASKS_RSSISTOP,
+    radio::TASKS_BCSTART,
+    radio::TASKS_BCSTOP,
+    rtc0::TASKS_START,
+    rtc0::TASKS_STOP,
+    rtc0::TASKS_CLEAR,
+    rtc0::TASKS_TRIGOVRFLW,
+    timer0::TASKS_START,
+    timer0::TASKS_STOP,
+    timer0::TASKS_COUNT,
+    timer0::TASKS_CLEAR,
+    timer0::TASKS_CAPTURE,
+    saadc::TASKS_START,
+    saadc::TASKS_SAMPLE,
+    saadc::TASKS_STOP,
+);
@@ -0,0 +1,180 @@
+//! HAL interface to the Quadrature Decoder (QDEC) peripheral
+//!
+//! The QDEC samples the two phase outputs of a quadrature encoder, e.g. a
+//! rotary knob or the wheel of a mouse, and accumulates the movement in a
+//! signed counter.
+//!
+//! The hardware accumulator only holds a few hundred counts before it
+//! overflows and discards samples. `Position` avoids this by draining the
+//! accumulator from the QDEC interrupt into a 32 bit software position.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 36
+//! - nrf52840: Section 6.17
+use core::sync::atomic::{AtomicI32, Ordering};
+
+pub use crate::target::qdec::{
+    reportper::REPORTPERW as ReportPeriod, sampleper::SAMPLEPERW as SamplePeriod,
+};
+
+use crate::gpio::{Floating, Input, Output, Pin, PushPull};
+use crate::target::QDEC;
+
+/// Interface to the QDEC peripheral
+pub struct Qdec {
+    periph: QDEC,
+}
+
+impl Qdec {
+    /// Take ownership of the QDEC peripheral, and start sampling
+    ///
+    /// The inputs are debounced, and the LED, if any, is on while sampling.
+    pub fn new(qdec: QDEC, pins: Pins, sample_period: SamplePeriod) -> Self {
+        qdec.psel.a.write(|w| {
+            let w = unsafe { w.pin().bits(pins.a.pin) };
+            #[cfg(feature = "52840")]
+            let w = w.port().bit(pins.a.port);
+            w.connect().connected()
+        });
+        qdec.psel.b.write(|w| {
+            let w = unsafe { w.pin().bits(pins.b.pin) };
+            #[cfg(feature = "52840")]
+            let w = w.port().bit(pins.b.port);
+            w.connect().connected()
+        });
+        match &pins.led {
+            Some(led) => qdec.psel.led.write(|w| {
+                let w = unsafe { w.pin().bits(led.pin) };
+                #[cfg(feature = "52840")]
+                let w = w.port().bit(led.port);
+                w.connect().connected()
+            }),
+            None => qdec.psel.led.write(|w| w.connect().disconnected()),
+        }
+
+        qdec.sampleper
+            .write(|w| w.sampleper().variant(sample_period));
+        qdec.dbfen.write(|w| w.dbfen().enabled());
+        qdec.enable.write(|w| w.enable().enabled());
+        qdec.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        Qdec { periph: qdec }
+    }
+
+    /// Return the movement since the last call, and clear it
+    pub fn read(&mut self) -> i32 {
+        self.periph.tasks_readclracc.write(|w| unsafe { w.bits(1) });
+        self.periph.accread.read().bits() as i32
+    }
+
+    /// Report the movement through the QDEC interrupt
+    ///
+    /// Every `report_period` samples in which the encoder moved, and whenever
+    /// the accumulator overflows, `on_interrupt` adds the movement to
+    /// `position`. The QDEC interrupt must be unmasked in the NVIC
+    /// separately.
+    pub fn enable_interrupt(&mut self, report_period: ReportPeriod) {
+        self.periph
+            .reportper
+            .write(|w| w.reportper().variant(report_period));
+        self.periph.events_reportrdy.reset();
+        self.periph.events_accof.reset();
+        self.periph
+            .intenset
+            .write(|w| w.reportrdy().set().accof().set());
+    }
+
+    /// Stop reporting the movement through the QDEC interrupt
+    pub fn disable_interrupt(&mut self) {
+        self.periph
+            .intenclr
+            .write(|w| w.reportrdy().clear().accof().clear());
+    }
+
+    /// Add the reported movement to `position`
+    ///
+    /// To be called from the QDEC interrupt handler.
+    pub fn on_interrupt(&mut self, position: &Position) {
+        let report = self.periph.events_reportrdy.read().bits() != 0;
+        let overflow = self.periph.events_accof.read().bits() != 0;
+        if !report && !overflow {
+            return;
+        }
+        self.periph.events_reportrdy.reset();
+        self.periph.events_accof.reset();
+
+        // The accumulator is read here instead of with the REPORTRDY to
+        // READCLRACC shortcut, so an overflow right after a report can't
+        // replace the reported value before it has been read
+        position.add(self.read());
+    }
+
+    /// Stop sampling, and return the underlying QDEC peripheral
+    pub fn free(self) -> QDEC {
+        self.periph
+            .intenclr
+            .write(|w| w.reportrdy().clear().accof().clear());
+        self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+        while self.periph.events_stopped.read().bits() == 0 {}
+        self.periph.events_stopped.reset();
+        self.periph.enable.write(|w| w.enable().disabled());
+        self.periph
+    }
+}
+
+/// Position of a quadrature encoder, maintained by the QDEC interrupt
+///
+/// The position is meant to be shared between the QDEC interrupt handler,
+/// which calls `Qdec::on_interrupt`, and the main loop, e.g. as a `static`.
+/// All methods can be called from either.
+pub struct Position {
+    position: AtomicI32,
+    taken: AtomicI32,
+}
+
+impl Position {
+    pub const fn new() -> Self {
+        Position {
+            position: AtomicI32::new(0),
+            taken: AtomicI32::new(0),
+        }
+    }
+
+    /// Return the position, which wraps around on overflow
+    pub fn get(&self) -> i32 {
+        self.position.load(Ordering::SeqCst)
+    }
+
+    /// Return the movement since the last call
+    pub fn take_delta(&self) -> i32 {
+        let position = self.get();
+        position.wrapping_sub(self.taken.swap(position, Ordering::SeqCst))
+    }
+
+    fn add(&self, delta: i32) {
+        // The position is only ever written by the interrupt handler, so a
+        // separate load and store can't lose an update
+        let position = self.position.load(Ordering::SeqCst);
+        self.position
+            .store(position.wrapping_add(delta), Ordering::SeqCst);
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// GPIO pins for the QDEC
+pub struct Pins {
+    /// Phase A input
+    pub a: Pin<Input<Floating>>,
+
+    /// Phase B input
+    pub b: Pin<Input<Floating>>,
+
+    /// Output for the LED illuminating an optical encoder, None if unused
+    pub led: Option<Pin<Output<PushPull>>>,
+}
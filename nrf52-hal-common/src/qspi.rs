@@ -0,0 +1,990 @@
+//! HAL interface to the Quad Serial Peripheral Interface (QSPI) peripheral
+//!
+//! The QSPI reads, writes and erases external flash memory over one, two or
+//! four data lines, using EasyDMA. Commands that the peripheral doesn't issue
+//! on its own, e.g. reading the JEDEC ID or setting the quad enable bit of the
+//! flash, are sent with `Qspi::custom_instruction`.
+//!
+//! The flash is also mapped into the address space at `XIP_START`, so code
+//! can be executed from it in place, see `Qspi::enable_xip`.
+//!
+//! See product specification:
+//!
+//! - nrf52840: Section 6.18
+#[cfg(feature = "async")]
+use core::future::poll_fn;
+use core::slice;
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+#[cfg(feature = "async")]
+use core::task::Poll;
+
+use cortex_m::asm;
+#[cfg(feature = "async")]
+use cortex_m::interrupt::Nr;
+#[cfg(feature = "embedded-storage-async")]
+use embedded_storage::nor_flash::{ErrorType, NorFlashError, NorFlashErrorKind};
+#[cfg(feature = "embedded-storage-async")]
+use embedded_storage_async::nor_flash::{NorFlash, ReadNorFlash};
+
+pub use crate::target::qspi::erase::len::LENW as EraseSize;
+
+use crate::gpio::{Output, Pin, PushPull};
+use crate::slice_in_ram;
+#[cfg(feature = "async")]
+use crate::target::Interrupt;
+use crate::target::{NVMC, QSPI};
+#[cfg(feature = "async")]
+use crate::waker;
+
+/// Largest number of bytes in a read or write
+pub const MAX_TRANSFER: usize = 0x3_FFFC;
+
+/// Largest number of data bytes in a custom instruction
+pub const MAX_INSTRUCTION_DATA: usize = 8;
+
+/// Start and size of the region that the flash is mapped to
+pub const XIP_START: usize = 0x1200_0000;
+pub const XIP_SIZE: usize = 0x0800_0000;
+
+/// Largest delay of the sampling of the data lines
+pub const MAX_RX_DELAY: u8 = 7;
+
+/// Clock that SCK is divided from
+const SCK_SOURCE: u32 = 32_000_000;
+
+/// Common flash commands
+const READ_STATUS: u8 = 0x05;
+const WRITE_ENABLE: u8 = 0x06;
+const READ_JEDEC_ID: u8 = 0x9F;
+
+/// Write In Progress bit of the flash status register
+const STATUS_WIP: u8 = 0x01;
+
+/// Interface to the QSPI peripheral
+pub struct Qspi {
+    periph: QSPI,
+}
+
+impl Qspi {
+    /// Take ownership of the QSPI peripheral, and activate the interface
+    ///
+    /// The interface starts out with the default `Config`, which should work
+    /// with any flash, and can be changed with `configure`.
+    pub fn new(qspi: QSPI, pins: Pins) -> Self {
+        qspi.psel.sck.write(|w| {
+            let w = unsafe { w.pin().bits(pins.sck.pin) };
+            let w = w.port().bit(pins.sck.port);
+            w.connect().connected()
+        });
+        qspi.psel.csn.write(|w| {
+            let w = unsafe { w.pin().bits(pins.csn.pin) };
+            let w = w.port().bit(pins.csn.port);
+            w.connect().connected()
+        });
+        qspi.psel.io0.write(|w| {
+            let w = unsafe { w.pin().bits(pins.io0.pin) };
+            let w = w.port().bit(pins.io0.port);
+            w.connect().connected()
+        });
+        qspi.psel.io1.write(|w| {
+            let w = unsafe { w.pin().bits(pins.io1.pin) };
+            let w = w.port().bit(pins.io1.port);
+            w.connect().connected()
+        });
+        match &pins.io2 {
+            Some(io2) => qspi.psel.io2.write(|w| {
+                let w = unsafe { w.pin().bits(io2.pin) };
+                let w = w.port().bit(io2.port);
+                w.connect().connected()
+            }),
+            None => qspi.psel.io2.write(|w| w.connect().disconnected()),
+        }
+        match &pins.io3 {
+            Some(io3) => qspi.psel.io3.write(|w| {
+                let w = unsafe { w.pin().bits(io3.pin) };
+                let w = w.port().bit(io3.port);
+                w.connect().connected()
+            }),
+            None => qspi.psel.io3.write(|w| w.connect().disconnected()),
+        }
+
+        qspi.ifconfig0.write(|w| w.dpmenable().disable());
+        qspi.ifconfig1.write(|w| w.dpmen().exit());
+        qspi.enable.write(|w| w.enable().enabled());
+
+        let mut qspi = Qspi { periph: qspi };
+        qspi.apply(&Config::default());
+        qspi
+    }
+
+    /// Change the configuration of the interface, see `Config`
+    ///
+    /// The configuration is validated, and left unchanged if it is invalid.
+    pub fn configure(&mut self, config: &Config) -> Result<(), Error> {
+        config.validate()?;
+        if config.uses_quad_lines()
+            && (self.periph.psel.io2.read().connect().is_disconnected()
+                || self.periph.psel.io3.read().connect().is_disconnected())
+        {
+            return Err(Error::QuadPinsNotConnected);
+        }
+        self.wake();
+
+        self.periph.events_ready.reset();
+        self.periph.tasks_deactivate.write(|w| unsafe { w.bits(1) });
+        self.wait_ready();
+        self.apply(config);
+        Ok(())
+    }
+
+    /// Read `buffer.len()` bytes from the flash, starting at `address`
+    ///
+    /// The address and the length of the buffer must be multiples of 4, and
+    /// the buffer must be word aligned and in RAM.
+    pub fn read(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.start_read(address, buffer)?;
+        self.wait_ready();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        Ok(())
+    }
+
+    /// Write `data` to the flash, starting at `address`, and wait until the
+    /// flash has finished programming it
+    ///
+    /// The address and the length of the data must be multiples of 4, and the
+    /// data must be word aligned and in RAM. The written region must have
+    /// been erased.
+    pub fn write(&mut self, address: u32, data: &[u8]) -> Result<(), Error> {
+        self.start_write(address, data)?;
+        self.wait_ready();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        self.wait_while_busy()
+    }
+
+    /// Erase the block of `size` starting at `address`, or the whole flash,
+    /// and wait until the flash has finished erasing it
+    ///
+    /// The address must be aligned to the size of the block, and is ignored
+    /// when erasing the whole flash.
+    pub fn erase(&mut self, address: u32, size: EraseSize) -> Result<(), Error> {
+        self.start_erase(address, size)?;
+        self.wait_ready();
+
+        self.wait_while_busy()
+    }
+
+    /// Read `buffer.len()` bytes from the flash, starting at `address`,
+    /// without blocking
+    ///
+    /// This works like `read`, but the future waits for the end of the
+    /// transfer through the interrupt of the QSPI, whose handler must call
+    /// `Qspi::handle_interrupt`. The interrupt must be unmasked in the NVIC.
+    /// If the future is dropped before, it blocks until the transfer has
+    /// ended, so EasyDMA doesn't outlive the buffer.
+    #[cfg(feature = "async")]
+    pub async fn read_async(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), Error> {
+        self.start_read(address, buffer)?;
+        self.ready_async().await;
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        Ok(())
+    }
+
+    /// Write `data` to the flash, starting at `address`, and wait until the
+    /// flash has finished programming it, without blocking
+    ///
+    /// See `write` and `read_async`. The status of the flash is polled until
+    /// it is no longer busy, yielding to the executor in between.
+    #[cfg(feature = "async")]
+    pub async fn write_async(&mut self, address: u32, data: &[u8]) -> Result<(), Error> {
+        self.start_write(address, data)?;
+        self.ready_async().await;
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        self.wait_while_busy_async().await
+    }
+
+    /// Erase the block of `size` starting at `address`, or the whole flash,
+    /// and wait until the flash has finished erasing it, without blocking
+    ///
+    /// See `erase` and `write_async`.
+    #[cfg(feature = "async")]
+    pub async fn erase_async(&mut self, address: u32, size: EraseSize) -> Result<(), Error> {
+        self.start_erase(address, size)?;
+        self.ready_async().await;
+
+        self.wait_while_busy_async().await
+    }
+
+    /// Wait until the flash has finished writing or erasing, without blocking
+    #[cfg(feature = "async")]
+    pub async fn wait_while_busy_async(&mut self) -> Result<(), Error> {
+        // Reading the status only takes a few microseconds
+        while self.read_status()? & STATUS_WIP != 0 {
+            waker::yield_now().await;
+        }
+        Ok(())
+    }
+
+    /// Wake the future waiting for the QSPI, to be called from the interrupt
+    /// handler of the QSPI
+    ///
+    /// The READY event is left set, for the future to see it.
+    #[cfg(feature = "async")]
+    pub fn handle_interrupt() {
+        // This is safe, as only the READY interrupt enable is written to,
+        // which is owned by the futures
+        let qspi = unsafe { &*QSPI::ptr() };
+        if qspi.events_ready.read().bits() != 0 {
+            qspi.intenclr.write(|w| w.ready().clear());
+            waker::wake(Interrupt::QSPI);
+        }
+    }
+
+    /// Send the command `opcode`, followed by the bytes of `data`, while
+    /// receiving the bytes of `response`
+    ///
+    /// As on a SPI bus, the bytes are sent and received at the same time, so
+    /// the instruction lasts for the longer of `data` and `response`, and
+    /// zeros are sent after the end of `data`. Each of them can hold up to
+    /// `MAX_INSTRUCTION_DATA` bytes. IO2 and IO3 are held high throughout,
+    /// which deasserts WP and HOLD on flash that has these pins.
+    ///
+    /// Some examples of commands, which may differ between flash parts:
+    ///
+    /// - `custom_instruction(0x06, &[], &mut [])` sets the write enable latch
+    /// - `custom_instruction(0x31, &[0x02], &mut [])` writes the second status
+    ///   register, which holds the quad enable bit on many parts
+    /// - `custom_instruction(0x35, &[], &mut status)` reads the second status
+    ///   register
+    pub fn custom_instruction(
+        &mut self,
+        opcode: u8,
+        data: &[u8],
+        response: &mut [u8],
+    ) -> Result<(), Error> {
+        if data.len() > MAX_INSTRUCTION_DATA || response.len() > MAX_INSTRUCTION_DATA {
+            return Err(Error::InstructionTooLong);
+        }
+        self.wake();
+
+        let mut bytes = [0; MAX_INSTRUCTION_DATA];
+        bytes[..data.len()].copy_from_slice(data);
+        self.periph.cinstrdat0.write(|w| unsafe {
+            w.byte0()
+                .bits(bytes[0])
+                .byte1()
+                .bits(bytes[1])
+                .byte2()
+                .bits(bytes[2])
+                .byte3()
+                .bits(bytes[3])
+        });
+        self.periph.cinstrdat1.write(|w| unsafe {
+            w.byte4()
+                .bits(bytes[4])
+                .byte5()
+                .bits(bytes[5])
+                .byte6()
+                .bits(bytes[6])
+                .byte7()
+                .bits(bytes[7])
+        });
+
+        // The length includes the opcode, and writing the configuration
+        // starts the instruction
+        let length = 1 + data.len().max(response.len());
+        self.periph.events_ready.reset();
+        self.periph.cinstrconf.write(|w| unsafe {
+            w.opcode()
+                .bits(opcode)
+                .length()
+                .bits(length as u8)
+                .lio2()
+                .set_bit()
+                .lio3()
+                .set_bit()
+        });
+        self.wait_ready();
+
+        let low = self.periph.cinstrdat0.read().bits().to_le_bytes();
+        let high = self.periph.cinstrdat1.read().bits().to_le_bytes();
+        bytes[..4].copy_from_slice(&low);
+        bytes[4..].copy_from_slice(&high);
+        response.copy_from_slice(&bytes[..response.len()]);
+
+        Ok(())
+    }
+
+    /// Read the manufacturer ID, memory type and capacity of the flash
+    pub fn read_jedec_id(&mut self) -> Result<[u8; 3], Error> {
+        let mut id = [0; 3];
+        self.custom_instruction(READ_JEDEC_ID, &[], &mut id)?;
+        Ok(id)
+    }
+
+    /// Read the (first) status register of the flash
+    pub fn read_status(&mut self) -> Result<u8, Error> {
+        let mut status = [0];
+        self.custom_instruction(READ_STATUS, &[], &mut status)?;
+        Ok(status[0])
+    }
+
+    /// Set the write enable latch of the flash
+    ///
+    /// This is needed before writing a status register. Writes and erases
+    /// set the latch on their own.
+    pub fn write_enable(&mut self) -> Result<(), Error> {
+        self.custom_instruction(WRITE_ENABLE, &[], &mut [])
+    }
+
+    /// Block until the flash has finished writing or erasing
+    pub fn wait_while_busy(&mut self) -> Result<(), Error> {
+        while self.read_status()? & STATUS_WIP != 0 {}
+        Ok(())
+    }
+
+    /// Let the flash enter deep power-down with `sleep`
+    ///
+    /// `enter` is the time the flash takes to enter deep power-down (tDP),
+    /// and `exit` the time it takes to become ready again (tRES1), both in
+    /// units of 256 ns.
+    pub fn enable_deep_power_down(&mut self, enter: u16, exit: u16) {
+        self.periph
+            .dpmdur
+            .write(|w| unsafe { w.enter().bits(enter).exit().bits(exit) });
+        self.periph.ifconfig0.modify(|_, w| w.dpmenable().enable());
+    }
+
+    /// Put the flash into deep power-down, to cut its standby current
+    ///
+    /// Reads, writes, erases, custom instructions and `enable_xip` wake the
+    /// flash up again on their own.
+    pub fn sleep(&mut self) -> Result<(), Error> {
+        if self.periph.ifconfig0.read().dpmenable().is_disable() {
+            return Err(Error::DeepPowerDownDisabled);
+        }
+        if self.is_sleeping() {
+            return Ok(());
+        }
+
+        self.periph.ifconfig1.modify(|_, w| w.dpmen().enter());
+        while self.periph.status.read().dpm().is_disabled() {}
+        Ok(())
+    }
+
+    /// Bring the flash out of deep power-down, if it is in it
+    pub fn wake(&mut self) {
+        if !self.is_sleeping() {
+            return;
+        }
+
+        self.periph.ifconfig1.modify(|_, w| w.dpmen().exit());
+        while self.periph.status.read().dpm().is_enabled() {}
+    }
+
+    /// Return true if the flash is in deep power-down
+    pub fn is_sleeping(&self) -> bool {
+        self.periph.status.read().dpm().is_enabled()
+    }
+
+    /// Execute code from the flash in place
+    ///
+    /// The flash, starting at `offset`, is mapped to `XIP_START`, and read
+    /// with the read opcode of the interface on every access. As long as the
+    /// returned `Xip` exists, the flash can't be written or erased, so the
+    /// mapped code can't change under the CPU.
+    ///
+    /// The instruction cache also caches the mapped region. It is invalidated
+    /// here, so code written since the last time XIP was enabled isn't
+    /// shadowed by stale cache lines.
+    pub fn enable_xip(&mut self, offset: u32) -> Xip<'_> {
+        self.wake();
+        self.periph.xipoffset.write(|w| unsafe { w.bits(offset) });
+
+        // Disabling the cache invalidates its content
+        let nvmc = unsafe { &*NVMC::ptr() };
+        if nvmc.icachecnf.read().cacheen().is_enabled() {
+            nvmc.icachecnf.modify(|_, w| w.cacheen().disabled());
+            nvmc.icachecnf.modify(|_, w| w.cacheen().enabled());
+        }
+
+        // Make sure the new mapping is in place before any instruction is
+        // fetched from it
+        asm::dsb();
+        asm::isb();
+
+        Xip { _qspi: self }
+    }
+
+    /// Deactivate the interface, and return the underlying QSPI peripheral
+    pub fn free(self) -> QSPI {
+        self.periph.events_ready.reset();
+        self.periph.tasks_deactivate.write(|w| unsafe { w.bits(1) });
+        while self.periph.events_ready.read().bits() == 0 {}
+        self.periph.events_ready.reset();
+
+        // nRF52840 anomaly 122: the QSPI keeps drawing current after being
+        // disabled, unless these undocumented registers are written first
+        unsafe {
+            core::ptr::write_volatile(0x4002_9010 as *mut u32, 1);
+            core::ptr::write_volatile(0x4002_9054 as *mut u32, 1);
+        }
+        self.periph.enable.write(|w| w.enable().disabled());
+        self.periph
+    }
+
+    fn apply(&mut self, config: &Config) {
+        self.periph.ifconfig0.modify(|_, w| {
+            let w = match config.read_opcode {
+                ReadOpcode::FastRead => w.readoc().fastread(),
+                ReadOpcode::Read2O => w.readoc().read2o(),
+                ReadOpcode::Read2IO => w.readoc().read2io(),
+                ReadOpcode::Read4O => w.readoc().read4o(),
+                ReadOpcode::Read4IO => w.readoc().read4io(),
+            };
+            let w = match config.write_opcode {
+                WriteOpcode::PP => w.writeoc().pp(),
+                WriteOpcode::PP2O => w.writeoc().pp2o(),
+                WriteOpcode::PP4O => w.writeoc().pp4o(),
+                WriteOpcode::PP4IO => w.writeoc().pp4io(),
+            };
+            let w = match config.address_mode {
+                AddressMode::_24Bit => w.addrmode()._24bit(),
+                AddressMode::_32Bit => w.addrmode()._32bit(),
+            };
+            match config.page_size {
+                PageSize::_256Bytes => w.ppsize()._256bytes(),
+                PageSize::_512Bytes => w.ppsize()._512bytes(),
+            }
+        });
+        // SCK = 32 MHz / (SCKFREQ + 1)
+        let sckfreq = (SCK_SOURCE / config.sck_frequency - 1) as u8;
+        self.periph.ifconfig1.modify(|_, w| {
+            let w = unsafe { w.sckdelay().bits(config.sck_delay).sckfreq().bits(sckfreq) };
+            match config.spi_mode {
+                SpiMode::Mode0 => w.spimode().mode0(),
+                SpiMode::Mode3 => w.spimode().mode3(),
+            }
+        });
+        self.periph
+            .iftiming
+            .write(|w| unsafe { w.rxdelay().bits(config.rx_delay) });
+
+        self.periph.events_ready.reset();
+        self.periph.tasks_activate.write(|w| unsafe { w.bits(1) });
+        self.wait_ready();
+    }
+
+    /// Start reading from the flash, see `read`
+    fn start_read(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), Error> {
+        check_transfer(address, buffer)?;
+        self.wake();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+
+        self.periph
+            .read
+            .dst
+            .write(|w| unsafe { w.bits(buffer.as_mut_ptr() as u32) });
+        self.periph.read.src.write(|w| unsafe { w.bits(address) });
+        self.periph
+            .read
+            .cnt
+            .write(|w| unsafe { w.bits(buffer.len() as u32) });
+        self.periph.events_ready.reset();
+        self.periph.tasks_readstart.write(|w| unsafe { w.bits(1) });
+
+        Ok(())
+    }
+
+    /// Start writing to the flash, see `write`
+    fn start_write(&mut self, address: u32, data: &[u8]) -> Result<(), Error> {
+        check_transfer(address, data)?;
+        self.wake();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+
+        self.periph
+            .write
+            .src
+            .write(|w| unsafe { w.bits(data.as_ptr() as u32) });
+        self.periph.write.dst.write(|w| unsafe { w.bits(address) });
+        self.periph
+            .write
+            .cnt
+            .write(|w| unsafe { w.bits(data.len() as u32) });
+        self.periph.events_ready.reset();
+        self.periph.tasks_writestart.write(|w| unsafe { w.bits(1) });
+
+        Ok(())
+    }
+
+    /// Start erasing the flash, see `erase`
+    fn start_erase(&mut self, address: u32, size: EraseSize) -> Result<(), Error> {
+        let mask = match &size {
+            EraseSize::_4KB => 0xFFF,
+            EraseSize::_64KB => 0xFFFF,
+            EraseSize::ALL => 0,
+        };
+        if address & mask != 0 {
+            return Err(Error::Unaligned);
+        }
+        self.wake();
+
+        self.periph.erase.ptr.write(|w| unsafe { w.bits(address) });
+        self.periph.erase.len.write(|w| w.len().variant(size));
+        self.periph.events_ready.reset();
+        self.periph.tasks_erasestart.write(|w| unsafe { w.bits(1) });
+
+        Ok(())
+    }
+
+    fn wait_ready(&mut self) {
+        while self.periph.events_ready.read().bits() == 0 {}
+        self.periph.events_ready.reset();
+    }
+
+    /// Wait for the READY event through the interrupt of the QSPI
+    ///
+    /// If the future is dropped before, it blocks until the event.
+    #[cfg(feature = "async")]
+    async fn ready_async(&mut self) {
+        struct WaitOnDrop<'a>(Option<&'a mut Qspi>);
+
+        impl<'a> Drop for WaitOnDrop<'a> {
+            fn drop(&mut self) {
+                if let Some(qspi) = self.0.take() {
+                    qspi.periph.intenclr.write(|w| w.ready().clear());
+                    qspi.wait_ready();
+                }
+            }
+        }
+
+        let nr = Interrupt::QSPI.nr();
+        let mut guard = WaitOnDrop(Some(self));
+
+        poll_fn(|cx| {
+            let qspi = guard.0.as_mut().unwrap();
+
+            // Register before checking, so the event can't be missed in
+            // between
+            waker::register_nr(nr, cx.waker());
+            qspi.periph.intenset.write(|w| w.ready().set());
+
+            if qspi.periph.events_ready.read().bits() != 0 {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        let qspi = guard.0.take().unwrap();
+        qspi.periph.intenclr.write(|w| w.ready().clear());
+        qspi.periph.events_ready.reset();
+    }
+}
+
+/// Flash mapped into the address space, see `Qspi::enable_xip`
+pub struct Xip<'a> {
+    _qspi: &'a mut Qspi,
+}
+
+impl<'a> Xip<'a> {
+    /// Return the address of the mapped flash
+    ///
+    /// A function at `offset` into the mapped flash is called through a
+    /// pointer to `XIP_START + offset`, with the thumb bit set.
+    pub fn as_ptr(&self) -> *const u8 {
+        XIP_START as *const u8
+    }
+
+    /// Return the first `len` bytes of the mapped flash
+    ///
+    /// Panics if `len` is larger than `XIP_SIZE`.
+    pub fn as_slice(&self, len: usize) -> &[u8] {
+        assert!(len <= XIP_SIZE);
+        unsafe { slice::from_raw_parts(self.as_ptr(), len) }
+    }
+}
+
+impl<'a> Drop for Xip<'a> {
+    fn drop(&mut self) {
+        // Complete all accesses to the mapped flash before it can be written
+        asm::dsb();
+        asm::isb();
+    }
+}
+
+fn check_transfer(address: u32, buffer: &[u8]) -> Result<(), Error> {
+    if buffer.is_empty() {
+        return Err(Error::BufferEmpty);
+    }
+    if buffer.len() > MAX_TRANSFER {
+        return Err(Error::BufferTooLong);
+    }
+    if address % 4 != 0 || buffer.len() % 4 != 0 || buffer.as_ptr() as usize % 4 != 0 {
+        return Err(Error::Unaligned);
+    }
+    if !slice_in_ram(buffer) {
+        return Err(Error::DMABufferNotInDataMemory);
+    }
+    Ok(())
+}
+
+/// Configuration of the interface
+///
+/// Use the builder methods to change the defaults, then apply the
+/// configuration using `Qspi::configure`, which validates it.
+///
+/// The default configuration matches the configuration the interface is set
+/// up with by `Qspi::new`: FASTREAD and PP over a single data line, 24 bit
+/// addresses, 256 byte pages and SPI mode 0, at 8 MHz.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    read_opcode: ReadOpcode,
+    write_opcode: WriteOpcode,
+    address_mode: AddressMode,
+    page_size: PageSize,
+    spi_mode: SpiMode,
+    sck_frequency: u32,
+    sck_delay: u8,
+    rx_delay: u8,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read the flash with `opcode`
+    ///
+    /// Opcodes using four data lines need IO2 and IO3, and the quad enable
+    /// bit of the flash to be set, see `Qspi::custom_instruction`.
+    pub fn read_opcode(mut self, opcode: ReadOpcode) -> Self {
+        self.read_opcode = opcode;
+        self
+    }
+
+    /// Write the flash with `opcode`
+    ///
+    /// Opcodes using four data lines need IO2 and IO3, and the quad enable
+    /// bit of the flash to be set, see `Qspi::custom_instruction`.
+    pub fn write_opcode(mut self, opcode: WriteOpcode) -> Self {
+        self.write_opcode = opcode;
+        self
+    }
+
+    /// Send addresses with 24 or 32 bits
+    ///
+    /// Flash larger than 16 MB must be switched to 32 bit addresses as well,
+    /// usually with the command 0xB7.
+    pub fn address_mode(mut self, mode: AddressMode) -> Self {
+        self.address_mode = mode;
+        self
+    }
+
+    /// Set the size of the pages the flash programs at once
+    pub fn page_size(mut self, size: PageSize) -> Self {
+        self.page_size = size;
+        self
+    }
+
+    /// Set the polarity and phase of the clock
+    pub fn spi_mode(mut self, mode: SpiMode) -> Self {
+        self.spi_mode = mode;
+        self
+    }
+
+    /// Set the frequency of SCK in Hz
+    ///
+    /// The frequency must be 32 MHz divided by 1 to 16, e.g. 32 MHz, 16 MHz
+    /// or 8 MHz. At 32 MHz, the data may need to be sampled later with
+    /// `rx_delay`.
+    pub fn sck_frequency(mut self, hz: u32) -> Self {
+        self.sck_frequency = hz;
+        self
+    }
+
+    /// Set the minimum time CSN is held high between commands, in units of
+    /// 62.5 ns
+    pub fn sck_delay(mut self, delay: u8) -> Self {
+        self.sck_delay = delay;
+        self
+    }
+
+    /// Delay the sampling of the data lines by `delay` cycles of the 64 MHz
+    /// clock, at most `MAX_RX_DELAY`
+    pub fn rx_delay(mut self, delay: u8) -> Self {
+        self.rx_delay = delay;
+        self
+    }
+
+    fn uses_quad_lines(&self) -> bool {
+        match (self.read_opcode, self.write_opcode) {
+            (ReadOpcode::Read4O, _) | (ReadOpcode::Read4IO, _) => true,
+            (_, WriteOpcode::PP4O) | (_, WriteOpcode::PP4IO) => true,
+            _ => false,
+        }
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        let divider = match self.sck_frequency {
+            0 => 0,
+            hz => SCK_SOURCE / hz,
+        };
+        if divider < 1 || divider > 16 || divider * self.sck_frequency != SCK_SOURCE {
+            return Err(Error::UnsupportedFrequency);
+        }
+        if self.rx_delay > MAX_RX_DELAY {
+            return Err(Error::RxDelayOutOfRange);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            read_opcode: ReadOpcode::FastRead,
+            write_opcode: WriteOpcode::PP,
+            address_mode: AddressMode::_24Bit,
+            page_size: PageSize::_256Bytes,
+            spi_mode: SpiMode::Mode0,
+            sck_frequency: 8_000_000,
+            sck_delay: 1,
+            rx_delay: 2,
+        }
+    }
+}
+
+/// Opcode used to read the flash
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReadOpcode {
+    /// 0x0B, on a single data line
+    FastRead,
+    /// 0x3B, with dual data output
+    Read2O,
+    /// 0xBB, with dual data and address
+    Read2IO,
+    /// 0x6B, with quad data output
+    Read4O,
+    /// 0xEB, with quad data and address
+    Read4IO,
+}
+
+/// Opcode used to write the flash
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WriteOpcode {
+    /// 0x02, on a single data line
+    PP,
+    /// 0xA2, with dual data
+    PP2O,
+    /// 0x32, with quad data
+    PP4O,
+    /// 0x38, with quad data and address
+    PP4IO,
+}
+
+/// Width of the addresses sent to the flash
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressMode {
+    _24Bit,
+    _32Bit,
+}
+
+/// Size of the pages of the flash
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PageSize {
+    _256Bytes,
+    _512Bytes,
+}
+
+/// Polarity and phase of the clock
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SpiMode {
+    /// Clock idles low, data is sampled on the rising edge
+    Mode0,
+    /// Clock idles high, data is sampled on the rising edge
+    Mode3,
+}
+
+/// GPIO pins for the QSPI
+pub struct Pins {
+    /// Clock output
+    pub sck: Pin<Output<PushPull>>,
+
+    /// Chip select output, active low
+    pub csn: Pin<Output<PushPull>>,
+
+    /// Data line 0, the data output in single line operation
+    pub io0: Pin<Output<PushPull>>,
+
+    /// Data line 1, the data input in single line operation
+    pub io1: Pin<Output<PushPull>>,
+
+    /// Data line 2, the WP input of the flash in single and dual line
+    /// operation, None if unused
+    pub io2: Option<Pin<Output<PushPull>>>,
+
+    /// Data line 3, the HOLD input of the flash in single and dual line
+    /// operation, None if unused
+    pub io3: Option<Pin<Output<PushPull>>>,
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    BufferEmpty,
+    BufferTooLong,
+    Unaligned,
+    InstructionTooLong,
+    DeepPowerDownDisabled,
+    UnsupportedFrequency,
+    RxDelayOutOfRange,
+    QuadPinsNotConnected,
+    DMABufferNotInDataMemory,
+    OutOfBounds,
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl NorFlashError for Error {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::Unaligned => NorFlashErrorKind::NotAligned,
+            Error::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+/// Number of bytes that `QspiFlash` reads or writes at once
+#[cfg(feature = "embedded-storage-async")]
+const BOUNCE_SIZE: usize = 256;
+
+/// External flash of `capacity` bytes behind the QSPI, for the async
+/// `NorFlash` traits of embedded-storage
+///
+/// The data is copied through a word aligned buffer on the stack, so the
+/// buffers passed to the traits can have any alignment, and be in flash.
+/// See `Qspi::read_async` for the interrupt handler.
+#[cfg(feature = "embedded-storage-async")]
+pub struct QspiFlash {
+    qspi: Qspi,
+    capacity: usize,
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl QspiFlash {
+    pub fn new(qspi: Qspi, capacity: usize) -> Self {
+        QspiFlash { qspi, capacity }
+    }
+
+    /// Return the interface to the QSPI
+    pub fn free(self) -> Qspi {
+        self.qspi
+    }
+
+    fn check_range(&self, offset: u32, len: usize) -> Result<(), Error> {
+        if offset % 4 != 0 || len % 4 != 0 {
+            return Err(Error::Unaligned);
+        }
+        if offset as usize + len > self.capacity {
+            return Err(Error::OutOfBounds);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl ErrorType for QspiFlash {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl ReadNorFlash for QspiFlash {
+    const READ_SIZE: usize = 4;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Error> {
+        self.check_range(offset, bytes.len())?;
+
+        let mut bounce = [0u32; BOUNCE_SIZE / 4];
+        for (i, chunk) in bytes.chunks_mut(BOUNCE_SIZE).enumerate() {
+            let buffer =
+                unsafe { slice::from_raw_parts_mut(bounce.as_mut_ptr() as *mut u8, chunk.len()) };
+            let address = offset + (i * BOUNCE_SIZE) as u32;
+            self.qspi.read_async(address, buffer).await?;
+            chunk.copy_from_slice(buffer);
+        }
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+#[cfg(feature = "embedded-storage-async")]
+impl NorFlash for QspiFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = 4096;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Error> {
+        if from as usize % Self::ERASE_SIZE != 0 || to as usize % Self::ERASE_SIZE != 0 {
+            return Err(Error::Unaligned);
+        }
+        if from > to || to as usize > self.capacity {
+            return Err(Error::OutOfBounds);
+        }
+
+        for address in (from..to).step_by(Self::ERASE_SIZE) {
+            self.qspi.erase_async(address, EraseSize::_4KB).await?;
+        }
+        Ok(())
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+        self.check_range(offset, bytes.len())?;
+
+        let mut bounce = [0u32; BOUNCE_SIZE / 4];
+        for (i, chunk) in bytes.chunks(BOUNCE_SIZE).enumerate() {
+            let buffer =
+                unsafe { slice::from_raw_parts_mut(bounce.as_mut_ptr() as *mut u8, chunk.len()) };
+            buffer.copy_from_slice(chunk);
+            let address = offset + (i * BOUNCE_SIZE) as u32;
+            self.qspi.write_async(address, buffer).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,1351 @@
+//! HAL interface to the RADIO peripheral
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 23
+//! - nrf52840: Section 6.20
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+use crate::target::RADIO;
+
+use crate::ccm::{self, Ccm, CcmData};
+use crate::ppi::{self, FixedChannel};
+use crate::slice_in_ram;
+use crate::prelude::*;
+use crate::rtc::{self, Rtc, RtcCompareReg, Started};
+use crate::timer::{self, Timer};
+
+// Re-export SVD variants to allow user to directly set values
+pub use crate::target::radio::{
+    mode::MODEW as Mode,
+    txpower::TXPOWERW as TxPower,
+    state::STATER as State,
+};
+
+/// Number of 1 MHz wide frequencies the radio can be tuned to
+///
+/// Frequency `n` corresponds to 2400 + `n` MHz.
+pub const FREQUENCY_COUNT: usize = 101;
+
+/// Default access address, as used on the BLE advertising channels
+pub const DEFAULT_ACCESS_ADDRESS: u32 = 0x8E89_BED6;
+
+/// Maximum length of a packet payload, not including the length byte
+pub const MAX_PAYLOAD_LEN: usize = 255;
+
+/// Maximum interframe spacing in µs
+#[cfg(feature = "52840")]
+pub const MAX_TIFS: u16 = 1023;
+/// Maximum interframe spacing in µs
+#[cfg(any(feature = "52810", feature = "52832"))]
+pub const MAX_TIFS: u16 = 255;
+
+/// Maximum number of header bytes that can be inspected by
+/// `Radio::receive_filtered`
+pub const MAX_HEADER_LEN: usize = 16;
+
+/// Capture register of the timestamp timer that the ADDRESS event is captured into
+pub const TIMESTAMP_ADDRESS_CC: usize = 1;
+
+/// Capture register of the timestamp timer that the END event is captured into
+pub const TIMESTAMP_END_CC: usize = 2;
+
+/// Timer values captured while a packet was sent or received
+#[derive(Clone, Copy, Debug)]
+pub struct Timestamp {
+    /// Timer value when the access address was sent or received
+    pub address: u32,
+    /// Timer value when the last bit of the packet was sent or received
+    pub end: u32,
+}
+
+/// Interface to the RADIO peripheral
+///
+/// Packets are handed to and from the radio as a single buffer, where the
+/// first byte is the length of the payload that follows it. The buffer must
+/// reside in data RAM, as it is accessed by EasyDMA.
+///
+/// The radio is configured with a BLE-like link configuration on creation: a
+/// single access address (see `DEFAULT_ACCESS_ADDRESS`), an 8 bit length
+/// field and a 24 bit CRC. Use `configure_link` to change it.
+pub struct Radio(RADIO);
+
+impl Radio {
+    pub fn new(radio: RADIO) -> Self {
+        // Power cycle the radio to bring all registers into a known state
+        radio.power.write(|w| w.power().disabled());
+        radio.power.write(|w| w.power().enabled());
+
+        radio.mode.write(|w| w.mode().ble_1mbit());
+        radio.txpower.write(|w| w.txpower()._0d_bm());
+        radio.frequency.write(|w| unsafe { w.frequency().bits(2) });
+
+        // 8 bit length field, no S0/S1 fields
+        radio
+            .pcnf0
+            .write(|w| unsafe { w.lflen().bits(8).s0len().bit(false).s1len().bits(0) });
+        radio.pcnf1.write(|w| unsafe {
+            w.maxlen()
+                .bits(MAX_PAYLOAD_LEN as u8)
+                .statlen()
+                .bits(0)
+                .balen()
+                .bits(3)
+                .endian()
+                .little()
+                .whiteen()
+                .disabled()
+        });
+
+        let mut radio = Radio(radio);
+
+        // The default configuration is always valid, so this can't fail
+        let _ = radio.configure_link(&LinkConfig::default());
+
+        radio
+    }
+
+    /// Apply a link configuration
+    ///
+    /// The configuration is validated before any register is written, so the
+    /// radio configuration is left untouched if an error is returned.
+    pub fn configure_link(&mut self, config: &LinkConfig) -> Result<(), Error> {
+        config.validate()?;
+
+        self.disable();
+
+        self.0
+            .base0
+            .write(|w| unsafe { w.bits(config.base0) });
+        self.0
+            .base1
+            .write(|w| unsafe { w.bits(config.base1) });
+        self.0.prefix0.write(|w| unsafe {
+            w.bits(u32::from_le_bytes([
+                config.prefixes[0],
+                config.prefixes[1],
+                config.prefixes[2],
+                config.prefixes[3],
+            ]))
+        });
+        self.0.prefix1.write(|w| unsafe {
+            w.bits(u32::from_le_bytes([
+                config.prefixes[4],
+                config.prefixes[5],
+                config.prefixes[6],
+                config.prefixes[7],
+            ]))
+        });
+        self.0
+            .pcnf1
+            .modify(|_, w| unsafe { w.balen().bits(config.base_len - 1) });
+        self.0
+            .txaddress
+            .write(|w| unsafe { w.txaddress().bits(config.tx_address) });
+        self.0
+            .rxaddresses
+            .write(|w| unsafe { w.bits(config.rx_addresses as u32) });
+
+        self.0.crccnf.write(|w| unsafe {
+            w.bits(config.crc_len as u32 | (config.crc_skip_address as u32) << 8)
+        });
+        self.0
+            .crcpoly
+            .write(|w| unsafe { w.crcpoly().bits(config.crc_poly) });
+        self.0
+            .crcinit
+            .write(|w| unsafe { w.crcinit().bits(config.crc_init) });
+
+        match config.whitening {
+            Some(iv) => {
+                self.0
+                    .datawhiteiv
+                    .write(|w| unsafe { w.datawhiteiv().bits(iv) });
+                self.0.pcnf1.modify(|_, w| w.whiteen().enabled());
+            }
+            None => self.0.pcnf1.modify(|_, w| w.whiteen().disabled()),
+        }
+
+        self.0
+            .tifs
+            .write(|w| unsafe { w.bits(config.tifs as u32) });
+
+        Ok(())
+    }
+
+    /// Set the frequency of the radio to 2400 + `frequency` MHz
+    ///
+    /// `frequency` must be smaller than `FREQUENCY_COUNT`.
+    pub fn set_frequency(&mut self, frequency: u8) -> Result<(), Error> {
+        if frequency as usize >= FREQUENCY_COUNT {
+            return Err(Error::FrequencyOutOfRange);
+        }
+
+        self.0
+            .frequency
+            .write(|w| unsafe { w.frequency().bits(frequency) });
+
+        Ok(())
+    }
+
+    /// Set the data rate and modulation of the radio
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.0.mode.write(|w| w.mode().variant(mode));
+    }
+
+    /// Set the output power of the radio
+    pub fn set_tx_power(&mut self, power: TxPower) {
+        self.0.txpower.write(|w| w.txpower().variant(power));
+    }
+
+    /// Return the current state of the radio
+    pub fn state(&self) -> State {
+        self.0.state.read().state()
+    }
+
+    /// Transmit a packet, blocking until it has been sent
+    ///
+    /// The first byte of `packet` is the length of the payload that follows.
+    pub fn transmit(&mut self, packet: &[u8]) -> Result<(), Error> {
+        if packet.is_empty() || packet.len() < 1 + packet[0] as usize {
+            return Err(Error::BufferTooShort);
+        }
+        if !slice_in_ram(packet) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+
+        self.disable();
+        self.transmit_prepared(packet.as_ptr() as u32);
+
+        Ok(())
+    }
+
+    /// Receive a packet, blocking until one has arrived
+    ///
+    /// `packet` must be large enough to hold the length byte and a payload of
+    /// `MAX_PAYLOAD_LEN` bytes. On success, the first byte of `packet` holds
+    /// the length of the received payload. If the packet failed the CRC check,
+    /// `Error::Crc` is returned.
+    ///
+    /// The RSSI is sampled while the packet is being received, and can be
+    /// read afterwards using `rssi_sample`.
+    pub fn receive(&mut self, packet: &mut [u8]) -> Result<(), Error> {
+        if packet.len() < 1 + MAX_PAYLOAD_LEN {
+            return Err(Error::BufferTooShort);
+        }
+
+        self.disable();
+        self.set_packet_ptr(packet.as_mut_ptr() as u32);
+
+        self.0.shorts.write(|w| {
+            w.ready_start()
+                .enabled()
+                .end_disable()
+                .enabled()
+                .address_rssistart()
+                .enabled()
+                .disabled_rssistop()
+                .enabled()
+        });
+        self.0.tasks_rxen.write(|w| unsafe { w.bits(1) });
+
+        // Wait for reception to end, the radio is disabled by the shortcut
+        while self.0.events_disabled.read().bits() == 0 {}
+        self.0.events_disabled.write(|w| w);
+        self.0.events_end.write(|w| w);
+        self.0.events_address.write(|w| w);
+        self.0.events_rssiend.write(|w| w);
+        self.0.shorts.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        if self.0.crcstatus.read().crcstatus().is_crcerror() {
+            return Err(Error::Crc);
+        }
+
+        Ok(())
+    }
+
+    /// Encrypt a packet on-the-fly while transmitting it
+    ///
+    /// `packet` is a clear packet in the CCM format, a 3 byte header followed
+    /// by the payload (see the `ccm` module). The CCM writes the encrypted
+    /// packet into `buffer`, from which it is transmitted, so `buffer` must
+    /// have room for the header, the payload and the MIC. The key stream
+    /// generation is started by the radio READY event through the
+    /// pre-programmed PPI channel 24, and the encryption by the shortcut
+    /// following it.
+    ///
+    /// The packet format of the radio is switched to the CCM format for the
+    /// duration of the transmission. The packet counter of `data` is not
+    /// incremented.
+    pub fn transmit_encrypted(
+        &mut self,
+        ccm: &mut Ccm,
+        data: &mut CcmData,
+        packet: &[u8],
+        buffer: &mut [u8],
+        scratch: &mut [u8],
+        ppi_ksgen: &mut ppi::Ppi24,
+    ) -> Result<(), Error> {
+        let len = ccm.check_packet(packet, scratch)?;
+        let mic = if len > 0 { ccm::MIC_SIZE } else { 0 };
+        if len > usize::from(ccm.max_payload_len()) {
+            return Err(Error::Ccm(ccm::Error::PayloadTooLong));
+        }
+        if buffer.len() < ccm::HEADER_SIZE + len + mic {
+            return Err(Error::BufferTooShort);
+        }
+
+        self.disable();
+        let pcnf0 = self.set_ccm_format();
+
+        ccm.set_mode(true, self.is_2mbit());
+        ccm.prepare(data, packet.as_ptr() as u32, buffer.as_mut_ptr() as u32, scratch);
+        ccm.set_crypt_on_keystream(true);
+        ppi_ksgen.enable();
+
+        self.transmit_prepared(buffer.as_ptr() as u32);
+
+        ppi_ksgen.disable();
+        self.0.pcnf0.write(|w| unsafe { w.bits(pcnf0) });
+
+        ccm.wait()?;
+        Ok(())
+    }
+
+    /// Receive a packet, decrypting it on-the-fly
+    ///
+    /// The packet is received into `buffer`, which must be large enough to
+    /// hold the CCM header and a payload of `MAX_PAYLOAD_LEN` bytes, and the
+    /// CCM writes the decrypted packet into `packet`, which must have room
+    /// for the header and the largest payload accepted by `ccm`. The key
+    /// stream generation is started by the radio READY event through the
+    /// pre-programmed PPI channel 24, and the decryption by the ADDRESS event
+    /// through channel 25.
+    ///
+    /// If the packet failed the CRC check, `Error::Crc` is returned, and if it
+    /// failed the MIC check, `Error::Ccm(ccm::Error::WrongMic)`. The packet
+    /// format of the radio is switched to the CCM format for the duration of
+    /// the reception. The packet counter of `data` is not incremented.
+    pub fn receive_encrypted(
+        &mut self,
+        ccm: &mut Ccm,
+        data: &mut CcmData,
+        buffer: &mut [u8],
+        packet: &mut [u8],
+        scratch: &mut [u8],
+        ppi_ksgen: &mut ppi::Ppi24,
+        ppi_crypt: &mut ppi::Ppi25,
+    ) -> Result<(), Error> {
+        if buffer.len() < ccm::HEADER_SIZE + MAX_PAYLOAD_LEN {
+            return Err(Error::BufferTooShort);
+        }
+        if packet.len() < ccm::HEADER_SIZE + usize::from(ccm.max_payload_len()) {
+            return Err(Error::BufferTooShort);
+        }
+        if !slice_in_ram(packet) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+        if scratch.len() < ccm.scratch_size() {
+            return Err(Error::Ccm(ccm::Error::InsufficientScratchArea));
+        }
+
+        self.disable();
+        let pcnf0 = self.set_ccm_format();
+
+        ccm.set_mode(false, self.is_2mbit());
+        ccm.prepare(data, buffer.as_ptr() as u32, packet.as_mut_ptr() as u32, scratch);
+        ccm.set_crypt_on_keystream(false);
+        ppi_ksgen.enable();
+        ppi_crypt.enable();
+
+        self.set_packet_ptr(buffer.as_mut_ptr() as u32);
+        self.0
+            .shorts
+            .write(|w| w.ready_start().enabled().end_disable().enabled());
+        self.0.tasks_rxen.write(|w| unsafe { w.bits(1) });
+
+        // Wait for reception to end, the radio is disabled by the shortcut
+        while self.0.events_disabled.read().bits() == 0 {}
+        self.0.events_disabled.write(|w| w);
+        self.0.events_end.write(|w| w);
+        self.0.events_address.write(|w| w);
+        self.0.shorts.reset();
+
+        ppi_ksgen.disable();
+        ppi_crypt.disable();
+        self.0.pcnf0.write(|w| unsafe { w.bits(pcnf0) });
+
+        let crypt = ccm.wait();
+
+        if self.0.crcstatus.read().crcstatus().is_crcerror() {
+            return Err(Error::Crc);
+        }
+        crypt?;
+        if buffer[1] > 0 && ccm.mic_failed() {
+            return Err(Error::Ccm(ccm::Error::WrongMic));
+        }
+
+        Ok(())
+    }
+
+    /// Transmit a packet, then receive the response to it
+    ///
+    /// The radio is switched from TX to RX by hardware shortcuts, so the
+    /// receiver is ready after the interframe spacing configured by
+    /// `LinkConfig::tifs` (150 µs for BLE), without any involvement of the
+    /// CPU.
+    ///
+    /// The response must start arriving within `cycles` of `timer` after the
+    /// transmission has been started, otherwise `Error::Timeout` is returned.
+    /// This method assumes the interrupt for the given timer is NOT enabled.
+    /// `rx_packet` has the same requirements as the buffer passed to
+    /// `receive`.
+    pub fn transmit_then_receive<I>(
+        &mut self,
+        tx_packet: &[u8],
+        rx_packet: &mut [u8],
+        timer: &mut Timer<I>,
+        cycles: u32,
+    ) -> Result<(), Error>
+    where
+        I: timer::Instance,
+    {
+        if tx_packet.is_empty() || tx_packet.len() < 1 + tx_packet[0] as usize {
+            return Err(Error::BufferTooShort);
+        }
+        if rx_packet.len() < 1 + MAX_PAYLOAD_LEN {
+            return Err(Error::BufferTooShort);
+        }
+        if !slice_in_ram(tx_packet) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+
+        self.disable();
+        self.set_packet_ptr(tx_packet.as_ptr() as u32);
+
+        self.0.shorts.write(|w| {
+            w.ready_start()
+                .enabled()
+                .end_disable()
+                .enabled()
+                .disabled_rxen()
+                .enabled()
+        });
+        self.0.tasks_txen.write(|w| unsafe { w.bits(1) });
+        timer.start(cycles);
+
+        // The packet pointer is read when the reception is started, so it can
+        // be switched as soon as the transmission has ended. The address event
+        // of the transmission is cleared along with its end event, so only
+        // the address of the response is waited for below.
+        while self.0.events_end.read().bits() == 0 {}
+        self.0.events_end.write(|w| w);
+        self.0.events_address.write(|w| w);
+        self.set_packet_ptr(rx_packet.as_mut_ptr() as u32);
+
+        // Once the radio has been disabled after transmitting, the receiver
+        // is enabled by the shortcut. Make sure the radio isn't enabled again
+        // after the reception.
+        while self.0.events_disabled.read().bits() == 0 {}
+        self.0.events_disabled.write(|w| w);
+        self.0.shorts.write(|w| {
+            w.ready_start()
+                .enabled()
+                .end_disable()
+                .enabled()
+                .address_rssistart()
+                .enabled()
+                .disabled_rssistop()
+                .enabled()
+        });
+
+        let mut timeout = false;
+        while self.0.events_disabled.read().bits() == 0 {
+            // Once the address has been received, the timer expiring doesn't
+            // end the reception anymore
+            if timer.wait().is_ok() && self.0.events_address.read().bits() == 0 {
+                timeout = true;
+                break;
+            }
+        }
+
+        self.0.shorts.reset();
+        self.disable();
+        self.0.events_end.write(|w| w);
+        self.0.events_address.write(|w| w);
+        self.0.events_rssiend.write(|w| w);
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        if timeout {
+            return Err(Error::Timeout);
+        }
+        if self.0.crcstatus.read().crcstatus().is_crcerror() {
+            return Err(Error::Crc);
+        }
+
+        Ok(())
+    }
+
+    /// Receive a packet, then transmit an acknowledgement
+    ///
+    /// The radio is switched from RX to TX by hardware shortcuts, so `ack` is
+    /// transmitted after the interframe spacing configured by
+    /// `LinkConfig::tifs` (150 µs for BLE), without any involvement of the
+    /// CPU. If the received packet fails the CRC check, the acknowledgement
+    /// is not sent and `Error::Crc` is returned.
+    ///
+    /// `rx_packet` has the same requirements as the buffer passed to
+    /// `receive`, `ack` the same as the one passed to `transmit`.
+    pub fn receive_then_transmit(
+        &mut self,
+        rx_packet: &mut [u8],
+        ack: &[u8],
+    ) -> Result<(), Error> {
+        if rx_packet.len() < 1 + MAX_PAYLOAD_LEN {
+            return Err(Error::BufferTooShort);
+        }
+        if ack.is_empty() || ack.len() < 1 + ack[0] as usize {
+            return Err(Error::BufferTooShort);
+        }
+        if !slice_in_ram(ack) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+
+        self.disable();
+        self.set_packet_ptr(rx_packet.as_mut_ptr() as u32);
+
+        self.0.shorts.write(|w| {
+            w.ready_start()
+                .enabled()
+                .end_disable()
+                .enabled()
+                .address_rssistart()
+                .enabled()
+                .disabled_txen()
+                .enabled()
+        });
+        self.0.tasks_rxen.write(|w| unsafe { w.bits(1) });
+
+        while self.0.events_end.read().bits() == 0 {}
+        self.0.events_end.write(|w| w);
+
+        if self.0.crcstatus.read().crcstatus().is_crcerror() {
+            // Don't acknowledge a corrupted packet. If the transmitter has
+            // already been enabled by the shortcut, this aborts its ramp-up.
+            self.0.shorts.reset();
+            self.disable();
+            self.stop_rssi();
+            self.0.events_address.write(|w| w);
+            self.0.events_rssiend.write(|w| w);
+
+            compiler_fence(SeqCst);
+
+            return Err(Error::Crc);
+        }
+
+        // The packet pointer is read when the transmission is started, so it
+        // can be switched as soon as the reception has ended
+        self.set_packet_ptr(ack.as_ptr() as u32);
+
+        // Once the radio has been disabled after receiving, the transmitter
+        // is enabled by the shortcut. Make sure the radio isn't enabled again
+        // after the transmission.
+        while self.0.events_disabled.read().bits() == 0 {}
+        self.0.events_disabled.write(|w| w);
+        self.stop_rssi();
+        self.0
+            .shorts
+            .write(|w| w.ready_start().enabled().end_disable().enabled());
+
+        while self.0.events_disabled.read().bits() == 0 {}
+        self.0.events_disabled.write(|w| w);
+        self.0.events_end.write(|w| w);
+        self.0.events_address.write(|w| w);
+        self.0.events_rssiend.write(|w| w);
+        self.0.shorts.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        Ok(())
+    }
+
+    /// Receive a packet, inspecting its header before the packet is complete
+    ///
+    /// The bit counter is started once the address has been received. After
+    /// another `header_len` bytes have been received, `inspect` is called with
+    /// these bytes, starting with the length byte. If `inspect` returns
+    /// `false`, the packet is dropped and the radio keeps listening for the
+    /// next one. This allows filtering packets in software, e.g. by length or
+    /// by an address in the header, without waiting for the whole packet.
+    ///
+    /// `header_len` must be between 1 and `MAX_HEADER_LEN`. Otherwise, this
+    /// behaves like `receive`.
+    pub fn receive_filtered<F>(
+        &mut self,
+        packet: &mut [u8],
+        header_len: usize,
+        mut inspect: F,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&[u8]) -> bool,
+    {
+        if header_len == 0 || header_len > MAX_HEADER_LEN {
+            return Err(Error::InvalidHeaderLength);
+        }
+        if packet.len() < 1 + MAX_PAYLOAD_LEN {
+            return Err(Error::BufferTooShort);
+        }
+
+        self.disable();
+        self.set_packet_ptr(packet.as_mut_ptr() as u32);
+        self.set_bit_counter(8 * header_len as u32);
+
+        self.0.shorts.write(|w| {
+            w.ready_start()
+                .enabled()
+                .end_disable()
+                .enabled()
+                .address_rssistart()
+                .enabled()
+                .disabled_rssistop()
+                .enabled()
+                .address_bcstart()
+                .enabled()
+        });
+
+        'listen: loop {
+            self.0.events_bcmatch.write(|w| w);
+            self.0.tasks_rxen.write(|w| unsafe { w.bits(1) });
+
+            // Wait for reception to end, the radio is disabled by the shortcut
+            while self.0.events_disabled.read().bits() == 0 {
+                if self.0.events_bcmatch.read().bits() == 0 {
+                    continue;
+                }
+                self.0.events_bcmatch.write(|w| w);
+
+                // The header has been written to RAM by EasyDMA, while the
+                // rest of the packet is still being received. Copy the header
+                // without creating a reference to memory that is being
+                // written to.
+                compiler_fence(SeqCst);
+                let mut header = [0; MAX_HEADER_LEN];
+                for (i, b) in header[..header_len].iter_mut().enumerate() {
+                    *b = unsafe { core::ptr::read_volatile(packet.as_ptr().add(i)) };
+                }
+
+                if !inspect(&header[..header_len]) {
+                    self.disable();
+                    continue 'listen;
+                }
+            }
+            break;
+        }
+
+        self.0.events_disabled.write(|w| w);
+        self.0.events_end.write(|w| w);
+        self.0.events_address.write(|w| w);
+        self.0.events_rssiend.write(|w| w);
+        self.0.events_bcmatch.write(|w| w);
+        self.0.shorts.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        if self.0.crcstatus.read().crcstatus().is_crcerror() {
+            return Err(Error::Crc);
+        }
+
+        Ok(())
+    }
+
+    /// Set the number of bits after the address at which the bit counter
+    /// generates the BCMATCH event
+    pub fn set_bit_counter(&mut self, bits: u32) {
+        self.0.bcc.write(|w| unsafe { w.bits(bits) });
+    }
+
+    /// Start the bit counter
+    ///
+    /// Usually, the bit counter is started by the ADDRESS_BCSTART shortcut
+    /// instead.
+    pub fn start_bit_counter(&mut self) {
+        self.0.tasks_bcstart.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Stop the bit counter
+    pub fn stop_bit_counter(&mut self) {
+        self.0.tasks_bcstop.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Capture the value of `timer` whenever the ADDRESS and END events occur
+    ///
+    /// The events are connected to the `TIMESTAMP_ADDRESS_CC` and
+    /// `TIMESTAMP_END_CC` capture tasks of `timer` through the given PPI
+    /// channels. The timer must be running for the captured values to be
+    /// meaningful, see `Timer::start_free_running`.
+    pub fn enable_timestamps<T, A, E>(
+        &mut self,
+        timer: &Timer<T>,
+        address_channel: &mut A,
+        end_channel: &mut E,
+    ) where
+        T: timer::Instance,
+        A: ppi::Channel,
+        E: ppi::Channel,
+    {
+        address_channel.set_event_endpoint(&self.0.events_address);
+        address_channel.set_task_endpoint(timer.task_capture(TIMESTAMP_ADDRESS_CC));
+        address_channel.enable();
+
+        end_channel.set_event_endpoint(&self.0.events_end);
+        end_channel.set_task_endpoint(timer.task_capture(TIMESTAMP_END_CC));
+        end_channel.enable();
+    }
+
+    /// Return the timer values captured for the last packet that was sent or
+    /// received
+    ///
+    /// Timestamps must have been enabled using `enable_timestamps`, with the
+    /// same `timer`.
+    pub fn timestamp<T>(&self, timer: &Timer<T>) -> Timestamp
+    where
+        T: timer::Instance,
+    {
+        Timestamp {
+            address: timer.read_capture(TIMESTAMP_ADDRESS_CC),
+            end: timer.read_capture(TIMESTAMP_END_CC),
+        }
+    }
+
+    /// Receive a packet and return the timer values captured while receiving it
+    ///
+    /// This behaves like `receive`. Timestamps must have been enabled using
+    /// `enable_timestamps`, with the same `timer`.
+    pub fn receive_timestamped<T>(
+        &mut self,
+        packet: &mut [u8],
+        timer: &Timer<T>,
+    ) -> Result<Timestamp, Error>
+    where
+        T: timer::Instance,
+    {
+        self.receive(packet)?;
+
+        Ok(self.timestamp(timer))
+    }
+
+    /// Start sampling the received signal strength
+    ///
+    /// The radio must be in the `RX` state for the sample to be meaningful.
+    pub fn start_rssi(&mut self) {
+        self.0.tasks_rssistart.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Stop sampling the received signal strength
+    pub fn stop_rssi(&mut self) {
+        self.0.tasks_rssistop.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Return the last received signal strength sample in dBm
+    ///
+    /// After a call to `receive`, this is the signal strength of the received
+    /// packet.
+    pub fn rssi_sample(&self) -> i8 {
+        // The register holds the magnitude of the (negative) signal strength
+        -(self.0.rssisample.read().rssisample().bits() as i8)
+    }
+
+    /// Measure the received signal strength on the current frequency in dBm
+    ///
+    /// If the radio is currently receiving, the signal strength is sampled
+    /// without disturbing the reception. Otherwise, the receiver is enabled
+    /// with all addresses disabled for the duration of the measurement, so no
+    /// packet can be received while measuring.
+    pub fn rssi(&mut self) -> i8 {
+        if self.state() == State::RX {
+            return self.sample_rssi();
+        }
+
+        self.disable();
+
+        let rxaddresses = self.0.rxaddresses.read().bits();
+        self.0.rxaddresses.reset();
+
+        self.0.shorts.write(|w| w.ready_start().enabled());
+        self.0.tasks_rxen.write(|w| unsafe { w.bits(1) });
+        while self.0.events_ready.read().bits() == 0 {}
+        self.0.events_ready.write(|w| w);
+        self.0.shorts.reset();
+
+        let rssi = self.sample_rssi();
+
+        self.disable();
+        self.0.rxaddresses.write(|w| unsafe { w.bits(rxaddresses) });
+
+        rssi
+    }
+
+    /// Measure the noise floor on every frequency the radio can be tuned to
+    ///
+    /// `results[n]` holds the received signal strength in dBm at
+    /// 2400 + `n` MHz. The radio is left tuned to the frequency it was tuned
+    /// to before the scan.
+    pub fn scan_noise(&mut self, results: &mut [i8; FREQUENCY_COUNT]) {
+        let frequency = self.0.frequency.read().bits();
+
+        for (n, result) in results.iter_mut().enumerate() {
+            self.0
+                .frequency
+                .write(|w| unsafe { w.frequency().bits(n as u8) });
+            *result = self.rssi();
+        }
+
+        self.0.frequency.write(|w| unsafe { w.bits(frequency) });
+    }
+
+    /// Start transmitting an unmodulated carrier at 2400 + `frequency` MHz
+    ///
+    /// This is intended for regulatory and production testing. The carrier is
+    /// transmitted until the returned `TestTransmission` is stopped or
+    /// dropped.
+    pub fn start_carrier(
+        &mut self,
+        frequency: u8,
+        power: TxPower,
+    ) -> Result<TestTransmission<'_>, Error> {
+        self.disable();
+        self.set_frequency(frequency)?;
+        self.set_tx_power(power);
+
+        // The radio transmits a carrier while it is idling in TX mode
+        self.0.shorts.reset();
+        self.0.tasks_txen.write(|w| unsafe { w.bits(1) });
+
+        Ok(TestTransmission { radio: self })
+    }
+
+    /// Start transmitting a modulated carrier at 2400 + `frequency` MHz
+    ///
+    /// `buffer` is filled with a packet that has a PRBS9 payload, which is
+    /// then transmitted back to back until the returned `TestTransmission` is
+    /// stopped or dropped. `buffer` must be at least 2 bytes long. Payloads of
+    /// more than `MAX_PAYLOAD_LEN` bytes are truncated.
+    ///
+    /// This is intended for regulatory and production testing.
+    pub fn start_modulated_carrier<'a>(
+        &'a mut self,
+        frequency: u8,
+        power: TxPower,
+        buffer: &'a mut [u8],
+    ) -> Result<TestTransmission<'a>, Error> {
+        if buffer.len() < 2 {
+            return Err(Error::BufferTooShort);
+        }
+
+        let len = core::cmp::min(buffer.len() - 1, MAX_PAYLOAD_LEN);
+        buffer[0] = len as u8;
+        fill_prbs9(&mut buffer[1..=len]);
+
+        self.disable();
+        self.set_frequency(frequency)?;
+        self.set_tx_power(power);
+        self.set_packet_ptr(buffer.as_ptr() as u32);
+
+        self.0
+            .shorts
+            .write(|w| w.ready_start().enabled().end_start().enabled());
+        self.0.tasks_txen.write(|w| unsafe { w.bits(1) });
+
+        Ok(TestTransmission { radio: self })
+    }
+
+    /// Disable the radio, blocking until it has been disabled
+    pub fn disable(&mut self) {
+        if self.state() != State::DISABLED {
+            self.0.tasks_disable.write(|w| unsafe { w.bits(1) });
+            while self.0.events_disabled.read().bits() == 0 {}
+        }
+        self.0.events_disabled.write(|w| w);
+    }
+
+    /// Return the raw interface to the underlying RADIO peripheral
+    pub fn free(self) -> RADIO {
+        self.0
+    }
+
+    fn sample_rssi(&mut self) -> i8 {
+        self.0.events_rssiend.write(|w| w);
+        self.start_rssi();
+        while self.0.events_rssiend.read().bits() == 0 {}
+        self.0.events_rssiend.write(|w| w);
+        self.stop_rssi();
+
+        self.rssi_sample()
+    }
+
+    /// Transmit the packet at `ptr`, blocking until it has been sent
+    fn transmit_prepared(&mut self, ptr: u32) {
+        self.set_packet_ptr(ptr);
+
+        self.0
+            .shorts
+            .write(|w| w.ready_start().enabled().end_disable().enabled());
+        self.0.tasks_txen.write(|w| unsafe { w.bits(1) });
+
+        // Wait for transmission to end, the radio is disabled by the shortcut
+        while self.0.events_disabled.read().bits() == 0 {}
+        self.0.events_disabled.write(|w| w);
+        self.0.events_end.write(|w| w);
+        self.0.shorts.reset();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+    }
+
+    /// Switch to the packet format of the CCM, returning the previous PCNF0
+    ///
+    /// The CCM expects an S0 byte, the length and an S1 byte in RAM, where S1
+    /// is not sent over the air.
+    fn set_ccm_format(&mut self) -> u32 {
+        let pcnf0 = self.0.pcnf0.read().bits();
+        self.0.pcnf0.write(|w| unsafe {
+            w.lflen()
+                .bits(8)
+                .s0len()
+                .bit(true)
+                .s1len()
+                .bits(0)
+                .s1incl()
+                .include()
+        });
+        pcnf0
+    }
+
+    fn is_2mbit(&self) -> bool {
+        let mode = self.0.mode.read().mode();
+        mode.is_nrf_2mbit() || mode.is_ble_2mbit()
+    }
+
+    fn set_packet_ptr(&mut self, ptr: u32) {
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+
+        self.0.packetptr.write(|w| unsafe { w.bits(ptr) });
+    }
+}
+
+/// Mask of the 24 bit RTC counter
+const RTC_COUNTER_MASK: u32 = 0x00FF_FFFF;
+
+/// Hops the radio through a table of frequencies on a fixed schedule
+///
+/// This is a building block for frequency hopping protocols. The schedule is
+/// divided into slots of equal length, measured in RTC ticks. Slot `n` uses
+/// the frequency `channels[n % channels.len()]` (see `Radio::set_frequency`).
+///
+/// `start` tunes the radio to the first channel and arms an RTC compare
+/// register for the end of the slot. When the compare event fires, `hop`
+/// must be called to tune the radio to the next channel and arm the compare
+/// register for the end of the next slot. The slot boundaries are computed
+/// from the schedule, not from the time `hop` is called, so interrupt latency
+/// doesn't accumulate. Optionally, `connect_disable` ends every slot exactly
+/// on time by disabling the radio through PPI.
+///
+/// When the timing of the peer is learned, e.g. from a received packet, the
+/// schedule can be aligned to it using `resync`.
+pub struct HoppingSchedule<'a> {
+    channels: &'a [u8],
+    index: usize,
+    slot_ticks: u32,
+    slot_end: u32,
+    compare: RtcCompareReg,
+}
+
+impl<'a> HoppingSchedule<'a> {
+    /// Create a schedule hopping through `channels`, staying on every channel
+    /// for `slot_ticks` RTC ticks
+    pub fn new(
+        channels: &'a [u8],
+        slot_ticks: u32,
+        compare: RtcCompareReg,
+    ) -> Result<Self, Error> {
+        if channels.is_empty()
+            || channels.iter().any(|&c| c as usize >= FREQUENCY_COUNT)
+        {
+            return Err(Error::FrequencyOutOfRange);
+        }
+        if slot_ticks == 0 || slot_ticks > RTC_COUNTER_MASK / 2 {
+            return Err(Error::InvalidSlotLength);
+        }
+
+        Ok(HoppingSchedule {
+            channels,
+            index: 0,
+            slot_ticks,
+            slot_end: 0,
+            compare,
+        })
+    }
+
+    /// Start the schedule with the first channel, in a slot starting now
+    pub fn start<T>(&mut self, radio: &mut Radio, rtc: &mut Rtc<T, Started>)
+    where
+        T: rtc::Instance,
+    {
+        let now = rtc.get_counter();
+        self.resync(radio, rtc, 0, now);
+    }
+
+    /// Advance to the next slot
+    ///
+    /// Must be called when the compare event of the RTC fires. Tunes the radio
+    /// to the channel of the next slot, arms the compare register for the end
+    /// of that slot and returns the new channel.
+    ///
+    /// The radio must not be transmitting or receiving, as changing the
+    /// frequency only takes effect when the radio is enabled.
+    pub fn hop<T>(&mut self, radio: &mut Radio, rtc: &mut Rtc<T, Started>) -> u8
+    where
+        T: rtc::Instance,
+    {
+        rtc.get_event_triggered(self.compare_interrupt(), true);
+
+        self.index = (self.index + 1) % self.channels.len();
+        self.slot_end = self.slot_end.wrapping_add(self.slot_ticks) & RTC_COUNTER_MASK;
+        self.apply(radio, rtc)
+    }
+
+    /// Align the schedule to a peer
+    ///
+    /// Makes the slot with index `index` (into the channel table) start at
+    /// the RTC counter value `slot_start`, e.g. the time a synchronization
+    /// packet of the peer was received, and tunes the radio accordingly.
+    /// Returns the channel of the current slot.
+    pub fn resync<T>(
+        &mut self,
+        radio: &mut Radio,
+        rtc: &mut Rtc<T, Started>,
+        index: usize,
+        slot_start: u32,
+    ) -> u8
+    where
+        T: rtc::Instance,
+    {
+        rtc.get_event_triggered(self.compare_interrupt(), true);
+
+        self.index = index % self.channels.len();
+        self.slot_end = slot_start.wrapping_add(self.slot_ticks) & RTC_COUNTER_MASK;
+        self.apply(radio, rtc)
+    }
+
+    /// Disable the radio through PPI whenever a slot ends
+    ///
+    /// This ends any transmission or reception exactly at the slot boundary,
+    /// independent of interrupt latency.
+    pub fn connect_disable<T, C>(
+        &self,
+        radio: &Radio,
+        rtc: &mut Rtc<T, Started>,
+        channel: &mut C,
+    ) where
+        T: rtc::Instance,
+        C: ppi::Channel,
+    {
+        rtc.enable_event(self.compare_interrupt());
+        channel.set_event_endpoint(rtc.event_compare(self.compare));
+        channel.set_task_endpoint(&radio.0.tasks_disable);
+        channel.enable();
+    }
+
+    /// Return the channel of the current slot
+    pub fn channel(&self) -> u8 {
+        self.channels[self.index]
+    }
+
+    /// Return the index of the current slot into the channel table
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Return the RTC counter value at which the current slot ends
+    pub fn slot_end(&self) -> u32 {
+        self.slot_end
+    }
+
+    fn apply<T>(&mut self, radio: &mut Radio, rtc: &mut Rtc<T, Started>) -> u8
+    where
+        T: rtc::Instance,
+    {
+        let channel = self.channel();
+
+        // The channels have been validated in `new`, and `slot_end` is
+        // masked to 24 bits
+        let _ = radio.set_frequency(channel);
+        let _ = rtc.set_compare(self.compare, self.slot_end);
+
+        channel
+    }
+
+    fn compare_interrupt(&self) -> rtc::RtcInterrupt {
+        match self.compare {
+            RtcCompareReg::Compare0 => rtc::RtcInterrupt::Compare0,
+            RtcCompareReg::Compare1 => rtc::RtcInterrupt::Compare1,
+            RtcCompareReg::Compare2 => rtc::RtcInterrupt::Compare2,
+            RtcCompareReg::Compare3 => rtc::RtcInterrupt::Compare3,
+        }
+    }
+}
+
+/// Link configuration of the radio
+///
+/// Describes the addresses, CRC, data whitening and interframe spacing used on
+/// the link. Use the builder methods to change the defaults, then apply the
+/// configuration using `Radio::configure_link`, which validates it.
+///
+/// The default configuration matches the configuration the radio is set up
+/// with by `Radio::new`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct LinkConfig {
+    base0: u32,
+    base1: u32,
+    prefixes: [u8; 8],
+    base_len: u8,
+    tx_address: u8,
+    rx_addresses: u8,
+    crc_len: u8,
+    crc_poly: u32,
+    crc_init: u32,
+    crc_skip_address: bool,
+    whitening: Option<u8>,
+    tifs: u16,
+}
+
+impl LinkConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Use `address` as the 32 bit access address of logical address 0
+    ///
+    /// This sets the base address length to 3 bytes, as used by BLE.
+    pub fn access_address(mut self, address: u32) -> Self {
+        self.base_len = 3;
+        self.base0 = address << 8;
+        self.prefixes[0] = (address >> 24) as u8;
+        self
+    }
+
+    /// Set the base address used by logical address 0
+    ///
+    /// If the base address is shorter than 4 bytes, the least significant
+    /// bytes of `base` are not used.
+    pub fn base0(mut self, base: u32) -> Self {
+        self.base0 = base;
+        self
+    }
+
+    /// Set the base address used by logical addresses 1 to 7
+    ///
+    /// If the base address is shorter than 4 bytes, the least significant
+    /// bytes of `base` are not used.
+    pub fn base1(mut self, base: u32) -> Self {
+        self.base1 = base;
+        self
+    }
+
+    /// Set the prefix bytes of the logical addresses
+    ///
+    /// `prefixes[n]` is the prefix of logical address `n`.
+    pub fn prefixes(mut self, prefixes: [u8; 8]) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+
+    /// Set the length of the base addresses, in bytes (2 to 4)
+    pub fn base_length(mut self, bytes: u8) -> Self {
+        self.base_len = bytes;
+        self
+    }
+
+    /// Set the logical address (0 to 7) used for transmission
+    pub fn tx_address(mut self, logical: u8) -> Self {
+        self.tx_address = logical;
+        self
+    }
+
+    /// Set the logical addresses used for reception
+    ///
+    /// Bit `n` of `mask` enables reception on logical address `n`.
+    pub fn rx_addresses(mut self, mask: u8) -> Self {
+        self.rx_addresses = mask;
+        self
+    }
+
+    /// Set the CRC length in bytes (0 to 3), polynomial and initial value
+    ///
+    /// A length of 0 disables the CRC. `poly` and `init` must fit into the
+    /// given number of bytes.
+    pub fn crc(mut self, len: u8, poly: u32, init: u32) -> Self {
+        self.crc_len = len;
+        self.crc_poly = poly;
+        self.crc_init = init;
+        self
+    }
+
+    /// Set whether the address is excluded from the CRC calculation
+    pub fn crc_skip_address(mut self, skip: bool) -> Self {
+        self.crc_skip_address = skip;
+        self
+    }
+
+    /// Enable data whitening with the given 7 bit initial value, or disable it
+    pub fn whitening(mut self, iv: Option<u8>) -> Self {
+        self.whitening = iv;
+        self
+    }
+
+    /// Set the interframe spacing in µs, at most `MAX_TIFS`
+    ///
+    /// Defaults to 150 µs, the interframe spacing of BLE.
+    pub fn tifs(mut self, us: u16) -> Self {
+        self.tifs = us;
+        self
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.base_len < 2 || self.base_len > 4 {
+            return Err(Error::InvalidBaseAddressLength);
+        }
+        if self.tx_address > 7 {
+            return Err(Error::InvalidLogicalAddress);
+        }
+
+        // The length is checked first, the mask of a longer CRC would overflow
+        if self.crc_len > 3 {
+            return Err(Error::InvalidCrcConfig);
+        }
+        let crc_mask = (1u32 << (8 * self.crc_len as u32)) - 1;
+        if self.crc_poly & !crc_mask != 0 || self.crc_init & !crc_mask != 0 {
+            return Err(Error::InvalidCrcConfig);
+        }
+
+        if let Some(iv) = self.whitening {
+            if iv > 0x7F {
+                return Err(Error::InvalidWhiteningIv);
+            }
+        }
+        if self.tifs > MAX_TIFS {
+            return Err(Error::TifsOutOfRange);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        LinkConfig {
+            base0: 0,
+            base1: 0,
+            prefixes: [0; 8],
+            base_len: 3,
+            tx_address: 0,
+            rx_addresses: 0x01,
+            crc_len: 3,
+            crc_poly: 0x00_065B,
+            crc_init: 0x55_5555,
+            crc_skip_address: true,
+            whitening: None,
+            tifs: 150,
+        }
+        .access_address(DEFAULT_ACCESS_ADDRESS)
+    }
+}
+
+/// A running test transmission, see `Radio::start_carrier` and
+/// `Radio::start_modulated_carrier`
+///
+/// The transmission is stopped when this is dropped.
+pub struct TestTransmission<'a> {
+    radio: &'a mut Radio,
+}
+
+impl<'a> TestTransmission<'a> {
+    /// Stop the test transmission
+    pub fn stop(self) {
+        // Stopping is handled by `Drop`
+    }
+}
+
+impl<'a> Drop for TestTransmission<'a> {
+    fn drop(&mut self) {
+        self.radio.0.shorts.reset();
+        self.radio.disable();
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+    }
+}
+
+/// Fill `buf` with the PRBS9 sequence (x^9 + x^5 + 1), as used by the BLE
+/// direct test mode
+fn fill_prbs9(buf: &mut [u8]) {
+    let mut state: u16 = 0x1FF;
+
+    for byte in buf {
+        let mut out = 0;
+        for bit in 0..8 {
+            let next = ((state >> 8) ^ (state >> 4)) & 1;
+            out |= ((state & 1) as u8) << bit;
+            state = ((state << 1) | next) & 0x1FF;
+        }
+        *byte = out;
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    FrequencyOutOfRange,
+    BufferTooShort,
+    /// EasyDMA can only read from data memory, read only buffers in flash will fail
+    DMABufferNotInDataMemory,
+    Crc,
+    InvalidBaseAddressLength,
+    InvalidLogicalAddress,
+    InvalidCrcConfig,
+    InvalidWhiteningIv,
+    TifsOutOfRange,
+    InvalidHeaderLength,
+    Timeout,
+    InvalidSlotLength,
+    Ccm(ccm::Error),
+}
+
+impl From<ccm::Error> for Error {
+    fn from(e: ccm::Error) -> Self {
+        Error::Ccm(e)
+    }
+}
@@ -1,7 +1,15 @@
 //! HAL interface to the RNG peripheral
 //!
-//! See nRF52832 product specification, chapter 26.
+//! With bias correction, the generated numbers are suitable for cryptographic
+//! use, so the driver implements `rand_core::CryptoRng`. Bias correction can
+//! only be disabled by converting into `Rng<Uncorrected>`, which doesn't.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 26
+//! - nrf52840: Section 6.19
 
+use core::marker::PhantomData;
 
 use rand_core::{CryptoRng, RngCore};
 
@@ -10,30 +18,98 @@ use crate::target::RNG;
 
 /// Interface to the RNG peripheral
 ///
-/// Right now, this is very basic, only providing blocking interfaces.
-pub struct Rng(RNG);
+/// The type parameter tracks whether bias correction is enabled.
+pub struct Rng<B = BiasCorrected> {
+    periph: RNG,
+    stop_when_idle: bool,
+    _bias: PhantomData<B>,
+}
+
+/// Bias correction is enabled
+pub struct BiasCorrected;
+
+/// Bias correction is disabled
+pub struct Uncorrected;
 
 impl Rng {
+    /// Take ownership of the RNG peripheral
+    ///
+    /// Bias correction is enabled, which ensures a uniform distribution of
+    /// the generated bits at the cost of a lower and non-deterministic rate.
     pub fn new(rng: RNG) -> Self {
+        Self::with_config(rng, RngConfig::default())
+    }
+
+    /// Take ownership of the RNG peripheral, using the given configuration
+    pub fn with_config(rng: RNG, config: RngConfig) -> Self {
         rng.config.write(|w| w.dercen().enabled());
-        Self(rng)
+        let mut rng = Rng {
+            periph: rng,
+            stop_when_idle: true,
+            _bias: PhantomData,
+        };
+        rng.set_stop_when_idle(config.stop_when_idle);
+        rng
+    }
+
+    /// Disable bias correction
+    ///
+    /// Without bias correction, bytes are generated about four times faster,
+    /// but the distribution of the bits is not guaranteed to be uniform, so
+    /// the result doesn't implement `CryptoRng`.
+    pub fn into_uncorrected(self) -> Rng<Uncorrected> {
+        self.periph.config.write(|w| w.dercen().disabled());
+        Rng {
+            periph: self.periph,
+            stop_when_idle: self.stop_when_idle,
+            _bias: PhantomData,
+        }
+    }
+}
+
+impl Rng<Uncorrected> {
+    /// Enable bias correction again
+    pub fn into_bias_corrected(self) -> Rng {
+        self.periph.config.write(|w| w.dercen().enabled());
+        Rng {
+            periph: self.periph,
+            stop_when_idle: self.stop_when_idle,
+            _bias: PhantomData,
+        }
+    }
+}
+
+impl<B> Rng<B> {
+    /// Stop the RNG after every request, or keep it running
+    ///
+    /// Stopping saves power between requests. Keeping it running avoids the
+    /// startup delay of the first byte of every request.
+    pub fn set_stop_when_idle(&mut self, stop: bool) {
+        self.stop_when_idle = stop;
+        if stop {
+            self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+        } else {
+            self.periph.tasks_start.write(|w| unsafe { w.bits(1) });
+        }
     }
 
     /// Fill the provided buffer with random bytes
     ///
     /// Will block until the buffer is full.
     pub fn random(&mut self, buf: &mut [u8]) {
-        self.0.tasks_start.write(|w| unsafe { w.bits(1) });
+        self.periph.tasks_start.write(|w| unsafe { w.bits(1) });
 
         for b in buf {
             // Wait for random byte to become ready, reset the flag once it is
-            while self.0.events_valrdy.read().bits() == 0 {}
-            self.0.events_valrdy.write(|w| unsafe { w.bits(0) });
+            while self.periph.events_valrdy.read().bits() == 0 {}
+            self.periph.events_valrdy.write(|w| unsafe { w.bits(0) });
 
-            *b = self.0.value.read().value().bits();
+            *b = self.periph.value.read().value().bits();
         }
 
-        self.0.tasks_stop.write(|w| unsafe { w.bits(1) });
+        if self.stop_when_idle {
+            self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+        }
     }
 
     /// Return a random `u8`
@@ -74,9 +150,153 @@ impl Rng {
             (buf[6] as u64) << 48 |
             (buf[7] as u64) << 56
     }
+
+    /// Generate random bytes in the background, collecting them in `buffer`
+    ///
+    /// See `RngPool`.
+    pub fn into_pool(self, buffer: &mut [u8]) -> RngPool<'_, B> {
+        let mut pool = RngPool {
+            rng: self,
+            buffer,
+            read: 0,
+            len: 0,
+        };
+        pool.refill();
+        pool
+    }
+
+    /// Return the underlying RNG peripheral
+    pub fn free(self) -> RNG {
+        self.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+        self.periph
+    }
+}
+
+/// Configuration of the RNG
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RngConfig {
+    /// Stop the RNG when no bytes are requested
+    pub stop_when_idle: bool,
+}
+
+impl Default for RngConfig {
+    fn default() -> Self {
+        RngConfig {
+            stop_when_idle: true,
+        }
+    }
+}
+
+/// Random bytes collected in the background
+///
+/// The RNG generates bytes into a ring buffer using the VALRDY interrupt, so
+/// requests can usually be served without waiting for the RNG. Once the
+/// buffer is full, the RNG is stopped until bytes are taken out again.
+///
+/// `on_interrupt` must be called from the RNG interrupt handler, and the RNG
+/// interrupt must be unmasked in the NVIC. Requests larger than the bytes
+/// available block until the RNG has generated the rest.
+pub struct RngPool<'a, B = BiasCorrected> {
+    rng: Rng<B>,
+    buffer: &'a mut [u8],
+    read: usize,
+    len: usize,
+}
+
+impl<'a, B> RngPool<'a, B> {
+    /// Collect a generated byte
+    ///
+    /// To be called from the RNG interrupt handler.
+    pub fn on_interrupt(&mut self) {
+        if self.rng.periph.events_valrdy.read().bits() == 0 {
+            return;
+        }
+        self.rng.periph.events_valrdy.write(|w| unsafe { w.bits(0) });
+
+        if self.len < self.buffer.len() {
+            let write = (self.read + self.len) % self.buffer.len();
+            self.buffer[write] = self.rng.periph.value.read().value().bits();
+            self.len += 1;
+        }
+
+        if self.len == self.buffer.len() {
+            if self.rng.stop_when_idle {
+                self.rng.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+            }
+            self.rng.periph.intenclr.write(|w| w.valrdy().clear());
+        }
+    }
+
+    /// Return the number of bytes that can be taken without waiting
+    pub fn available(&self) -> usize {
+        self.len
+    }
+
+    /// Fill the provided buffer with random bytes
+    ///
+    /// Takes bytes from the pool first, and blocks for the rest.
+    pub fn random(&mut self, buf: &mut [u8]) {
+        for b in buf {
+            if self.len == 0 {
+                // The interrupt may be pending already, `on_interrupt` will
+                // find the event cleared
+                self.rng.periph.tasks_start.write(|w| unsafe { w.bits(1) });
+                while self.rng.periph.events_valrdy.read().bits() == 0 {}
+                self.rng.periph.events_valrdy.write(|w| unsafe { w.bits(0) });
+                *b = self.rng.periph.value.read().value().bits();
+            } else {
+                *b = self.buffer[self.read];
+                self.read = (self.read + 1) % self.buffer.len();
+                self.len -= 1;
+            }
+        }
+
+        self.refill();
+    }
+
+    /// Stop generating bytes, and return the RNG
+    pub fn free(self) -> Rng<B> {
+        self.rng.periph.intenclr.write(|w| w.valrdy().clear());
+        if self.rng.stop_when_idle {
+            self.rng.periph.tasks_stop.write(|w| unsafe { w.bits(1) });
+        }
+        self.rng
+    }
+
+    fn refill(&mut self) {
+        if self.len < self.buffer.len() {
+            self.rng.periph.intenset.write(|w| w.valrdy().set());
+            self.rng.periph.tasks_start.write(|w| unsafe { w.bits(1) });
+        }
+    }
 }
 
-impl RngCore for Rng {
+impl<'a, B> RngCore for RngPool<'a, B> {
+    fn next_u32(&mut self) -> u32 {
+        let mut buf = [0; 4];
+        self.random(&mut buf);
+        u32::from_le_bytes(buf)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut buf = [0; 8];
+        self.random(&mut buf);
+        u64::from_le_bytes(buf)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.random(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        Ok(self.fill_bytes(dest))
+    }
+}
+
+impl<'a> CryptoRng for RngPool<'a> {}
+
+impl<B> RngCore for Rng<B> {
     fn next_u32(&mut self) -> u32 {
         self.random_u32()
     }
@@ -1,11 +1,21 @@
 //! A high level interface for RTC peripherals
 
+#[cfg(feature = "async")]
+use core::future::poll_fn;
 use core::ops::Deref;
+#[cfg(feature = "async")]
+use core::task::Poll;
 
+#[cfg(feature = "async")]
+use cortex_m::interrupt::Nr;
+
+use crate::irq::Irq;
 use crate::target::{rtc0, Interrupt, NVIC, RTC0, RTC1};
 
-#[cfg(not(feature = "52810"))]
+#[cfg(rtc2)]
 use crate::target::RTC2;
+#[cfg(feature = "async")]
+use crate::waker;
 
 // Zero Size Type State structs
 
@@ -17,6 +27,7 @@ pub struct Started;
 /// An opaque high level interface to an RTC peripheral
 pub struct Rtc<T, M> {
     periph: T,
+    irq: Option<Irq>,
     _mode: M,
 }
 
@@ -27,6 +38,7 @@ where
     pub fn new(rtc: T) -> Self {
         Rtc {
             periph: rtc,
+            irq: None,
             _mode: Stopped,
         }
     }
@@ -43,6 +55,7 @@ pub enum RtcInterrupt {
 }
 
 /// Compare registers available on the RTCn
+#[derive(Clone, Copy)]
 pub enum RtcCompareReg {
     Compare0,
     Compare1,
@@ -61,6 +74,7 @@ where
         }
         Rtc {
             periph: self.periph,
+            irq: self.irq,
             _mode: Started,
         }
     }
@@ -72,6 +86,7 @@ where
         }
         Rtc {
             periph: self.periph,
+            irq: self.irq,
             _mode: Stopped,
         }
     }
@@ -161,6 +176,30 @@ where
         orig == 1
     }
 
+    /// Return whether a given interrupt/event has been generated
+    pub fn is_event_triggered(&self, evt: RtcInterrupt) -> bool {
+        match evt {
+            RtcInterrupt::Tick => self.periph.events_tick.read().bits() != 0,
+            RtcInterrupt::Overflow => self.periph.events_ovrflw.read().bits() != 0,
+            RtcInterrupt::Compare0 => self.periph.events_compare[0].read().bits() != 0,
+            RtcInterrupt::Compare1 => self.periph.events_compare[1].read().bits() != 0,
+            RtcInterrupt::Compare2 => self.periph.events_compare[2].read().bits() != 0,
+            RtcInterrupt::Compare3 => self.periph.events_compare[3].read().bits() != 0,
+        }
+    }
+
+    /// Reset a given interrupt/event
+    pub fn reset_event(&mut self, evt: RtcInterrupt) {
+        match evt {
+            RtcInterrupt::Tick => self.periph.events_tick.write(|w| w),
+            RtcInterrupt::Overflow => self.periph.events_ovrflw.write(|w| w),
+            RtcInterrupt::Compare0 => self.periph.events_compare[0].write(|w| w),
+            RtcInterrupt::Compare1 => self.periph.events_compare[1].write(|w| w),
+            RtcInterrupt::Compare2 => self.periph.events_compare[2].write(|w| w),
+            RtcInterrupt::Compare3 => self.periph.events_compare[3].write(|w| w),
+        }
+    }
+
     /// Set the compare value of a given register. The compare registers have a width
     /// of 24 bits
     pub fn set_compare(&mut self, reg: RtcCompareReg, val: u32) -> Result<(), Error> {
@@ -182,11 +221,58 @@ where
         Ok(())
     }
 
+    /// Return the COMPARE event of a given compare register, for use as a PPI
+    /// event end point
+    pub fn event_compare(&self, reg: RtcCompareReg) -> &rtc0::EVENTS_COMPARE {
+        let reg = match reg {
+            RtcCompareReg::Compare0 => 0,
+            RtcCompareReg::Compare1 => 1,
+            RtcCompareReg::Compare2 => 2,
+            RtcCompareReg::Compare3 => 3,
+        };
+
+        &self.periph.events_compare[reg]
+    }
+
     /// Obtain the current value of the Real Time Counter, 24 bits of range
     pub fn get_counter(&self) -> u32 {
         self.periph.counter.read().bits()
     }
 
+    /// Take ownership of the interrupt of the RTC instance, to be accessed
+    /// through `irq`
+    ///
+    /// Panics if `irq` isn't the interrupt of the instance.
+    pub fn with_irq(mut self, irq: Irq) -> Self {
+        assert!(irq.is(T::INTERRUPT));
+        self.irq = Some(irq);
+        self
+    }
+
+    /// Return the interrupt of the instance, if owned, see `with_irq`
+    pub fn irq(&mut self) -> Option<&mut Irq> {
+        self.irq.as_mut()
+    }
+
+    /// Wake the future waiting for a `Deadline`, to be called from the
+    /// interrupt handler of the instance
+    ///
+    /// The COMPARE event is left set, for the deadline to see it.
+    #[cfg(feature = "async")]
+    pub fn handle_interrupt() {
+        // This is safe, as only the COMPARE interrupt enables are written to,
+        // which are owned by the deadlines
+        let rtc = unsafe { &*T::ptr() };
+        let inten = rtc.intenset.read().bits();
+        for n in 0..4 {
+            let mask = 1 << (16 + n);
+            if inten & mask != 0 && rtc.events_compare[n].read().bits() != 0 {
+                rtc.intenclr.write(|w| unsafe { w.bits(mask) });
+                waker::wake(T::INTERRUPT);
+            }
+        }
+    }
+
     /// Destructure the high level interface. Does not reset any configuration made
     /// to the given RTC peripheral
     pub fn release(self) -> T {
@@ -194,8 +280,98 @@ where
     }
 }
 
+impl<T> Rtc<T, Started>
+where
+    T: Instance,
+{
+    /// Start a deadline that elapses after `ticks` periods of the RTC, using
+    /// the given compare register
+    ///
+    /// The deadline must be shorter than the 24 bits range of the counter.
+    /// As the RTC can't match a compare value less than two ticks ahead, it
+    /// elapses after at least two ticks.
+    pub fn deadline(&mut self, reg: RtcCompareReg, ticks: u32) -> Result<Deadline<'_, T>, Error> {
+        if ticks >= (1 << 24) {
+            return Err(Error::CompareOutOfRange);
+        }
+
+        let reg = match reg {
+            RtcCompareReg::Compare0 => 0,
+            RtcCompareReg::Compare1 => 1,
+            RtcCompareReg::Compare2 => 2,
+            RtcCompareReg::Compare3 => 3,
+        };
+
+        let target = (self.get_counter() + ticks.max(2)) & 0xFF_FFFF;
+        unsafe {
+            self.periph.cc[reg].write(|w| w.bits(target));
+        }
+        self.periph.events_compare[reg].write(|w| w);
+
+        Ok(Deadline { rtc: self, reg })
+    }
+}
+
+/// A point in time, relative to the counter of a started RTC
+///
+/// Created with `Rtc::deadline`. Dropping it frees its compare register.
+pub struct Deadline<'a, T>
+where
+    T: Instance,
+{
+    rtc: &'a mut Rtc<T, Started>,
+    reg: usize,
+}
+
+impl<'a, T> Deadline<'a, T>
+where
+    T: Instance,
+{
+    /// Return whether the deadline has elapsed
+    pub fn is_elapsed(&self) -> bool {
+        self.rtc.periph.events_compare[self.reg].read().bits() != 0
+    }
+
+    /// Wait until the deadline has elapsed, without blocking
+    ///
+    /// The future is woken through the interrupt of the instance, whose
+    /// handler must call `Rtc::<T, _>::handle_interrupt`. The interrupt must
+    /// be unmasked in the NVIC.
+    #[cfg(feature = "async")]
+    pub async fn elapsed(&mut self) {
+        let nr = T::INTERRUPT.nr();
+        let mask = 1 << (16 + self.reg);
+
+        poll_fn(|cx| {
+            // Register before checking, so the compare can't be missed in
+            // between
+            waker::register_nr(nr, cx.waker());
+            self.rtc.periph.intenset.write(|w| unsafe { w.bits(mask) });
+
+            if self.is_elapsed() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl<'a, T> Drop for Deadline<'a, T>
+where
+    T: Instance,
+{
+    fn drop(&mut self) {
+        let mask = 1 << (16 + self.reg);
+        self.rtc.periph.intenclr.write(|w| unsafe { w.bits(mask) });
+        self.rtc.periph.events_compare[self.reg].write(|w| w);
+    }
+}
+
 /// Error types associated with the RTC peripheral interface
 #[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     PrescalerOutOfRange,
     CompareOutOfRange,
@@ -218,11 +394,13 @@ where
     }
 }
 
-
 /// Implemented by all RTC instances
 pub trait Instance: Deref<Target = rtc0::RegisterBlock> {
     /// This interrupt associated with this RTC instance
     const INTERRUPT: Interrupt;
+
+    /// Return a pointer to the register block of the instance
+    fn ptr() -> *const rtc0::RegisterBlock;
 }
 
 macro_rules! impl_instance {
@@ -230,6 +408,10 @@ macro_rules! impl_instance {
         $(
             impl Instance for $name {
                 const INTERRUPT: Interrupt = Interrupt::$name;
+
+                fn ptr() -> *const rtc0::RegisterBlock {
+                    $name::ptr()
+                }
             }
         )*
     }
@@ -237,5 +419,5 @@ macro_rules! impl_instance {
 
 impl_instance!(RTC0, RTC1,);
 
-#[cfg(not(feature = "52810"))]
+#[cfg(rtc2)]
 impl_instance!(RTC2,);
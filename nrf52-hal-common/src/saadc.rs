@@ -3,6 +3,7 @@ use crate::{
     target::SAADC,
 };
 use core::{
+    cell::UnsafeCell,
     hint::unreachable_unchecked,
     sync::atomic::{compiler_fence, Ordering::SeqCst},
 };
@@ -66,37 +67,14 @@ impl Saadc {
 
         Saadc(saadc)
     }
-}
 
-pub struct SaadcConfig {
-    resolution: Resolution,
-    oversample: Oversample,
-    reference: Reference,
-    gain: Gain,
-    resistor: Resistor,
-    time: Time,
-}
-
-// 0 volts reads as 0, VDD volts reads as u16::MAX
-impl Default for SaadcConfig {
-    fn default() -> Self {
-        SaadcConfig {
-            resolution: Resolution::_14BIT,
-            oversample: Oversample::OVER8X,
-            reference: Reference::VDD1_4,
-            gain: Gain::GAIN1_4,
-            resistor: Resistor::BYPASS,
-            time: Time::_20US,
-        }
-    }
-}
-
-impl<PIN> OneShot<Saadc, u16, PIN> for Saadc
-where
-    PIN: Channel<Saadc, ID = u8>,
-{
-    type Error = ();
-    fn read(&mut self, _pin: &mut PIN) -> nb::Result<u16, Self::Error> {
+    /// Start sampling `pin`, without blocking
+    ///
+    /// The sample is retrieved by calling `poll_sample`.
+    pub fn start_sample<PIN>(&mut self, _pin: &mut PIN)
+    where
+        PIN: Channel<Saadc, ID = u8>,
+    {
         match PIN::channel() {
             0 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input0()),
             1 => self.0.ch[0].pselp.write(|w| w.pselp().analog_input1()),
@@ -111,11 +89,10 @@ where
             _ => unsafe { unreachable_unchecked() },
         }
 
-        let mut val: u16 = 0;
         self.0
             .result
             .ptr
-            .write(|w| unsafe { w.ptr().bits(((&mut val) as *mut _) as u32) });
+            .write(|w| unsafe { w.ptr().bits(SAMPLE.0.get() as u32) });
         self.0
             .result
             .maxcnt
@@ -127,8 +104,13 @@ where
 
         self.0.tasks_start.write(|w| unsafe { w.bits(1) });
         self.0.tasks_sample.write(|w| unsafe { w.bits(1) });
+    }
 
-        while self.0.events_end.read().bits() == 0 {}
+    /// Return the sample started by `start_sample` once it is ready
+    pub fn poll_sample(&mut self) -> nb::Result<u16, ()> {
+        if self.0.events_end.read().bits() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
         self.0.events_end.reset();
 
         // Will only occur if more than one channel has been enabled
@@ -136,10 +118,139 @@ where
             return Err(nb::Error::Other(()));
         }
 
-        // Second fence to prevent optimizations creating issues with the EasyDMA-modified `val`
+        // Second fence to prevent optimizations creating issues with the EasyDMA-modified sample
         compiler_fence(SeqCst);
 
-        Ok(val)
+        // EasyDMA is done with the sample, and it is only accessed through
+        // the `Saadc`
+        Ok(unsafe { *SAMPLE.0.get() })
+    }
+
+    /// Enable the interrupt for a given event
+    ///
+    /// The interrupt of the instance must be unmasked in the NVIC separately.
+    pub fn enable_interrupt(&mut self, evt: SaadcEvent) {
+        match evt {
+            SaadcEvent::Started => self.0.intenset.write(|w| w.started().set()),
+            SaadcEvent::End => self.0.intenset.write(|w| w.end().set()),
+            SaadcEvent::Done => self.0.intenset.write(|w| w.done().set()),
+            SaadcEvent::ResultDone => self.0.intenset.write(|w| w.resultdone().set()),
+            SaadcEvent::CalibrateDone => self.0.intenset.write(|w| w.calibratedone().set()),
+            SaadcEvent::Stopped => self.0.intenset.write(|w| w.stopped().set()),
+        }
+    }
+
+    /// Disable the interrupt for a given event
+    pub fn disable_interrupt(&mut self, evt: SaadcEvent) {
+        match evt {
+            SaadcEvent::Started => self.0.intenclr.write(|w| w.started().clear()),
+            SaadcEvent::End => self.0.intenclr.write(|w| w.end().clear()),
+            SaadcEvent::Done => self.0.intenclr.write(|w| w.done().clear()),
+            SaadcEvent::ResultDone => self.0.intenclr.write(|w| w.resultdone().clear()),
+            SaadcEvent::CalibrateDone => self.0.intenclr.write(|w| w.calibratedone().clear()),
+            SaadcEvent::Stopped => self.0.intenclr.write(|w| w.stopped().clear()),
+        }
+    }
+
+    /// Return whether a given event has been generated
+    pub fn is_event_triggered(&self, evt: SaadcEvent) -> bool {
+        match evt {
+            SaadcEvent::Started => self.0.events_started.read().bits() != 0,
+            SaadcEvent::End => self.0.events_end.read().bits() != 0,
+            SaadcEvent::Done => self.0.events_done.read().bits() != 0,
+            SaadcEvent::ResultDone => self.0.events_resultdone.read().bits() != 0,
+            SaadcEvent::CalibrateDone => self.0.events_calibratedone.read().bits() != 0,
+            SaadcEvent::Stopped => self.0.events_stopped.read().bits() != 0,
+        }
+    }
+
+    /// Reset a given event
+    pub fn reset_event(&mut self, evt: SaadcEvent) {
+        match evt {
+            SaadcEvent::Started => self.0.events_started.write(|w| w),
+            SaadcEvent::End => self.0.events_end.write(|w| w),
+            SaadcEvent::Done => self.0.events_done.write(|w| w),
+            SaadcEvent::ResultDone => self.0.events_resultdone.write(|w| w),
+            SaadcEvent::CalibrateDone => self.0.events_calibratedone.write(|w| w),
+            SaadcEvent::Stopped => self.0.events_stopped.write(|w| w),
+        }
+    }
+}
+
+/// The sample is written by EasyDMA to a static, rather than to the stack, so
+/// it can still be written once `start_sample` has returned
+struct Sample(UnsafeCell<u16>);
+
+// There is only one SAADC, and the sample is only accessed through it
+unsafe impl Sync for Sample {}
+
+static SAMPLE: Sample = Sample(UnsafeCell::new(0));
+
+/// Events that can be generated by the SAADC peripheral
+#[derive(Clone, Copy, Debug)]
+pub enum SaadcEvent {
+    /// The ADC has started
+    Started,
+    /// The result buffer is full
+    End,
+    /// A conversion has completed
+    Done,
+    /// A result is ready to be transferred to RAM
+    ResultDone,
+    /// The offset calibration has completed
+    CalibrateDone,
+    /// The ADC has been stopped
+    Stopped,
+}
+
+pub struct SaadcConfig {
+    resolution: Resolution,
+    oversample: Oversample,
+    reference: Reference,
+    gain: Gain,
+    resistor: Resistor,
+    time: Time,
+}
+
+// 0 volts reads as 0, VDD volts reads as u16::MAX
+impl Default for SaadcConfig {
+    fn default() -> Self {
+        SaadcConfig {
+            resolution: Resolution::_14BIT,
+            oversample: Oversample::OVER8X,
+            reference: Reference::VDD1_4,
+            gain: Gain::GAIN1_4,
+            resistor: Resistor::BYPASS,
+            time: Time::_20US,
+        }
+    }
+}
+
+// The fields are register values of the PAC, which are formatted raw
+#[cfg(feature = "defmt")]
+impl defmt::Format for SaadcConfig {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "SaadcConfig {{ resolution: {=u8}, oversample: {=u8}, reference: {=bool}, gain: {=u8}, resistor: {=u8}, time: {=u8} }}",
+            self.resolution._bits(),
+            self.oversample._bits(),
+            self.reference._bits(),
+            self.gain._bits(),
+            self.resistor._bits(),
+            self.time._bits(),
+        )
+    }
+}
+
+impl<PIN> OneShot<Saadc, u16, PIN> for Saadc
+where
+    PIN: Channel<Saadc, ID = u8>,
+{
+    type Error = ();
+    fn read(&mut self, pin: &mut PIN) -> nb::Result<u16, Self::Error> {
+        self.start_sample(pin);
+        nb::block!(self.poll_sample()).map_err(nb::Error::Other)
     }
 }
 
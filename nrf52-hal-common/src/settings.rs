@@ -0,0 +1,333 @@
+//! Flash backed key-value settings store
+//!
+//! Settings are appended as records to one page of a ring of flash pages,
+//! where a later record for a key replaces the earlier ones. When the page is
+//! full, the current value of every key is copied to the next page of the
+//! ring, spreading the erase cycles over all pages.
+//!
+//! Every record is protected by a CRC, so a record that was interrupted by a
+//! reset is ignored. A new page only becomes valid once all records have been
+//! copied to it.
+use embedded_storage::nor_flash::ReadNorFlash;
+
+use crate::nvmc::{self, crc32_update, Nvmc, PAGE_SIZE, WORD_SIZE};
+
+/// Marks a page that belongs to the settings store
+const PAGE_MAGIC: u32 = 0x5345_5454;
+/// Magic and sequence number
+const PAGE_HEADER_SIZE: usize = 8;
+/// Key and length, followed by the CRC
+const RECORD_HEADER_SIZE: usize = 8;
+const ERASED: u32 = 0xFFFF_FFFF;
+
+/// Largest value that can be stored
+pub const MAX_VALUE_LEN: usize = PAGE_SIZE - PAGE_HEADER_SIZE - RECORD_HEADER_SIZE;
+
+/// Flash backed key-value store
+pub struct Settings {
+    nvmc: Nvmc,
+    pages: usize,
+    active: usize,
+    sequence: u32,
+    free: usize,
+}
+
+/// Location of a record within a page
+#[derive(Clone, Copy)]
+struct Record {
+    offset: usize,
+    key: u16,
+    len: usize,
+}
+
+impl Record {
+    fn size(&self) -> usize {
+        RECORD_HEADER_SIZE + padded(self.len)
+    }
+}
+
+impl Settings {
+    /// Create a settings store in the flash region of `nvmc`
+    ///
+    /// The region must span at least two pages. If it does not contain a
+    /// settings store yet, a new, empty one is created.
+    pub fn new(nvmc: Nvmc) -> Result<Self, Error> {
+        let pages = nvmc.capacity() / PAGE_SIZE;
+        if pages < 2 {
+            return Err(Error::TooFewPages);
+        }
+
+        let mut settings = Settings {
+            nvmc,
+            pages,
+            active: 0,
+            sequence: 0,
+            free: PAGE_HEADER_SIZE,
+        };
+
+        let mut active = None;
+        for page in 0..pages {
+            if settings.read_word(page, 0)? != PAGE_MAGIC {
+                continue;
+            }
+            let sequence = settings.read_word(page, 4)?;
+            match active {
+                Some((_, s)) if s >= sequence => {}
+                _ => active = Some((page, sequence)),
+            }
+        }
+
+        match active {
+            Some((page, sequence)) => {
+                settings.active = page;
+                settings.sequence = sequence;
+                settings.free = settings.find_free(page)?;
+            }
+            None => {
+                settings.nvmc.erase_page(0)?;
+                settings.write_page_header(0, 0)?;
+            }
+        }
+
+        Ok(settings)
+    }
+
+    /// Read the value of `key` into `buffer`
+    ///
+    /// Returns the length of the value, or `None` if the key is not set.
+    pub fn get(&mut self, key: u16, buffer: &mut [u8]) -> Result<Option<usize>, Error> {
+        let record = match self.find_latest(key)? {
+            Some(record) if record.len > 0 => record,
+            _ => return Ok(None),
+        };
+
+        if buffer.len() < record.len {
+            return Err(Error::BufferTooShort);
+        }
+
+        let address = self.address(self.active, record.offset + RECORD_HEADER_SIZE);
+        self.nvmc.read(address, &mut buffer[..record.len])?;
+
+        Ok(Some(record.len))
+    }
+
+    /// Set the value of `key`
+    ///
+    /// Keys can be any value except `0xFFFF`. Setting an empty value removes
+    /// the key.
+    pub fn set(&mut self, key: u16, value: &[u8]) -> Result<(), Error> {
+        if key == 0xFFFF {
+            return Err(Error::InvalidKey);
+        }
+        if value.len() > MAX_VALUE_LEN {
+            return Err(Error::ValueTooLarge);
+        }
+
+        let size = RECORD_HEADER_SIZE + padded(value.len());
+        if self.free + size > PAGE_SIZE {
+            self.compact()?;
+            if self.free + size > PAGE_SIZE {
+                return Err(Error::Full);
+            }
+        }
+
+        let header = record_header(key, value.len());
+        let crc = !crc32_update(crc32_update(!0, &header.to_le_bytes()), value);
+
+        let (page, offset) = (self.active, self.free);
+        self.free += size;
+
+        self.write_word(page, offset, header)?;
+        self.write_word(page, offset + 4, crc)?;
+        for (i, chunk) in value.chunks(WORD_SIZE).enumerate() {
+            let mut word = [0xFF; WORD_SIZE];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.write_word(
+                page,
+                offset + RECORD_HEADER_SIZE + i * WORD_SIZE,
+                u32::from_le_bytes(word),
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Remove `key`
+    pub fn remove(&mut self, key: u16) -> Result<(), Error> {
+        match self.find_latest(key)? {
+            Some(record) if record.len > 0 => self.set(key, &[]),
+            _ => Ok(()),
+        }
+    }
+
+    /// Return the underlying NVMC
+    pub fn free(self) -> Nvmc {
+        self.nvmc
+    }
+
+    /// Copy the current value of every key to the next page
+    fn compact(&mut self) -> Result<(), Error> {
+        let (from, to) = (self.active, (self.active + 1) % self.pages);
+        self.nvmc.erase_page(to as u32 * PAGE_SIZE as u32)?;
+
+        let mut dst = PAGE_HEADER_SIZE;
+        let mut offset = PAGE_HEADER_SIZE;
+        while let Some(record) = self.next_record(from, offset)? {
+            offset += record.size();
+
+            if record.len == 0 || !self.is_valid(from, record)? {
+                continue;
+            }
+            if self.is_replaced(from, record)? {
+                continue;
+            }
+
+            for i in (0..record.size()).step_by(WORD_SIZE) {
+                let word = self.read_word(from, record.offset + i)?;
+                self.write_word(to, dst + i, word)?;
+            }
+            dst += record.size();
+        }
+
+        // The new page only becomes valid once everything has been copied
+        self.write_page_header(to, self.sequence.wrapping_add(1))?;
+        self.active = to;
+        self.sequence = self.sequence.wrapping_add(1);
+        self.free = dst;
+
+        Ok(())
+    }
+
+    /// Find the latest valid record of `key` in the active page
+    fn find_latest(&mut self, key: u16) -> Result<Option<Record>, Error> {
+        let mut latest = None;
+
+        let mut offset = PAGE_HEADER_SIZE;
+        while let Some(record) = self.next_record(self.active, offset)? {
+            offset += record.size();
+            if record.key == key && self.is_valid(self.active, record)? {
+                latest = Some(record);
+            }
+        }
+
+        Ok(latest)
+    }
+
+    /// Is there a later valid record for the same key?
+    fn is_replaced(&mut self, page: usize, record: Record) -> Result<bool, Error> {
+        let mut offset = record.offset + record.size();
+        while let Some(later) = self.next_record(page, offset)? {
+            offset += later.size();
+            if later.key == record.key && self.is_valid(page, later)? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Return the record at `offset`, if there is one
+    fn next_record(&mut self, page: usize, offset: usize) -> Result<Option<Record>, Error> {
+        if offset + RECORD_HEADER_SIZE > PAGE_SIZE {
+            return Ok(None);
+        }
+
+        let header = self.read_word(page, offset)?;
+        if header == ERASED {
+            return Ok(None);
+        }
+
+        let record = Record {
+            offset,
+            key: header as u16,
+            len: (header >> 16) as usize,
+        };
+        if offset + record.size() > PAGE_SIZE {
+            return Ok(None);
+        }
+
+        Ok(Some(record))
+    }
+
+    /// Check the CRC of a record
+    fn is_valid(&mut self, page: usize, record: Record) -> Result<bool, Error> {
+        let header = record_header(record.key, record.len);
+        let expected = self.read_word(page, record.offset + 4)?;
+
+        let mut crc = crc32_update(!0, &header.to_le_bytes());
+        let mut remaining = record.len;
+        let mut offset = record.offset + RECORD_HEADER_SIZE;
+        while remaining > 0 {
+            let word = self.read_word(page, offset)?.to_le_bytes();
+            let n = remaining.min(WORD_SIZE);
+            crc = crc32_update(crc, &word[..n]);
+            remaining -= n;
+            offset += WORD_SIZE;
+        }
+
+        Ok(!crc == expected)
+    }
+
+    /// Find the offset of the first free record in a page
+    fn find_free(&mut self, page: usize) -> Result<usize, Error> {
+        let mut offset = PAGE_HEADER_SIZE;
+        while let Some(record) = self.next_record(page, offset)? {
+            offset += record.size();
+        }
+
+        // A corrupted header may claim more space than is left in the page
+        if offset + RECORD_HEADER_SIZE <= PAGE_SIZE
+            && self.read_word(page, offset)? != ERASED
+        {
+            return Ok(PAGE_SIZE);
+        }
+
+        Ok(offset)
+    }
+
+    fn write_page_header(&mut self, page: usize, sequence: u32) -> Result<(), Error> {
+        self.write_word(page, 4, sequence)?;
+        self.write_word(page, 0, PAGE_MAGIC)
+    }
+
+    fn read_word(&mut self, page: usize, offset: usize) -> Result<u32, Error> {
+        let mut word = [0; WORD_SIZE];
+        self.nvmc.read(self.address(page, offset), &mut word)?;
+        Ok(u32::from_le_bytes(word))
+    }
+
+    fn write_word(&mut self, page: usize, offset: usize, word: u32) -> Result<(), Error> {
+        self.nvmc.write_word(self.address(page, offset), word)?;
+        Ok(())
+    }
+
+    fn address(&self, page: usize, offset: usize) -> u32 {
+        (page * PAGE_SIZE + offset) as u32
+    }
+}
+
+fn record_header(key: u16, len: usize) -> u32 {
+    u32::from(key) | (len as u32) << 16
+}
+
+/// Round `len` up to a multiple of the word size
+fn padded(len: usize) -> usize {
+    (len + WORD_SIZE - 1) / WORD_SIZE * WORD_SIZE
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    Flash(nvmc::Error),
+    TooFewPages,
+    InvalidKey,
+    ValueTooLarge,
+    BufferTooShort,
+    Full,
+}
+
+impl From<nvmc::Error> for Error {
+    fn from(e: nvmc::Error) -> Self {
+        Error::Flash(e)
+    }
+}
@@ -1,31 +1,49 @@
 //! HAL interface to the SPIM peripheral
 //!
-//! See product specification, chapter 31.
+//! See product specification:
+//!
+//! - nrf52832: Section 31
+//! - nrf52840: Section 6.25
 use core::ops::Deref;
+use core::slice::{self, ChunksMut};
 use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
 
 pub use crate::target::spim0::frequency::FREQUENCYW as Frequency;
 pub use embedded_hal::spi::{Mode, Phase, Polarity, MODE_0, MODE_1, MODE_2, MODE_3};
 
-use crate::target::{spim0, SPIM0};
+use crate::target::{spim0, Interrupt, SPIM0};
 use core::iter::repeat_with;
+use embedded_dma::{ReadBuffer, WriteBuffer};
 
-#[cfg(any(feature = "52832", feature = "52840"))]
-use crate::target::{SPIM1, SPIM2};
+#[cfg(spim1)]
+use crate::target::SPIM1;
+#[cfg(spim2)]
+use crate::target::SPIM2;
+#[cfg(spim3)]
+use crate::target::SPIM3;
 
+use crate::dma::{self, ArrayList, Notifier, Progress, Transfer, Transferable};
 use crate::gpio::{Floating, Input, Output, Pin, PushPull};
+use crate::hal::digital::v2;
+use crate::irq::{EventFlags, Irq};
 use crate::prelude::*;
-use crate::target_constants::{EASY_DMA_SIZE, FORCE_COPY_BUFFER_SIZE};
+use crate::target_constants::FORCE_COPY_BUFFER_SIZE;
+#[cfg(feature = "async")]
+use crate::waker;
 use crate::{slice_in_ram, DmaSlice};
 
 
+/// Maximum length of a single EasyDMA transfer, which depends on the width of
+/// the MAXCNT registers of the chip
+pub const MAX_DMA_LEN: usize = crate::target_constants::EASY_DMA_SIZE;
+
 /// Interface to a SPIM instance
 ///
 /// This is a very basic interface that comes with the following limitations:
 /// - The SPIM instances share the same address space with instances of SPIS,
 ///   SPI, TWIM, TWIS, and TWI. You need to make sure that conflicting instances
 ///   are disabled before using `Spim`. See product specification, section 15.2.
-pub struct Spim<T>(T);
+pub struct Spim<T>(T, Option<Irq>);
 
 impl<T> embedded_hal::blocking::spi::Transfer<u8> for Spim<T>
 where
@@ -37,7 +55,7 @@ where
         // If the slice isn't in RAM, we can't write back to it at all
         ram_slice_check(words)?;
 
-        words.chunks(EASY_DMA_SIZE).try_for_each(|chunk| {
+        dma::chunks(words).try_for_each(|chunk| {
             self.do_spi_dma_transfer(
                 DmaSlice::from_slice(chunk),
                 DmaSlice::from_slice(chunk),
@@ -57,21 +75,13 @@ where
     fn write<'w>(&mut self, words: &'w [u8]) -> Result<(), Error> {
         // Mask on segment where Data RAM is located on nrf52840 and nrf52832
         // Upper limit is choosen to entire area where DataRam can be placed
-        let needs_copy = !slice_in_ram(words);
-
-        let chunk_sz = if needs_copy {
-            FORCE_COPY_BUFFER_SIZE
-        } else {
-            EASY_DMA_SIZE
-        };
-
-        let step = if needs_copy {
-            Self::spi_dma_copy
+        if slice_in_ram(words) {
+            dma::chunks(words).try_for_each(|c| self.spi_dma_no_copy(c))
         } else {
-            Self::spi_dma_no_copy
-        };
-
-        words.chunks(chunk_sz).try_for_each(|c| step(self, c))
+            words
+                .chunks(FORCE_COPY_BUFFER_SIZE)
+                .try_for_each(|c| self.spi_dma_copy(c))
+        }
     }
 }
 impl<T> Spim<T>
@@ -146,7 +156,7 @@ where
             // there.
             unsafe { w.orc().bits(orc) });
 
-        Spim(spim)
+        Spim(spim, None)
     }
 
     /// Internal helper function to setup and execute SPIM DMA transfer
@@ -155,7 +165,19 @@ where
         tx: DmaSlice,
         rx: DmaSlice,
     ) -> Result<(), Error> {
+        self.start_spi_dma_transfer(tx, rx);
 
+        // Wait for END event
+        //
+        // This event is triggered once both transmitting and receiving are
+        // done.
+        while self.0.events_end.read().bits() == 0 {}
+
+        self.finish_spi_dma_transfer()
+    }
+
+    /// Internal helper function to setup and start SPIM DMA transfer
+    fn start_spi_dma_transfer(&mut self, tx: DmaSlice, rx: DmaSlice) {
         // Conservative compiler fence to prevent optimizations that do not
         // take in to account actions by DMA. The fence has been placed here,
         // before any DMA action has started
@@ -184,22 +206,19 @@ where
             // safe. Please refer to the explanation there.
             unsafe { w.maxcnt().bits(rx.len as _) });
 
+        if T::notifier().is_enabled() {
+            self.0.intenset.write(|w| w.end().set());
+        }
+
         // Start SPI transaction
         self.0.tasks_start.write(|w|
             // `1` is a valid value to write to task registers.
             unsafe { w.bits(1) });
+    }
 
-        // Conservative compiler fence to prevent optimizations that do not
-        // take in to account actions by DMA. The fence has been placed here,
-        // after all possible DMA actions have completed
-        compiler_fence(SeqCst);
-
-        // Wait for END event
-        //
-        // This event is triggered once both transmitting and receiving are
-        // done.
-        while self.0.events_end.read().bits() == 0 {}
-
+    /// Internal helper function to clean up after a SPIM DMA transfer has
+    /// ended, and check that all bytes were transferred
+    fn finish_spi_dma_transfer(&mut self) -> Result<(), Error> {
         // Reset the event, otherwise it will always read `1` from now on.
         self.0.events_end.write(|w| w);
 
@@ -208,10 +227,10 @@ where
         // after all possible DMA actions have completed
         compiler_fence(SeqCst);
 
-        if self.0.txd.amount.read().bits() != tx.len {
+        if self.0.txd.amount.read().bits() != self.0.txd.maxcnt.read().bits() {
             return Err(Error::Transmit);
         }
-        if self.0.rxd.amount.read().bits() != rx.len {
+        if self.0.rxd.amount.read().bits() != self.0.rxd.maxcnt.read().bits() {
             return Err(Error::Receive);
         }
         Ok(())
@@ -248,7 +267,7 @@ where
         chip_select.set_low();
 
         // Don't return early, as we must reset the CS pin
-        let res = buffer.chunks(EASY_DMA_SIZE).try_for_each(|chunk| {
+        let res = dma::chunks(buffer).try_for_each(|chunk| {
             self.do_spi_dma_transfer(
                 DmaSlice::from_slice(chunk),
                 DmaSlice::from_slice(chunk),
@@ -260,6 +279,45 @@ where
         res
     }
 
+    /// Start reading and writing from a SPI slave, using a single buffer,
+    /// without blocking
+    ///
+    /// This starts a transaction like `transfer`, which is driven by calling
+    /// `PendingTransfer::poll` until it no longer returns `WouldBlock`.
+    /// Dropping the `PendingTransfer` before stops the transaction. With
+    /// owned buffers, `dma_transfer_start` can be used safely instead.
+    ///
+    /// # Safety
+    ///
+    /// The `PendingTransfer` must not be leaked, e.g. with `mem::forget`, as
+    /// only dropping it stops EasyDMA from accessing `buffer` after the
+    /// borrow has ended.
+    pub unsafe fn start_transfer<'a>(
+        &'a mut self,
+        chip_select: &'a mut Pin<Output<PushPull>>,
+        buffer: &'a mut [u8],
+    ) -> Result<PendingTransfer<'a, T>, Error> {
+        ram_slice_check(buffer)?;
+
+        let mut chunks = dma::chunks_mut(buffer);
+        let first = chunks.next();
+
+        if let Some(chunk) = &first {
+            chip_select.set_low();
+            self.start_spi_dma_transfer(
+                DmaSlice::from_slice(chunk),
+                DmaSlice::from_slice(chunk),
+            );
+        }
+
+        Ok(PendingTransfer {
+            spim: self,
+            chip_select,
+            chunks,
+            done: first.is_none(),
+        })
+    }
+
     /// Read and write from a SPI slave, using separate read and write buffers
     ///
     /// This method implements a complete read transaction, which consists of
@@ -280,8 +338,8 @@ where
         ram_slice_check(tx_buffer)?;
         ram_slice_check(rx_buffer)?;
 
-        let txi = tx_buffer.chunks(EASY_DMA_SIZE);
-        let rxi = rx_buffer.chunks_mut(EASY_DMA_SIZE);
+        let txi = dma::chunks(tx_buffer);
+        let rxi = dma::chunks_mut(rx_buffer);
 
         chip_select.set_low();
 
@@ -320,13 +378,11 @@ where
         // as long as there is data to send. We then chain a repeat to
         // the end so once all chunks have been exhausted, we will keep
         // getting Nones out of the iterators
-        let txi = tx_buffer
-            .chunks(EASY_DMA_SIZE)
+        let txi = dma::chunks(tx_buffer)
             .map(|c| Some(c))
             .chain(repeat_with(|| None));
 
-        let rxi = rx_buffer
-            .chunks_mut(EASY_DMA_SIZE)
+        let rxi = dma::chunks_mut(rx_buffer)
             .map(|c| Some(c))
             .chain(repeat_with(|| None));
 
@@ -371,12 +427,536 @@ where
         self.transfer_split_uneven(chip_select, tx_buffer, &mut [0u8; 0])
     }
 
+    /// Read and write a list of frames from a SPI slave, using the ArrayList
+    /// mode of EasyDMA
+    ///
+    /// For each of the `N` frames, the chip select pin is asserted, the item
+    /// of `tx_list` is transmitted while the item of `rx_list` is received,
+    /// and the pin is released again. The hardware advances to the next items
+    /// by itself.
+    ///
+    /// The items must have a length of at most `MAX_DMA_LEN` bytes.
+    pub fn transfer_list<const N: usize, const TX_LEN: usize, const RX_LEN: usize>(
+        &mut self,
+        chip_select: &mut Pin<Output<PushPull>>,
+        tx_list: &ArrayList<N, TX_LEN>,
+        rx_list: &mut ArrayList<N, RX_LEN>,
+    ) -> Result<(), Error> {
+        if !dma::fits_in_maxcnt(TX_LEN) {
+            return Err(Error::TxBufferTooLong);
+        }
+        if !dma::fits_in_maxcnt(RX_LEN) {
+            return Err(Error::RxBufferTooLong);
+        }
+        ram_slice_check(tx_list.as_bytes())?;
+
+        self.0.txd.list.write(|w| w.list().array_list());
+        self.0.rxd.list.write(|w| w.list().array_list());
+
+        let mut first = Some((tx_list.first_item(), rx_list.first_item_mut()));
+        let res = (0..N).try_for_each(|_| {
+            chip_select.set_low();
+
+            match first.take() {
+                Some((tx, rx)) => self.start_spi_dma_transfer(tx, rx),
+                None => self.0.tasks_start.write(|w|
+                    // `1` is a valid value to write to task registers.
+                    unsafe { w.bits(1) }),
+            }
+            while self.0.events_end.read().bits() == 0 {}
+
+            // Don't return early, as we must reset the CS pin
+            let res = self.finish_spi_dma_transfer();
+
+            chip_select.set_high();
+
+            res
+        });
+
+        self.0.txd.list.write(|w| w.list().disabled());
+        self.0.rxd.list.write(|w| w.list().disabled());
+
+        res
+    }
+
+    /// Read and write from a SPI slave, using owned DMA buffers
+    ///
+    /// This works like `transfer_split_uneven`, but the buffers are accessed
+    /// through the `embedded_dma` traits, so they can be `&'static mut`
+    /// slices, static cells or pool boxes instead of borrowed stack memory.
+    /// Both buffers are handed back once the transaction has ended, also if
+    /// it failed.
+    ///
+    /// Each buffer must have a length of at most `MAX_DMA_LEN` bytes.
+    pub fn dma_transfer<TxB, RxB>(
+        &mut self,
+        chip_select: &mut Pin<Output<PushPull>>,
+        tx_buffer: TxB,
+        mut rx_buffer: RxB,
+    ) -> Result<(TxB, RxB), (Error, TxB, RxB)>
+    where
+        TxB: ReadBuffer<Word = u8>,
+        RxB: WriteBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffers
+        let (tx_ptr, tx_len) = unsafe { tx_buffer.read_buffer() };
+        let (rx_ptr, rx_len) = unsafe { rx_buffer.write_buffer() };
+        let tx = unsafe { slice::from_raw_parts(tx_ptr, tx_len) };
+
+        let res = if !dma::fits_in_maxcnt(tx_len) {
+            Err(Error::TxBufferTooLong)
+        } else if !dma::fits_in_maxcnt(rx_len) {
+            Err(Error::RxBufferTooLong)
+        } else {
+            ram_slice_check(tx).and_then(|()| {
+                chip_select.set_low();
+
+                // Don't return early, as we must reset the CS pin
+                let res = self.do_spi_dma_transfer(
+                    DmaSlice::from_slice(tx),
+                    DmaSlice::from_raw_parts(rx_ptr, rx_len),
+                );
+
+                chip_select.set_high();
+
+                res
+            })
+        };
+
+        match res {
+            Ok(()) => Ok((tx_buffer, rx_buffer)),
+            Err(e) => Err((e, tx_buffer, rx_buffer)),
+        }
+    }
+
+    /// Start a transaction with a SPI slave in the background, using owned DMA
+    /// buffers
+    ///
+    /// This transmits `tx_buffer` and fills `rx_buffer` like `dma_transfer`,
+    /// but returns right away. The chip select pin must be driven by the
+    /// caller. The buffers and the `Spim` are handed back by `Transfer::wait`.
+    ///
+    /// Each buffer must have a length of at most `MAX_DMA_LEN` bytes.
+    pub fn dma_transfer_start<TxB, RxB>(
+        mut self,
+        tx_buffer: TxB,
+        mut rx_buffer: RxB,
+    ) -> Result<Transfer<(TxB, RxB), Self>, (Error, TxB, RxB, Self)>
+    where
+        TxB: ReadBuffer<Word = u8>,
+        RxB: WriteBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffers, which the transfer does
+        // until it has ended
+        let (tx_ptr, tx_len) = unsafe { tx_buffer.read_buffer() };
+        let (rx_ptr, rx_len) = unsafe { rx_buffer.write_buffer() };
+        let tx = unsafe { slice::from_raw_parts(tx_ptr, tx_len) };
+
+        let res = if !dma::fits_in_maxcnt(tx_len) {
+            Err(Error::TxBufferTooLong)
+        } else if !dma::fits_in_maxcnt(rx_len) {
+            Err(Error::RxBufferTooLong)
+        } else {
+            ram_slice_check(tx)
+        };
+        if let Err(e) = res {
+            return Err((e, tx_buffer, rx_buffer, self));
+        }
+
+        self.start_spi_dma_transfer(
+            DmaSlice::from_slice(tx),
+            DmaSlice::from_raw_parts(rx_ptr, rx_len),
+        );
+        Ok(Transfer::new((tx_buffer, rx_buffer), self))
+    }
+
+    /// Read and write from a SPI slave, without blocking
+    ///
+    /// This works like `transfer_split_uneven`, but the future waits for the
+    /// end of each EasyDMA transfer through the interrupt of the instance,
+    /// whose handler must call `Spim::<T>::handle_interrupt`. The interrupt
+    /// must be unmasked in the NVIC. Dropping the future stops the transfer.
+    #[cfg(feature = "async")]
+    pub async fn transfer_async(
+        &mut self,
+        chip_select: &mut Pin<Output<PushPull>>,
+        tx_buffer: &[u8],
+        rx_buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        ram_slice_check(tx_buffer)?;
+        ram_slice_check(rx_buffer)?;
+
+        let mut txi = dma::chunks(tx_buffer);
+        let mut rxi = dma::chunks_mut(rx_buffer);
+
+        chip_select.set_low();
+
+        // Don't return early, as we must reset the CS pin
+        let mut res = Ok(());
+        loop {
+            let (t, r) = match (txi.next(), rxi.next()) {
+                (None, None) => break,
+                (t, r) => (
+                    t.map(|t| DmaSlice::from_slice(t))
+                        .unwrap_or_else(|| DmaSlice::null()),
+                    r.map(|r| DmaSlice::from_slice(r))
+                        .unwrap_or_else(|| DmaSlice::null()),
+                ),
+            };
+
+            self.start_spi_dma_transfer(t, r);
+            res = dma::finish(self, T::INTERRUPT, |spim| {
+                spim.0.intenset.write(|w| w.end().set())
+            }).await;
+            if res.is_err() {
+                break;
+            }
+        }
+
+        chip_select.set_high();
+
+        res
+    }
+
+    /// Write to an SPI slave, without blocking
+    ///
+    /// See `transfer_async`.
+    #[cfg(feature = "async")]
+    pub async fn write_async(
+        &mut self,
+        chip_select: &mut Pin<Output<PushPull>>,
+        tx_buffer: &[u8],
+    ) -> Result<(), Error> {
+        self.transfer_async(chip_select, tx_buffer, &mut []).await
+    }
+
+    /// Enable the interrupt for a given event
+    ///
+    /// The interrupt of the instance must be unmasked in the NVIC separately.
+    pub fn enable_interrupt(&mut self, evt: SpimEvent) {
+        match evt {
+            SpimEvent::Started => self.0.intenset.write(|w| w.started().set()),
+            SpimEvent::EndTx => self.0.intenset.write(|w| w.endtx().set()),
+            SpimEvent::EndRx => self.0.intenset.write(|w| w.endrx().set()),
+            SpimEvent::End => self.0.intenset.write(|w| w.end().set()),
+            SpimEvent::Stopped => self.0.intenset.write(|w| w.stopped().set()),
+        }
+    }
+
+    /// Disable the interrupt for a given event
+    pub fn disable_interrupt(&mut self, evt: SpimEvent) {
+        match evt {
+            SpimEvent::Started => self.0.intenclr.write(|w| w.started().clear()),
+            SpimEvent::EndTx => self.0.intenclr.write(|w| w.endtx().clear()),
+            SpimEvent::EndRx => self.0.intenclr.write(|w| w.endrx().clear()),
+            SpimEvent::End => self.0.intenclr.write(|w| w.end().clear()),
+            SpimEvent::Stopped => self.0.intenclr.write(|w| w.stopped().clear()),
+        }
+    }
+
+    /// Return whether a given event has been generated
+    pub fn is_event_triggered(&self, evt: SpimEvent) -> bool {
+        match evt {
+            SpimEvent::Started => self.0.events_started.read().bits() != 0,
+            SpimEvent::EndTx => self.0.events_endtx.read().bits() != 0,
+            SpimEvent::EndRx => self.0.events_endrx.read().bits() != 0,
+            SpimEvent::End => self.0.events_end.read().bits() != 0,
+            SpimEvent::Stopped => self.0.events_stopped.read().bits() != 0,
+        }
+    }
+
+    /// Reset a given event
+    pub fn reset_event(&mut self, evt: SpimEvent) {
+        match evt {
+            SpimEvent::Started => self.0.events_started.write(|w| w),
+            SpimEvent::EndTx => self.0.events_endtx.write(|w| w),
+            SpimEvent::EndRx => self.0.events_endrx.write(|w| w),
+            SpimEvent::End => self.0.events_end.write(|w| w),
+            SpimEvent::Stopped => self.0.events_stopped.write(|w| w),
+        }
+    }
+
+    /// Notify the `Notifier` of this instance at the end of every transfer
+    ///
+    /// `Spim::<T>::handle_interrupt` must be called from the interrupt handler
+    /// of the instance, which must be unmasked in the NVIC. The `hook`, if
+    /// any, is called from the interrupt handler.
+    pub fn enable_notifications(&mut self, hook: Option<fn()>) {
+        T::notifier().enable(hook);
+    }
+
+    /// Stop notifying the end of transfers
+    pub fn disable_notifications(&mut self) {
+        T::notifier().disable();
+        self.0.intenclr.write(|w| w.end().clear());
+    }
+
+    /// Return the `Notifier` of this instance
+    pub fn notifier(&self) -> &'static Notifier {
+        T::notifier()
+    }
+
+    /// Notify the end of a transfer, and wake the future waiting for it, to be
+    /// called from the interrupt handler of the instance
+    ///
+    /// The END event is left set, for the transfer to be finished by its
+    /// owner.
+    pub fn handle_interrupt() {
+        // This is safe, as only the END interrupt enable is written to, which
+        // the driver doesn't rely on
+        let spim = unsafe { &*T::ptr() };
+        if spim.events_end.read().bits() != 0 {
+            spim.intenclr.write(|w| w.end().clear());
+            T::notifier().notify();
+            #[cfg(feature = "async")]
+            waker::wake(T::INTERRUPT);
+        }
+    }
+
+    /// Record the events of the instance whose interrupt is enabled, see
+    /// `enable_interrupt`, and reset them, to be called from the interrupt
+    /// handler of the instance
+    ///
+    /// The recorded events are consumed with `take_event`. As they are reset,
+    /// they must not be waited for by the driver at the same time.
+    pub fn record_events() {
+        // This is safe, as only the events whose interrupt has been enabled
+        // by the user are reset
+        unsafe {
+            let inten = (*T::ptr()).intenset.read().bits();
+            T::event_flags().record(T::ptr() as *const u8, inten);
+        }
+    }
+
+    /// Return whether `evt` has been recorded by `record_events` since it was
+    /// last taken, and clear it
+    ///
+    /// This doesn't disable interrupts, so it can be polled cheaply from
+    /// thread mode.
+    pub fn take_event(evt: SpimEvent) -> bool {
+        T::event_flags().take(evt.mask())
+    }
+
+    /// Return whether `evt` has been recorded by `record_events`, without
+    /// clearing it
+    pub fn is_event_recorded(evt: SpimEvent) -> bool {
+        T::event_flags().is_set(evt.mask())
+    }
+
+    /// Take ownership of the interrupt of the SPIM instance, to be accessed
+    /// through `irq`
+    ///
+    /// Panics if `irq` isn't the interrupt of the instance.
+    pub fn with_irq(mut self, irq: Irq) -> Self {
+        assert!(irq.is(T::INTERRUPT));
+        self.1 = Some(irq);
+        self
+    }
+
+    /// Return the interrupt of the instance, if owned, see `with_irq`
+    pub fn irq(&mut self) -> Option<&mut Irq> {
+        self.1.as_mut()
+    }
+
     /// Return the raw interface to the underlying SPIM peripheral
     pub fn free(self) -> T {
         self.0
     }
 }
 
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::spi::Error for Error {
+    fn kind(&self) -> embedded_hal_1::spi::ErrorKind {
+        use embedded_hal_1::spi::ErrorKind;
+
+        match self {
+            // The transfer ended before all bytes had been moved between the
+            // buffers and the bus
+            Error::Transmit | Error::Receive => ErrorKind::Overrun,
+            Error::TxBufferTooLong
+            | Error::RxBufferTooLong
+            | Error::DMABufferNotInDataMemory => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::spi::ErrorType for Spim<T>
+where
+    T: Instance,
+{
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::spi::SpiBus<u8> for Spim<T>
+where
+    T: Instance,
+{
+    fn read(&mut self, words: &mut [u8]) -> Result<(), Error> {
+        self.transfer_uneven(&[], words)
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Error> {
+        embedded_hal::blocking::spi::Write::write(self, words)
+    }
+
+    fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Error> {
+        self.transfer_uneven(write, read)
+    }
+
+    fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Error> {
+        embedded_hal::blocking::spi::Transfer::transfer(self, words).map(|_| ())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        // Every transfer has ended once the methods return
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<T> Spim<T>
+where
+    T: Instance,
+{
+    /// Transmit `tx_buffer` while receiving into `rx_buffer`, for as many
+    /// bytes as the longer one, without a chip select pin
+    ///
+    /// The shorter buffer is padded with the `orc` value, or incoming bytes
+    /// are discarded, as with `transfer_split_uneven`.
+    fn transfer_uneven(&mut self, tx_buffer: &[u8], rx_buffer: &mut [u8]) -> Result<(), Error> {
+        use crate::target_constants::EASY_DMA_SIZE;
+
+        ram_slice_check(tx_buffer)?;
+        ram_slice_check(rx_buffer)?;
+
+        let len = tx_buffer.len().max(rx_buffer.len());
+        let tx_len = tx_buffer.len();
+        let rx_len = rx_buffer.len();
+        let mut offset = 0;
+        while offset < len {
+            let end = len.min(offset + EASY_DMA_SIZE);
+            let tx = tx_buffer.get(offset..end.min(tx_len)).unwrap_or(&[]);
+            let rx = rx_buffer.get_mut(offset..end.min(rx_len)).unwrap_or(&mut []);
+            self.do_spi_dma_transfer(DmaSlice::from_slice(tx), DmaSlice::from_slice(rx))?;
+            offset = end;
+        }
+
+        Ok(())
+    }
+}
+
+/// A SPIM instance that is used by a single device, for the `SpiDevice`
+/// trait of embedded-hal 1.0
+///
+/// The chip select pin is asserted for the duration of each transaction.
+#[cfg(feature = "embedded-hal-1")]
+pub struct SpimDevice<T> {
+    spim: Spim<T>,
+    chip_select: Pin<Output<PushPull>>,
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<T> SpimDevice<T>
+where
+    T: Instance,
+{
+    /// Use `spim` for the device selected by `chip_select`, which is released
+    pub fn new(spim: Spim<T>, mut chip_select: Pin<Output<PushPull>>) -> Self {
+        let _ = embedded_hal_1::digital::OutputPin::set_high(&mut chip_select);
+        SpimDevice { spim, chip_select }
+    }
+
+    /// Return the SPIM instance and the chip select pin
+    pub fn free(self) -> (Spim<T>, Pin<Output<PushPull>>) {
+        (self.spim, self.chip_select)
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::spi::ErrorType for SpimDevice<T>
+where
+    T: Instance,
+{
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::spi::SpiDevice<u8> for SpimDevice<T>
+where
+    T: Instance,
+{
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal_1::spi::Operation<'_, u8>],
+    ) -> Result<(), Error> {
+        use crate::clocks::HFCLK_FREQ;
+        use embedded_hal_1::digital::OutputPin;
+        use embedded_hal_1::spi::{Operation, SpiBus};
+
+        let _ = OutputPin::set_low(&mut self.chip_select);
+
+        // Don't return early, as we must reset the CS pin
+        let res = operations.iter_mut().try_for_each(|op| match op {
+            Operation::Read(words) => SpiBus::read(&mut self.spim, words),
+            Operation::Write(words) => SpiBus::write(&mut self.spim, words),
+            Operation::Transfer(read, write) => SpiBus::transfer(&mut self.spim, read, write),
+            Operation::TransferInPlace(words) => self.spim.transfer_in_place(words),
+            Operation::DelayNs(ns) => {
+                let cycles = *ns as u64 * HFCLK_FREQ as u64 / 1_000_000_000;
+                cortex_m::asm::delay(cycles as u32);
+                Ok(())
+            }
+        });
+
+        let _ = OutputPin::set_high(&mut self.chip_select);
+
+        res
+    }
+}
+
+impl<T> Transferable for Spim<T>
+where
+    T: Instance,
+{
+    type Error = Error;
+
+    fn is_done(&mut self) -> bool {
+        self.0.events_end.read().bits() != 0
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        self.finish_spi_dma_transfer()
+    }
+
+    fn cancel(&mut self) -> Progress {
+        self.0.tasks_stop.write(|w|
+            // `1` is a valid value to write to task registers.
+            unsafe { w.bits(1) });
+
+        // The transfer may have ended before it could be stopped
+        while self.0.events_stopped.read().bits() == 0
+            && self.0.events_end.read().bits() == 0
+        {}
+
+        self.0.events_stopped.write(|w| w);
+        self.0.events_end.write(|w| w);
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        Progress {
+            transmitted: self.0.txd.amount.read().bits() as usize,
+            received: self.0.rxd.amount.read().bits() as usize,
+        }
+    }
+}
+
 /// GPIO pins for SPIM interface
 pub struct Pins {
     /// SPI clock
@@ -391,7 +971,36 @@ pub struct Pins {
     pub miso: Option<Pin<Input<Floating>>>,
 }
 
+/// Events that can be generated by the SPIM peripheral
+#[derive(Clone, Copy, Debug)]
+pub enum SpimEvent {
+    /// The transaction has started
+    Started,
+    /// The whole transmit buffer has been sent
+    EndTx,
+    /// The receive buffer is full
+    EndRx,
+    /// Both the transmission and the reception have ended
+    End,
+    /// The transaction has been stopped
+    Stopped,
+}
+
+impl SpimEvent {
+    /// Return the mask of the interrupt enable bit of the event
+    fn mask(self) -> u32 {
+        1 << match self {
+            SpimEvent::Started => 19,
+            SpimEvent::EndTx => 8,
+            SpimEvent::EndRx => 4,
+            SpimEvent::End => 6,
+            SpimEvent::Stopped => 1,
+        }
+    }
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     TxBufferTooLong,
     RxBufferTooLong,
@@ -409,14 +1018,110 @@ fn ram_slice_check(slice: &[u8]) -> Result<(), Error> {
     }
 }
 
+/// A transaction started by `Spim::start_transfer`
+pub struct PendingTransfer<'a, T>
+where
+    T: Instance,
+{
+    spim: &'a mut Spim<T>,
+    chip_select: &'a mut Pin<Output<PushPull>>,
+    chunks: ChunksMut<'a, u8>,
+    done: bool,
+}
+
+impl<'a, T> PendingTransfer<'a, T>
+where
+    T: Instance,
+{
+    /// Check whether the transaction has ended, starting the transfer of the
+    /// next part of the buffer if needed
+    pub fn poll(&mut self) -> nb::Result<(), Error> {
+        if self.done {
+            return Ok(());
+        }
+        if self.spim.0.events_end.read().bits() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        match (self.spim.finish_spi_dma_transfer(), self.chunks.next()) {
+            (Ok(()), Some(chunk)) => {
+                self.spim.start_spi_dma_transfer(
+                    DmaSlice::from_slice(chunk),
+                    DmaSlice::from_slice(chunk),
+                );
+                Err(nb::Error::WouldBlock)
+            }
+            (res, _) => {
+                self.done = true;
+                let _ = v2::OutputPin::set_high(self.chip_select);
+                res.map_err(nb::Error::Other)
+            }
+        }
+    }
+}
+
+impl<'a, T> Drop for PendingTransfer<'a, T>
+where
+    T: Instance,
+{
+    fn drop(&mut self) {
+        if !self.done {
+            self.spim.cancel();
+            let _ = v2::OutputPin::set_high(self.chip_select);
+        }
+    }
+}
 
 /// Implemented by all SPIM instances
-pub trait Instance: Deref<Target = spim0::RegisterBlock> {}
+pub trait Instance: Deref<Target = spim0::RegisterBlock> {
+    /// Return a pointer to the register block of the instance
+    fn ptr() -> *const spim0::RegisterBlock;
+
+    /// Return the `Notifier` of the instance
+    fn notifier() -> &'static Notifier;
+
+    /// Return the `EventFlags` of the instance
+    fn event_flags() -> &'static EventFlags;
+
+    /// The interrupt associated with this SPIM instance
+    const INTERRUPT: Interrupt;
+}
+
+macro_rules! impl_instance {
+    ($($spim:ident: $interrupt:ident,)*) => {
+        $(
+            impl Instance for $spim {
+                const INTERRUPT: Interrupt = Interrupt::$interrupt;
+
+                fn ptr() -> *const spim0::RegisterBlock {
+                    $spim::ptr()
+                }
+
+                fn notifier() -> &'static Notifier {
+                    static NOTIFIER: Notifier = Notifier::new();
+                    &NOTIFIER
+                }
+
+                fn event_flags() -> &'static EventFlags {
+                    static EVENT_FLAGS: EventFlags = EventFlags::new();
+                    &EVENT_FLAGS
+                }
+            }
+        )*
+    }
+}
+
+// SPIM0 shares its interrupt with TWIM0 where there is one
+#[cfg(feature = "52810")]
+impl_instance!(SPIM0: SPIM0_SPIS0_SPI0,);
+#[cfg(not(feature = "52810"))]
+impl_instance!(SPIM0: SPIM0_SPIS0_TWIM0_TWIS0_SPI0_TWI0,);
 
-impl Instance for SPIM0 {}
+#[cfg(spim1)]
+impl_instance!(SPIM1: SPIM1_SPIS1_TWIM1_TWIS1_SPI1_TWI1,);
 
-#[cfg(any(feature = "52832", feature = "52840"))]
-impl Instance for SPIM1 {}
+#[cfg(spim2)]
+impl_instance!(SPIM2: SPIM2_SPIS2_SPI2,);
 
-#[cfg(any(feature = "52832", feature = "52840"))]
-impl Instance for SPIM2 {}
+#[cfg(spim3)]
+impl_instance!(SPIM3: SPIM3,);
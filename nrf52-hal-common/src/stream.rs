@@ -0,0 +1,65 @@
+//! Callback-driven streaming of double-buffered peripherals
+//!
+//! The streams of the I2S and the PDM alternate between two buffers, handing
+//! out one to be refilled or processed while the peripheral transfers the
+//! other. `CallbackStream` does this from the peripheral interrupt, calling a
+//! closure with every buffer as soon as the peripheral is done with it, so
+//! the buffer juggling is shared by all such peripherals.
+
+/// A stream alternating between two buffers
+pub trait DoubleBuffered {
+    /// Word the buffers consist of
+    type Word;
+
+    /// Return the buffer the peripheral has just completed, if any
+    ///
+    /// The buffer is queued again right away, so it must be refilled or
+    /// processed within the time it takes to transfer the other buffer.
+    fn next_completed(&mut self) -> Option<&mut [Self::Word]>;
+
+    /// Enable the interrupt signalling a completed buffer
+    fn enable_interrupt(&mut self);
+
+    /// Disable the interrupt signalling a completed buffer
+    fn disable_interrupt(&mut self);
+}
+
+/// A stream calling a closure with every completed buffer
+///
+/// `on_interrupt` must be called from the interrupt handler of the
+/// peripheral, and its interrupt must be unmasked in the NVIC. The closure
+/// refills the buffers of a transmitting stream, and consumes those of a
+/// receiving one.
+pub struct CallbackStream<S, F> {
+    stream: S,
+    callback: F,
+}
+
+impl<S, F> CallbackStream<S, F>
+where
+    S: DoubleBuffered,
+    F: FnMut(&mut [S::Word]),
+{
+    /// Call `callback` with every buffer `stream` completes
+    pub fn new(mut stream: S, callback: F) -> Self {
+        stream.enable_interrupt();
+        CallbackStream { stream, callback }
+    }
+
+    /// Pass the completed buffer to the closure
+    ///
+    /// To be called from the interrupt handler of the peripheral.
+    pub fn on_interrupt(&mut self) {
+        if let Some(buffer) = self.stream.next_completed() {
+            (self.callback)(buffer);
+        }
+    }
+
+    /// Stop calling the closure, and return the stream and the closure
+    ///
+    /// The stream keeps running, and can be stopped as usual.
+    pub fn free(mut self) -> (S, F) {
+        self.stream.disable_interrupt();
+        (self.stream, self.callback)
+    }
+}
@@ -52,7 +52,30 @@ impl Temp {
         } else {
             self.0.events_datardy.reset(); // clear event
             let raw = self.0.temp.read().bits();
+            // The sensor keeps drawing current until it is stopped explicitly
+            // (nRF52832 anomaly 30)
+            unsafe {
+                self.0.tasks_stop.write(|w| w.bits(1));
+            }
             Ok(I30F2::from_bits(raw as i32))
         }
     }
+
+    /// Enables the interrupt signalling a finished measurement.
+    ///
+    /// The result can then be retrieved by calling `read` from the interrupt
+    /// handler.
+    pub fn enable_interrupt(&mut self) {
+        self.0.intenset.write(|w| w.datardy().set());
+    }
+
+    /// Disables the interrupt signalling a finished measurement.
+    pub fn disable_interrupt(&mut self) {
+        self.0.intenclr.write(|w| w.datardy().clear());
+    }
+
+    /// Releases the temperature sensor's register block.
+    pub fn free(self) -> TEMP {
+        self.0
+    }
 }
@@ -0,0 +1,182 @@
+//! Embassy time driver based on RTC1
+//!
+//! This implements the `time-driver` interface of embassy on RTC1, so
+//! `embassy-time` timers and the peripherals of this HAL can be used
+//! together. The RTC counts at 32.768 kHz, and its 24 bit counter is extended
+//! to 64 bits by counting half periods, so timestamps don't wrap around.
+//!
+//! The driver is started with `init`, and the interrupt handler of RTC1 must
+//! call `on_interrupt`. The low frequency clock must be running.
+//!
+//! ``` ignore
+//! let irq = Irq::take(Interrupt::RTC1).unwrap();
+//! time_driver::init(p.RTC1, irq);
+//!
+//! #[interrupt]
+//! fn RTC1() {
+//!     time_driver::on_interrupt();
+//! }
+//! ```
+//!
+//! The RTC uses its compare registers 0 and 1: 0 for the alarm, and 1 for
+//! detecting the middle of each period.
+use core::cell::{Cell, RefCell};
+use core::sync::atomic::{compiler_fence, AtomicU32, Ordering};
+use core::task::Waker;
+
+use cortex_m::interrupt::{self, CriticalSection, Mutex};
+use embassy_time_driver::{time_driver_impl, Driver};
+use embassy_time_queue_utils::Queue;
+
+use crate::irq::Irq;
+use crate::target::{rtc0, Interrupt, RTC1};
+
+/// Timestamps at least this far ahead wait for a later half period, so the
+/// compare register never matches too early
+const ALARM_HORIZON: u64 = 0xC0_0000;
+
+fn rtc() -> &'static rtc0::RegisterBlock {
+    // This is safe, as RTC1 is owned by the driver since `init`
+    unsafe { &*RTC1::ptr() }
+}
+
+/// Return the 64 bits timestamp of `counter` during half period `period`
+///
+/// The counter is in the first half of its range during even half periods,
+/// and in the second half during odd ones. Around the start of a half period,
+/// the counter may not match `period` yet, which the XOR corrects.
+fn calc_now(period: u32, counter: u32) -> u64 {
+    ((period as u64) << 23) + ((counter ^ ((period & 1) << 23)) as u64)
+}
+
+struct RtcDriver {
+    /// Number of half periods of the counter since it has been started
+    period: AtomicU32,
+    /// Timestamp of the alarm, `u64::MAX` if none
+    alarm: Mutex<Cell<u64>>,
+    queue: Mutex<RefCell<Queue>>,
+}
+
+time_driver_impl!(static DRIVER: RtcDriver = RtcDriver {
+    period: AtomicU32::new(0),
+    alarm: Mutex::new(Cell::new(u64::MAX)),
+    queue: Mutex::new(RefCell::new(Queue::new())),
+});
+
+/// Start the time driver on RTC1
+///
+/// `irq` must be the interrupt of RTC1, which is unmasked, and stays taken
+/// by the driver. Panics otherwise.
+pub fn init(rtc1: RTC1, mut irq: Irq) {
+    assert!(irq.is(Interrupt::RTC1));
+
+    rtc1.tasks_stop.write(|w| unsafe { w.bits(1) });
+    rtc1.prescaler.write(|w| unsafe { w.bits(0) });
+    rtc1.cc[1].write(|w| unsafe { w.bits(0x80_0000) });
+    rtc1.intenset.write(|w| w.ovrflw().set().compare1().set());
+    rtc1.tasks_clear.write(|w| unsafe { w.bits(1) });
+    rtc1.tasks_start.write(|w| unsafe { w.bits(1) });
+
+    // Wait for the counter to start, so `now` is valid
+    while rtc1.counter.read().bits() == 0 {}
+
+    irq.unpend();
+    irq.unmask();
+    core::mem::forget(irq);
+}
+
+/// Advance the time driver, to be called from the interrupt handler of RTC1
+pub fn on_interrupt() {
+    DRIVER.on_interrupt();
+}
+
+impl RtcDriver {
+    fn on_interrupt(&self) {
+        let r = rtc();
+        if r.events_ovrflw.read().bits() != 0 {
+            r.events_ovrflw.write(|w| w);
+            self.next_period();
+        }
+        if r.events_compare[1].read().bits() != 0 {
+            r.events_compare[1].write(|w| w);
+            self.next_period();
+        }
+        if r.events_compare[0].read().bits() != 0 {
+            r.events_compare[0].write(|w| w);
+            interrupt::free(|cs| self.trigger_alarm(cs));
+        }
+    }
+
+    fn next_period(&self) {
+        interrupt::free(|cs| {
+            let period = self.period.load(Ordering::Relaxed) + 1;
+            self.period.store(period, Ordering::Relaxed);
+
+            // Enable the alarm once it's within range of the compare register
+            let start = (period as u64) << 23;
+            if self.alarm.borrow(cs).get() < start + ALARM_HORIZON {
+                rtc().intenset.write(|w| w.compare0().set());
+            }
+        })
+    }
+
+    fn trigger_alarm(&self, cs: &CriticalSection) {
+        let mut queue = self.queue.borrow(cs).borrow_mut();
+        let mut next = queue.next_expiration(self.now());
+        while !self.set_alarm(cs, next) {
+            next = queue.next_expiration(self.now());
+        }
+    }
+
+    /// Set the alarm to `timestamp`
+    ///
+    /// Return `false` if the timestamp has passed already, in which case the
+    /// alarm is cleared.
+    fn set_alarm(&self, cs: &CriticalSection, timestamp: u64) -> bool {
+        let r = rtc();
+        self.alarm.borrow(cs).set(timestamp);
+
+        let now = self.now();
+        if timestamp <= now {
+            r.intenclr.write(|w| w.compare0().clear());
+            self.alarm.borrow(cs).set(u64::MAX);
+            return false;
+        }
+
+        // Writing N or N + 1 to the compare register, when the counter is N,
+        // may not generate a COMPARE event
+        let safe_timestamp = timestamp.max(now + 3);
+        r.cc[0].write(|w| unsafe { w.bits(safe_timestamp as u32 & 0xFF_FFFF) });
+
+        // Otherwise, the alarm is enabled by `next_period`
+        if timestamp - now < ALARM_HORIZON {
+            r.intenset.write(|w| w.compare0().set());
+        } else {
+            r.intenclr.write(|w| w.compare0().clear());
+        }
+
+        true
+    }
+}
+
+impl Driver for RtcDriver {
+    fn now(&self) -> u64 {
+        // The period has to be read before the counter
+        let period = self.period.load(Ordering::Relaxed);
+        compiler_fence(Ordering::Acquire);
+        let counter = rtc().counter.read().bits();
+        calc_now(period, counter)
+    }
+
+    fn schedule_wake(&self, at: u64, waker: &Waker) {
+        interrupt::free(|cs| {
+            let mut queue = self.queue.borrow(cs).borrow_mut();
+            if queue.schedule_wake(at, waker) {
+                let mut next = queue.next_expiration(self.now());
+                while !self.set_alarm(cs, next) {
+                    next = queue.next_expiration(self.now());
+                }
+            }
+        })
+    }
+}
@@ -4,20 +4,23 @@
 
 use core::ops::Deref;
 
+use crate::irq::{EventFlags, Irq};
 use crate::target::{timer0, Interrupt, NVIC, TIMER0, TIMER1, TIMER2};
 use embedded_hal::{prelude::*, timer};
 use nb::{self, block};
 use void::{unreachable, Void};
 
-#[cfg(any(feature = "52832", feature = "52840"))]
-use crate::target::{TIMER3, TIMER4};
+#[cfg(timer3)]
+use crate::target::TIMER3;
+#[cfg(timer4)]
+use crate::target::TIMER4;
 
 
 /// Interface to a TIMER instance
 ///
 /// Right now, this is a very basic interface. The timer will always be
 /// hardcoded to a frequency of 1 MHz and 32 bits accuracy.
-pub struct Timer<T>(T);
+pub struct Timer<T>(T, Option<Irq>);
 
 impl<T> Timer<T>
 where
@@ -32,7 +35,22 @@ where
         );
         timer.bitmode.write(|w| w.bitmode()._32bit());
 
-        Timer(timer)
+        Timer(timer, None)
+    }
+
+    /// Take ownership of the interrupt of the TIMER instance, to be accessed
+    /// through `irq`
+    ///
+    /// Panics if `irq` isn't the interrupt of the instance.
+    pub fn with_irq(mut self, irq: Irq) -> Self {
+        assert!(irq.is(T::INTERRUPT));
+        self.1 = Some(irq);
+        self
+    }
+
+    /// Return the interrupt of the instance, if owned, see `with_irq`
+    pub fn irq(&mut self) -> Option<&mut Irq> {
+        self.1.as_mut()
     }
 
     /// Return the raw interface to the underlying timer peripheral
@@ -66,6 +84,73 @@ where
         nvic.disable(T::INTERRUPT);
     }
 
+    /// Enable the interrupt for a given event
+    ///
+    /// Unlike `enable_interrupt`, this doesn't unmask the interrupt of the
+    /// instance in the NVIC. TIMER0 to TIMER2 only have the events of compare
+    /// registers 0 to 3.
+    pub fn enable_event_interrupt(&mut self, evt: TimerEvent) {
+        match evt {
+            TimerEvent::Compare0 => self.0.intenset.write(|w| w.compare0().set()),
+            TimerEvent::Compare1 => self.0.intenset.write(|w| w.compare1().set()),
+            TimerEvent::Compare2 => self.0.intenset.write(|w| w.compare2().set()),
+            TimerEvent::Compare3 => self.0.intenset.write(|w| w.compare3().set()),
+            TimerEvent::Compare4 => self.0.intenset.write(|w| w.compare4().set()),
+            TimerEvent::Compare5 => self.0.intenset.write(|w| w.compare5().set()),
+        }
+    }
+
+    /// Disable the interrupt for a given event
+    pub fn disable_event_interrupt(&mut self, evt: TimerEvent) {
+        match evt {
+            TimerEvent::Compare0 => self.0.intenclr.write(|w| w.compare0().clear()),
+            TimerEvent::Compare1 => self.0.intenclr.write(|w| w.compare1().clear()),
+            TimerEvent::Compare2 => self.0.intenclr.write(|w| w.compare2().clear()),
+            TimerEvent::Compare3 => self.0.intenclr.write(|w| w.compare3().clear()),
+            TimerEvent::Compare4 => self.0.intenclr.write(|w| w.compare4().clear()),
+            TimerEvent::Compare5 => self.0.intenclr.write(|w| w.compare5().clear()),
+        }
+    }
+
+    /// Return whether a given event has been generated
+    pub fn is_event_triggered(&self, evt: TimerEvent) -> bool {
+        self.0.events_compare[evt as usize].read().bits() != 0
+    }
+
+    /// Reset a given event
+    pub fn reset_event(&mut self, evt: TimerEvent) {
+        self.0.events_compare[evt as usize].write(|w| w);
+    }
+
+    /// Record the events of the instance whose interrupt is enabled, see
+    /// `enable_event_interrupt`, and reset them, to be called from the
+    /// interrupt handler of the instance
+    ///
+    /// The recorded events are consumed with `take_event`.
+    pub fn record_events() {
+        // This is safe, as only the events whose interrupt has been enabled
+        // by the user are reset
+        unsafe {
+            let inten = (*T::ptr()).intenset.read().bits();
+            T::event_flags().record(T::ptr() as *const u8, inten);
+        }
+    }
+
+    /// Return whether `evt` has been recorded by `record_events` since it was
+    /// last taken, and clear it
+    ///
+    /// This doesn't disable interrupts, so it can be polled cheaply from
+    /// thread mode.
+    pub fn take_event(evt: TimerEvent) -> bool {
+        T::event_flags().take(1 << (16 + evt as u32))
+    }
+
+    /// Return whether `evt` has been recorded by `record_events`, without
+    /// clearing it
+    pub fn is_event_recorded(evt: TimerEvent) -> bool {
+        T::event_flags().is_set(1 << (16 + evt as u32))
+    }
+
     pub fn delay(&mut self, cycles: u32) {
         self.start(cycles);
         match block!(self.wait()) {
@@ -73,6 +158,42 @@ where
             Err(x) => unreachable(x),
         }
     }
+
+    /// Start the timer in free running mode
+    ///
+    /// The counter is cleared and then runs continuously, wrapping around
+    /// after 2^32 cycles. This is useful as a time base for captures that are
+    /// triggered through PPI. Calling `start` returns the timer to count down
+    /// mode.
+    pub fn start_free_running(&mut self) {
+        self.0.shorts.reset();
+        self.0.tasks_clear.write(|w| unsafe { w.bits(1) });
+        self.0.tasks_start.write(|w| unsafe { w.bits(1) });
+    }
+
+    /// Capture the current counter value into capture register `n` and
+    /// return it
+    ///
+    /// Capture register 0 is used by the count down mode. TIMER0 to TIMER2
+    /// have 4 capture registers, TIMER3 and TIMER4 have 6.
+    pub fn capture(&mut self, n: usize) -> u32 {
+        self.0.tasks_capture[n].write(|w| unsafe { w.bits(1) });
+        self.read_capture(n)
+    }
+
+    /// Return the value of capture register `n`
+    ///
+    /// The value has either been captured through `capture`, or by a PPI
+    /// channel triggering the task returned by `task_capture`.
+    pub fn read_capture(&self, n: usize) -> u32 {
+        self.0.cc[n].read().bits()
+    }
+
+    /// Return the task that captures the counter value into capture register
+    /// `n`, for use as a PPI task end point
+    pub fn task_capture(&self, n: usize) -> &timer0::TASKS_CAPTURE {
+        &self.0.tasks_capture[n]
+    }
 }
 
 impl<T> timer::CountDown for Timer<T>
@@ -89,6 +210,12 @@ where
     where
         Time: Into<Self::Time>,
     {
+        // Stop and reset the timer when the given number of cycles is reached.
+        // This is configured here, as `start_free_running` disables it.
+        self.0
+            .shorts
+            .write(|w| w.compare0_clear().enabled().compare0_stop().enabled());
+
         // Configure timer to trigger EVENTS_COMPARE when given number of cycles
         // is reached.
         self.0.cc[0].write(|w|
@@ -124,6 +251,24 @@ where
     }
 }
 
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::delay::DelayNs for Timer<T>
+where
+    T: Instance,
+{
+    fn delay_ns(&mut self, ns: u32) {
+        // The timer counts at 1 MHz
+        self.delay_us(ns / 1000 + (ns % 1000 != 0) as u32);
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        // A compare value of 0 would only be reached after wrapping around
+        if us != 0 {
+            self.delay(us);
+        }
+    }
+}
+
 impl<T> timer::Cancel for Timer<T>
 where
     T: Instance,
@@ -139,10 +284,35 @@ where
 }
 
 
+/// Events that can be generated by the TIMER peripheral
+#[derive(Clone, Copy, Debug)]
+pub enum TimerEvent {
+    /// The counter reached the value of compare register 0
+    Compare0,
+    /// The counter reached the value of compare register 1
+    Compare1,
+    /// The counter reached the value of compare register 2
+    Compare2,
+    /// The counter reached the value of compare register 3
+    Compare3,
+    /// The counter reached the value of compare register 4, TIMER3 and TIMER4
+    /// only
+    Compare4,
+    /// The counter reached the value of compare register 5, TIMER3 and TIMER4
+    /// only
+    Compare5,
+}
+
 /// Implemented by all `TIMER` instances
 pub trait Instance: Deref<Target = timer0::RegisterBlock> {
     /// This interrupt associated with this RTC instance
     const INTERRUPT: Interrupt;
+
+    /// Return a pointer to the register block of the instance
+    fn ptr() -> *const timer0::RegisterBlock;
+
+    /// Return the `EventFlags` of the instance
+    fn event_flags() -> &'static EventFlags;
 }
 
 macro_rules! impl_instance {
@@ -150,6 +320,15 @@ macro_rules! impl_instance {
         $(
             impl Instance for $name {
                 const INTERRUPT: Interrupt = Interrupt::$name;
+
+                fn ptr() -> *const timer0::RegisterBlock {
+                    $name::ptr()
+                }
+
+                fn event_flags() -> &'static EventFlags {
+                    static EVENT_FLAGS: EventFlags = EventFlags::new();
+                    &EVENT_FLAGS
+                }
             }
         )*
     }
@@ -157,5 +336,8 @@ macro_rules! impl_instance {
 
 impl_instance!(TIMER0, TIMER1, TIMER2,);
 
-#[cfg(any(feature = "52832", feature = "52840"))]
-impl_instance!(TIMER3, TIMER4,);
+#[cfg(timer3)]
+impl_instance!(TIMER3,);
+
+#[cfg(timer4)]
+impl_instance!(TIMER4,);
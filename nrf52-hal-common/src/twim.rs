@@ -4,16 +4,21 @@
 //!
 //! - nrf52832: Section 33
 //! - nrf52840: Section 6.31
+use core::marker::PhantomData;
+#[cfg(feature = "embedded-hal-1")]
+use core::mem;
 use core::ops::Deref;
+use core::slice;
 use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
 
 use crate::target::{
     twim0,
+    Interrupt,
     P0,
     TWIM0,
 };
 
-#[cfg(any(feature = "52832", feature = "52840"))]
+#[cfg(twim1)]
 use crate::target::TWIM1;
 
 use crate::gpio::{
@@ -22,11 +27,26 @@ use crate::gpio::{
     Input,
 };
 
-use crate::target_constants::EASY_DMA_SIZE;
+use crate::dma::{self, ArrayList, Notifier, Progress, Transfer, Transferable};
+use crate::irq::{EventFlags, Irq};
+use crate::{slice_in_ram, DmaSlice};
+#[cfg(feature = "async")]
+use crate::waker;
+
+use embedded_dma::{ReadBuffer, WriteBuffer};
+#[cfg(feature = "embedded-hal-1")]
+use embedded_hal_1::i2c::Operation;
+
+#[cfg(feature = "embedded-hal-1")]
+use crate::target_constants::FORCE_COPY_BUFFER_SIZE;
 
 pub use crate::target::twim0::frequency::FREQUENCYW as Frequency;
 
 
+/// Maximum length of a single EasyDMA transfer, which depends on the width of
+/// the MAXCNT registers of the chip
+pub const MAX_DMA_LEN: usize = crate::target_constants::EASY_DMA_SIZE;
+
 /// Interface to a TWIM instance
 ///
 /// This is a very basic interface that comes with the following limitation:
@@ -36,7 +56,7 @@ pub use crate::target::twim0::frequency::FREQUENCYW as Frequency;
 /// conflicting instances are disabled before using `Twim`. Please refer to the
 /// product specification for more information (section 15.2 for nRF52832,
 /// section 6.1.2 for nRF52840).
-pub struct Twim<T>(T);
+pub struct Twim<T>(T, Option<Irq>);
 
 impl<T> Twim<T> where T: Instance {
     pub fn new(twim: T, pins: Pins, frequency: Frequency) -> Self {
@@ -82,13 +102,14 @@ impl<T> Twim<T> where T: Instance {
         twim.frequency.write(|w| w.frequency().variant(frequency));
 
 
-        Twim(twim)
+        Twim(twim, None)
     }
 
     /// Write to an I2C slave
     ///
-    /// The buffer must have a length of at most 255 bytes on the nRF52832
-    /// and at most 65535 bytes on the nRF52840.
+    /// The buffer must have a length of at most `MAX_DMA_LEN` bytes. Unlike
+    /// with SPIM and UARTE, longer buffers can't be split into several
+    /// transfers, as that would split the I2C transaction.
     pub fn write(&mut self,
         address: u8,
         buffer:  &[u8],
@@ -96,7 +117,7 @@ impl<T> Twim<T> where T: Instance {
         -> Result<(), Error>
     {
 
-        if buffer.len() > EASY_DMA_SIZE {
+        if !dma::fits_in_maxcnt(buffer.len()) {
             return Err(Error::TxBufferTooLong);
         }
 
@@ -161,15 +182,14 @@ impl<T> Twim<T> where T: Instance {
 
     /// Read from an I2C slave
     ///
-    /// The buffer must have a length of at most 255 bytes on the nRF52832
-    /// and at most 65535 bytes on the nRF52840.
+    /// The buffer must have a length of at most `MAX_DMA_LEN` bytes.
     pub fn read(&mut self,
         address: u8,
         buffer:  &mut [u8],
     )
         -> Result<(), Error>
     {
-        if buffer.len() > EASY_DMA_SIZE {
+        if !dma::fits_in_maxcnt(buffer.len()) {
             return Err(Error::RxBufferTooLong);
         }
 
@@ -238,7 +258,7 @@ impl<T> Twim<T> where T: Instance {
     /// Write data to an I2C slave, then read data from the slave without
     /// triggering a stop condition between the two
     ///
-    /// The buffer must have a length of at most 255 bytes.
+    /// The buffers must have a length of at most `MAX_DMA_LEN` bytes.
     pub fn write_then_read(&mut self,
         address: u8,
         wr_buffer:  &[u8],
@@ -246,11 +266,11 @@ impl<T> Twim<T> where T: Instance {
     )
         -> Result<(), Error>
     {
-        if wr_buffer.len() > EASY_DMA_SIZE {
+        if !dma::fits_in_maxcnt(wr_buffer.len()) {
             return Err(Error::TxBufferTooLong);
         }
 
-        if rd_buffer.len() > EASY_DMA_SIZE {
+        if !dma::fits_in_maxcnt(rd_buffer.len()) {
             return Err(Error::RxBufferTooLong);
         }
 
@@ -343,6 +363,556 @@ impl<T> Twim<T> where T: Instance {
         Ok(())
     }
 
+    /// Write to an I2C slave from an owned DMA buffer
+    ///
+    /// This works like `write`, but the buffer is accessed through the
+    /// `embedded_dma` traits, so it can be a `&'static` slice, a static cell
+    /// or a pool box instead of borrowed stack memory. The buffer is handed
+    /// back once the transaction has ended, also if it failed.
+    pub fn dma_write<B>(&mut self, address: u8, buffer: B) -> Result<B, (Error, B)>
+    where
+        B: ReadBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffer
+        let (ptr, len) = unsafe { buffer.read_buffer() };
+        let tx = unsafe { slice::from_raw_parts(ptr, len) };
+
+        let res = ram_slice_check(tx).and_then(|()| self.write(address, tx));
+        match res {
+            Ok(()) => Ok(buffer),
+            Err(e) => Err((e, buffer)),
+        }
+    }
+
+    /// Read from an I2C slave into an owned DMA buffer
+    ///
+    /// This works like `read`, with a buffer accessed through the
+    /// `embedded_dma` traits, see `dma_write`.
+    pub fn dma_read<B>(&mut self, address: u8, mut buffer: B) -> Result<B, (Error, B)>
+    where
+        B: WriteBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffer
+        let (ptr, len) = unsafe { buffer.write_buffer() };
+        let rx = unsafe { slice::from_raw_parts_mut(ptr, len) };
+
+        match self.read(address, rx) {
+            Ok(()) => Ok(buffer),
+            Err(e) => Err((e, buffer)),
+        }
+    }
+
+    /// Write data to an I2C slave, then read data from the slave without
+    /// triggering a stop condition between the two, using owned DMA buffers
+    ///
+    /// This works like `write_then_read`, with buffers accessed through the
+    /// `embedded_dma` traits, see `dma_write`.
+    pub fn dma_write_then_read<TxB, RxB>(&mut self,
+        address: u8,
+        wr_buffer: TxB,
+        mut rd_buffer: RxB,
+    )
+        -> Result<(TxB, RxB), (Error, TxB, RxB)>
+    where
+        TxB: ReadBuffer<Word = u8>,
+        RxB: WriteBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffers
+        let (tx_ptr, tx_len) = unsafe { wr_buffer.read_buffer() };
+        let (rx_ptr, rx_len) = unsafe { rd_buffer.write_buffer() };
+        let tx = unsafe { slice::from_raw_parts(tx_ptr, tx_len) };
+        let rx = unsafe { slice::from_raw_parts_mut(rx_ptr, rx_len) };
+
+        let res = ram_slice_check(tx)
+            .and_then(|()| self.write_then_read(address, tx, rx));
+        match res {
+            Ok(()) => Ok((wr_buffer, rd_buffer)),
+            Err(e) => Err((e, wr_buffer, rd_buffer)),
+        }
+    }
+
+    /// Start writing to an I2C slave in the background from an owned DMA
+    /// buffer
+    ///
+    /// This writes `buffer` like `dma_write`, but returns right away. The
+    /// buffer and the `Twim` are handed back by `Transfer::wait`.
+    pub fn dma_write_start<B>(mut self, address: u8, buffer: B)
+        -> Result<Transfer<B, Self>, (Error, B, Self)>
+    where
+        B: ReadBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffer, which the transfer does
+        // until it has ended
+        let (ptr, len) = unsafe { buffer.read_buffer() };
+        let tx = unsafe { slice::from_raw_parts(ptr, len) };
+
+        if let Err(e) = tx_slice_check(tx) {
+            return Err((e, buffer, self));
+        }
+
+        self.start_dma_transfer(address, DmaSlice::from_slice(tx), DmaSlice::null());
+        Ok(Transfer::new(buffer, self))
+    }
+
+    /// Start reading from an I2C slave in the background into an owned DMA
+    /// buffer
+    ///
+    /// The transfer ends once `buffer` is full. The buffer and the `Twim`
+    /// are handed back by `Transfer::wait`.
+    pub fn dma_read_start<B>(mut self, address: u8, mut buffer: B)
+        -> Result<Transfer<B, Self>, (Error, B, Self)>
+    where
+        B: WriteBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffer, which the transfer does
+        // until it has ended
+        let (ptr, len) = unsafe { buffer.write_buffer() };
+
+        if !dma::fits_in_maxcnt(len) {
+            return Err((Error::RxBufferTooLong, buffer, self));
+        }
+
+        self.start_dma_transfer(address, DmaSlice::null(), DmaSlice::from_raw_parts(ptr, len));
+        Ok(Transfer::new(buffer, self))
+    }
+
+    /// Start writing to an I2C slave, then reading from it without a stop
+    /// condition between the two, in the background with owned DMA buffers
+    ///
+    /// The buffers and the `Twim` are handed back by `Transfer::wait`.
+    pub fn dma_write_then_read_start<TxB, RxB>(mut self,
+        address: u8,
+        wr_buffer: TxB,
+        mut rd_buffer: RxB,
+    )
+        -> Result<Transfer<(TxB, RxB), Self>, (Error, TxB, RxB, Self)>
+    where
+        TxB: ReadBuffer<Word = u8>,
+        RxB: WriteBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffers, which the transfer does
+        // until it has ended
+        let (tx_ptr, tx_len) = unsafe { wr_buffer.read_buffer() };
+        let (rx_ptr, rx_len) = unsafe { rd_buffer.write_buffer() };
+        let tx = unsafe { slice::from_raw_parts(tx_ptr, tx_len) };
+
+        let res = tx_slice_check(tx).and_then(|()| {
+            if !dma::fits_in_maxcnt(rx_len) {
+                Err(Error::RxBufferTooLong)
+            } else {
+                Ok(())
+            }
+        });
+        if let Err(e) = res {
+            return Err((e, wr_buffer, rd_buffer, self));
+        }
+
+        self.start_dma_transfer(
+            address,
+            DmaSlice::from_slice(tx),
+            DmaSlice::from_raw_parts(rx_ptr, rx_len),
+        );
+        Ok(Transfer::new((wr_buffer, rd_buffer), self))
+    }
+
+    /// Set up and start a transaction that writes `tx`, then reads `rx`,
+    /// either of which can be empty, and ends with a stop condition
+    fn start_dma_transfer(&mut self, address: u8, tx: DmaSlice, rx: DmaSlice) {
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // before any DMA action has started
+        compiler_fence(SeqCst);
+
+        self.0.address.write(|w| unsafe { w.address().bits(address) });
+
+        // The caller guarantees that the buffers stay valid until the
+        // transaction has ended, and that their lengths fit in MAXCNT
+        self.0.txd.ptr.write(|w| unsafe { w.ptr().bits(tx.ptr) });
+        self.0.txd.maxcnt.write(|w| unsafe { w.maxcnt().bits(tx.len as _) });
+        self.0.rxd.ptr.write(|w| unsafe { w.ptr().bits(rx.ptr) });
+        self.0.rxd.maxcnt.write(|w| unsafe { w.maxcnt().bits(rx.len as _) });
+
+        self.trigger_dma_transfer(tx.len, rx.len);
+    }
+
+    /// Start a transaction with the buffers that are already set up
+    fn trigger_dma_transfer(&mut self, tx_len: u32, rx_len: u32) {
+        if rx_len == 0 {
+            self.0.shorts.write(|w| w.lasttx_stop().enabled());
+        } else if tx_len == 0 {
+            self.0.shorts.write(|w| w.lastrx_stop().enabled());
+        } else {
+            self.0.shorts.write(|w|
+                w.lasttx_startrx().enabled()
+                 .lastrx_stop().enabled()
+            );
+        }
+
+        if T::notifier().is_enabled() {
+            self.0.intenset.write(|w| w.stopped().set().error().set());
+        }
+
+        // `1` is a valid value to write to task registers.
+        if tx_len == 0 {
+            self.0.tasks_startrx.write(|w| unsafe { w.bits(1) });
+        } else {
+            self.0.tasks_starttx.write(|w| unsafe { w.bits(1) });
+        }
+    }
+
+    /// Clear the source of an error, and return it
+    fn take_error_source(&mut self) -> Option<Error> {
+        let source = self.0.errorsrc.read();
+        // The flags are cleared by writing `1` to them
+        self.0.errorsrc.write(|w| unsafe { w.bits(source.bits()) });
+
+        if source.anack().bit_is_set() {
+            Some(Error::AddressNack)
+        } else if source.dnack().bit_is_set() {
+            Some(Error::DataNack)
+        } else if source.overrun().bit_is_set() {
+            Some(Error::Overrun)
+        } else {
+            None
+        }
+    }
+
+    /// Start writing to an I2C slave, without blocking
+    ///
+    /// This starts a transaction like `write`, whose end is checked by
+    /// calling `PendingTransaction::poll`. Dropping the `PendingTransaction`
+    /// before stops the transaction. With owned buffers, `dma_write_start`
+    /// can be used safely instead.
+    ///
+    /// # Safety
+    ///
+    /// The `PendingTransaction` must not be leaked, e.g. with `mem::forget`,
+    /// as only dropping it stops EasyDMA from accessing `buffer` after the
+    /// borrow has ended.
+    pub unsafe fn start_write<'a>(&'a mut self, address: u8, buffer: &'a [u8])
+        -> Result<PendingTransaction<'a, T>, Error>
+    {
+        tx_slice_check(buffer)?;
+
+        self.start_dma_transfer(address, DmaSlice::from_slice(buffer), DmaSlice::null());
+        Ok(PendingTransaction::new(self))
+    }
+
+    /// Start reading from an I2C slave, without blocking
+    ///
+    /// See `start_write`, also for the safety requirements.
+    pub unsafe fn start_read<'a>(&'a mut self, address: u8, buffer: &'a mut [u8])
+        -> Result<PendingTransaction<'a, T>, Error>
+    {
+        if !dma::fits_in_maxcnt(buffer.len()) {
+            return Err(Error::RxBufferTooLong);
+        }
+
+        self.start_dma_transfer(address, DmaSlice::null(), DmaSlice::from_slice(buffer));
+        Ok(PendingTransaction::new(self))
+    }
+
+    /// Start writing to an I2C slave, then reading from it without a stop
+    /// condition between the two, without blocking
+    ///
+    /// See `start_write`, also for the safety requirements.
+    pub unsafe fn start_write_then_read<'a>(&'a mut self,
+        address: u8,
+        wr_buffer: &'a [u8],
+        rd_buffer: &'a mut [u8],
+    )
+        -> Result<PendingTransaction<'a, T>, Error>
+    {
+        tx_slice_check(wr_buffer)?;
+        if !dma::fits_in_maxcnt(rd_buffer.len()) {
+            return Err(Error::RxBufferTooLong);
+        }
+
+        self.start_dma_transfer(
+            address,
+            DmaSlice::from_slice(wr_buffer),
+            DmaSlice::from_slice(rd_buffer),
+        );
+        Ok(PendingTransaction::new(self))
+    }
+
+    /// Write a list of messages to an I2C slave, using the ArrayList mode of
+    /// EasyDMA
+    ///
+    /// Each item of `list` is written in a transaction of its own, and the
+    /// hardware advances to the next item by itself. The items must have a
+    /// length of at most `MAX_DMA_LEN` bytes.
+    pub fn write_list<const N: usize, const LEN: usize>(&mut self,
+        address: u8,
+        list: &ArrayList<N, LEN>,
+    )
+        -> Result<(), Error>
+    {
+        if !dma::fits_in_maxcnt(LEN) {
+            return Err(Error::TxBufferTooLong);
+        }
+        ram_slice_check(list.as_bytes())?;
+
+        self.0.txd.list.write(|w| w.list().array_list());
+        let res = self.run_list(address, N, list.first_item(), DmaSlice::null());
+        self.0.txd.list.write(|w| w.list().disabled());
+
+        res
+    }
+
+    /// Read a list of messages from an I2C slave, using the ArrayList mode of
+    /// EasyDMA
+    ///
+    /// Each item of `list` is filled in a transaction of its own, and the
+    /// hardware advances to the next item by itself. The items must have a
+    /// length of at most `MAX_DMA_LEN` bytes.
+    pub fn read_list<const N: usize, const LEN: usize>(&mut self,
+        address: u8,
+        list: &mut ArrayList<N, LEN>,
+    )
+        -> Result<(), Error>
+    {
+        if !dma::fits_in_maxcnt(LEN) {
+            return Err(Error::RxBufferTooLong);
+        }
+
+        self.0.rxd.list.write(|w| w.list().array_list());
+        let res = self.run_list(address, N, DmaSlice::null(), list.first_item_mut());
+        self.0.rxd.list.write(|w| w.list().disabled());
+
+        res
+    }
+
+    /// Run `count` transactions, with the buffers advanced by the hardware in
+    /// between
+    fn run_list(&mut self, address: u8, count: usize, tx: DmaSlice, rx: DmaSlice)
+        -> Result<(), Error>
+    {
+        let (tx_len, rx_len) = (tx.len, rx.len);
+        let mut first = Some((tx, rx));
+        for _ in 0..count {
+            if let Some((tx, rx)) = first.take() {
+                self.start_dma_transfer(address, tx, rx);
+            } else {
+                self.trigger_dma_transfer(tx_len, rx_len);
+            }
+
+            while !self.is_done() {}
+
+            // Conservative compiler fence to prevent optimizations that do not
+            // take in to account actions by DMA. The fence has been placed here,
+            // after all possible DMA actions have completed
+            compiler_fence(SeqCst);
+
+            self.finish()?;
+        }
+
+        Ok(())
+    }
+
+    /// Write to an I2C slave, without blocking
+    ///
+    /// This works like `write`, but the future waits for the end of the
+    /// transaction through the interrupt of the instance, whose handler must
+    /// call `Twim::<T>::handle_interrupt`. The interrupt must be unmasked in
+    /// the NVIC. Dropping the future stops the transaction.
+    #[cfg(feature = "async")]
+    pub async fn write_async(&mut self, address: u8, buffer: &[u8]) -> Result<(), Error> {
+        tx_slice_check(buffer)?;
+
+        self.start_dma_transfer(address, DmaSlice::from_slice(buffer), DmaSlice::null());
+        self.finish_async().await
+    }
+
+    /// Read from an I2C slave, without blocking
+    ///
+    /// See `write_async`.
+    #[cfg(feature = "async")]
+    pub async fn read_async(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        if !dma::fits_in_maxcnt(buffer.len()) {
+            return Err(Error::RxBufferTooLong);
+        }
+
+        self.start_dma_transfer(address, DmaSlice::null(), DmaSlice::from_slice(buffer));
+        self.finish_async().await
+    }
+
+    /// Write to an I2C slave, then read from it without a stop condition
+    /// between the two, without blocking
+    ///
+    /// See `write_async`.
+    #[cfg(feature = "async")]
+    pub async fn write_then_read_async(&mut self,
+        address: u8,
+        wr_buffer: &[u8],
+        rd_buffer: &mut [u8],
+    )
+        -> Result<(), Error>
+    {
+        tx_slice_check(wr_buffer)?;
+        if !dma::fits_in_maxcnt(rd_buffer.len()) {
+            return Err(Error::RxBufferTooLong);
+        }
+
+        self.start_dma_transfer(
+            address,
+            DmaSlice::from_slice(wr_buffer),
+            DmaSlice::from_slice(rd_buffer),
+        );
+        self.finish_async().await
+    }
+
+    /// Wait for the end of the transaction that has been started, without
+    /// blocking
+    #[cfg(feature = "async")]
+    async fn finish_async(&mut self) -> Result<(), Error> {
+        dma::finish(self, T::INTERRUPT, |twim| {
+            twim.0.intenset.write(|w| w.stopped().set().error().set())
+        }).await
+    }
+
+    /// Enable the interrupt for a given event
+    ///
+    /// The interrupt of the instance must be unmasked in the NVIC separately.
+    pub fn enable_interrupt(&mut self, evt: TwimEvent) {
+        match evt {
+            TwimEvent::TxStarted => self.0.intenset.write(|w| w.txstarted().set()),
+            TwimEvent::RxStarted => self.0.intenset.write(|w| w.rxstarted().set()),
+            TwimEvent::LastTx => self.0.intenset.write(|w| w.lasttx().set()),
+            TwimEvent::LastRx => self.0.intenset.write(|w| w.lastrx().set()),
+            TwimEvent::Suspended => self.0.intenset.write(|w| w.suspended().set()),
+            TwimEvent::Stopped => self.0.intenset.write(|w| w.stopped().set()),
+            TwimEvent::Error => self.0.intenset.write(|w| w.error().set()),
+        }
+    }
+
+    /// Disable the interrupt for a given event
+    pub fn disable_interrupt(&mut self, evt: TwimEvent) {
+        match evt {
+            TwimEvent::TxStarted => self.0.intenclr.write(|w| w.txstarted().clear()),
+            TwimEvent::RxStarted => self.0.intenclr.write(|w| w.rxstarted().clear()),
+            TwimEvent::LastTx => self.0.intenclr.write(|w| w.lasttx().clear()),
+            TwimEvent::LastRx => self.0.intenclr.write(|w| w.lastrx().clear()),
+            TwimEvent::Suspended => self.0.intenclr.write(|w| w.suspended().clear()),
+            TwimEvent::Stopped => self.0.intenclr.write(|w| w.stopped().clear()),
+            TwimEvent::Error => self.0.intenclr.write(|w| w.error().clear()),
+        }
+    }
+
+    /// Return whether a given event has been generated
+    pub fn is_event_triggered(&self, evt: TwimEvent) -> bool {
+        match evt {
+            TwimEvent::TxStarted => self.0.events_txstarted.read().bits() != 0,
+            TwimEvent::RxStarted => self.0.events_rxstarted.read().bits() != 0,
+            TwimEvent::LastTx => self.0.events_lasttx.read().bits() != 0,
+            TwimEvent::LastRx => self.0.events_lastrx.read().bits() != 0,
+            TwimEvent::Suspended => self.0.events_suspended.read().bits() != 0,
+            TwimEvent::Stopped => self.0.events_stopped.read().bits() != 0,
+            TwimEvent::Error => self.0.events_error.read().bits() != 0,
+        }
+    }
+
+    /// Reset a given event
+    pub fn reset_event(&mut self, evt: TwimEvent) {
+        match evt {
+            TwimEvent::TxStarted => self.0.events_txstarted.write(|w| w),
+            TwimEvent::RxStarted => self.0.events_rxstarted.write(|w| w),
+            TwimEvent::LastTx => self.0.events_lasttx.write(|w| w),
+            TwimEvent::LastRx => self.0.events_lastrx.write(|w| w),
+            TwimEvent::Suspended => self.0.events_suspended.write(|w| w),
+            TwimEvent::Stopped => self.0.events_stopped.write(|w| w),
+            TwimEvent::Error => self.0.events_error.write(|w| w),
+        }
+    }
+
+    /// Notify the `Notifier` of this instance at the end of every transfer
+    ///
+    /// `Twim::<T>::handle_interrupt` must be called from the interrupt handler
+    /// of the instance, which must be unmasked in the NVIC. The `hook`, if
+    /// any, is called from the interrupt handler.
+    pub fn enable_notifications(&mut self, hook: Option<fn()>) {
+        T::notifier().enable(hook);
+    }
+
+    /// Stop notifying the end of transfers
+    pub fn disable_notifications(&mut self) {
+        T::notifier().disable();
+        self.0.intenclr.write(|w| w.stopped().clear().error().clear());
+    }
+
+    /// Return the `Notifier` of this instance
+    pub fn notifier(&self) -> &'static Notifier {
+        T::notifier()
+    }
+
+    /// Notify the end of a transfer, and wake the future waiting for it, to be
+    /// called from the interrupt handler of the instance
+    ///
+    /// The STOPPED and ERROR events are left set, for the transfer to be
+    /// finished by its owner.
+    pub fn handle_interrupt() {
+        // This is safe, as only the interrupt enables are written to, which
+        // the driver doesn't rely on
+        let twim = unsafe { &*T::ptr() };
+        if twim.events_stopped.read().bits() != 0 || twim.events_error.read().bits() != 0 {
+            twim.intenclr.write(|w| w.stopped().clear().error().clear());
+            T::notifier().notify();
+            #[cfg(feature = "async")]
+            waker::wake(T::INTERRUPT);
+        }
+    }
+
+    /// Record the events of the instance whose interrupt is enabled, see
+    /// `enable_interrupt`, and reset them, to be called from the interrupt
+    /// handler of the instance
+    ///
+    /// The recorded events are consumed with `take_event`. As they are reset,
+    /// they must not be waited for by the driver at the same time.
+    pub fn record_events() {
+        // This is safe, as only the events whose interrupt has been enabled
+        // by the user are reset
+        unsafe {
+            let inten = (*T::ptr()).intenset.read().bits();
+            T::event_flags().record(T::ptr() as *const u8, inten);
+        }
+    }
+
+    /// Return whether `evt` has been recorded by `record_events` since it was
+    /// last taken, and clear it
+    ///
+    /// This doesn't disable interrupts, so it can be polled cheaply from
+    /// thread mode.
+    pub fn take_event(evt: TwimEvent) -> bool {
+        T::event_flags().take(evt.mask())
+    }
+
+    /// Return whether `evt` has been recorded by `record_events`, without
+    /// clearing it
+    pub fn is_event_recorded(evt: TwimEvent) -> bool {
+        T::event_flags().is_set(evt.mask())
+    }
+
+    /// Take ownership of the interrupt of the TWIM instance, to be accessed
+    /// through `irq`
+    ///
+    /// Panics if `irq` isn't the interrupt of the instance.
+    pub fn with_irq(mut self, irq: Irq) -> Self {
+        assert!(irq.is(T::INTERRUPT));
+        self.1 = Some(irq);
+        self
+    }
+
+    /// Return the interrupt of the instance, if owned, see `with_irq`
+    pub fn irq(&mut self) -> Option<&mut Irq> {
+        self.1.as_mut()
+    }
+
     /// Return the raw interface to the underlying TWIM peripheral
     pub fn free(self) -> T {
         self.0
@@ -375,6 +945,209 @@ impl<T> embedded_hal::blocking::i2c::WriteRead for Twim<T> where T: Instance {
     }
 }
 
+#[cfg(feature = "embedded-hal-1")]
+impl embedded_hal_1::i2c::Error for Error {
+    fn kind(&self) -> embedded_hal_1::i2c::ErrorKind {
+        use embedded_hal_1::i2c::{ErrorKind, NoAcknowledgeSource};
+
+        match self {
+            Error::AddressNack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address),
+            Error::DataNack => ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data),
+            Error::Overrun => ErrorKind::Overrun,
+            _ => ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::i2c::ErrorType for Twim<T> where T: Instance {
+    type Error = Error;
+}
+
+/// Operations of the same kind that follow each other are transferred
+/// without a repeated start condition in between. If there are several of
+/// them, or a write is not in RAM, they are copied through a buffer of
+/// `FORCE_COPY_BUFFER_SIZE` bytes on the stack, which limits their total
+/// length. The hardware can't suspend the transaction after reading, so it is
+/// suspended after the writes followed by further operations, and continued
+/// with a repeated start condition. Empty operations are skipped.
+#[cfg(feature = "embedded-hal-1")]
+impl<T> embedded_hal_1::i2c::I2c for Twim<T> where T: Instance {
+    fn transaction(&mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    )
+        -> Result<(), Error>
+    {
+        let mut tx_copy = [0; FORCE_COPY_BUFFER_SIZE];
+        let mut rx_copy = [0; FORCE_COPY_BUFFER_SIZE];
+
+        self.0.address.write(|w| unsafe { w.address().bits(address) });
+
+        let mut rest = operations;
+        let mut suspended = false;
+        while rest.iter().any(|op| operation_len(op) != 0) {
+            // Each step reads, then writes, as a read can be followed by a
+            // write without suspending
+            let (reads, tail) = split_run(mem::take(&mut rest), true);
+            let (writes, tail) = split_run(tail, false);
+            rest = tail;
+            let last = rest.iter().all(|op| operation_len(op) == 0);
+
+            let tx = gather_writes(writes, &mut tx_copy)?;
+            let (rx, rx_copied) = read_buffer(reads, &mut rx_copy)?;
+
+            // Conservative compiler fence to prevent optimizations that do not
+            // take in to account actions by DMA. The fence has been placed here,
+            // before any DMA action has started
+            compiler_fence(SeqCst);
+
+            // The buffers stay valid until the step has ended, and their
+            // lengths have been checked to fit in MAXCNT
+            self.0.txd.ptr.write(|w| unsafe { w.ptr().bits(tx.ptr) });
+            self.0.txd.maxcnt.write(|w| unsafe { w.maxcnt().bits(tx.len as _) });
+            self.0.rxd.ptr.write(|w| unsafe { w.ptr().bits(rx.ptr) });
+            self.0.rxd.maxcnt.write(|w| unsafe { w.maxcnt().bits(rx.len as _) });
+
+            // Without writes, this is the last step, see above
+            self.0.shorts.write(|w| {
+                if tx.len == 0 {
+                    w.lastrx_stop().enabled()
+                } else {
+                    let w = if rx.len != 0 { w.lastrx_starttx().enabled() } else { w };
+                    if last { w.lasttx_stop().enabled() } else { w.lasttx_suspend().enabled() }
+                }
+            });
+
+            // `1` is a valid value to write to task registers.
+            if rx.len != 0 {
+                self.0.tasks_startrx.write(|w| unsafe { w.bits(1) });
+            } else {
+                self.0.tasks_starttx.write(|w| unsafe { w.bits(1) });
+            }
+            if suspended {
+                self.0.tasks_resume.write(|w| unsafe { w.bits(1) });
+            }
+
+            while self.0.events_suspended.read().bits() == 0 && !self.is_done() {}
+
+            // Conservative compiler fence to prevent optimizations that do not
+            // take in to account actions by DMA. The fence has been placed here,
+            // after all possible DMA actions of the step have completed
+            compiler_fence(SeqCst);
+
+            if self.0.events_error.read().bits() != 0 {
+                let source = self.take_error_source();
+                // The transaction can only be stopped if it isn't suspended
+                self.0.tasks_resume.write(|w| unsafe { w.bits(1) });
+                self.cancel();
+                self.0.events_suspended.write(|w| w); // reset event
+                return Err(source.unwrap_or(Error::Transmit));
+            }
+
+            let bad_write = self.0.txd.amount.read().bits() != tx.len;
+            let bad_read  = self.0.rxd.amount.read().bits() != rx.len;
+
+            self.0.events_suspended.write(|w| w); // reset event
+            self.0.events_lasttx.write(|w| w); // reset event
+            self.0.events_lastrx.write(|w| w); // reset event
+            suspended = !last;
+
+            if tx.len != 0 && bad_write {
+                self.0.tasks_resume.write(|w| unsafe { w.bits(1) });
+                self.cancel();
+                return Err(Error::Transmit);
+            }
+            if rx.len != 0 && bad_read {
+                self.0.tasks_resume.write(|w| unsafe { w.bits(1) });
+                self.cancel();
+                return Err(Error::Receive);
+            }
+
+            if rx_copied {
+                scatter_reads(reads, &rx_copy);
+            }
+        }
+
+        self.0.events_stopped.write(|w| w); // reset event
+        self.0.shorts.write(|w| w);
+
+        Ok(())
+    }
+}
+
+impl<T> Transferable for Twim<T> where T: Instance {
+    type Error = Error;
+
+    fn is_done(&mut self) -> bool {
+        self.0.events_stopped.read().bits() != 0
+            || self.0.events_error.read().bits() != 0
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        // An error, e.g. a NACK, doesn't end the transaction by itself
+        let failed = self.0.events_error.read().bits() != 0;
+        let mut source = None;
+        if failed {
+            self.0.tasks_stop.write(|w| unsafe { w.bits(1) });
+            while self.0.events_stopped.read().bits() == 0 {}
+
+            source = self.take_error_source();
+            self.0.events_error.write(|w| w); // reset event
+        }
+
+        self.0.events_lasttx.write(|w| w); // reset event
+        self.0.events_lastrx.write(|w| w); // reset event
+        self.0.events_stopped.write(|w| w); // reset event
+        self.0.shorts.write(|w| w);
+
+        if let Some(e) = source {
+            return Err(e);
+        }
+
+        let bad_write = self.0.txd.amount.read().bits() != self.0.txd.maxcnt.read().bits();
+        let bad_read  = self.0.rxd.amount.read().bits() != self.0.rxd.maxcnt.read().bits();
+
+        if bad_write || (failed && !bad_read) {
+            return Err(Error::Transmit);
+        }
+
+        if bad_read {
+            return Err(Error::Receive);
+        }
+
+        Ok(())
+    }
+
+    fn cancel(&mut self) -> Progress {
+        if self.0.events_stopped.read().bits() == 0 {
+            self.0.tasks_stop.write(|w|
+                // `1` is a valid value to write to task registers.
+                unsafe { w.bits(1) }
+            );
+            while self.0.events_stopped.read().bits() == 0 {}
+        }
+
+        self.take_error_source();
+
+        self.0.events_error.write(|w| w); // reset event
+        self.0.events_lasttx.write(|w| w); // reset event
+        self.0.events_lastrx.write(|w| w); // reset event
+        self.0.events_stopped.write(|w| w); // reset event
+        self.0.shorts.write(|w| w);
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        Progress {
+            transmitted: self.0.txd.amount.read().bits() as usize,
+            received: self.0.rxd.amount.read().bits() as usize,
+        }
+    }
+}
+
 /// The pins used by the TWIN peripheral
 ///
 /// Currently, only P0 pins are supported.
@@ -387,19 +1160,263 @@ pub struct Pins {
 }
 
 
+/// Events that can be generated by the TWIM peripheral
+#[derive(Clone, Copy, Debug)]
+pub enum TwimEvent {
+    /// The transmission has started
+    TxStarted,
+    /// The reception has started
+    RxStarted,
+    /// The last byte of the transmit buffer is being sent
+    LastTx,
+    /// The last byte of the receive buffer is being received
+    LastRx,
+    /// The transaction has been suspended
+    Suspended,
+    /// The transaction has been stopped
+    Stopped,
+    /// An error occurred, e.g. a NACK
+    Error,
+}
+
+impl TwimEvent {
+    /// Return the mask of the interrupt enable bit of the event
+    fn mask(self) -> u32 {
+        1 << match self {
+            TwimEvent::TxStarted => 20,
+            TwimEvent::RxStarted => 19,
+            TwimEvent::LastTx => 24,
+            TwimEvent::LastRx => 23,
+            TwimEvent::Suspended => 18,
+            TwimEvent::Stopped => 1,
+            TwimEvent::Error => 9,
+        }
+    }
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     TxBufferTooLong,
     RxBufferTooLong,
+    /// EasyDMA can only read from data memory, read only buffers in flash will fail
+    DMABufferNotInDataMemory,
     Transmit,
     Receive,
+    /// The slave didn't acknowledge its address
+    AddressNack,
+    /// The slave didn't acknowledge a byte that has been written
+    DataNack,
+    /// A byte has been received before the previous one had been stored
+    Overrun,
+}
+
+fn tx_slice_check(slice: &[u8]) -> Result<(), Error> {
+    if !dma::fits_in_maxcnt(slice.len()) {
+        return Err(Error::TxBufferTooLong);
+    }
+    ram_slice_check(slice)
+}
+
+fn ram_slice_check(slice: &[u8]) -> Result<(), Error> {
+    if slice_in_ram(slice) {
+        Ok(())
+    } else {
+        Err(Error::DMABufferNotInDataMemory)
+    }
+}
+
+#[cfg(feature = "embedded-hal-1")]
+fn operation_len(operation: &Operation<'_>) -> usize {
+    match operation {
+        Operation::Read(buffer) => buffer.len(),
+        Operation::Write(buffer) => buffer.len(),
+    }
+}
+
+/// Split the run of reads, or writes, at the start of `operations` off the
+/// rest, along with the empty operations in it
+#[cfg(feature = "embedded-hal-1")]
+fn split_run<'a, 'b>(operations: &'a mut [Operation<'b>], reads: bool)
+    -> (&'a mut [Operation<'b>], &'a mut [Operation<'b>])
+{
+    let len = operations.iter()
+        .take_while(|op| match op {
+            Operation::Read(buffer) => reads || buffer.is_empty(),
+            Operation::Write(buffer) => !reads || buffer.is_empty(),
+        })
+        .count();
+    operations.split_at_mut(len)
+}
+
+/// Return the buffer for a run of writes, which is copied into `copy` unless
+/// it's a single buffer in RAM
+#[cfg(feature = "embedded-hal-1")]
+fn gather_writes(writes: &[Operation<'_>], copy: &mut [u8]) -> Result<DmaSlice, Error> {
+    let mut buffers = writes.iter().filter_map(|op| match op {
+        Operation::Write(buffer) if !buffer.is_empty() => Some(&**buffer),
+        _ => None,
+    });
+
+    if let (Some(buffer), None) = (buffers.next(), buffers.next()) {
+        if slice_in_ram(buffer) {
+            tx_slice_check(buffer)?;
+            return Ok(DmaSlice::from_slice(buffer));
+        }
+    }
+
+    let mut len = 0;
+    for op in writes {
+        if let Operation::Write(buffer) = op {
+            let end = len + buffer.len();
+            if end > copy.len() {
+                return Err(Error::TxBufferTooLong);
+            }
+            copy[len..end].copy_from_slice(buffer);
+            len = end;
+        }
+    }
+
+    tx_slice_check(&copy[..len])?;
+    Ok(DmaSlice::from_slice(&copy[..len]))
+}
+
+/// Return the buffer for a run of reads, and whether it is `copy`, which the
+/// received bytes have to be scattered from
+#[cfg(feature = "embedded-hal-1")]
+fn read_buffer(reads: &mut [Operation<'_>], copy: &mut [u8]) -> Result<(DmaSlice, bool), Error> {
+    let mut len = 0;
+    let mut buffers = 0;
+    let mut single = DmaSlice::null();
+    for op in reads.iter_mut() {
+        if let Operation::Read(buffer) = op {
+            if !buffer.is_empty() {
+                len += buffer.len();
+                buffers += 1;
+                single = DmaSlice::from_slice(buffer);
+            }
+        }
+    }
+
+    if buffers <= 1 {
+        if !dma::fits_in_maxcnt(len) {
+            return Err(Error::RxBufferTooLong);
+        }
+        return Ok((single, false));
+    }
+
+    if len > copy.len() || !dma::fits_in_maxcnt(len) {
+        return Err(Error::RxBufferTooLong);
+    }
+    Ok((DmaSlice::from_slice(&copy[..len]), true))
+}
+
+/// Copy the bytes received into `copy` to a run of reads
+#[cfg(feature = "embedded-hal-1")]
+fn scatter_reads(reads: &mut [Operation<'_>], copy: &[u8]) {
+    let mut start = 0;
+    for op in reads {
+        if let Operation::Read(buffer) = op {
+            let end = start + buffer.len();
+            buffer.copy_from_slice(&copy[start..end]);
+            start = end;
+        }
+    }
+}
+
+
+/// A transaction started by `Twim::start_write`, `Twim::start_read` or
+/// `Twim::start_write_then_read`
+pub struct PendingTransaction<'a, T>
+where
+    T: Instance,
+{
+    twim: &'a mut Twim<T>,
+    done: bool,
+    // The buffers stay borrowed until the transaction has ended
+    _buffers: PhantomData<&'a mut [u8]>,
 }
 
+impl<'a, T> PendingTransaction<'a, T> where T: Instance {
+    fn new(twim: &'a mut Twim<T>) -> Self {
+        PendingTransaction {
+            twim,
+            done: false,
+            _buffers: PhantomData,
+        }
+    }
+
+    /// Check whether the transaction has ended
+    pub fn poll(&mut self) -> nb::Result<(), Error> {
+        if self.done {
+            return Ok(());
+        }
+        if !self.twim.is_done() {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        // Conservative compiler fence to prevent optimizations that do not
+        // take in to account actions by DMA. The fence has been placed here,
+        // after all possible DMA actions have completed
+        compiler_fence(SeqCst);
+
+        self.done = true;
+        self.twim.finish().map_err(nb::Error::Other)
+    }
+}
+
+impl<'a, T> Drop for PendingTransaction<'a, T> where T: Instance {
+    fn drop(&mut self) {
+        if !self.done {
+            self.twim.cancel();
+        }
+    }
+}
 
 /// Implemented by all TWIM instances
-pub trait Instance: Deref<Target=twim0::RegisterBlock> {}
+pub trait Instance: Deref<Target=twim0::RegisterBlock> {
+    /// Return a pointer to the register block of the instance
+    fn ptr() -> *const twim0::RegisterBlock;
+
+    /// Return the `Notifier` of the instance
+    fn notifier() -> &'static Notifier;
+
+    /// Return the `EventFlags` of the instance
+    fn event_flags() -> &'static EventFlags;
+
+    /// The interrupt associated with this TWIM instance
+    const INTERRUPT: Interrupt;
+}
+
+macro_rules! impl_instance {
+    ($($twim:ident: $interrupt:ident,)*) => {
+        $(
+            impl Instance for $twim {
+                const INTERRUPT: Interrupt = Interrupt::$interrupt;
+
+                fn ptr() -> *const twim0::RegisterBlock {
+                    $twim::ptr()
+                }
+
+                fn notifier() -> &'static Notifier {
+                    static NOTIFIER: Notifier = Notifier::new();
+                    &NOTIFIER
+                }
+
+                fn event_flags() -> &'static EventFlags {
+                    static EVENT_FLAGS: EventFlags = EventFlags::new();
+                    &EVENT_FLAGS
+                }
+            }
+        )*
+    }
+}
 
-impl Instance for TWIM0 {}
+// TWIM0 shares its interrupt with SPIM0 where there is one
+#[cfg(feature = "52810")]
+impl_instance!(TWIM0: TWIM0_TWIS0_TWI0,);
+#[cfg(not(feature = "52810"))]
+impl_instance!(TWIM0: SPIM0_SPIS0_TWIM0_TWIS0_SPI0_TWI0,);
 
-#[cfg(any(feature = "52832", feature = "52840"))]
-impl Instance for TWIM1 {}
+#[cfg(twim1)]
+impl_instance!(TWIM1: SPIM1_SPIS1_TWIM1_TWIS1_SPI1_TWI1,);
@@ -4,16 +4,28 @@
 //!
 //! - nrf52832: Section 35
 //! - nrf52840: Section 6.34
+use core::cell::UnsafeCell;
 use core::ops::Deref;
-use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+use core::slice;
+use core::sync::atomic::{compiler_fence, AtomicBool, Ordering::SeqCst};
 use core::fmt;
 
 use crate::target::{
     uarte0,
+    Interrupt,
     UARTE0,
 };
 
+#[cfg(uarte1)]
+use crate::target::UARTE1;
+
+use crate::dma::{self, Notifier, Progress, Transfer, Transferable};
+use crate::irq::{EventFlags, Irq};
+use crate::slice_in_ram;
+#[cfg(feature = "embedded-io")]
 use crate::target_constants::EASY_DMA_SIZE;
+#[cfg(any(feature = "embedded-io", feature = "ufmt"))]
+use crate::target_constants::FORCE_COPY_BUFFER_SIZE;
 use crate::prelude::*;
 use crate::gpio::{
     Pin,
@@ -23,6 +35,21 @@ use crate::gpio::{
     Floating,
 };
 use crate::timer::{self, Timer};
+#[cfg(feature = "async")]
+use crate::rtc::{self, Deadline};
+#[cfg(feature = "async")]
+use crate::waker;
+#[cfg(feature = "async")]
+use core::future::{poll_fn, Future};
+#[cfg(feature = "async")]
+use core::pin::pin;
+#[cfg(feature = "async")]
+use core::task::Poll;
+
+use embedded_dma::{ReadBuffer, WriteBuffer};
+
+#[cfg(feature = "bbqueue")]
+use bbqueue::GrantW;
 
 // Re-export SVD variants to allow user to directly set values
 pub use crate::target::uarte0::{
@@ -31,6 +58,10 @@ pub use crate::target::uarte0::{
 };
 
 
+/// Maximum length of a single EasyDMA transfer, which depends on the width of
+/// the MAXCNT registers of the chip
+pub const MAX_DMA_LEN: usize = crate::target_constants::EASY_DMA_SIZE;
+
 /// Interface to a UARTE instance
 ///
 /// This is a very basic interface that comes with the following limitations:
@@ -39,7 +70,7 @@ pub use crate::target::uarte0::{
 ///   are disabled before using `Uarte`. See product specification:
 ///     - nrf52832: Section 15.2
 ///     - nrf52840: Section 6.1.2
-pub struct Uarte<T>(T);
+pub struct Uarte<T>(T, Option<Irq>);
 
 impl<T> Uarte<T> where T: Instance {
     pub fn new(uarte: T, mut pins: Pins, parity: Parity, baudrate: Baudrate) -> Self {
@@ -98,24 +129,33 @@ impl<T> Uarte<T> where T: Instance {
             w.baudrate().variant(baudrate)
         );
 
-        Uarte(uarte)
+        Uarte(uarte, None)
     }
 
     /// Write via UARTE
     ///
     /// This method uses transmits all bytes in `tx_buffer`
     ///
-    /// The buffer must have a length of at most 255 bytes on the nRF52832
-    /// and at most 65535 bytes on the nRF52840.
+    /// Buffers longer than a single EasyDMA transfer, `MAX_DMA_LEN` bytes,
+    /// are sent in several transfers.
     pub fn write(&mut self,
         tx_buffer  : &[u8],
     )
         -> Result<(), Error>
     {
-        if tx_buffer.len() > EASY_DMA_SIZE {
-            return Err(Error::TxBufferTooLong);
-        }
+        dma::chunks(tx_buffer).try_for_each(|chunk| {
+            self.start_write(chunk);
 
+            // Wait for transmission to end
+            while self.0.events_endtx.read().bits() == 0 {}
+
+            self.finalize_write()
+        })
+    }
+
+    /// Start a UARTE write transaction by setting the control
+    /// values and triggering a write task
+    fn start_write(&mut self, tx_buffer: &[u8]) {
         // Conservative compiler fence to prevent optimizations that do not
         // take in to account actions by DMA. The fence has been placed here,
         // before any DMA action has started
@@ -140,23 +180,29 @@ impl<T> Uarte<T> where T: Instance {
             // values.
             unsafe { w.maxcnt().bits(tx_buffer.len() as _) });
 
+        if T::notifier().is_enabled() {
+            self.0.intenset.write(|w| w.endtx().set());
+        }
+
         // Start UARTE Transmit transaction
         self.0.tasks_starttx.write(|w|
             // `1` is a valid value to write to task registers.
             unsafe { w.bits(1) });
+    }
 
-        // Wait for transmission to end
-        while self.0.events_endtx.read().bits() == 0 {}
-
-        // Reset the event, otherwise it will always read `1` from now on.
+    /// Finalize a UARTE write transaction by clearing the event, and check
+    /// that all bytes were transmitted
+    fn finalize_write(&mut self) -> Result<(), Error> {
+        // Reset the events, otherwise they will always read `1` from now on.
         self.0.events_endtx.write(|w| w);
+        self.0.events_txstarted.write(|w| w);
 
         // Conservative compiler fence to prevent optimizations that do not
         // take in to account actions by DMA. The fence has been placed here,
         // after all possible DMA actions have completed
         compiler_fence(SeqCst);
 
-        if self.0.txd.amount.read().bits() != tx_buffer.len() as u32 {
+        if self.0.txd.amount.read().bits() != self.0.txd.maxcnt.read().bits() {
             return Err(Error::Transmit);
         }
 
@@ -168,24 +214,27 @@ impl<T> Uarte<T> where T: Instance {
     /// This method fills all bytes in `rx_buffer`, and blocks
     /// until the buffer is full.
     ///
-    /// The buffer must have a length of at most 255 bytes
+    /// Buffers longer than a single EasyDMA transfer are filled in several
+    /// transfers, like with `write`.
     pub fn read(&mut self,
         rx_buffer  : &mut [u8],
     )
         -> Result<(), Error>
     {
-        self.start_read(rx_buffer)?;
+        dma::chunks_mut(rx_buffer).try_for_each(|chunk| {
+            self.start_read(chunk)?;
 
-        // Wait for transmission to end
-        while self.0.events_endrx.read().bits() == 0 {}
+            // Wait for transmission to end
+            while self.0.events_endrx.read().bits() == 0 {}
 
-        self.finalize_read();
+            self.finalize_read();
 
-        if self.0.rxd.amount.read().bits() != rx_buffer.len() as u32 {
-            return Err(Error::Receive);
-        }
+            if self.0.rxd.amount.read().bits() != chunk.len() as u32 {
+                return Err(Error::Receive);
+            }
 
-        Ok(())
+            Ok(())
+        })
     }
 
     /// Read via UARTE
@@ -201,7 +250,7 @@ impl<T> Uarte<T> where T: Instance {
     /// and in cases where a timeout does NOT occur, the timer will be left running
     /// until completion.
     ///
-    /// The buffer must have a length of at most 255 bytes
+    /// The buffer must have a length of at most `MAX_DMA_LEN` bytes.
     pub fn read_timeout<I>(
         &mut self,
         rx_buffer: &mut [u8],
@@ -251,10 +300,8 @@ impl<T> Uarte<T> where T: Instance {
     /// Start a UARTE read transaction by setting the control
     /// values and triggering a read task
     fn start_read(&mut self, rx_buffer: &mut [u8]) -> Result<(), Error> {
-        // This is overly restrictive. See (similar SPIM issue):
-        // https://github.com/nrf-rs/nrf52/issues/17
-        if rx_buffer.len() > u8::max_value() as usize {
-            return Err(Error::TxBufferTooLong);
+        if !dma::fits_in_maxcnt(rx_buffer.len()) {
+            return Err(Error::RxBufferTooLong);
         }
 
         // Conservative compiler fence to prevent optimizations that do not
@@ -281,6 +328,10 @@ impl<T> Uarte<T> where T: Instance {
             // range of values.
             unsafe { w.maxcnt().bits(rx_buffer.len() as _) });
 
+        if T::notifier().is_enabled() {
+            self.0.intenset.write(|w| w.endrx().set());
+        }
+
         // Start UARTE Receive transaction
         self.0.tasks_startrx.write(|w|
             // `1` is a valid value to write to task registers.
@@ -291,8 +342,9 @@ impl<T> Uarte<T> where T: Instance {
 
     /// Finalize a UARTE read transaction by clearing the event
     fn finalize_read(&mut self) {
-        // Reset the event, otherwise it will always read `1` from now on.
+        // Reset the events, otherwise they will always read `1` from now on.
         self.0.events_endrx.write(|w| w);
+        self.0.events_rxstarted.write(|w| w);
 
         // Conservative compiler fence to prevent optimizations that do not
         // take in to account actions by DMA. The fence has been placed here,
@@ -300,6 +352,33 @@ impl<T> Uarte<T> where T: Instance {
         compiler_fence(SeqCst);
     }
 
+    /// Read via UARTE until at least one byte has been received, and return
+    /// the number of bytes received
+    ///
+    /// The reception is stopped after the first byte, and the bytes held in
+    /// the FIFO are flushed into `rx_buffer` as well.
+    #[cfg(feature = "embedded-io")]
+    fn read_available(&mut self, rx_buffer: &mut [u8]) -> Result<usize, Error> {
+        let len = rx_buffer.len().min(EASY_DMA_SIZE);
+        if len == 0 {
+            return Ok(0);
+        }
+
+        self.0.events_rxdrdy.write(|w| w);
+        self.start_read(&mut rx_buffer[..len])?;
+
+        // Wait for the first byte
+        while self.0.events_rxdrdy.read().bits() == 0
+            && self.0.events_endrx.read().bits() == 0 {}
+
+        if self.0.events_endrx.read().bits() == 0 {
+            self.cancel_read();
+        }
+        self.finalize_read();
+
+        Ok(self.0.rxd.amount.read().bits() as usize)
+    }
+
     /// Stop an unfinished UART read transaction and flush FIFO to DMA buffer
     fn cancel_read(&mut self) {
         // Stop reception
@@ -322,12 +401,643 @@ impl<T> Uarte<T> where T: Instance {
         // The event flag itself is later reset by `finalize_read`.
     }
 
+    /// Write via UARTE from an owned DMA buffer
+    ///
+    /// This works like `write`, but the buffer is accessed through the
+    /// `embedded_dma` traits, so it can be a `&'static` slice, a static cell
+    /// or a pool box instead of borrowed stack memory. The buffer is handed
+    /// back once the transmission has ended, also if it failed.
+    pub fn dma_write<B>(&mut self, tx_buffer: B) -> Result<B, (Error, B)>
+    where
+        B: ReadBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffer
+        let (ptr, len) = unsafe { tx_buffer.read_buffer() };
+        let tx = unsafe { slice::from_raw_parts(ptr, len) };
+
+        let res = if slice_in_ram(tx) {
+            self.write(tx)
+        } else {
+            Err(Error::DMABufferNotInDataMemory)
+        };
+        match res {
+            Ok(()) => Ok(tx_buffer),
+            Err(e) => Err((e, tx_buffer)),
+        }
+    }
+
+    /// Read via UARTE into an owned DMA buffer
+    ///
+    /// This works like `read`, with a buffer accessed through the
+    /// `embedded_dma` traits, see `dma_write`.
+    pub fn dma_read<B>(&mut self, mut rx_buffer: B) -> Result<B, (Error, B)>
+    where
+        B: WriteBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffer
+        let (ptr, len) = unsafe { rx_buffer.write_buffer() };
+        let rx = unsafe { slice::from_raw_parts_mut(ptr, len) };
+
+        match self.read(rx) {
+            Ok(()) => Ok(rx_buffer),
+            Err(e) => Err((e, rx_buffer)),
+        }
+    }
+
+    /// Start writing via UARTE in the background from an owned DMA buffer
+    ///
+    /// This transmits all bytes of `tx_buffer` like `dma_write`, but returns
+    /// right away. The buffer and the `Uarte` are handed back by
+    /// `Transfer::wait`.
+    ///
+    /// The buffer must have a length of at most `MAX_DMA_LEN` bytes.
+    pub fn dma_write_start<B>(mut self, tx_buffer: B)
+        -> Result<Transfer<B, Self>, (Error, B, Self)>
+    where
+        B: ReadBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffer, which the transfer does
+        // until it has ended
+        let (ptr, len) = unsafe { tx_buffer.read_buffer() };
+        let tx = unsafe { slice::from_raw_parts(ptr, len) };
+
+        if !dma::fits_in_maxcnt(len) {
+            return Err((Error::TxBufferTooLong, tx_buffer, self));
+        }
+        if !slice_in_ram(tx) {
+            return Err((Error::DMABufferNotInDataMemory, tx_buffer, self));
+        }
+
+        self.start_write(tx);
+        Ok(Transfer::new(tx_buffer, self))
+    }
+
+    /// Start reading via UARTE in the background into an owned DMA buffer
+    ///
+    /// The transfer ends once `rx_buffer` is full. The buffer and the `Uarte`
+    /// are handed back by `Transfer::wait`.
+    ///
+    /// The buffer must have a length of at most `MAX_DMA_LEN` bytes.
+    pub fn dma_read_start<B>(mut self, mut rx_buffer: B)
+        -> Result<Transfer<B, Self>, (Error, B, Self)>
+    where
+        B: WriteBuffer<Word = u8>,
+    {
+        // The buffer traits guarantee that the memory stays valid and in
+        // place for as long as we own the buffer, which the transfer does
+        // until it has ended
+        let (ptr, len) = unsafe { rx_buffer.write_buffer() };
+        let rx = unsafe { slice::from_raw_parts_mut(ptr, len) };
+
+        match self.start_read(rx) {
+            Ok(()) => Ok(Transfer::new(rx_buffer, self)),
+            Err(e) => Err((e, rx_buffer, self)),
+        }
+    }
+
+    /// Start reading via UARTE in the background directly into a write grant
+    /// of a `bbqueue`
+    ///
+    /// The received data lands in the queue without being copied, and is
+    /// made available to its consumer, e.g. at another priority level, by
+    /// committing the grant. After `Transfer::wait`, the whole grant has been
+    /// received. After `Transfer::cancel`, e.g. when the line has gone idle,
+    /// the received part of the grant is given by `Progress::received`.
+    ///
+    /// The grant must have a length of at most `MAX_DMA_LEN` bytes, see
+    /// `Producer::grant_max_remaining`.
+    #[cfg(feature = "bbqueue")]
+    pub fn dma_read_grant_start<const N: usize>(mut self, mut grant: GrantW<'static, N>)
+        -> Result<Transfer<GrantW<'static, N>, Self>, (Error, GrantW<'static, N>, Self)>
+    {
+        // The grant points into the `'static` queue, and is reserved for us
+        // until it is committed, which can only happen once the transfer has
+        // handed it back
+        let buf = grant.buf();
+        let rx = unsafe { slice::from_raw_parts_mut(buf.as_mut_ptr(), buf.len()) };
+
+        match self.start_read(rx) {
+            Ok(()) => Ok(Transfer::new(grant, self)),
+            Err(e) => Err((e, grant, self)),
+        }
+    }
+
+    /// Write via UARTE, without blocking
+    ///
+    /// This works like `write`, but the future waits for the end of each
+    /// EasyDMA transfer through the interrupt of the instance, whose handler
+    /// must call `Uarte::<T>::handle_interrupt`. The interrupt must be
+    /// unmasked in the NVIC. Dropping the future stops the transmission.
+    #[cfg(feature = "async")]
+    pub async fn write_async(&mut self, tx_buffer: &[u8]) -> Result<(), Error> {
+        if !slice_in_ram(tx_buffer) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+
+        for chunk in dma::chunks(tx_buffer) {
+            self.start_write(chunk);
+            self.finish_async().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Read via UARTE, without blocking
+    ///
+    /// The future completes once `rx_buffer` is full. See `write_async`.
+    #[cfg(feature = "async")]
+    pub async fn read_async(&mut self, rx_buffer: &mut [u8]) -> Result<(), Error> {
+        for chunk in dma::chunks_mut(rx_buffer) {
+            self.start_read(chunk)?;
+            self.finish_async().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Fill `rx_buffer` via UARTE, without blocking
+    ///
+    /// See `read_async`.
+    #[cfg(feature = "async")]
+    pub async fn read_exact(&mut self, rx_buffer: &mut [u8]) -> Result<(), Error> {
+        self.read_async(rx_buffer).await
+    }
+
+    /// Fill `rx_buffer` via UARTE before `deadline` has elapsed, without
+    /// blocking
+    ///
+    /// If the deadline elapses first, the reception is stopped and an
+    /// `Error::Timeout(n)` is returned, where `n` is the number of bytes
+    /// received. The interrupt handlers of both the UARTE and the RTC must be
+    /// set up, see `read_async` and `Deadline::elapsed`.
+    #[cfg(feature = "async")]
+    pub async fn read_exact_timeout<R>(
+        &mut self,
+        rx_buffer: &mut [u8],
+        deadline: &mut Deadline<'_, R>,
+    ) -> Result<(), Error>
+    where
+        R: rtc::Instance,
+    {
+        let mut received = 0;
+        for chunk in dma::chunks_mut(rx_buffer) {
+            self.start_read(chunk)?;
+            if !self.finish_before(deadline).await? {
+                return Err(Error::Timeout(received + self.0.rxd.amount.read().bits() as usize));
+            }
+            received += chunk.len();
+        }
+
+        Ok(())
+    }
+
+    /// Read via UARTE until `delimiter` has been received, without blocking
+    ///
+    /// The received bytes, including the delimiter, are stored in
+    /// `rx_buffer`, and their number is returned. If the buffer fills up
+    /// before the delimiter is received, the length of the buffer is
+    /// returned. The bytes are received one at a time, held in the FIFO of
+    /// the UARTE in between.
+    #[cfg(feature = "async")]
+    pub async fn read_until(&mut self, rx_buffer: &mut [u8], delimiter: u8) -> Result<usize, Error> {
+        for i in 0..rx_buffer.len() {
+            self.start_read(&mut rx_buffer[i..=i])?;
+            self.finish_async().await?;
+            if rx_buffer[i] == delimiter {
+                return Ok(i + 1);
+            }
+        }
+
+        Ok(rx_buffer.len())
+    }
+
+    /// Read via UARTE until `delimiter` has been received, or `deadline` has
+    /// elapsed, without blocking
+    ///
+    /// See `read_until` and `read_exact_timeout`.
+    #[cfg(feature = "async")]
+    pub async fn read_until_timeout<R>(
+        &mut self,
+        rx_buffer: &mut [u8],
+        delimiter: u8,
+        deadline: &mut Deadline<'_, R>,
+    ) -> Result<usize, Error>
+    where
+        R: rtc::Instance,
+    {
+        for i in 0..rx_buffer.len() {
+            self.start_read(&mut rx_buffer[i..=i])?;
+            if !self.finish_before(deadline).await? {
+                return Err(Error::Timeout(i + self.0.rxd.amount.read().bits() as usize));
+            }
+            if rx_buffer[i] == delimiter {
+                return Ok(i + 1);
+            }
+        }
+
+        Ok(rx_buffer.len())
+    }
+
+    /// Write all of `tx_buffer` via UARTE, without blocking
+    ///
+    /// See `write_async`.
+    #[cfg(feature = "async")]
+    pub async fn write_all(&mut self, tx_buffer: &[u8]) -> Result<(), Error> {
+        self.write_async(tx_buffer).await
+    }
+
+    /// Write all of `tx_buffer` via UARTE before `deadline` has elapsed,
+    /// without blocking
+    ///
+    /// If the deadline elapses first, the transmission is stopped and an
+    /// `Error::Timeout(n)` is returned, where `n` is the number of bytes sent.
+    /// See `read_exact_timeout`.
+    #[cfg(feature = "async")]
+    pub async fn write_all_timeout<R>(
+        &mut self,
+        tx_buffer: &[u8],
+        deadline: &mut Deadline<'_, R>,
+    ) -> Result<(), Error>
+    where
+        R: rtc::Instance,
+    {
+        if !slice_in_ram(tx_buffer) {
+            return Err(Error::DMABufferNotInDataMemory);
+        }
+
+        let mut sent = 0;
+        for chunk in dma::chunks(tx_buffer) {
+            self.start_write(chunk);
+            if !self.finish_before(deadline).await? {
+                return Err(Error::Timeout(sent + self.0.txd.amount.read().bits() as usize));
+            }
+            sent += chunk.len();
+        }
+
+        Ok(())
+    }
+
+    /// Wait for the end of the transfer that has been started, without
+    /// blocking
+    #[cfg(feature = "async")]
+    async fn finish_async(&mut self) -> Result<(), Error> {
+        dma::finish(self, T::INTERRUPT, |uarte| {
+            uarte.0.intenset.write(|w| w.endtx().set().endrx().set())
+        }).await
+    }
+
+    /// Wait for the end of the transfer that has been started, or for
+    /// `deadline` to elapse, without blocking
+    ///
+    /// Return `false` if the deadline has elapsed first, in which case the
+    /// transfer has been cancelled.
+    #[cfg(feature = "async")]
+    async fn finish_before<R>(&mut self, deadline: &mut Deadline<'_, R>) -> Result<bool, Error>
+    where
+        R: rtc::Instance,
+    {
+        // Both futures are dropped on return, which cancels the transfer if it
+        // hasn't ended
+        let mut transfer = pin!(self.finish_async());
+        let mut elapsed = pin!(deadline.elapsed());
+
+        poll_fn(|cx| {
+            if let Poll::Ready(res) = transfer.as_mut().poll(cx) {
+                return Poll::Ready(res.map(|()| true));
+            }
+            if elapsed.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Ok(false));
+            }
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Enable the interrupt for a given event
+    ///
+    /// The interrupt of the instance must be unmasked in the NVIC separately.
+    pub fn enable_interrupt(&mut self, evt: UarteEvent) {
+        match evt {
+            UarteEvent::Cts => self.0.intenset.write(|w| w.cts().set()),
+            UarteEvent::Ncts => self.0.intenset.write(|w| w.ncts().set()),
+            UarteEvent::RxdRdy => self.0.intenset.write(|w| w.rxdrdy().set()),
+            UarteEvent::EndRx => self.0.intenset.write(|w| w.endrx().set()),
+            UarteEvent::TxdRdy => self.0.intenset.write(|w| w.txdrdy().set()),
+            UarteEvent::EndTx => self.0.intenset.write(|w| w.endtx().set()),
+            UarteEvent::Error => self.0.intenset.write(|w| w.error().set()),
+            UarteEvent::RxTo => self.0.intenset.write(|w| w.rxto().set()),
+            UarteEvent::RxStarted => self.0.intenset.write(|w| w.rxstarted().set()),
+            UarteEvent::TxStarted => self.0.intenset.write(|w| w.txstarted().set()),
+            UarteEvent::TxStopped => self.0.intenset.write(|w| w.txstopped().set()),
+        }
+    }
+
+    /// Disable the interrupt for a given event
+    pub fn disable_interrupt(&mut self, evt: UarteEvent) {
+        match evt {
+            UarteEvent::Cts => self.0.intenclr.write(|w| w.cts().clear()),
+            UarteEvent::Ncts => self.0.intenclr.write(|w| w.ncts().clear()),
+            UarteEvent::RxdRdy => self.0.intenclr.write(|w| w.rxdrdy().clear()),
+            UarteEvent::EndRx => self.0.intenclr.write(|w| w.endrx().clear()),
+            UarteEvent::TxdRdy => self.0.intenclr.write(|w| w.txdrdy().clear()),
+            UarteEvent::EndTx => self.0.intenclr.write(|w| w.endtx().clear()),
+            UarteEvent::Error => self.0.intenclr.write(|w| w.error().clear()),
+            UarteEvent::RxTo => self.0.intenclr.write(|w| w.rxto().clear()),
+            UarteEvent::RxStarted => self.0.intenclr.write(|w| w.rxstarted().clear()),
+            UarteEvent::TxStarted => self.0.intenclr.write(|w| w.txstarted().clear()),
+            UarteEvent::TxStopped => self.0.intenclr.write(|w| w.txstopped().clear()),
+        }
+    }
+
+    /// Return whether a given event has been generated
+    pub fn is_event_triggered(&self, evt: UarteEvent) -> bool {
+        match evt {
+            UarteEvent::Cts => self.0.events_cts.read().bits() != 0,
+            UarteEvent::Ncts => self.0.events_ncts.read().bits() != 0,
+            UarteEvent::RxdRdy => self.0.events_rxdrdy.read().bits() != 0,
+            UarteEvent::EndRx => self.0.events_endrx.read().bits() != 0,
+            UarteEvent::TxdRdy => self.0.events_txdrdy.read().bits() != 0,
+            UarteEvent::EndTx => self.0.events_endtx.read().bits() != 0,
+            UarteEvent::Error => self.0.events_error.read().bits() != 0,
+            UarteEvent::RxTo => self.0.events_rxto.read().bits() != 0,
+            UarteEvent::RxStarted => self.0.events_rxstarted.read().bits() != 0,
+            UarteEvent::TxStarted => self.0.events_txstarted.read().bits() != 0,
+            UarteEvent::TxStopped => self.0.events_txstopped.read().bits() != 0,
+        }
+    }
+
+    /// Reset a given event
+    pub fn reset_event(&mut self, evt: UarteEvent) {
+        match evt {
+            UarteEvent::Cts => self.0.events_cts.write(|w| w),
+            UarteEvent::Ncts => self.0.events_ncts.write(|w| w),
+            UarteEvent::RxdRdy => self.0.events_rxdrdy.write(|w| w),
+            UarteEvent::EndRx => self.0.events_endrx.write(|w| w),
+            UarteEvent::TxdRdy => self.0.events_txdrdy.write(|w| w),
+            UarteEvent::EndTx => self.0.events_endtx.write(|w| w),
+            UarteEvent::Error => self.0.events_error.write(|w| w),
+            UarteEvent::RxTo => self.0.events_rxto.write(|w| w),
+            UarteEvent::RxStarted => self.0.events_rxstarted.write(|w| w),
+            UarteEvent::TxStarted => self.0.events_txstarted.write(|w| w),
+            UarteEvent::TxStopped => self.0.events_txstopped.write(|w| w),
+        }
+    }
+
+    /// Notify the `Notifier` of this instance at the end of every transfer
+    ///
+    /// `Uarte::<T>::handle_interrupt` must be called from the interrupt
+    /// handler of the instance, which must be unmasked in the NVIC. The
+    /// `hook`, if any, is called from the interrupt handler.
+    pub fn enable_notifications(&mut self, hook: Option<fn()>) {
+        T::notifier().enable(hook);
+    }
+
+    /// Stop notifying the end of transfers
+    pub fn disable_notifications(&mut self) {
+        T::notifier().disable();
+        self.0.intenclr.write(|w| w.endtx().clear().endrx().clear());
+    }
+
+    /// Return the `Notifier` of this instance
+    pub fn notifier(&self) -> &'static Notifier {
+        T::notifier()
+    }
+
+    /// Notify the end of a transfer, and wake the future waiting for it, to be
+    /// called from the interrupt handler of the instance
+    ///
+    /// The ENDTX and ENDRX events are left set, for the transfer to be
+    /// finished by its owner.
+    pub fn handle_interrupt() {
+        // This is safe, as only the interrupt enables are written to, which
+        // the driver doesn't rely on
+        let uarte = unsafe { &*T::ptr() };
+        let mut ended = false;
+        if uarte.events_endtx.read().bits() != 0 {
+            uarte.intenclr.write(|w| w.endtx().clear());
+            ended = true;
+        }
+        if uarte.events_endrx.read().bits() != 0 {
+            uarte.intenclr.write(|w| w.endrx().clear());
+            ended = true;
+        }
+        if ended {
+            T::notifier().notify();
+            #[cfg(feature = "async")]
+            waker::wake(T::INTERRUPT);
+        }
+    }
+
+    /// Record the events of the instance whose interrupt is enabled, see
+    /// `enable_interrupt`, and reset them, to be called from the interrupt
+    /// handler of the instance
+    ///
+    /// The recorded events are consumed with `take_event`. As they are reset,
+    /// they must not be waited for by the driver at the same time.
+    pub fn record_events() {
+        // This is safe, as only the events whose interrupt has been enabled
+        // by the user are reset
+        unsafe {
+            let inten = (*T::ptr()).intenset.read().bits();
+            T::event_flags().record(T::ptr() as *const u8, inten);
+        }
+    }
+
+    /// Return whether `evt` has been recorded by `record_events` since it was
+    /// last taken, and clear it
+    ///
+    /// This doesn't disable interrupts, so it can be polled cheaply from
+    /// thread mode.
+    pub fn take_event(evt: UarteEvent) -> bool {
+        T::event_flags().take(evt.mask())
+    }
+
+    /// Return whether `evt` has been recorded by `record_events`, without
+    /// clearing it
+    pub fn is_event_recorded(evt: UarteEvent) -> bool {
+        T::event_flags().is_set(evt.mask())
+    }
+
+    /// Take ownership of the interrupt of the UARTE instance, to be accessed
+    /// through `irq`
+    ///
+    /// Panics if `irq` isn't the interrupt of the instance.
+    pub fn with_irq(mut self, irq: Irq) -> Self {
+        assert!(irq.is(T::INTERRUPT));
+        self.1 = Some(irq);
+        self
+    }
+
+    /// Return the interrupt of the instance, if owned, see `with_irq`
+    pub fn irq(&mut self) -> Option<&mut Irq> {
+        self.1.as_mut()
+    }
+
     /// Return the raw interface to the underlying UARTE peripheral
     pub fn free(self) -> T {
         self.0
     }
 }
 
+impl<T> Transferable for Uarte<T> where T: Instance {
+    type Error = Error;
+
+    fn is_done(&mut self) -> bool {
+        self.0.events_endtx.read().bits() != 0
+            || self.0.events_endrx.read().bits() != 0
+    }
+
+    fn finish(&mut self) -> Result<(), Error> {
+        // Only one transfer can be running, as it owns the `Uarte`
+        if self.0.events_endtx.read().bits() != 0 {
+            return self.finalize_write();
+        }
+
+        self.finalize_read();
+
+        if self.0.rxd.amount.read().bits() != self.0.rxd.maxcnt.read().bits() {
+            return Err(Error::Receive);
+        }
+
+        Ok(())
+    }
+
+    fn cancel(&mut self) -> Progress {
+        // Only one transfer can be running, as it owns the `Uarte`. Its
+        // direction is known once EasyDMA has picked up the buffer.
+        loop {
+            if self.0.events_rxstarted.read().bits() != 0 {
+                if self.0.events_endrx.read().bits() == 0 {
+                    self.cancel_read();
+                }
+                self.finalize_read();
+
+                return Progress {
+                    transmitted: 0,
+                    received: self.0.rxd.amount.read().bits() as usize,
+                };
+            }
+
+            if self.0.events_txstarted.read().bits() != 0 {
+                if self.0.events_endtx.read().bits() == 0 {
+                    self.0.tasks_stoptx.write(|w|
+                        // `1` is a valid value to write to task registers.
+                        unsafe { w.bits(1) });
+                    while self.0.events_txstopped.read().bits() == 0 {}
+                    self.0.events_txstopped.write(|w| w);
+                }
+                // An incomplete transmission is expected here
+                let _ = self.finalize_write();
+
+                return Progress {
+                    transmitted: self.0.txd.amount.read().bits() as usize,
+                    received: 0,
+                };
+            }
+        }
+    }
+}
+
+/// The single byte buffers of the `serial` traits, and whether they are in
+/// use by a transfer
+///
+/// They are static, as the transfers continue between calls, while the
+/// `Uarte` may be moved.
+pub struct SerialBuffers {
+    tx: UnsafeCell<u8>,
+    rx: UnsafeCell<u8>,
+    tx_busy: AtomicBool,
+    rx_busy: AtomicBool,
+}
+
+// Each instance has its own buffers, which are only accessed through the
+// `Uarte` owning it
+unsafe impl Sync for SerialBuffers {}
+
+impl SerialBuffers {
+    const fn new() -> Self {
+        SerialBuffers {
+            tx: UnsafeCell::new(0),
+            rx: UnsafeCell::new(0),
+            tx_busy: AtomicBool::new(false),
+            rx_busy: AtomicBool::new(false),
+        }
+    }
+}
+
+/// Sends one byte at a time with EasyDMA
+///
+/// The transmission continues after `write` has returned, and must be
+/// flushed before calling the other methods of `Uarte`.
+impl<T> embedded_hal::serial::Write<u8> for Uarte<T> where T: Instance {
+    type Error = Error;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Error> {
+        self.flush()?;
+
+        let buffers = T::serial_buffers();
+        // The previous transmission has ended, so the buffer isn't in use
+        let tx_buffer = unsafe {
+            *buffers.tx.get() = word;
+            slice::from_raw_parts(buffers.tx.get(), 1)
+        };
+        self.start_write(tx_buffer);
+        buffers.tx_busy.store(true, SeqCst);
+
+        Ok(())
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Error> {
+        let buffers = T::serial_buffers();
+        if !buffers.tx_busy.load(SeqCst) {
+            return Ok(());
+        }
+        if self.0.events_endtx.read().bits() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        buffers.tx_busy.store(false, SeqCst);
+        self.finalize_write().map_err(nb::Error::Other)
+    }
+}
+
+impl<T> embedded_hal::blocking::serial::write::Default<u8> for Uarte<T> where T: Instance {}
+
+/// Receives one byte at a time with EasyDMA
+///
+/// The reception is started by the first call of `read`, and continues until
+/// a byte has been received. Bytes arriving in between are held in the FIFO
+/// of the UARTE.
+impl<T> embedded_hal::serial::Read<u8> for Uarte<T> where T: Instance {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Error> {
+        let buffers = T::serial_buffers();
+        if !buffers.rx_busy.load(SeqCst) {
+            // No reception is running, so the buffer isn't in use
+            let rx_buffer = unsafe { slice::from_raw_parts_mut(buffers.rx.get(), 1) };
+            self.start_read(rx_buffer)?;
+            buffers.rx_busy.store(true, SeqCst);
+        }
+        if self.0.events_endrx.read().bits() == 0 {
+            return Err(nb::Error::WouldBlock);
+        }
+
+        self.finalize_read();
+        buffers.rx_busy.store(false, SeqCst);
+
+        if self.0.rxd.amount.read().bits() != 1 {
+            return Err(nb::Error::Other(Error::Receive));
+        }
+
+        // The reception has ended, so the buffer isn't in use
+        Ok(unsafe { *buffers.rx.get() })
+    }
+}
+
 impl<T> fmt::Write for Uarte<T> where T: Instance {
     fn write_str(&mut self, s: &str) -> fmt::Result {
         // Copy all data into an on-stack buffer so we never try to EasyDMA from
@@ -342,6 +1052,175 @@ impl<T> fmt::Write for Uarte<T> where T: Instance {
     }
 }
 
+/// Formatting with ufmt, which doesn't pull in the code of `core::fmt`
+#[cfg(feature = "ufmt")]
+impl<T> ufmt::uWrite for Uarte<T> where T: Instance {
+    type Error = Error;
+
+    fn write_str(&mut self, s: &str) -> Result<(), Error> {
+        // Copy all data into an on-stack buffer, like `fmt::Write`
+        let mut buf = [0; FORCE_COPY_BUFFER_SIZE];
+        for block in s.as_bytes().chunks(FORCE_COPY_BUFFER_SIZE) {
+            buf[..block.len()].copy_from_slice(block);
+            self.write(&buf[..block.len()])?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Error for Error {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Error::Timeout(_) => embedded_io::ErrorKind::TimedOut,
+            _ => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T> embedded_io::ErrorType for Uarte<T> where T: Instance {
+    type Error = Error;
+}
+
+/// Returns the bytes that have been received once the first one has arrived,
+/// see `BufferedUarte` for fewer, larger transfers
+#[cfg(feature = "embedded-io")]
+impl<T> embedded_io::Read for Uarte<T> where T: Instance {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        self.read_available(buf)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T> embedded_io::Write for Uarte<T> where T: Instance {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if slice_in_ram(buf) {
+            Uarte::write(self, buf)?;
+            return Ok(buf.len());
+        }
+
+        // Copy data from flash into an on-stack buffer, like `fmt::Write`
+        let mut copy = [0; FORCE_COPY_BUFFER_SIZE];
+        let len = buf.len().min(FORCE_COPY_BUFFER_SIZE);
+        copy[..len].copy_from_slice(&buf[..len]);
+        Uarte::write(self, &copy[..len])?;
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        // Only a byte sent through the `serial` traits may still be pending
+        nb::block!(embedded_hal::serial::Write::flush(self))
+    }
+}
+
+/// Receives one byte at a time, see `read_async`
+#[cfg(all(feature = "embedded-io", feature = "async"))]
+impl<T> embedded_io_async::Read for Uarte<T> where T: Instance {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        self.read_async(&mut buf[..1]).await?;
+        Ok(1)
+    }
+}
+
+#[cfg(all(feature = "embedded-io", feature = "async"))]
+impl<T> embedded_io_async::Write for Uarte<T> where T: Instance {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        if slice_in_ram(buf) {
+            self.write_async(buf).await?;
+            return Ok(buf.len());
+        }
+
+        // Copy data from flash into an on-stack buffer, like the blocking
+        // `write`
+        let mut copy = [0; FORCE_COPY_BUFFER_SIZE];
+        let len = buf.len().min(FORCE_COPY_BUFFER_SIZE);
+        copy[..len].copy_from_slice(&buf[..len]);
+        self.write_async(&copy[..len]).await?;
+        Ok(len)
+    }
+}
+
+/// A UARTE with a receive buffer of `N` bytes, for the `BufRead` trait of
+/// embedded-io
+///
+/// The buffer is filled with the bytes that have been received once the
+/// first one has arrived, up to a single EasyDMA transfer.
+#[cfg(feature = "embedded-io")]
+pub struct BufferedUarte<T, const N: usize> {
+    uarte: Uarte<T>,
+    buffer: [u8; N],
+    start: usize,
+    end: usize,
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T, const N: usize> BufferedUarte<T, N> where T: Instance {
+    pub fn new(uarte: Uarte<T>) -> Self {
+        BufferedUarte {
+            uarte,
+            buffer: [0; N],
+            start: 0,
+            end: 0,
+        }
+    }
+
+    /// Return the raw interface to the UARTE, dropping the buffered bytes
+    pub fn free(self) -> Uarte<T> {
+        self.uarte
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T, const N: usize> embedded_io::ErrorType for BufferedUarte<T, N> where T: Instance {
+    type Error = Error;
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T, const N: usize> embedded_io::BufRead for BufferedUarte<T, N> where T: Instance {
+    fn fill_buf(&mut self) -> Result<&[u8], Error> {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = self.uarte.read_available(&mut self.buffer)?;
+        }
+
+        Ok(&self.buffer[self.start..self.end])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.start = self.end.min(self.start + amt);
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T, const N: usize> embedded_io::Read for BufferedUarte<T, N> where T: Instance {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        use embedded_io::BufRead;
+
+        let available = self.fill_buf()?;
+        let len = available.len().min(buf.len());
+        buf[..len].copy_from_slice(&available[..len]);
+        self.consume(len);
+        Ok(len)
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+impl<T, const N: usize> embedded_io::Write for BufferedUarte<T, N> where T: Instance {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        embedded_io::Write::write(&mut self.uarte, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        embedded_io::Write::flush(&mut self.uarte)
+    }
+}
+
 pub struct Pins {
     pub rxd: Pin<Input<Floating>>,
     pub txd: Pin<Output<PushPull>>,
@@ -350,16 +1229,112 @@ pub struct Pins {
 }
 
 
+/// Events that can be generated by the UARTE peripheral
+#[derive(Clone, Copy, Debug)]
+pub enum UarteEvent {
+    /// CTS has been activated
+    Cts,
+    /// CTS has been deactivated
+    Ncts,
+    /// A byte has been received
+    RxdRdy,
+    /// The receive buffer is full
+    EndRx,
+    /// A byte has been sent
+    TxdRdy,
+    /// The whole transmit buffer has been sent
+    EndTx,
+    /// A framing, parity, overrun or break error occurred
+    Error,
+    /// The reception has timed out after being stopped
+    RxTo,
+    /// The reception has started
+    RxStarted,
+    /// The transmission has started
+    TxStarted,
+    /// The transmission has been stopped
+    TxStopped,
+}
+
+impl UarteEvent {
+    /// Return the mask of the interrupt enable bit of the event
+    fn mask(self) -> u32 {
+        1 << match self {
+            UarteEvent::Cts => 0,
+            UarteEvent::Ncts => 1,
+            UarteEvent::RxdRdy => 2,
+            UarteEvent::EndRx => 4,
+            UarteEvent::TxdRdy => 7,
+            UarteEvent::EndTx => 8,
+            UarteEvent::Error => 9,
+            UarteEvent::RxTo => 17,
+            UarteEvent::RxStarted => 19,
+            UarteEvent::TxStarted => 20,
+            UarteEvent::TxStopped => 22,
+        }
+    }
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     TxBufferTooLong,
     RxBufferTooLong,
+    /// EasyDMA can only read from data memory, read only buffers in flash will fail
+    DMABufferNotInDataMemory,
     Transmit,
     Receive,
     Timeout(usize),
 }
 
 
-pub trait Instance: Deref<Target = uarte0::RegisterBlock> {}
+pub trait Instance: Deref<Target = uarte0::RegisterBlock> {
+    /// Return a pointer to the register block of the instance
+    fn ptr() -> *const uarte0::RegisterBlock;
+
+    /// Return the `Notifier` of the instance
+    fn notifier() -> &'static Notifier;
+
+    /// Return the `EventFlags` of the instance
+    fn event_flags() -> &'static EventFlags;
+
+    /// Return the buffers of the `serial` traits of the instance
+    fn serial_buffers() -> &'static SerialBuffers;
+
+    /// The interrupt associated with this UARTE instance
+    const INTERRUPT: Interrupt;
+}
+
+macro_rules! impl_instance {
+    ($($uarte:ident: $interrupt:ident,)*) => {
+        $(
+            impl Instance for $uarte {
+                const INTERRUPT: Interrupt = Interrupt::$interrupt;
+
+                fn ptr() -> *const uarte0::RegisterBlock {
+                    $uarte::ptr()
+                }
+
+                fn notifier() -> &'static Notifier {
+                    static NOTIFIER: Notifier = Notifier::new();
+                    &NOTIFIER
+                }
+
+                fn event_flags() -> &'static EventFlags {
+                    static EVENT_FLAGS: EventFlags = EventFlags::new();
+                    &EVENT_FLAGS
+                }
+
+                fn serial_buffers() -> &'static SerialBuffers {
+                    static SERIAL_BUFFERS: SerialBuffers = SerialBuffers::new();
+                    &SERIAL_BUFFERS
+                }
+            }
+        )*
+    }
+}
+
+impl_instance!(UARTE0: UARTE0_UART0,);
 
-impl Instance for UARTE0 {}
+#[cfg(uarte1)]
+impl_instance!(UARTE1: UARTE1,);
@@ -0,0 +1,81 @@
+//! HAL interface to the User Information Configuration Registers (UICR)
+//!
+//! The UICR are non-volatile registers in flash, programmed through the NVMC.
+//! Their contents take effect after the next reset.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 14
+//! - nrf52840: Section 4.5
+use crate::nvmc::Nvmc;
+use crate::target::UICR;
+
+/// Interface to the UICR
+pub struct Uicr(UICR);
+
+impl Uicr {
+    /// Take ownership of the UICR
+    pub fn new(uicr: UICR) -> Self {
+        Uicr(uicr)
+    }
+
+    /// Is the access port protection enabled?
+    pub fn is_approtect_enabled(&self) -> bool {
+        self.0.approtect.read().pall().is_enabled()
+    }
+
+    /// Enable the access port protection
+    ///
+    /// This blocks all debugger access to the CPU and memory after the next
+    /// reset. It can only be undone by a full chip erase through the CTRL-AP,
+    /// which also erases all flash and RAM. Use this for production lockdown.
+    pub fn enable_approtect(&mut self, nvmc: &mut Nvmc, _confirm: ApprotectConfirmation) {
+        nvmc.enable_write();
+        self.0.approtect.write(|w| w.pall().enabled());
+        nvmc.wait_ready();
+        nvmc.enable_read();
+    }
+
+    /// Are P0.09 and P0.10 configured as GPIO instead of the NFC antenna?
+    #[cfg(nfct)]
+    pub fn are_nfc_pins_gpio(&self) -> bool {
+        self.0.nfcpins.read().protect().is_disabled()
+    }
+
+    /// Configure P0.09 and P0.10 as GPIO instead of the NFC antenna
+    ///
+    /// The pins can be used as GPIO after the next reset. Until then, the
+    /// `gpio` module panics in debug builds when they are configured. Going
+    /// back to NFC requires erasing the UICR.
+    #[cfg(nfct)]
+    pub fn set_nfc_pins_as_gpio(&mut self, nvmc: &mut Nvmc) {
+        if self.are_nfc_pins_gpio() {
+            return;
+        }
+
+        nvmc.enable_write();
+        self.0.nfcpins.write(|w| w.protect().disabled());
+        nvmc.wait_ready();
+        nvmc.enable_read();
+    }
+
+    /// Return the underlying UICR peripheral
+    pub fn free(self) -> UICR {
+        self.0
+    }
+}
+
+/// Confirms that the access port protection is meant to be enabled
+///
+/// Enabling the protection permanently disables debugging, so it requires
+/// this token, which can only be obtained from
+/// `ApprotectConfirmation::CONFIRM`.
+pub struct ApprotectConfirmation {
+    _private: (),
+}
+
+impl ApprotectConfirmation {
+    /// I understand that enabling the access port protection disables all
+    /// debugger access until the chip is fully erased
+    pub const CONFIRM: ApprotectConfirmation = ApprotectConfirmation { _private: () };
+}
@@ -0,0 +1,711 @@
+//! HAL interface to the USB device (USBD) peripheral
+//!
+//! `Usbd` implements the `UsbBus` trait of the `usb-device` crate, so USB
+//! classes built on it, e.g. CDC-ACM serial ports or HID devices, can be used
+//! on the nRF52840.
+//!
+//! The USBD needs the external high frequency oscillator. It is enabled and
+//! connected to the host while the USB supply (VBUS) is present, following
+//! the USB events of the POWER peripheral, see `Usbd::on_power_interrupt`.
+//! Isochronous endpoints are not supported.
+//!
+//! While the host suspends the bus, the USBD is kept in its low power mode,
+//! and `Usbd::remote_wakeup` can wake up the host if it allows to.
+//!
+//! See product specification:
+//!
+//! - nrf52840: Section 6.35
+use core::cell::Cell;
+use core::ptr;
+use core::sync::atomic::{compiler_fence, Ordering::SeqCst};
+
+use cortex_m::interrupt::{self, Mutex};
+use cortex_m::peripheral::SCB;
+use usb_device::{
+    bus::{PollResult, UsbBus, UsbBusAllocator},
+    endpoint::{EndpointAddress, EndpointType},
+    Result, UsbDirection, UsbError,
+};
+
+use crate::clocks::{Clocks, ExternalOscillator};
+use crate::power::{Bootloader, Power, PowerEvent, UsbRegulatorStatus};
+use crate::target::{usbd, POWER, USBD};
+
+/// Number of endpoints in each direction, including the control endpoint
+const NUM_ENDPOINTS: usize = 8;
+
+/// Largest packet of the control and bulk/interrupt endpoints
+const MAX_PACKET_SIZE: usize = 64;
+
+/// State of the control transfer on endpoint 0
+#[derive(Clone, Copy)]
+struct Ep0State {
+    direction: UsbDirection,
+    remaining: u16,
+    is_set_address: bool,
+}
+
+impl Ep0State {
+    const fn new() -> Self {
+        Ep0State {
+            direction: UsbDirection::Out,
+            remaining: 0,
+            is_set_address: false,
+        }
+    }
+}
+
+/// USB bus on the USBD peripheral
+pub struct Usbd {
+    periph: Mutex<USBD>,
+    max_packet_size_0: u16,
+    used_in: u8,
+    used_out: u8,
+    ep0: Mutex<Cell<Ep0State>>,
+    busy_in: Mutex<Cell<u8>>,
+    wakeup_requested: Mutex<Cell<bool>>,
+    bus_enabled: Mutex<Cell<bool>>,
+}
+
+impl Usbd {
+    /// Take ownership of the USBD peripheral
+    ///
+    /// The returned allocator is passed to the USB classes and the
+    /// `UsbDeviceBuilder`.
+    pub fn new<L, LSTAT>(
+        usbd: USBD,
+        _clocks: &Clocks<ExternalOscillator, L, LSTAT>,
+    ) -> UsbBusAllocator<Self> {
+        UsbBusAllocator::new(Usbd {
+            periph: Mutex::new(usbd),
+            max_packet_size_0: 0,
+            used_in: 0,
+            used_out: 0,
+            ep0: Mutex::new(Cell::new(Ep0State::new())),
+            busy_in: Mutex::new(Cell::new(0)),
+            wakeup_requested: Mutex::new(Cell::new(false)),
+            bus_enabled: Mutex::new(Cell::new(false)),
+        })
+    }
+
+    /// Wake up the host from suspend
+    ///
+    /// Only allowed if the host has enabled remote wakeup, see
+    /// `UsbDevice::remote_wakeup_enabled`. The USBD leaves its low power mode,
+    /// and signals the resume to the host once it is ready to, from `poll`.
+    pub fn remote_wakeup(&self) {
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+
+            regs.lowpower.write(|w| w.lowpower().force_normal());
+            self.wakeup_requested.borrow(cs).set(true);
+        });
+    }
+
+    /// Detach from the host, and reset into `bootloader`
+    ///
+    /// This is the runtime part of a DFU: the USBD is disconnected, so the
+    /// host sees the device leave before the bootloader enumerates.
+    pub fn detach_to_bootloader(
+        &self,
+        bootloader: Bootloader,
+        power: &mut Power,
+        scb: &mut SCB,
+    ) -> ! {
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+            regs.usbpullup.write(|w| w.connect().disabled());
+            regs.enable.write(|w| w.enable().disabled());
+        });
+
+        power.reset_into_bootloader(bootloader.magic(), scb)
+    }
+
+    /// Return the number of the current frame, as received with the last SOF
+    /// packet
+    ///
+    /// The frame number is incremented every millisecond while the bus is
+    /// active, and wraps around at 2047.
+    pub fn frame_number(&self) -> u16 {
+        interrupt::free(|cs| self.periph.borrow(cs).framecntr.read().framecntr().bits())
+    }
+
+    /// Enable or disable the interrupt on every SOF packet
+    ///
+    /// The USBD interrupt then fires every millisecond while the bus is
+    /// active, which can be used as a time base synchronized to the host.
+    pub fn set_sof_interrupt(&self, enabled: bool) {
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+            if enabled {
+                regs.intenset.write(|w| w.sof().set());
+            } else {
+                regs.intenclr.write(|w| w.sof().clear());
+            }
+        });
+    }
+
+    /// Enable the POWER interrupts for VBUS being detected, removed and the
+    /// USB regulator being ready
+    ///
+    /// `on_power_interrupt` must then be called from the `POWER_CLOCK`
+    /// interrupt handler, and the interrupt must be unmasked in the NVIC.
+    pub fn enable_power_interrupts(&self, power: &mut Power) {
+        power.enable_interrupt(PowerEvent::UsbDetected);
+        power.enable_interrupt(PowerEvent::UsbPowerReady);
+        power.enable_interrupt(PowerEvent::UsbRemoved);
+    }
+
+    /// Enable, connect and disable the USBD as the USB cable is plugged in and
+    /// removed
+    ///
+    /// To be called from the `POWER_CLOCK` interrupt handler. Has no effect
+    /// before the bus has been enabled by `UsbDeviceBuilder::build`.
+    pub fn on_power_interrupt(&self, power: &mut Power) {
+        let detected = power.get_event_triggered(PowerEvent::UsbDetected, true);
+        let ready = power.get_event_triggered(PowerEvent::UsbPowerReady, true);
+        let removed = power.get_event_triggered(PowerEvent::UsbRemoved, true);
+        if !detected && !ready && !removed {
+            return;
+        }
+
+        interrupt::free(|cs| {
+            if self.bus_enabled.borrow(cs).get() {
+                // The events may have piled up, so only the current status
+                // counts
+                self.follow_vbus(self.periph.borrow(cs), power.usb_regulator_status());
+            }
+        });
+    }
+
+    /// Bring the USBD into the state matching the USB supply
+    ///
+    /// The USBD is enabled as soon as VBUS is detected, but only connected to
+    /// the host once the USB regulator is ready.
+    fn follow_vbus(&self, regs: &USBD, status: UsbRegulatorStatus) {
+        let enabled = regs.enable.read().enable().is_enabled();
+
+        if !status.vbus_detected {
+            if enabled {
+                regs.usbpullup.write(|w| w.connect().disabled());
+                regs.enable.write(|w| w.enable().disabled());
+            }
+            return;
+        }
+
+        if !enabled {
+            errata::pre_enable();
+            regs.enable.write(|w| w.enable().enabled());
+            while regs.eventcause.read().ready().is_not_detected() {}
+            // Write 1 to clear
+            regs.eventcause.write(|w| w.ready().set_bit());
+            errata::post_enable();
+
+            regs.intenset.write(|w| {
+                w.usbreset()
+                    .set()
+                    .usbevent()
+                    .set()
+                    .ep0setup()
+                    .set()
+                    .ep0datadone()
+                    .set()
+                    .epdata()
+                    .set()
+            });
+        }
+
+        if status.output_ready {
+            // Connect to the host, which starts the enumeration
+            regs.usbpullup.write(|w| w.connect().enabled());
+        }
+    }
+
+    fn read_setup(&self, regs: &USBD, ep0: &Cell<Ep0State>, buf: &mut [u8]) -> Result<usize> {
+        if buf.len() < 8 {
+            return Err(UsbError::BufferOverflow);
+        }
+
+        buf[0] = regs.bmrequesttype.read().bits() as u8;
+        buf[1] = regs.brequest.read().bits() as u8;
+        buf[2] = regs.wvaluel.read().bits() as u8;
+        buf[3] = regs.wvalueh.read().bits() as u8;
+        buf[4] = regs.windexl.read().bits() as u8;
+        buf[5] = regs.windexh.read().bits() as u8;
+        buf[6] = regs.wlengthl.read().bits() as u8;
+        buf[7] = regs.wlengthh.read().bits() as u8;
+        regs.events_ep0setup.reset();
+
+        // A new control transfer aborts the previous one
+        regs.shorts
+            .modify(|_, w| w.ep0datadone_ep0status().disabled());
+
+        let direction = if buf[0] & 0x80 != 0 {
+            UsbDirection::In
+        } else {
+            UsbDirection::Out
+        };
+        let remaining = u16::from(buf[6]) | u16::from(buf[7]) << 8;
+        ep0.set(Ep0State {
+            direction,
+            remaining,
+            // The USBD completes SET_ADDRESS requests by itself
+            is_set_address: buf[0] == 0x00 && buf[1] == 0x05,
+        });
+
+        if direction == UsbDirection::Out && remaining > 0 {
+            regs.tasks_ep0rcvout.write(|w| unsafe { w.bits(1) });
+        }
+
+        Ok(8)
+    }
+}
+
+impl UsbBus for Usbd {
+    fn alloc_ep(
+        &mut self,
+        ep_dir: UsbDirection,
+        ep_addr: Option<EndpointAddress>,
+        ep_type: EndpointType,
+        max_packet_size: u16,
+        _interval: u8,
+    ) -> Result<EndpointAddress> {
+        if ep_type == EndpointType::Isochronous {
+            return Err(UsbError::Unsupported);
+        }
+        if usize::from(max_packet_size) > MAX_PACKET_SIZE {
+            return Err(UsbError::EndpointMemoryOverflow);
+        }
+
+        let used = match ep_dir {
+            UsbDirection::In => &mut self.used_in,
+            UsbDirection::Out => &mut self.used_out,
+        };
+
+        let index = match ep_addr {
+            Some(addr) => {
+                let index = addr.index();
+                if index >= NUM_ENDPOINTS || *used & (1 << index) != 0 {
+                    return Err(UsbError::InvalidEndpoint);
+                }
+                index
+            }
+            None => (1..NUM_ENDPOINTS)
+                .find(|index| *used & (1 << index) == 0)
+                .ok_or(UsbError::EndpointOverflow)?,
+        };
+        *used |= 1 << index;
+
+        if index == 0 {
+            self.max_packet_size_0 = max_packet_size;
+        }
+
+        Ok(EndpointAddress::from_parts(index, ep_dir))
+    }
+
+    /// Enable the bus
+    ///
+    /// If VBUS is missing, or the USB regulator isn't ready yet, the rest is
+    /// left to `on_power_interrupt`.
+    fn enable(&mut self) {
+        interrupt::free(|cs| {
+            self.bus_enabled.borrow(cs).set(true);
+
+            let status = unsafe { &*POWER::ptr() }.usbregstatus.read();
+            self.follow_vbus(
+                self.periph.borrow(cs),
+                UsbRegulatorStatus {
+                    vbus_detected: status.vbusdetect().bit(),
+                    output_ready: status.outputrdy().bit(),
+                },
+            );
+        });
+    }
+
+    fn reset(&self) {
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+
+            regs.epinen
+                .write(|w| unsafe { w.bits(u32::from(self.used_in)) });
+            regs.epouten
+                .write(|w| unsafe { w.bits(u32::from(self.used_out)) });
+            regs.epdatastatus.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+
+            // Writing the size of an OUT endpoint allows it to receive data
+            for index in 1..NUM_ENDPOINTS {
+                if self.used_out & (1 << index) != 0 {
+                    regs.size.epout[index].reset();
+                }
+            }
+
+            self.ep0.borrow(cs).set(Ep0State::new());
+            self.busy_in.borrow(cs).set(0);
+        });
+    }
+
+    fn set_device_address(&self, _addr: u8) {
+        // The USBD handles SET_ADDRESS requests by itself
+    }
+
+    fn write(&self, ep_addr: EndpointAddress, buf: &[u8]) -> Result<usize> {
+        let index = ep_addr.index();
+        if index >= NUM_ENDPOINTS || self.used_in & (1 << index) == 0 {
+            return Err(UsbError::InvalidEndpoint);
+        }
+        if buf.len() > MAX_PACKET_SIZE {
+            return Err(UsbError::BufferOverflow);
+        }
+
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+            let ep0 = self.ep0.borrow(cs);
+            let busy_in = self.busy_in.borrow(cs);
+
+            if index == 0 {
+                let mut state = ep0.get();
+
+                // The status stage of a control write is a zero length IN
+                // packet, which the USBD sends on request
+                if state.direction == UsbDirection::Out {
+                    if !buf.is_empty() {
+                        return Err(UsbError::InvalidState);
+                    }
+                    if !state.is_set_address {
+                        regs.tasks_ep0status.write(|w| unsafe { w.bits(1) });
+                    }
+                    return Ok(0);
+                }
+
+                // Once the last packet of a control read has been sent, the
+                // USBD completes the status stage by itself
+                state.remaining = state.remaining.saturating_sub(buf.len() as u16);
+                if state.remaining == 0 || buf.len() < usize::from(self.max_packet_size_0) {
+                    regs.shorts
+                        .modify(|_, w| w.ep0datadone_ep0status().enabled());
+                }
+                ep0.set(state);
+            } else if busy_in.get() & (1 << index) != 0 {
+                return Err(UsbError::WouldBlock);
+            }
+
+            // EasyDMA can't read from flash, so the data is copied to RAM
+            // first. The USBD copies it into its own buffer before the
+            // transfer ends, so the copy on the stack is sufficient.
+            let mut ram = [0; MAX_PACKET_SIZE];
+            ram[..buf.len()].copy_from_slice(buf);
+
+            dma(|| {
+                epin(regs, index)
+                    .ptr
+                    .write(|w| unsafe { w.ptr().bits(ram.as_ptr() as u32) });
+                epin(regs, index)
+                    .maxcnt
+                    .write(|w| unsafe { w.maxcnt().bits(buf.len() as u8) });
+
+                regs.events_endepin[index].reset();
+                regs.tasks_startepin[index].write(|w| unsafe { w.bits(1) });
+                while regs.events_endepin[index].read().bits() == 0 {}
+                regs.events_endepin[index].reset();
+            });
+
+            if index != 0 {
+                busy_in.set(busy_in.get() | (1 << index));
+            }
+
+            Ok(buf.len())
+        })
+    }
+
+    fn read(&self, ep_addr: EndpointAddress, buf: &mut [u8]) -> Result<usize> {
+        let index = ep_addr.index();
+        if index >= NUM_ENDPOINTS || self.used_out & (1 << index) == 0 {
+            return Err(UsbError::InvalidEndpoint);
+        }
+
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+            let ep0 = self.ep0.borrow(cs);
+
+            if index == 0 {
+                if regs.events_ep0setup.read().bits() != 0 {
+                    return self.read_setup(regs, ep0, buf);
+                }
+                if regs.events_ep0datadone.read().bits() == 0 {
+                    return Err(UsbError::WouldBlock);
+                }
+            } else if regs.epdatastatus.read().bits() & (1 << (16 + index)) == 0 {
+                return Err(UsbError::WouldBlock);
+            }
+
+            let size = regs.size.epout[index].read().size().bits() as usize;
+            if size > buf.len() {
+                return Err(UsbError::BufferOverflow);
+            }
+
+            if index == 0 {
+                regs.events_ep0datadone.reset();
+            } else {
+                // Write 1 to clear
+                regs.epdatastatus
+                    .write(|w| unsafe { w.bits(1 << (16 + index)) });
+            }
+
+            dma(|| {
+                epout(regs, index)
+                    .ptr
+                    .write(|w| unsafe { w.ptr().bits(buf.as_mut_ptr() as u32) });
+                epout(regs, index)
+                    .maxcnt
+                    .write(|w| unsafe { w.maxcnt().bits(size as u8) });
+
+                regs.events_endepout[index].reset();
+                regs.tasks_startepout[index].write(|w| unsafe { w.bits(1) });
+                while regs.events_endepout[index].read().bits() == 0 {}
+                regs.events_endepout[index].reset();
+            });
+
+            if index == 0 {
+                // Accept the next packet of the data stage
+                let mut state = ep0.get();
+                state.remaining = state.remaining.saturating_sub(size as u16);
+                if state.remaining > 0 {
+                    regs.tasks_ep0rcvout.write(|w| unsafe { w.bits(1) });
+                }
+                ep0.set(state);
+            }
+
+            Ok(size)
+        })
+    }
+
+    fn set_stalled(&self, ep_addr: EndpointAddress, stalled: bool) {
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+
+            // Endpoint 0 is stalled for the current control transfer only, and
+            // released by the next SETUP
+            if ep_addr.index() == 0 {
+                if stalled {
+                    regs.tasks_ep0stall.write(|w| unsafe { w.bits(1) });
+                }
+                return;
+            }
+
+            regs.epstall.write(|w| {
+                let w = unsafe { w.ep().bits(ep_addr.index() as u8) };
+                let w = match ep_addr.direction() {
+                    UsbDirection::In => w.io().in_(),
+                    UsbDirection::Out => w.io().out(),
+                };
+                if stalled {
+                    w.stall().stall()
+                } else {
+                    w.stall().un_stall()
+                }
+            });
+        });
+    }
+
+    fn is_stalled(&self, ep_addr: EndpointAddress) -> bool {
+        let index = ep_addr.index();
+        if index == 0 || index >= NUM_ENDPOINTS {
+            return false;
+        }
+
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+            match ep_addr.direction() {
+                UsbDirection::In => regs.halted.epin[index].read().getstatus().is_halted(),
+                UsbDirection::Out => regs.halted.epout[index].read().getstatus().is_halted(),
+            }
+        })
+    }
+
+    /// Enter the low power mode of the USBD, to meet the suspend current
+    /// limits
+    fn suspend(&self) {
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+            regs.lowpower.write(|w| w.lowpower().low_power());
+        });
+    }
+
+    fn resume(&self) {
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+            regs.lowpower.write(|w| w.lowpower().force_normal());
+        });
+    }
+
+    fn poll(&self) -> PollResult {
+        interrupt::free(|cs| {
+            let regs = self.periph.borrow(cs);
+            let ep0 = self.ep0.borrow(cs);
+            let busy_in = self.busy_in.borrow(cs);
+            let wakeup_requested = self.wakeup_requested.borrow(cs);
+
+            if regs.events_usbreset.read().bits() != 0 {
+                regs.events_usbreset.reset();
+                wakeup_requested.set(false);
+                return PollResult::Reset;
+            }
+
+            if regs.events_sof.read().bits() != 0 {
+                regs.events_sof.reset();
+            }
+
+            if regs.events_usbevent.read().bits() != 0 {
+                regs.events_usbevent.reset();
+
+                let cause = regs.eventcause.read();
+                // Write 1 to clear
+                regs.eventcause.write(|w| unsafe { w.bits(cause.bits()) });
+
+                // Once the USBD has left its low power mode after a remote
+                // wakeup request, it can drive the resume signalling, timed
+                // by the hardware
+                if cause.usbwuallowed().is_allowed() && wakeup_requested.get() {
+                    wakeup_requested.set(false);
+                    regs.dpdmvalue.write(|w| w.state().resume());
+                    regs.tasks_dpdmdrive.write(|w| unsafe { w.bits(1) });
+                    return PollResult::Resume;
+                }
+                if cause.suspend().is_detected() {
+                    return PollResult::Suspend;
+                }
+                if cause.resume().is_detected() {
+                    return PollResult::Resume;
+                }
+            }
+
+            let mut ep_out = 0;
+            let mut ep_in_complete = 0;
+            let mut ep_setup = 0;
+
+            if regs.events_ep0setup.read().bits() != 0 {
+                ep_setup |= 1;
+            }
+
+            // A completed IN packet is reported once, received OUT data until
+            // it has been read
+            if regs.events_ep0datadone.read().bits() != 0 {
+                if ep0.get().direction == UsbDirection::In {
+                    regs.events_ep0datadone.reset();
+                    ep_in_complete |= 1;
+                } else {
+                    ep_out |= 1;
+                }
+            }
+
+            if regs.events_epdata.read().bits() != 0 {
+                regs.events_epdata.reset();
+            }
+            let status = regs.epdatastatus.read().bits();
+            let sent = (status & 0xFE) as u8;
+            if sent != 0 {
+                // Write 1 to clear
+                regs.epdatastatus
+                    .write(|w| unsafe { w.bits(u32::from(sent)) });
+                busy_in.set(busy_in.get() & !sent);
+                ep_in_complete |= u16::from(sent);
+            }
+            ep_out |= ((status >> 16) & 0xFE) as u16;
+
+            if ep_out == 0 && ep_in_complete == 0 && ep_setup == 0 {
+                PollResult::None
+            } else {
+                PollResult::Data {
+                    ep_out,
+                    ep_in_complete,
+                    ep_setup,
+                }
+            }
+        })
+    }
+
+    const QUIRK_SET_ADDRESS_BEFORE_STATUS: bool = true;
+}
+
+fn epin(regs: &USBD, index: usize) -> &usbd::EPIN {
+    match index {
+        0 => &regs.epin0,
+        1 => &regs.epin1,
+        2 => &regs.epin2,
+        3 => &regs.epin3,
+        4 => &regs.epin4,
+        5 => &regs.epin5,
+        6 => &regs.epin6,
+        _ => &regs.epin7,
+    }
+}
+
+fn epout(regs: &USBD, index: usize) -> &usbd::EPOUT {
+    match index {
+        0 => &regs.epout0,
+        1 => &regs.epout1,
+        2 => &regs.epout2,
+        3 => &regs.epout3,
+        4 => &regs.epout4,
+        5 => &regs.epout5,
+        6 => &regs.epout6,
+        _ => &regs.epout7,
+    }
+}
+
+/// Run an EasyDMA transfer of the USBD
+fn dma<F: FnOnce()>(transfer: F) {
+    // Conservative compiler fence to prevent optimizations that do not
+    // take in to account actions by DMA. The fence has been placed here,
+    // before any DMA action has started
+    compiler_fence(SeqCst);
+    errata::pre_dma();
+
+    transfer();
+
+    errata::post_dma();
+    // Conservative compiler fence to prevent optimizations that do not
+    // take in to account actions by DMA. The fence has been placed here,
+    // after all possible DMA actions have completed
+    compiler_fence(SeqCst);
+}
+
+/// Workarounds for anomalies of the USBD
+mod errata {
+    use super::ptr;
+
+    /// Write to the undocumented registers used below, which are unlocked
+    /// for the writes if necessary
+    unsafe fn write_unlocked(writes: &[(u32, u32)]) {
+        let locked = ptr::read_volatile(0x4006_EC00 as *const u32) == 0;
+        if locked {
+            ptr::write_volatile(0x4006_EC00 as *mut u32, 0x9375);
+        }
+        for &(address, value) in writes {
+            ptr::write_volatile(address as *mut u32, value);
+        }
+        if locked {
+            ptr::write_volatile(0x4006_EC00 as *mut u32, 0x9375);
+        }
+    }
+
+    /// nRF52840 anomalies 171 and 187: the USBD might not reach its active
+    /// state, or not receive any packets
+    pub fn pre_enable() {
+        unsafe { write_unlocked(&[(0x4006_EC14, 0xC0), (0x4006_ED14, 0x03)]) };
+    }
+
+    pub fn post_enable() {
+        unsafe { write_unlocked(&[(0x4006_EC14, 0x00), (0x4006_ED14, 0x00)]) };
+    }
+
+    /// nRF52840 anomaly 199: the USBD can't receive tasks while an EasyDMA
+    /// transfer is running
+    pub fn pre_dma() {
+        unsafe { ptr::write_volatile(0x4002_7C1C as *mut u32, 0x82) };
+    }
+
+    pub fn post_dma() {
+        unsafe { ptr::write_volatile(0x4002_7C1C as *mut u32, 0x00) };
+    }
+}
@@ -0,0 +1,124 @@
+//! Registry of the wakers of interrupt driven futures
+//!
+//! Every peripheral interrupt has a slot for the `Waker` of the future that
+//! waits on it. The async methods of the drivers register their waker here,
+//! and the interrupt handler wakes it, either through the handler of the
+//! driver, e.g. `Uarte::<UARTE0>::handle_interrupt`, or with `wake` directly.
+//! Nothing depends on a specific executor, and simple applications can run
+//! a future with `block_on`.
+//!
+//! ``` ignore
+//! #[interrupt]
+//! fn UARTE0_UART0() {
+//!     Uarte::<UARTE0>::handle_interrupt();
+//! }
+//! ```
+use core::cell::RefCell;
+use core::future::{poll_fn, Future};
+use core::pin::pin;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+use cortex_m::asm;
+
+use cortex_m::interrupt::{self, Mutex, Nr};
+
+use crate::target::Interrupt;
+
+/// Number of interrupts of the largest supported chip
+const INTERRUPTS: usize = 48;
+
+const NO_WAKER: Option<Waker> = None;
+
+static WAKERS: Mutex<RefCell<[Option<Waker>; INTERRUPTS]>> =
+    Mutex::new(RefCell::new([NO_WAKER; INTERRUPTS]));
+
+/// Wake `waker` at the next call of `wake` for `interrupt`
+///
+/// This replaces the waker registered before, unless both wake the same
+/// task.
+pub fn register(interrupt: Interrupt, waker: &Waker) {
+    register_nr(interrupt.nr(), waker)
+}
+
+/// Register `waker` for the interrupt with the number `nr`
+///
+/// The `Interrupt` of the PAC isn't `Copy`, so this is used by the futures,
+/// which register again on every poll.
+pub(crate) fn register_nr(nr: u8, waker: &Waker) {
+    interrupt::free(|cs| {
+        let slot = &mut WAKERS.borrow(cs).borrow_mut()[nr as usize];
+        match slot {
+            Some(current) if current.will_wake(waker) => {}
+            _ => *slot = Some(waker.clone()),
+        }
+    });
+}
+
+/// Forget the waker registered for `interrupt`, if any
+pub fn unregister(interrupt: Interrupt) {
+    interrupt::free(|cs| WAKERS.borrow(cs).borrow_mut()[interrupt.nr() as usize] = None);
+}
+
+/// Wake the waker registered for `interrupt`, if any, to be called from the
+/// interrupt handler
+///
+/// The waker is woken once, and has to be registered again.
+pub fn wake(interrupt: Interrupt) {
+    let waker =
+        interrupt::free(|cs| WAKERS.borrow(cs).borrow_mut()[interrupt.nr() as usize].take());
+    if let Some(waker) = waker {
+        waker.wake();
+    }
+}
+
+/// Return `Pending` once, waking the task right away, so the executor can
+/// run other tasks while polling hardware that has no interrupt
+pub(crate) async fn yield_now() {
+    let mut yielded = false;
+    poll_fn(|cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+/// Run `future` to completion, sleeping with `WFE` between polls
+///
+/// The future is woken by an interrupt handler through `wake`, which signals
+/// an event with `SEV`. Taking any interrupt wakes the core as well, after
+/// which the future is polled again.
+pub fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    let mut future = pin!(future);
+
+    // The waker only signals an event, so it doesn't need any data
+    let waker = unsafe { Waker::from_raw(sev_raw_waker()) };
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+        asm::wfe();
+    }
+}
+
+fn sev_raw_waker() -> RawWaker {
+    fn clone(_: *const ()) -> RawWaker {
+        sev_raw_waker()
+    }
+    fn wake(_: *const ()) {
+        asm::sev();
+    }
+    fn drop(_: *const ()) {}
+
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake, drop);
+    RawWaker::new(core::ptr::null(), &VTABLE)
+}
@@ -0,0 +1,209 @@
+//! HAL interface to the Watchdog Timer (WDT) peripheral
+//!
+//! The watchdog resets the chip unless all enabled reload request registers
+//! are written before the timeout expires. Every reload request register is
+//! represented by a `WdtHandle`, so independent parts of an application can
+//! each be required to prove they are still running.
+//!
+//! Once started, the watchdog can only be stopped by a reset. It keeps
+//! running through a soft reset, so `Watchdog::try_new` detects whether it
+//! is already running and returns the handles configured before the reset.
+//!
+//! See product specification:
+//!
+//! - nrf52832: Section 40
+//! - nrf52840: Section 6.36
+use core::marker::PhantomData;
+
+use crate::hal::watchdog;
+use crate::target::{wdt, WDT};
+
+/// Number of reload request registers
+pub const HANDLE_COUNT: usize = 8;
+
+/// Value that needs to be written to a reload request register
+const RELOAD_VALUE: u32 = 0x6E52_4635;
+
+/// Frequency of the watchdog counter
+pub const TICKS_PER_SECOND: u32 = 32768;
+
+/// The watchdog is not running and can be configured
+pub struct Inactive;
+
+/// The watchdog is running
+pub struct Active;
+
+/// Interface to the WDT peripheral
+pub struct Watchdog<STATE> {
+    periph: WDT,
+    _state: PhantomData<STATE>,
+}
+
+/// Handles for the enabled reload request registers
+///
+/// Entries for reload request registers that are not enabled are `None`.
+pub type Handles = [Option<WdtHandle>; HANDLE_COUNT];
+
+impl Watchdog<Inactive> {
+    /// Take ownership of the WDT peripheral
+    ///
+    /// If the watchdog is already running, e.g. after a soft reset, it can
+    /// not be configured anymore. In that case the running watchdog is
+    /// returned as error, with the handles of the reload request registers
+    /// that were enabled when it was started.
+    pub fn try_new(wdt: WDT) -> Result<Self, (Watchdog<Active>, Handles)> {
+        if wdt.runstatus.read().bits() & 1 != 0 {
+            let enabled = wdt.rren.read().bits();
+            let watchdog = Watchdog {
+                periph: wdt,
+                _state: PhantomData,
+            };
+            return Err((watchdog, handles(enabled)));
+        }
+
+        Ok(Watchdog {
+            periph: wdt,
+            _state: PhantomData,
+        })
+    }
+
+    /// Set the timeout in 32.768 kHz ticks
+    ///
+    /// The timeout must be at least 15 ticks.
+    pub fn set_timeout(&mut self, ticks: u32) {
+        self.periph.crv.write(|w| unsafe { w.bits(ticks.max(0xF)) });
+    }
+
+    /// Keep the watchdog running while the CPU is sleeping, or pause it
+    ///
+    /// The watchdog runs while sleeping by default.
+    pub fn set_run_in_sleep(&mut self, run: bool) {
+        self.periph.config.modify(|_, w| w.sleep().bit(run));
+    }
+
+    /// Keep the watchdog running while the CPU is halted by a debugger, or
+    /// pause it
+    ///
+    /// The watchdog is paused while halted by default, so stepping through
+    /// code in a debugger does not reset the chip.
+    pub fn set_run_when_halted(&mut self, run: bool) {
+        self.periph.config.modify(|_, w| w.halt().bit(run));
+    }
+
+    /// Start the watchdog with `count` reload request registers
+    ///
+    /// Returns the running watchdog and one handle for each enabled reload
+    /// request register, all of which need to be used to feed the watchdog.
+    pub fn start(self, count: usize) -> (Watchdog<Active>, Handles) {
+        let count = count.max(1).min(HANDLE_COUNT);
+        let enabled = (1 << count) - 1;
+
+        self.periph.rren.write(|w| unsafe { w.bits(enabled) });
+        self.periph.tasks_start.write(|w| unsafe { w.bits(1) });
+
+        let watchdog = Watchdog {
+            periph: self.periph,
+            _state: PhantomData,
+        };
+        (watchdog, handles(enabled))
+    }
+}
+
+impl Watchdog<Active> {
+    /// Return the bit mask of the reload request registers that have not
+    /// been fed since the watchdog was last reloaded
+    ///
+    /// The hardware does not expose the watchdog counter, so this is the
+    /// closest to a remaining time that can be monitored.
+    pub fn pending_requests(&self) -> u8 {
+        self.periph.reqstatus.read().bits() as u8
+    }
+}
+
+impl<STATE> Watchdog<STATE> {
+    /// Enable the TIMEOUT interrupt
+    ///
+    /// The interrupt fires when the watchdog times out, two 32.768 kHz
+    /// cycles before the chip is reset. This leaves just enough time to
+    /// store a short crash record, e.g. in retained RAM or GPREGRET.
+    pub fn enable_interrupt(&mut self) {
+        self.periph.intenset.write(|w| w.timeout().set());
+    }
+
+    /// Disable the TIMEOUT interrupt
+    pub fn disable_interrupt(&mut self) {
+        self.periph.intenclr.write(|w| w.timeout().clear());
+    }
+
+    /// Has the watchdog timed out?
+    ///
+    /// To be checked from the interrupt handler. Clears the event if it is
+    /// set.
+    pub fn timed_out(&mut self) -> bool {
+        if self.periph.events_timeout.read().bits() == 0 {
+            return false;
+        }
+        self.periph.events_timeout.reset();
+        true
+    }
+
+    /// Is the watchdog running?
+    pub fn is_active(&self) -> bool {
+        self.periph.runstatus.read().bits() & 1 != 0
+    }
+
+    /// Return the timeout in 32.768 kHz ticks
+    pub fn timeout(&self) -> u32 {
+        self.periph.crv.read().bits()
+    }
+
+    /// Return the underlying WDT peripheral
+    ///
+    /// A running watchdog keeps running.
+    pub fn free(self) -> WDT {
+        self.periph
+    }
+}
+
+fn handles(enabled: u32) -> Handles {
+    let mut handles: Handles = [None, None, None, None, None, None, None, None];
+    for (i, handle) in handles.iter_mut().enumerate() {
+        if enabled & (1 << i) != 0 {
+            *handle = Some(WdtHandle { index: i as u8 });
+        }
+    }
+    handles
+}
+
+/// Feeds one reload request register of the watchdog
+///
+/// The watchdog is only reloaded once all handles have been fed.
+pub struct WdtHandle {
+    index: u8,
+}
+
+impl WdtHandle {
+    /// Feed the reload request register of this handle
+    pub fn pet(&mut self) {
+        regs().rr[self.index as usize].write(|w| unsafe { w.bits(RELOAD_VALUE) });
+    }
+
+    /// Return the number of the reload request register of this handle
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+}
+
+impl watchdog::Watchdog for WdtHandle {
+    fn feed(&mut self) {
+        self.pet()
+    }
+}
+
+/// Access the WDT registers
+///
+/// This is safe for the handles, as every handle only writes to its own
+/// reload request register.
+fn regs() -> &'static wdt::RegisterBlock {
+    unsafe { &*WDT::ptr() }
+}
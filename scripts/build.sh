@@ -3,12 +3,15 @@
 set -e
 
 cargo build --manifest-path nrf52810-hal/Cargo.toml --target thumbv7em-none-eabi
+cargo build --manifest-path nrf52810-hal/Cargo.toml --target thumbv7em-none-eabi --features="async embassy-time-driver embedded-hal-1 embedded-io embedded-storage-async"
 cargo build --manifest-path nrf52832-hal/Cargo.toml
 cargo build --manifest-path nrf52840-hal/Cargo.toml
+cargo build --manifest-path nrf52840-hal/Cargo.toml --features="cryptocell usb-device"
 cargo build --manifest-path boards/adafruit_nrf52pro/Cargo.toml --examples
 cargo build --manifest-path boards/adafruit-nrf52-bluefruit-le/Cargo.toml --examples
 cargo build --manifest-path boards/nRF52-DK/Cargo.toml --examples
 cargo build --manifest-path boards/nRF52840-DK/Cargo.toml --examples
+cargo build --manifest-path boards/nRF52840-Dongle/Cargo.toml --examples
 cargo build --manifest-path examples/rtfm-demo/Cargo.toml
 cargo build --manifest-path examples/rtfm-demo/Cargo.toml --no-default-features --features="52810" --target thumbv7em-none-eabi
 cargo build --manifest-path examples/rtfm-demo/Cargo.toml --no-default-features --features="52840"